@@ -0,0 +1,462 @@
+use crate::asm::aarch64::register::Register;
+use crate::asm::{
+    Address, AssemblerError, Condition, Imm32, Imm64, InstructionKind, Instructions, Label,
+    Relocation, RelocationKind, Symbol,
+};
+
+/// The register used to hold immediates and offsets that do not fit in the instruction that uses
+/// them. This is `x17`, the second intra-procedure-call register, so the assembled code must not
+/// use it for anything else.
+const TEMPORARY_REGISTER: Register = Register::X17;
+
+/// The opcodes of the three forms of a load or store.
+struct MemoryOpcodes {
+    /// A non-negative offset that is a multiple of 8, divided by 8 in the instruction.
+    scaled: u32,
+    /// A signed 9-bit offset.
+    unscaled: u32,
+    /// An offset in a register.
+    register: u32,
+}
+
+const LOAD: MemoryOpcodes = MemoryOpcodes {
+    scaled: 0xf940_0000,
+    unscaled: 0xf840_0000,
+    register: 0xf860_6800,
+};
+
+const STORE: MemoryOpcodes = MemoryOpcodes {
+    scaled: 0xf900_0000,
+    unscaled: 0xf800_0000,
+    register: 0xf820_6800,
+};
+
+const ADD_IMM: u32 = 0x9100_0000;
+const SUB_IMM: u32 = 0xd100_0000;
+
+/// Assembles `instructions` into `buf`.
+///
+/// Returns the relocations that must be applied to `buf` to resolve the symbols used by the
+/// instructions.
+pub fn assemble(
+    instructions: Instructions<Register>,
+    buf: &mut Vec<u8>,
+) -> Result<Vec<Relocation>, AssemblerError> {
+    let labels = instructions.labels_by_position();
+
+    let mut asm = Assembler {
+        buf,
+        label_locations: vec![None; instructions.labels.len()],
+        patches: Vec::with_capacity(instructions.labels.len()),
+        symbols: instructions.symbols,
+        relocations: Vec::new(),
+    };
+
+    for (kind, labels) in instructions.instructions.into_iter().zip(&labels) {
+        asm.bind_labels(labels);
+        asm.assemble_instruction(kind);
+    }
+    // Labels bound to the end of the function.
+    asm.bind_labels(&labels[labels.len() - 1]);
+
+    asm.finish()
+}
+
+struct Assembler<'asm> {
+    buf: &'asm mut Vec<u8>,
+    label_locations: Vec<Option<usize>>,
+    patches: Vec<Patch>,
+    symbols: Vec<String>,
+    relocations: Vec<Relocation>,
+}
+
+impl<'asm> Assembler<'asm> {
+    /// Adds a new patch for a [`Label`] in the instruction that starts at the current location of
+    /// the instruction pointer. The distance to the label in instructions will be written in the
+    /// immediate field of the instruction given by `kind`.
+    fn add_patch(&mut self, label: Label, kind: PatchKind) {
+        self.patches.push(Patch {
+            label,
+            start: self.buf.len(),
+            kind,
+        })
+    }
+
+    /// Adds a new relocation for a [`Symbol`] in the instruction that starts at the current
+    /// location of the instruction pointer.
+    fn add_relocation(&mut self, symbol: Symbol, kind: RelocationKind) {
+        self.relocations.push(Relocation {
+            offset: self.buf.len(),
+            symbol: self.symbols[symbol.0].clone(),
+            kind,
+            // Every AArch64 relocation is relative to the start of the instruction.
+            addend: 0,
+        })
+    }
+
+    fn push_instruction(&mut self, instruction: u32) {
+        self.buf.extend_from_slice(&instruction.to_le_bytes())
+    }
+
+    /// Sets the location of `labels` to the current location of the instruction pointer.
+    fn bind_labels(&mut self, labels: &[Label]) {
+        for label in labels {
+            self.label_locations[label.0] = Some(self.buf.len());
+        }
+    }
+
+    /// Assembles an instruction.
+    pub fn assemble_instruction(&mut self, kind: InstructionKind<Register>) {
+        match kind {
+            InstructionKind::LoadImm { src, dst } => self.assemble_load_imm(src, dst),
+            InstructionKind::LoadAddr { src, dst } => {
+                self.assemble_memory(&LOAD, dst, src.base, src.offset)
+            }
+            InstructionKind::LoadSymbolAddr { src, dst } => self.assemble_load_page(
+                src,
+                dst,
+                [RelocationKind::PageRelative, RelocationKind::PageOffset],
+                ADD_IMM,
+            ),
+            InstructionKind::LoadGotAddr { src, dst } => self.assemble_load_page(
+                src,
+                dst,
+                [
+                    RelocationKind::GotPageRelative,
+                    RelocationKind::GotPageOffset,
+                ],
+                LOAD.scaled,
+            ),
+            InstructionKind::LoadEffectiveAddr { base, index, dst } => {
+                self.assemble_add_registers(base, index, dst)
+            }
+            InstructionKind::Store { src, dst } => self.assemble_store(src, dst),
+            InstructionKind::Mov { src, dst } => self.assemble_mov(src, dst),
+            InstructionKind::Push(reg) => self.assemble_push(reg),
+            InstructionKind::Pop(reg) => self.assemble_pop(reg),
+            InstructionKind::Add { src, dst } => self.assemble_add_registers(dst, src, dst),
+            InstructionKind::AddImm { src, dst } => self.assemble_add_imm(src, dst),
+            InstructionKind::Mul { src, dst } => self.assemble_mul(src, dst),
+            InstructionKind::SetIfLess { src1, src2, dst } => {
+                self.assemble_set_if_less(src1, src2, dst)
+            }
+            InstructionKind::Compare { src1, src2 } => self.assemble_compare(src1, src2),
+            InstructionKind::CompareImm { src, imm } => self.assemble_compare_imm(src, imm),
+            InstructionKind::Test { src1, src2 } => self.assemble_test(src1, src2),
+            InstructionKind::Jump(target) => self.assemble_jump(target),
+            InstructionKind::JumpIfZero { src, target } => self.assemble_jump_if_zero(src, target),
+            InstructionKind::JumpIf { cond, target } => self.assemble_jump_if(cond, target),
+            InstructionKind::Return => self.assemble_return(),
+            InstructionKind::Call(target) => self.assemble_call(target),
+            InstructionKind::CallSymbol(target) => self.assemble_call_symbol(target),
+            InstructionKind::Nop => {}
+            InstructionKind::Float(..) => {
+                panic!("floating-point instructions are not supported on AArch64")
+            }
+        }
+    }
+
+    /// Assembles a `movz` or a `movn` followed by a `movk` for every 16-bit chunk of `src` that
+    /// the first instruction does not set already.
+    fn assemble_load_imm(&mut self, src: Imm64, dst: Register) {
+        const MOVZ: u32 = 0xd280_0000;
+        const MOVN: u32 = 0x9280_0000;
+        const MOVK: u32 = 0xf280_0000;
+
+        assert!(dst != Register::Sp, "cannot load an immediate into `sp`");
+
+        let chunks = (0..4).map(|hw| (src >> (16 * hw)) as u16);
+        // `movn` sets the other chunks to ones instead of zeros, so it is used if there are more
+        // chunks of ones than of zeros.
+        let ones = chunks.clone().filter(|chunk| *chunk == 0xffff).count();
+        let zeros = chunks.clone().filter(|chunk| *chunk == 0).count();
+        let (first, skipped) = if ones > zeros {
+            (MOVN, 0xffff)
+        } else {
+            (MOVZ, 0)
+        };
+
+        let mut opcode = first;
+        for (hw, chunk) in (0u32..).zip(chunks) {
+            if chunk == skipped {
+                continue;
+            }
+
+            let imm = if opcode == MOVN { !chunk } else { chunk };
+            self.push_instruction(opcode | hw << 21 | u32::from(imm) << 5 | dst.encode());
+            opcode = MOVK;
+        }
+
+        // Every chunk is zero or every chunk is ones.
+        if opcode == first {
+            self.push_instruction(first | dst.encode());
+        }
+    }
+
+    /// Assembles a load into `rt` or a store of `rt` at `[base+offset]`, using the shortest form
+    /// that can encode the offset.
+    fn assemble_memory(
+        &mut self,
+        opcodes: &MemoryOpcodes,
+        rt: Register,
+        base: Register,
+        offset: Imm32,
+    ) {
+        assert!(rt != Register::Sp, "cannot load or store `sp`");
+
+        let (rt, rn) = (rt.encode(), base.encode());
+
+        if offset >= 0 && offset % 8 == 0 && offset / 8 < 1 << 12 {
+            self.push_instruction(opcodes.scaled | (offset as u32 / 8) << 10 | rn << 5 | rt);
+        } else if (-256..256).contains(&offset) {
+            self.push_instruction(opcodes.unscaled | (offset as u32 & 0x1ff) << 12 | rn << 5 | rt);
+        } else {
+            self.assemble_load_imm(offset.into(), TEMPORARY_REGISTER);
+            let rm = TEMPORARY_REGISTER.encode();
+            self.push_instruction(opcodes.register | rm << 16 | rn << 5 | rt);
+        }
+    }
+
+    /// Assembles an `adrp` of the page of `src` followed by the instruction with opcode `opcode`
+    /// that adds the offset inside the page or loads from it.
+    fn assemble_load_page(
+        &mut self,
+        src: Symbol,
+        dst: Register,
+        kinds: [RelocationKind; 2],
+        opcode: u32,
+    ) {
+        assert!(dst != Register::Sp, "cannot load an address into `sp`");
+
+        let rd = dst.encode();
+
+        // adrp dst,src
+        self.add_relocation(src, kinds[0]);
+        self.push_instruction(0x9000_0000 | rd);
+
+        self.add_relocation(src, kinds[1]);
+        self.push_instruction(opcode | rd << 5 | rd);
+    }
+
+    fn assemble_store(&mut self, src: Register, dst: Address<Imm32, Register>) {
+        self.assemble_memory(&STORE, dst.base, src, dst.offset)
+    }
+
+    /// Assembles `add dst,lhs,rhs`.
+    ///
+    /// The shifted register form reads the zero register instead of `sp`, so an addition that
+    /// involves `sp` uses the extended register form, where `sp` can only be the first operand or
+    /// the destination.
+    fn assemble_add_registers(&mut self, lhs: Register, rhs: Register, dst: Register) {
+        if [lhs, rhs, dst].contains(&Register::Sp) {
+            let (lhs, rhs) = if rhs == Register::Sp {
+                (rhs, lhs)
+            } else {
+                (lhs, rhs)
+            };
+            assert!(rhs != Register::Sp, "cannot add `sp` to itself");
+
+            // add dst,lhs,rhs,uxtx
+            self.push_instruction(
+                0x8b20_6000 | rhs.encode() << 16 | lhs.encode() << 5 | dst.encode(),
+            );
+        } else {
+            self.push_instruction(
+                0x8b00_0000 | rhs.encode() << 16 | lhs.encode() << 5 | dst.encode(),
+            );
+        }
+    }
+
+    fn assemble_mov(&mut self, src: Register, dst: Register) {
+        if src == Register::Sp || dst == Register::Sp {
+            // add dst,src,0x0
+            self.push_instruction(ADD_IMM | src.encode() << 5 | dst.encode());
+        } else {
+            // orr dst,xzr,src
+            self.push_instruction(0xaa00_03e0 | src.encode() << 16 | dst.encode());
+        }
+    }
+
+    /// Assembles `str reg,[sp,-0x10]!`. The stack pointer must stay aligned to 16 bytes, so every
+    /// push takes 16 bytes.
+    fn assemble_push(&mut self, reg: Register) {
+        assert!(reg != Register::Sp, "cannot push `sp`");
+        self.push_instruction(0xf81f_0fe0 | reg.encode());
+    }
+
+    /// Assembles `ldr reg,[sp],0x10`.
+    fn assemble_pop(&mut self, reg: Register) {
+        assert!(reg != Register::Sp, "cannot pop `sp`");
+        self.push_instruction(0xf841_07e0 | reg.encode());
+    }
+
+    /// Assembles an `add` or a `sub` of a 12-bit immediate, which can be shifted by 12 bits. Any
+    /// other immediate is loaded into a register first.
+    fn assemble_add_imm(&mut self, src: Imm32, dst: Register) {
+        let (opcode, imm) = if src < 0 {
+            (SUB_IMM, src.unsigned_abs())
+        } else {
+            (ADD_IMM, src as u32)
+        };
+        let rd = dst.encode();
+
+        if imm < 1 << 12 {
+            self.push_instruction(opcode | imm << 10 | rd << 5 | rd);
+        } else if imm & 0xfff == 0 && imm >> 12 < 1 << 12 {
+            self.push_instruction(opcode | 1 << 22 | (imm >> 12) << 10 | rd << 5 | rd);
+        } else {
+            self.assemble_load_imm(src.into(), TEMPORARY_REGISTER);
+            self.assemble_add_registers(dst, TEMPORARY_REGISTER, dst);
+        }
+    }
+
+    /// Assembles `mul dst,dst,src`, which is `madd dst,dst,src,xzr`.
+    fn assemble_mul(&mut self, src: Register, dst: Register) {
+        assert!(
+            src != Register::Sp && dst != Register::Sp,
+            "cannot multiply `sp`"
+        );
+        self.push_instruction(0x9b00_7c00 | src.encode() << 16 | dst.encode() << 5 | dst.encode());
+    }
+
+    /// Assembles `cmp src1,src2` followed by `cset dst,lt`.
+    fn assemble_set_if_less(&mut self, src1: Register, src2: Register, dst: Register) {
+        assert!(dst != Register::Sp, "cannot set `sp` to a condition");
+
+        self.assemble_compare(src1, src2);
+        // csinc dst,xzr,xzr,ge
+        self.push_instruction(0x9a9f_a7e0 | dst.encode());
+    }
+
+    /// Assembles `subs xzr,src1,src2`, using the extended register form if `src1` is `sp`.
+    fn assemble_compare(&mut self, src1: Register, src2: Register) {
+        assert!(src2 != Register::Sp, "cannot compare with `sp`");
+
+        let opcode = if src1 == Register::Sp {
+            0xeb20_601f
+        } else {
+            0xeb00_001f
+        };
+        self.push_instruction(opcode | src2.encode() << 16 | src1.encode() << 5);
+    }
+
+    /// Assembles `cmp src,imm`, or `cmn src,-imm` for a negative immediate, which are `subs` and
+    /// `adds` with `xzr` as the destination. An immediate that does not fit in 12 bits, shifted
+    /// or not, is loaded into the temporary register first.
+    fn assemble_compare_imm(&mut self, src: Register, imm: Imm32) {
+        let (opcode, abs) = if imm < 0 {
+            (0xb100_001f, imm.unsigned_abs())
+        } else {
+            (0xf100_001f, imm as u32)
+        };
+        let rn = src.encode();
+
+        if abs < 1 << 12 {
+            self.push_instruction(opcode | abs << 10 | rn << 5);
+        } else if abs & 0xfff == 0 && abs >> 12 < 1 << 12 {
+            self.push_instruction(opcode | 1 << 22 | (abs >> 12) << 10 | rn << 5);
+        } else {
+            self.assemble_load_imm(imm.into(), TEMPORARY_REGISTER);
+            self.assemble_compare(src, TEMPORARY_REGISTER);
+        }
+    }
+
+    /// Assembles `ands xzr,src1,src2`.
+    fn assemble_test(&mut self, src1: Register, src2: Register) {
+        assert!(
+            src1 != Register::Sp && src2 != Register::Sp,
+            "cannot test `sp`"
+        );
+        self.push_instruction(0xea00_001f | src2.encode() << 16 | src1.encode() << 5);
+    }
+
+    fn assemble_jump(&mut self, target: Label) {
+        // b target
+        self.add_patch(target, PatchKind::Imm26);
+        self.push_instruction(0x1400_0000);
+    }
+
+    fn assemble_jump_if_zero(&mut self, src: Register, target: Label) {
+        assert!(src != Register::Sp, "cannot compare `sp` with zero");
+
+        // cbz src,target
+        self.add_patch(target, PatchKind::Imm19);
+        self.push_instruction(0xb400_0000 | src.encode());
+    }
+
+    fn assemble_jump_if(&mut self, cond: Condition, target: Label) {
+        let cond = match cond {
+            Condition::Equal => 0b0000,
+            Condition::NotEqual => 0b0001,
+            Condition::GreaterOrEqual => 0b1010,
+            Condition::Less => 0b1011,
+            Condition::Greater => 0b1100,
+            Condition::LessOrEqual => 0b1101,
+            Condition::Above => 0b1000,
+            Condition::AboveOrEqual => 0b0010,
+            Condition::Below => 0b0011,
+            Condition::BelowOrEqual => 0b1001,
+        };
+
+        // b.cond target
+        self.add_patch(target, PatchKind::Imm19);
+        self.push_instruction(0x5400_0000 | cond);
+    }
+
+    fn assemble_return(&mut self) {
+        // ret x30
+        self.push_instruction(0xd65f_03c0);
+    }
+
+    fn assemble_call(&mut self, target: Register) {
+        // blr target
+        self.push_instruction(0xd63f_0000 | target.encode() << 5);
+    }
+
+    fn assemble_call_symbol(&mut self, target: Symbol) {
+        // bl target
+        self.add_relocation(target, RelocationKind::Branch);
+        self.push_instruction(0x9400_0000);
+    }
+
+    pub fn finish(&mut self) -> Result<Vec<Relocation>, AssemblerError> {
+        for patch in &self.patches {
+            let patch_end = patch.start + std::mem::size_of::<u32>();
+
+            let label_location = self.label_locations[patch.label.0]
+                .ok_or(AssemblerError::MissingLabelLocation(patch.label))?;
+            // The distance is relative to the start of the instruction and counted in
+            // instructions.
+            let distance = (label_location as i64 - patch.start as i64) / 4;
+
+            let (bits, shift) = match patch.kind {
+                PatchKind::Imm26 => (26, 0),
+                PatchKind::Imm19 => (19, 5),
+            };
+            if !(-(1 << (bits - 1))..1 << (bits - 1)).contains(&distance) {
+                return Err(AssemblerError::LabelOutOfRange(patch.label));
+            }
+
+            let mut instruction =
+                u32::from_le_bytes(self.buf[patch.start..patch_end].try_into().unwrap());
+            instruction |= (distance as u32 & ((1 << bits) - 1)) << shift;
+            self.buf[patch.start..patch_end].copy_from_slice(&instruction.to_le_bytes());
+        }
+
+        Ok(std::mem::take(&mut self.relocations))
+    }
+}
+
+/// The immediate field of an instruction that holds the distance to a label.
+enum PatchKind {
+    /// Bits 0 to 25, used by `b`.
+    Imm26,
+    /// Bits 5 to 23, used by `cbz` and `b.cond`.
+    Imm19,
+}
+
+struct Patch {
+    label: Label,
+    start: usize,
+    kind: PatchKind,
+}
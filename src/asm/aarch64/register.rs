@@ -0,0 +1,102 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A 64-bit general purpose register.
+///
+/// The encoding `31` means `sp` or the zero register depending on the instruction. The zero
+/// register is never an operand, so only `sp` is represented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Register {
+    X0,
+    X1,
+    X2,
+    X3,
+    X4,
+    X5,
+    X6,
+    X7,
+    X8,
+    X9,
+    X10,
+    X11,
+    X12,
+    X13,
+    X14,
+    X15,
+    X16,
+    X17,
+    X18,
+    X19,
+    X20,
+    X21,
+    X22,
+    X23,
+    X24,
+    X25,
+    X26,
+    X27,
+    X28,
+    X29,
+    X30,
+    Sp,
+}
+
+impl Register {
+    pub const fn encode(self) -> u32 {
+        self as u32
+    }
+}
+
+const NAMES: [(Register, &str); 32] = [
+    (Register::X0, "x0"),
+    (Register::X1, "x1"),
+    (Register::X2, "x2"),
+    (Register::X3, "x3"),
+    (Register::X4, "x4"),
+    (Register::X5, "x5"),
+    (Register::X6, "x6"),
+    (Register::X7, "x7"),
+    (Register::X8, "x8"),
+    (Register::X9, "x9"),
+    (Register::X10, "x10"),
+    (Register::X11, "x11"),
+    (Register::X12, "x12"),
+    (Register::X13, "x13"),
+    (Register::X14, "x14"),
+    (Register::X15, "x15"),
+    (Register::X16, "x16"),
+    (Register::X17, "x17"),
+    (Register::X18, "x18"),
+    (Register::X19, "x19"),
+    (Register::X20, "x20"),
+    (Register::X21, "x21"),
+    (Register::X22, "x22"),
+    (Register::X23, "x23"),
+    (Register::X24, "x24"),
+    (Register::X25, "x25"),
+    (Register::X26, "x26"),
+    (Register::X27, "x27"),
+    (Register::X28, "x28"),
+    (Register::X29, "x29"),
+    (Register::X30, "x30"),
+    (Register::Sp, "sp"),
+];
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (_, name) = NAMES.iter().find(|(reg, _)| reg == self).unwrap();
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Register {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NAMES
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|(reg, _)| *reg)
+            .ok_or(())
+    }
+}
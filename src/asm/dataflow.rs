@@ -0,0 +1,374 @@
+//! Dataflow analyses over assembly, where every node is the index of an instruction.
+//!
+//! Registers are only read and written by the operands of the instructions: calls are not
+//! assumed to clobber any register and `ret` does not read any register. The registers that are
+//! live when the function returns or defined when it starts must be given to the analyses.
+use std::collections::BTreeSet;
+
+use crate::asm::{FloatKind, InstructionKind, Instructions};
+use crate::dataflow::{reverse_postorder, Analysis, Direction, Graph};
+
+impl<R: Copy> InstructionKind<R> {
+    /// Returns the registers read by this instruction.
+    pub fn uses(&self) -> Vec<R> {
+        match *self {
+            InstructionKind::LoadAddr { ref src, .. } => vec![src.base],
+            InstructionKind::LoadEffectiveAddr { base, index, .. } => vec![base, index],
+            InstructionKind::Store { src, ref dst } => vec![src, dst.base],
+            InstructionKind::Mov { src, .. } => vec![src],
+            InstructionKind::Push(src) => vec![src],
+            InstructionKind::Add { src, dst }
+            | InstructionKind::Mul { src, dst }
+            | InstructionKind::Float(_, FloatKind::Add { src, dst })
+            | InstructionKind::Float(_, FloatKind::Mul { src, dst }) => vec![src, dst],
+            InstructionKind::AddImm { dst, .. } => vec![dst],
+            InstructionKind::CompareImm { src, .. } => vec![src],
+            InstructionKind::SetIfLess { src1, src2, .. }
+            | InstructionKind::Float(_, FloatKind::SetIfLess { src1, src2, .. }) => {
+                vec![src1, src2]
+            }
+            InstructionKind::Compare { src1, src2 }
+            | InstructionKind::Test { src1, src2 }
+            | InstructionKind::Float(_, FloatKind::Compare { src1, src2 }) => vec![src1, src2],
+            InstructionKind::Float(_, FloatKind::FromInt { src, .. })
+            | InstructionKind::Float(_, FloatKind::ToInt { src, .. })
+            | InstructionKind::Float(_, FloatKind::Convert { src, .. }) => vec![src],
+            InstructionKind::JumpIfZero { src, .. } => vec![src],
+            InstructionKind::Call(target) => vec![target],
+            InstructionKind::LoadImm { .. }
+            | InstructionKind::LoadSymbolAddr { .. }
+            | InstructionKind::LoadGotAddr { .. }
+            | InstructionKind::Pop(_)
+            | InstructionKind::Jump(_)
+            | InstructionKind::JumpIf { .. }
+            | InstructionKind::Return
+            | InstructionKind::CallSymbol(_)
+            | InstructionKind::Nop => vec![],
+        }
+    }
+
+    /// Returns the register written by this instruction.
+    pub fn def(&self) -> Option<R> {
+        match *self {
+            InstructionKind::LoadImm { dst, .. }
+            | InstructionKind::LoadAddr { dst, .. }
+            | InstructionKind::LoadSymbolAddr { dst, .. }
+            | InstructionKind::LoadGotAddr { dst, .. }
+            | InstructionKind::LoadEffectiveAddr { dst, .. }
+            | InstructionKind::Mov { dst, .. }
+            | InstructionKind::Pop(dst)
+            | InstructionKind::Add { dst, .. }
+            | InstructionKind::AddImm { dst, .. }
+            | InstructionKind::Mul { dst, .. }
+            | InstructionKind::SetIfLess { dst, .. }
+            | InstructionKind::Float(_, FloatKind::Add { dst, .. })
+            | InstructionKind::Float(_, FloatKind::Mul { dst, .. })
+            | InstructionKind::Float(_, FloatKind::SetIfLess { dst, .. })
+            | InstructionKind::Float(_, FloatKind::FromInt { dst, .. })
+            | InstructionKind::Float(_, FloatKind::ToInt { dst, .. })
+            | InstructionKind::Float(_, FloatKind::Convert { dst, .. }) => Some(dst),
+            InstructionKind::Store { .. }
+            | InstructionKind::Push(_)
+            | InstructionKind::Compare { .. }
+            | InstructionKind::CompareImm { .. }
+            | InstructionKind::Float(_, FloatKind::Compare { .. })
+            | InstructionKind::Test { .. }
+            | InstructionKind::Jump(_)
+            | InstructionKind::JumpIfZero { .. }
+            | InstructionKind::JumpIf { .. }
+            | InstructionKind::Return
+            | InstructionKind::Call(_)
+            | InstructionKind::CallSymbol(_)
+            | InstructionKind::Nop => None,
+        }
+    }
+
+    /// Returns whether this instruction reads the flags.
+    pub fn reads_flags(&self) -> bool {
+        matches!(self, InstructionKind::JumpIf { .. })
+    }
+
+    /// Returns whether this instruction may overwrite the flags.
+    ///
+    /// Loading a zero is assembled as `xor reg,reg`, which clobbers the flags, and the flags are
+    /// not preserved across calls.
+    pub fn clobbers_flags(&self) -> bool {
+        match *self {
+            InstructionKind::LoadImm { src, .. } => src == 0,
+            InstructionKind::Add { .. }
+            | InstructionKind::AddImm { .. }
+            | InstructionKind::Mul { .. }
+            | InstructionKind::SetIfLess { .. }
+            | InstructionKind::Float(_, FloatKind::SetIfLess { .. })
+            | InstructionKind::Compare { .. }
+            | InstructionKind::CompareImm { .. }
+            | InstructionKind::Float(_, FloatKind::Compare { .. })
+            | InstructionKind::Test { .. }
+            | InstructionKind::JumpIfZero { .. }
+            | InstructionKind::Call(_)
+            | InstructionKind::CallSymbol(_) => true,
+            InstructionKind::LoadAddr { .. }
+            | InstructionKind::LoadSymbolAddr { .. }
+            | InstructionKind::LoadGotAddr { .. }
+            | InstructionKind::LoadEffectiveAddr { .. }
+            | InstructionKind::Store { .. }
+            | InstructionKind::Mov { .. }
+            | InstructionKind::Push(_)
+            | InstructionKind::Pop(_)
+            | InstructionKind::Jump(_)
+            | InstructionKind::JumpIf { .. }
+            | InstructionKind::Return
+            | InstructionKind::Nop
+            | InstructionKind::Float(_, FloatKind::Add { .. })
+            | InstructionKind::Float(_, FloatKind::Mul { .. })
+            | InstructionKind::Float(_, FloatKind::FromInt { .. })
+            | InstructionKind::Float(_, FloatKind::ToInt { .. })
+            | InstructionKind::Float(_, FloatKind::Convert { .. }) => false,
+        }
+    }
+}
+
+/// The control flow between the instructions of a function, the first instruction is the entry.
+#[derive(Debug, Clone)]
+pub struct InstructionGraph {
+    nodes: Vec<usize>,
+    successors: Vec<Vec<usize>>,
+    predecessors: Vec<Vec<usize>>,
+}
+
+impl InstructionGraph {
+    pub fn new<R>(instructions: &Instructions<R>) -> Self {
+        let len = instructions.len();
+
+        // A jump to the end of the function leaves it, like falling through the last instruction.
+        let position = |target| {
+            let index = instructions
+                .position(target)
+                .unwrap_or_else(|| panic!("label {target} is not bound"));
+            Some(index).filter(|index| *index < len)
+        };
+
+        let mut successors = vec![Vec::new(); len];
+        let mut predecessors = vec![Vec::new(); len];
+
+        for (index, kind) in instructions.iter().enumerate() {
+            let next = Some(index + 1).filter(|next| *next < len);
+
+            let targets = match *kind {
+                InstructionKind::Jump(target) => position(target).into_iter().collect(),
+                InstructionKind::JumpIfZero { target, .. }
+                | InstructionKind::JumpIf { target, .. } => {
+                    next.into_iter().chain(position(target)).collect()
+                }
+                InstructionKind::Return => vec![],
+                _ => next.into_iter().collect(),
+            };
+
+            for target in &targets {
+                predecessors[*target].push(index);
+            }
+            successors[index] = targets;
+        }
+
+        let nodes = if len == 0 {
+            Vec::new()
+        } else {
+            reverse_postorder(0, |index| &successors[index])
+        };
+
+        Self {
+            nodes,
+            successors,
+            predecessors,
+        }
+    }
+}
+
+impl Graph for InstructionGraph {
+    type Node = usize;
+
+    fn nodes(&self) -> &[usize] {
+        &self.nodes
+    }
+
+    fn successors(&self, index: usize) -> &[usize] {
+        &self.successors[index]
+    }
+
+    fn predecessors(&self, index: usize) -> &[usize] {
+        &self.predecessors[index]
+    }
+}
+
+/// Computes the registers whose current value may be read later.
+pub struct Liveness<'a, R> {
+    instructions: &'a Instructions<R>,
+    /// The registers that are live after `ret`, such as the register of the return value.
+    live_at_return: BTreeSet<R>,
+}
+
+impl<'a, R: Copy + Ord> Liveness<'a, R> {
+    pub fn new(instructions: &'a Instructions<R>, live_at_return: BTreeSet<R>) -> Self {
+        Self {
+            instructions,
+            live_at_return,
+        }
+    }
+}
+
+impl<'a, R: Copy + Ord> Analysis for Liveness<'a, R> {
+    type Node = usize;
+    type Domain = BTreeSet<R>;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn boundary(&self) -> BTreeSet<R> {
+        self.live_at_return.clone()
+    }
+
+    fn bottom(&self) -> BTreeSet<R> {
+        BTreeSet::new()
+    }
+
+    fn transfer(&self, index: usize, live: &mut BTreeSet<R>) {
+        let kind = &self.instructions.instructions[index];
+
+        if let Some(def) = kind.def() {
+            live.remove(&def);
+        }
+        live.extend(kind.uses());
+    }
+}
+
+/// Computes whether the current value of the flags may be read later.
+///
+/// An instruction that clobbers the flags cannot be placed where the flags are live, such as
+/// between a `cmp` and the conditional jump that reads it.
+pub struct LiveFlags<'a, R> {
+    instructions: &'a Instructions<R>,
+}
+
+impl<'a, R> LiveFlags<'a, R> {
+    pub fn new(instructions: &'a Instructions<R>) -> Self {
+        Self { instructions }
+    }
+}
+
+impl<'a, R: Copy> Analysis for LiveFlags<'a, R> {
+    type Node = usize;
+    type Domain = bool;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn boundary(&self) -> bool {
+        false
+    }
+
+    fn bottom(&self) -> bool {
+        false
+    }
+
+    fn transfer(&self, index: usize, live: &mut bool) {
+        let kind = &self.instructions.instructions[index];
+
+        if kind.clobbers_flags() {
+            *live = false;
+        }
+        *live |= kind.reads_flags();
+    }
+}
+
+/// An instruction that writes a register, `site` is `None` if the register is defined when the
+/// function starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Definition<R> {
+    pub reg: R,
+    pub site: Option<usize>,
+}
+
+/// Computes the instructions that may have produced the current value of each register.
+pub struct ReachingDefinitions<'a, R> {
+    instructions: &'a Instructions<R>,
+    /// The registers defined when the function starts, such as the arguments.
+    defined_at_entry: BTreeSet<R>,
+}
+
+impl<'a, R: Copy + Ord> ReachingDefinitions<'a, R> {
+    pub fn new(instructions: &'a Instructions<R>, defined_at_entry: BTreeSet<R>) -> Self {
+        Self {
+            instructions,
+            defined_at_entry,
+        }
+    }
+}
+
+impl<'a, R: Copy + Ord> Analysis for ReachingDefinitions<'a, R> {
+    type Node = usize;
+    type Domain = BTreeSet<Definition<R>>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self) -> BTreeSet<Definition<R>> {
+        self.defined_at_entry
+            .iter()
+            .map(|reg| Definition {
+                reg: *reg,
+                site: None,
+            })
+            .collect()
+    }
+
+    fn bottom(&self) -> BTreeSet<Definition<R>> {
+        BTreeSet::new()
+    }
+
+    fn transfer(&self, index: usize, definitions: &mut BTreeSet<Definition<R>>) {
+        if let Some(reg) = self.instructions.instructions[index].def() {
+            definitions.retain(|definition| definition.reg != reg);
+            definitions.insert(Definition {
+                reg,
+                site: Some(index),
+            });
+        }
+    }
+}
+
+/// Computes the registers that have been written in every path that reaches an instruction.
+///
+/// As in [`crate::mir::dataflow::DefiniteInit`], the state is the set of registers that may still
+/// be uninitialized.
+pub struct DefiniteInit<'a, R> {
+    instructions: &'a Instructions<R>,
+    /// The registers that are not initialized when the function starts.
+    uninit_at_entry: BTreeSet<R>,
+}
+
+impl<'a, R: Copy + Ord> DefiniteInit<'a, R> {
+    pub fn new(instructions: &'a Instructions<R>, uninit_at_entry: BTreeSet<R>) -> Self {
+        Self {
+            instructions,
+            uninit_at_entry,
+        }
+    }
+}
+
+impl<'a, R: Copy + Ord> Analysis for DefiniteInit<'a, R> {
+    type Node = usize;
+    type Domain = BTreeSet<R>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self) -> BTreeSet<R> {
+        self.uninit_at_entry.clone()
+    }
+
+    fn bottom(&self) -> BTreeSet<R> {
+        BTreeSet::new()
+    }
+
+    fn transfer(&self, index: usize, maybe_uninit: &mut BTreeSet<R>) {
+        if let Some(reg) = self.instructions.instructions[index].def() {
+            maybe_uninit.remove(&reg);
+        }
+    }
+}
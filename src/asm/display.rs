@@ -0,0 +1,163 @@
+//! Textual representation of assembly.
+//!
+//! The syntax follows the one used in the notes, where the destination is the last operand:
+//! ```text
+//! duplicate:
+//!     loadi 0x0,rax
+//!     loadi 0x0,rsi
+//! .L1:
+//!     slt rsi,rdi,rdx
+//!     jz rdx,.L3
+//!     addi 0x2,rax
+//!     addi 0x1,rsi
+//!     jmp .L1
+//! .L3:
+//!     ret
+//! ```
+use std::fmt;
+
+use crate::asm::{
+    Address, Condition, FloatKind, Imm64, InstructionKind, Instructions, Label, Precision,
+};
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ".L{}", self.0)
+    }
+}
+
+/// Helper to display an immediate in hexadecimal.
+struct Hex(Imm64);
+
+impl fmt::Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 < 0 {
+            write!(f, "-{:#x}", self.0.unsigned_abs())
+        } else {
+            write!(f, "{:#x}", self.0)
+        }
+    }
+}
+
+impl Condition {
+    /// Returns the mnemonic of the conditional jump on this condition.
+    pub(super) fn jump_mnemonic(self) -> &'static str {
+        match self {
+            Condition::Equal => "je",
+            Condition::NotEqual => "jne",
+            Condition::Less => "jl",
+            Condition::GreaterOrEqual => "jge",
+            Condition::Greater => "jg",
+            Condition::LessOrEqual => "jle",
+            Condition::Above => "ja",
+            Condition::AboveOrEqual => "jae",
+            Condition::Below => "jb",
+            Condition::BelowOrEqual => "jbe",
+        }
+    }
+}
+
+impl Precision {
+    /// Returns the suffix of the mnemonics of the instructions on floats of this precision.
+    pub(super) fn suffix(self) -> &'static str {
+        match self {
+            Precision::Single => "ss",
+            Precision::Double => "sd",
+        }
+    }
+}
+
+impl<R: fmt::Display> fmt::Display for Address<i32, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.offset < 0 {
+            write!(f, "{}{}", self.base, Hex(self.offset.into()))
+        } else {
+            write!(f, "{}+{}", self.base, Hex(self.offset.into()))
+        }
+    }
+}
+
+impl<R: fmt::Display> fmt::Display for Instructions<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let labels = self.labels_by_position();
+
+        for (index, kind) in self.instructions.iter().enumerate() {
+            for label in &labels[index] {
+                writeln!(f, "{label}:")?;
+            }
+
+            write!(f, "    ")?;
+
+            match kind {
+                InstructionKind::LoadImm { src, dst } => write!(f, "loadi {},{dst}", Hex(*src)),
+                InstructionKind::LoadAddr { src, dst } => write!(f, "load {src},{dst}"),
+                InstructionKind::LoadSymbolAddr { src, dst } => {
+                    write!(f, "lea {},{dst}", self.symbol_name(*src))
+                }
+                InstructionKind::LoadGotAddr { src, dst } => {
+                    write!(f, "load got {},{dst}", self.symbol_name(*src))
+                }
+                InstructionKind::LoadEffectiveAddr { base, index, dst } => {
+                    write!(f, "lea {base}+{index},{dst}")
+                }
+                InstructionKind::Store { src, dst } => write!(f, "store {src},{dst}"),
+                InstructionKind::Mov { src, dst } => write!(f, "mov {src},{dst}"),
+                InstructionKind::Push(reg) => write!(f, "push {reg}"),
+                InstructionKind::Pop(reg) => write!(f, "pop {reg}"),
+                InstructionKind::Add { src, dst } => write!(f, "add {src},{dst}"),
+                InstructionKind::AddImm { src, dst } => {
+                    write!(f, "addi {},{dst}", Hex((*src).into()))
+                }
+                InstructionKind::Mul { src, dst } => write!(f, "mul {src},{dst}"),
+                InstructionKind::SetIfLess { src1, src2, dst } => {
+                    write!(f, "slt {src1},{src2},{dst}")
+                }
+                InstructionKind::Compare { src1, src2 } => write!(f, "cmp {src1},{src2}"),
+                InstructionKind::CompareImm { src, imm } => {
+                    write!(f, "cmpi {src},{}", Hex((*imm).into()))
+                }
+                InstructionKind::Test { src1, src2 } => write!(f, "test {src1},{src2}"),
+                InstructionKind::Jump(target) => write!(f, "jmp {target}"),
+                InstructionKind::JumpIfZero { src, target } => write!(f, "jz {src},{target}"),
+                InstructionKind::JumpIf { cond, target } => {
+                    write!(f, "{} {target}", cond.jump_mnemonic())
+                }
+                InstructionKind::Return => write!(f, "ret"),
+                InstructionKind::Call(target) => write!(f, "call {target}"),
+                InstructionKind::CallSymbol(target) => {
+                    write!(f, "call {}", self.symbol_name(*target))
+                }
+                InstructionKind::Nop => write!(f, "nop"),
+                InstructionKind::Float(precision, kind) => {
+                    let suffix = precision.suffix();
+                    match kind {
+                        FloatKind::Add { src, dst } => write!(f, "add{suffix} {src},{dst}"),
+                        FloatKind::Mul { src, dst } => write!(f, "mul{suffix} {src},{dst}"),
+                        FloatKind::SetIfLess { src1, src2, dst } => {
+                            write!(f, "slt{suffix} {src1},{src2},{dst}")
+                        }
+                        FloatKind::Compare { src1, src2 } => {
+                            write!(f, "ucomi{suffix} {src1},{src2}")
+                        }
+                        FloatKind::FromInt { src, dst } => write!(f, "cvtsi2{suffix} {src},{dst}"),
+                        FloatKind::ToInt { src, dst } => write!(f, "cvtt{suffix}2si {src},{dst}"),
+                        // The suffix is the precision of the result.
+                        FloatKind::Convert { src, dst } => match precision {
+                            Precision::Single => write!(f, "cvtsd2ss {src},{dst}"),
+                            Precision::Double => write!(f, "cvtss2sd {src},{dst}"),
+                        },
+                    }
+                }
+            }?;
+
+            writeln!(f)?;
+        }
+
+        // Labels bound to the end of the function.
+        for label in &labels[self.instructions.len()] {
+            writeln!(f, "{label}:")?;
+        }
+
+        Ok(())
+    }
+}
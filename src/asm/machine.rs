@@ -0,0 +1,462 @@
+//! Machine functions: the instructions of a function grouped in basic blocks.
+//!
+//! Every block ends with an explicit [`MachineTerminator`], even the ones that fall through to the
+//! next block in [`Instructions`], so the blocks can be laid out in any order. The layout is the
+//! list of blocks in the order they are emitted, and jumps to the next block in the layout are only
+//! removed when the function is turned back into [`Instructions`].
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::asm::{Binding, Condition, Instruction, InstructionKind, Instructions, Label};
+use crate::dataflow::{reverse_postorder, Analysis, Direction, Graph};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MachineBlockId(usize);
+
+impl fmt::Display for MachineBlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "mbb{}", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub enum MachineTerminator<R> {
+    Jump(MachineBlockId),
+    /// Jumps to `then_bb` if `src` is zero and to `else_bb` otherwise.
+    JumpIfZero {
+        src: R,
+        then_bb: MachineBlockId,
+        else_bb: MachineBlockId,
+    },
+    /// Jumps to `then_bb` if the flags satisfy `cond` and to `else_bb` otherwise.
+    JumpIf {
+        cond: Condition,
+        then_bb: MachineBlockId,
+        else_bb: MachineBlockId,
+    },
+    Return,
+    /// Leaves the function by running past the end of its code. Only the last block of the layout
+    /// can end like this.
+    Exit,
+}
+
+impl<R: Copy> MachineTerminator<R> {
+    pub fn successors(&self) -> Vec<MachineBlockId> {
+        match *self {
+            MachineTerminator::Jump(target) => vec![target],
+            MachineTerminator::JumpIfZero {
+                then_bb, else_bb, ..
+            }
+            | MachineTerminator::JumpIf {
+                then_bb, else_bb, ..
+            } => vec![then_bb, else_bb],
+            MachineTerminator::Return | MachineTerminator::Exit => vec![],
+        }
+    }
+
+    pub fn successors_mut(&mut self) -> Vec<&mut MachineBlockId> {
+        match self {
+            MachineTerminator::Jump(target) => vec![target],
+            MachineTerminator::JumpIfZero {
+                then_bb, else_bb, ..
+            }
+            | MachineTerminator::JumpIf {
+                then_bb, else_bb, ..
+            } => vec![then_bb, else_bb],
+            MachineTerminator::Return | MachineTerminator::Exit => vec![],
+        }
+    }
+
+    /// Returns the registers read by this terminator.
+    pub fn uses(&self) -> Vec<R> {
+        match *self {
+            MachineTerminator::JumpIfZero { src, .. } => vec![src],
+            MachineTerminator::Jump(_)
+            | MachineTerminator::JumpIf { .. }
+            | MachineTerminator::Return
+            | MachineTerminator::Exit => vec![],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MachineBlock<R> {
+    /// The labels bound to the start of the block.
+    pub labels: Vec<Label>,
+    /// The instructions of the block, none of them is a jump or a `ret`.
+    pub instructions: Vec<InstructionKind<R>>,
+    pub terminator: MachineTerminator<R>,
+}
+
+#[derive(Debug)]
+pub struct MachineFunction<R> {
+    pub blocks: BTreeMap<MachineBlockId, MachineBlock<R>>,
+    /// The order in which the blocks are emitted, starting with the entry block. Blocks that are
+    /// not in the layout are dropped when the function is turned back into [`Instructions`].
+    pub layout: Vec<MachineBlockId>,
+    /// The labels and symbols of the function, without instructions.
+    shell: Instructions<R>,
+}
+
+impl<R: Copy> MachineFunction<R> {
+    /// Splits `instructions` into blocks, which start at the first instruction, at every label and
+    /// after every jump or `ret`. The end of the function is a block of its own, terminated by
+    /// [`MachineTerminator::Exit`], if it can be reached. The initial layout follows the order of
+    /// the instructions.
+    pub fn new(instructions: Instructions<R>) -> Self {
+        let labels = instructions.labels_by_position();
+        let len = instructions.len();
+
+        let mut starts = BTreeSet::from([0]);
+        for (index, kind) in instructions.iter().enumerate() {
+            if !labels[index].is_empty() {
+                starts.insert(index);
+            }
+            if is_branch(kind) {
+                starts.insert(index + 1);
+            }
+        }
+
+        let falls_off = !matches!(
+            instructions.instructions.last(),
+            Some(InstructionKind::Jump(_) | InstructionKind::Return)
+        );
+        if falls_off || !labels[len].is_empty() {
+            starts.insert(len);
+        } else {
+            starts.remove(&len);
+        }
+
+        let starts = starts.into_iter().collect::<Vec<_>>();
+        let ids = starts
+            .iter()
+            .enumerate()
+            .map(|(id, start)| (*start, MachineBlockId(id)))
+            .collect::<BTreeMap<_, _>>();
+        // Every bound label is at the start of a block.
+        let bound = (0..instructions.labels.len())
+            .map(|label| Some(ids[&instructions.position(Label(label))?]))
+            .collect::<Vec<_>>();
+        let block_of =
+            |label: Label| bound[label.0].unwrap_or_else(|| panic!("label {label} is not bound"));
+
+        let Instructions {
+            instructions: kinds,
+            labels: mut bindings,
+            symbols,
+        } = instructions;
+        let mut kinds = kinds.into_iter();
+        let mut blocks = BTreeMap::new();
+
+        for (id, start) in starts.iter().enumerate() {
+            let end = starts.get(id + 1).copied().unwrap_or(len);
+            let next = starts.get(id + 1).map(|_| MachineBlockId(id + 1));
+
+            let mut body = kinds.by_ref().take(end - start).collect::<Vec<_>>();
+            let terminator = match body.last() {
+                Some(&InstructionKind::Jump(target)) => MachineTerminator::Jump(block_of(target)),
+                Some(&InstructionKind::JumpIfZero { src, target }) => {
+                    MachineTerminator::JumpIfZero {
+                        src,
+                        then_bb: block_of(target),
+                        else_bb: next.expect("conditional jump falls off the function"),
+                    }
+                }
+                Some(&InstructionKind::JumpIf { cond, target }) => MachineTerminator::JumpIf {
+                    cond,
+                    then_bb: block_of(target),
+                    else_bb: next.expect("conditional jump falls off the function"),
+                },
+                Some(InstructionKind::Return) => MachineTerminator::Return,
+                _ => match next {
+                    Some(next) => MachineTerminator::Jump(next),
+                    None => MachineTerminator::Exit,
+                },
+            };
+            if body.last().is_some_and(is_branch) {
+                body.pop();
+            }
+
+            blocks.insert(
+                MachineBlockId(id),
+                MachineBlock {
+                    labels: labels[*start].clone(),
+                    instructions: body,
+                    terminator,
+                },
+            );
+        }
+
+        // The labels are bound again when the function is turned back into instructions.
+        for binding in &mut bindings {
+            if let Binding::Position(_) = binding {
+                *binding = Binding::Unbound;
+            }
+        }
+        let shell = Instructions {
+            instructions: Vec::new(),
+            labels: bindings,
+            symbols,
+        };
+
+        Self {
+            layout: blocks.keys().copied().collect(),
+            blocks,
+            shell,
+        }
+    }
+
+    pub fn entry(&self) -> MachineBlockId {
+        self.layout[0]
+    }
+
+    /// Adds a label that is not bound to any block.
+    pub fn add_label(&mut self) -> Label {
+        self.shell.add_label()
+    }
+
+    /// Emits the blocks in the order of the layout. Jumps to the next block are omitted, and a
+    /// conditional jump whose first target is the next block is inverted when possible.
+    pub fn into_instructions(self) -> Instructions<R> {
+        let Self {
+            mut blocks,
+            layout,
+            mut shell,
+        } = self;
+
+        let mut labels = BlockLabels {
+            labels: blocks
+                .iter()
+                .filter_map(|(id, block)| Some((*id, *block.labels.first()?)))
+                .collect(),
+            starts: BTreeMap::new(),
+        };
+
+        for (index, id) in layout.iter().enumerate() {
+            let next = layout.get(index + 1).copied();
+            let block = blocks.remove(id).expect("block is laid out twice");
+
+            labels.starts.insert(*id, shell.len());
+            for label in &block.labels {
+                shell.bind_label(*label);
+            }
+            if block.labels.is_empty() {
+                if let Some(label) = labels.labels.get(id) {
+                    shell.bind_label(*label);
+                }
+            }
+            for kind in block.instructions {
+                emit(&mut shell, kind);
+            }
+
+            match block.terminator {
+                MachineTerminator::Jump(target) => {
+                    if next != Some(target) {
+                        let target = labels.get(&mut shell, target);
+                        emit(&mut shell, InstructionKind::Jump(target));
+                    }
+                }
+                MachineTerminator::JumpIfZero {
+                    src,
+                    then_bb,
+                    else_bb,
+                } => {
+                    let target = labels.get(&mut shell, then_bb);
+                    emit(&mut shell, InstructionKind::JumpIfZero { src, target });
+                    if next != Some(else_bb) {
+                        let target = labels.get(&mut shell, else_bb);
+                        emit(&mut shell, InstructionKind::Jump(target));
+                    }
+                }
+                MachineTerminator::JumpIf {
+                    cond,
+                    then_bb,
+                    else_bb,
+                } => {
+                    let (cond, then_bb, else_bb) = if next == Some(then_bb) {
+                        (cond.negate(), else_bb, then_bb)
+                    } else {
+                        (cond, then_bb, else_bb)
+                    };
+                    if then_bb != else_bb {
+                        let target = labels.get(&mut shell, then_bb);
+                        emit(&mut shell, InstructionKind::JumpIf { cond, target });
+                    }
+                    if next != Some(else_bb) {
+                        let target = labels.get(&mut shell, else_bb);
+                        emit(&mut shell, InstructionKind::Jump(target));
+                    }
+                }
+                MachineTerminator::Return => emit(&mut shell, InstructionKind::Return),
+                MachineTerminator::Exit => {
+                    assert!(next.is_none(), "{id} leaves the function before the end");
+                }
+            }
+        }
+
+        shell
+    }
+}
+
+fn emit<R>(instructions: &mut Instructions<R>, kind: InstructionKind<R>) {
+    instructions.add_instruction(Instruction {
+        labels: Vec::new(),
+        kind,
+    });
+}
+
+/// The labels used to jump to each block while a function is emitted.
+struct BlockLabels {
+    labels: BTreeMap<MachineBlockId, Label>,
+    /// The position of each block emitted so far.
+    starts: BTreeMap<MachineBlockId, usize>,
+}
+
+impl BlockLabels {
+    /// Returns the label of a block, adding one if the block has none. Blocks only get a label if
+    /// a jump to them is emitted.
+    fn get<R>(&mut self, instructions: &mut Instructions<R>, id: MachineBlockId) -> Label {
+        *self.labels.entry(id).or_insert_with(|| {
+            let label = instructions.add_label();
+            if let Some(start) = self.starts.get(&id) {
+                instructions.bind_label_at(label, *start);
+            }
+            label
+        })
+    }
+}
+
+fn is_branch<R>(kind: &InstructionKind<R>) -> bool {
+    matches!(
+        kind,
+        InstructionKind::Jump(_)
+            | InstructionKind::JumpIfZero { .. }
+            | InstructionKind::JumpIf { .. }
+            | InstructionKind::Return
+    )
+}
+
+/// The edges between the blocks of a machine function.
+///
+/// As with [`crate::mir::cfg::Cfg`], blocks that cannot be reached from the entry block are only
+/// seen by [`MachineCfg::successors`] and [`MachineCfg::predecessors`].
+#[derive(Debug, Clone)]
+pub struct MachineCfg {
+    entry: MachineBlockId,
+    successors: BTreeMap<MachineBlockId, Vec<MachineBlockId>>,
+    predecessors: BTreeMap<MachineBlockId, Vec<MachineBlockId>>,
+    reverse_postorder: Vec<MachineBlockId>,
+}
+
+impl MachineCfg {
+    pub fn new<R: Copy>(func: &MachineFunction<R>) -> Self {
+        let entry = func.entry();
+
+        let mut successors = BTreeMap::new();
+        let mut predecessors = func
+            .blocks
+            .keys()
+            .map(|id| (*id, Vec::new()))
+            .collect::<BTreeMap<_, _>>();
+
+        for (id, block) in &func.blocks {
+            let targets = block.terminator.successors();
+            for target in &targets {
+                predecessors.get_mut(target).unwrap().push(*id);
+            }
+            successors.insert(*id, targets);
+        }
+
+        let reverse_postorder = reverse_postorder(entry, |id| &successors[&id]);
+
+        Self {
+            entry,
+            successors,
+            predecessors,
+            reverse_postorder,
+        }
+    }
+
+    pub fn entry(&self) -> MachineBlockId {
+        self.entry
+    }
+
+    pub fn successors(&self, id: MachineBlockId) -> &[MachineBlockId] {
+        &self.successors[&id]
+    }
+
+    pub fn predecessors(&self, id: MachineBlockId) -> &[MachineBlockId] {
+        &self.predecessors[&id]
+    }
+
+    /// Returns the blocks reachable from the entry block in reverse postorder.
+    pub fn reverse_postorder(&self) -> &[MachineBlockId] {
+        &self.reverse_postorder
+    }
+
+    pub fn is_reachable(&self, id: MachineBlockId) -> bool {
+        self.reverse_postorder.contains(&id)
+    }
+}
+
+impl Graph for MachineCfg {
+    type Node = MachineBlockId;
+
+    fn nodes(&self) -> &[MachineBlockId] {
+        self.reverse_postorder()
+    }
+
+    fn successors(&self, id: MachineBlockId) -> &[MachineBlockId] {
+        MachineCfg::successors(self, id)
+    }
+
+    fn predecessors(&self, id: MachineBlockId) -> &[MachineBlockId] {
+        MachineCfg::predecessors(self, id)
+    }
+}
+
+/// Computes the registers whose current value may be read later, at the boundaries of each block.
+///
+/// This is the block-level version of [`crate::asm::dataflow::Liveness`], with the same
+/// assumptions about calls and `ret`.
+pub struct BlockLiveness<'a, R> {
+    func: &'a MachineFunction<R>,
+    /// The registers that are live when the function returns or falls off its end.
+    live_at_return: BTreeSet<R>,
+}
+
+impl<'a, R: Copy + Ord> BlockLiveness<'a, R> {
+    pub fn new(func: &'a MachineFunction<R>, live_at_return: BTreeSet<R>) -> Self {
+        Self {
+            func,
+            live_at_return,
+        }
+    }
+}
+
+impl<'a, R: Copy + Ord> Analysis for BlockLiveness<'a, R> {
+    type Node = MachineBlockId;
+    type Domain = BTreeSet<R>;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn boundary(&self) -> BTreeSet<R> {
+        self.live_at_return.clone()
+    }
+
+    fn bottom(&self) -> BTreeSet<R> {
+        BTreeSet::new()
+    }
+
+    fn transfer(&self, id: MachineBlockId, live: &mut BTreeSet<R>) {
+        let block = &self.func.blocks[&id];
+
+        live.extend(block.terminator.uses());
+        for kind in block.instructions.iter().rev() {
+            if let Some(def) = kind.def() {
+                live.remove(&def);
+            }
+            live.extend(kind.uses());
+        }
+    }
+}
@@ -48,6 +48,54 @@ macro_rules! reg {
     (r15) => {
         $crate::asm::x86_64::Register::R15
     };
+    (xmm0) => {
+        $crate::asm::x86_64::Register::Xmm0
+    };
+    (xmm1) => {
+        $crate::asm::x86_64::Register::Xmm1
+    };
+    (xmm2) => {
+        $crate::asm::x86_64::Register::Xmm2
+    };
+    (xmm3) => {
+        $crate::asm::x86_64::Register::Xmm3
+    };
+    (xmm4) => {
+        $crate::asm::x86_64::Register::Xmm4
+    };
+    (xmm5) => {
+        $crate::asm::x86_64::Register::Xmm5
+    };
+    (xmm6) => {
+        $crate::asm::x86_64::Register::Xmm6
+    };
+    (xmm7) => {
+        $crate::asm::x86_64::Register::Xmm7
+    };
+    (xmm8) => {
+        $crate::asm::x86_64::Register::Xmm8
+    };
+    (xmm9) => {
+        $crate::asm::x86_64::Register::Xmm9
+    };
+    (xmm10) => {
+        $crate::asm::x86_64::Register::Xmm10
+    };
+    (xmm11) => {
+        $crate::asm::x86_64::Register::Xmm11
+    };
+    (xmm12) => {
+        $crate::asm::x86_64::Register::Xmm12
+    };
+    (xmm13) => {
+        $crate::asm::x86_64::Register::Xmm13
+    };
+    (xmm14) => {
+        $crate::asm::x86_64::Register::Xmm14
+    };
+    (xmm15) => {
+        $crate::asm::x86_64::Register::Xmm15
+    };
     ($expr:expr) => {
         $expr
     };
@@ -61,6 +109,25 @@ macro_rules! instruction_kind {
             dst: $crate::reg!($($reg)+),
         }
     };
+    (lea sym {$sym:expr},{$($reg:tt)+}) => {
+        $crate::asm::InstructionKind::LoadSymbolAddr {
+            src: $sym,
+            dst: $crate::reg!($($reg)+),
+        }
+    };
+    (load got {$sym:expr},{$($reg:tt)+}) => {
+        $crate::asm::InstructionKind::LoadGotAddr {
+            src: $sym,
+            dst: $crate::reg!($($reg)+),
+        }
+    };
+    (lea {$($base:tt)+}+{$($index:tt)+},{$($reg:tt)+}) => {
+        $crate::asm::InstructionKind::LoadEffectiveAddr {
+            base: $crate::reg!($($base)+),
+            index: $crate::reg!($($index)+),
+            dst: $crate::reg!($($reg)+),
+        }
+    };
     (load {$($addr:tt)+}+{$imm32:expr},{$($reg:tt)+}) => {
         $crate::asm::InstructionKind::LoadAddr {
             src: $crate::asm::Address {
@@ -112,6 +179,30 @@ macro_rules! instruction_kind {
             target: {$loc},
         }
     };
+    (cmp {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Compare {
+            src1: $crate::reg!($($reg1)*),
+            src2: $crate::reg!($($reg2)*),
+        }
+    };
+    (cmpi {$($reg:tt)+},{$imm32:expr}) => {
+        $crate::asm::InstructionKind::CompareImm {
+            src: $crate::reg!($($reg)+),
+            imm: $imm32,
+        }
+    };
+    (test {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Test {
+            src1: $crate::reg!($($reg1)*),
+            src2: $crate::reg!($($reg2)*),
+        }
+    };
+    (jcc {$cond:expr},{$loc:expr}) => {
+        $crate::asm::InstructionKind::JumpIf {
+            cond: $cond,
+            target: {$loc},
+        }
+    };
     (slt {$($reg1:tt)*},{$($reg2:tt)*},{$($reg3:tt)*}) => {
         $crate::asm::InstructionKind::SetIfLess {
             src1: $crate::reg!($($reg1)*),
@@ -119,9 +210,146 @@ macro_rules! instruction_kind {
             dst: $crate::reg!($($reg3)*),
         }
     };
+    (mul {$($reg1:tt)*}, {$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Mul {
+            src: $crate::reg!($($reg1)*),
+            dst: $crate::reg!($($reg2)*),
+        }
+    };
+    (addsd {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Double,
+            $crate::asm::FloatKind::Add {
+                src: $crate::reg!($($reg1)*),
+                dst: $crate::reg!($($reg2)*),
+            },
+        )
+    };
+    (addss {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Single,
+            $crate::asm::FloatKind::Add {
+                src: $crate::reg!($($reg1)*),
+                dst: $crate::reg!($($reg2)*),
+            },
+        )
+    };
+    (mulsd {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Double,
+            $crate::asm::FloatKind::Mul {
+                src: $crate::reg!($($reg1)*),
+                dst: $crate::reg!($($reg2)*),
+            },
+        )
+    };
+    (mulss {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Single,
+            $crate::asm::FloatKind::Mul {
+                src: $crate::reg!($($reg1)*),
+                dst: $crate::reg!($($reg2)*),
+            },
+        )
+    };
+    (sltsd {$($reg1:tt)*},{$($reg2:tt)*},{$($reg3:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Double,
+            $crate::asm::FloatKind::SetIfLess {
+                src1: $crate::reg!($($reg1)*),
+                src2: $crate::reg!($($reg2)*),
+                dst: $crate::reg!($($reg3)*),
+            },
+        )
+    };
+    (sltss {$($reg1:tt)*},{$($reg2:tt)*},{$($reg3:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Single,
+            $crate::asm::FloatKind::SetIfLess {
+                src1: $crate::reg!($($reg1)*),
+                src2: $crate::reg!($($reg2)*),
+                dst: $crate::reg!($($reg3)*),
+            },
+        )
+    };
+    (ucomisd {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Double,
+            $crate::asm::FloatKind::Compare {
+                src1: $crate::reg!($($reg1)*),
+                src2: $crate::reg!($($reg2)*),
+            },
+        )
+    };
+    (ucomiss {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Single,
+            $crate::asm::FloatKind::Compare {
+                src1: $crate::reg!($($reg1)*),
+                src2: $crate::reg!($($reg2)*),
+            },
+        )
+    };
+    (cvtsi2sd {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Double,
+            $crate::asm::FloatKind::FromInt {
+                src: $crate::reg!($($reg1)*),
+                dst: $crate::reg!($($reg2)*),
+            },
+        )
+    };
+    (cvtsi2ss {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Single,
+            $crate::asm::FloatKind::FromInt {
+                src: $crate::reg!($($reg1)*),
+                dst: $crate::reg!($($reg2)*),
+            },
+        )
+    };
+    (cvttsd2si {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Double,
+            $crate::asm::FloatKind::ToInt {
+                src: $crate::reg!($($reg1)*),
+                dst: $crate::reg!($($reg2)*),
+            },
+        )
+    };
+    (cvttss2si {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Single,
+            $crate::asm::FloatKind::ToInt {
+                src: $crate::reg!($($reg1)*),
+                dst: $crate::reg!($($reg2)*),
+            },
+        )
+    };
+    (cvtss2sd {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Double,
+            $crate::asm::FloatKind::Convert {
+                src: $crate::reg!($($reg1)*),
+                dst: $crate::reg!($($reg2)*),
+            },
+        )
+    };
+    (cvtsd2ss {$($reg1:tt)*},{$($reg2:tt)*}) => {
+        $crate::asm::InstructionKind::Float(
+            $crate::asm::Precision::Single,
+            $crate::asm::FloatKind::Convert {
+                src: $crate::reg!($($reg1)*),
+                dst: $crate::reg!($($reg2)*),
+            },
+        )
+    };
     (ret) => {
         $crate::asm::InstructionKind::Return
     };
+    (call sym {$sym:expr}) => {
+        $crate::asm::InstructionKind::CallSymbol($sym)
+    };
     (call {$($reg:tt)*}) => {
         $crate::asm::InstructionKind::Call($crate::reg!($($reg)*))
     };
@@ -134,14 +362,14 @@ macro_rules! instruction_kind {
 macro_rules! code {
     ($label:ident: $($tokens:tt)+) => {
         $crate::asm::Instruction {
-            label: Some($label),
+            labels: vec![$label],
             kind: $crate::instruction_kind!($($tokens)*)
         }
     };
 
     ($($tokens:tt)*) => {
         $crate::asm::Instruction {
-            label: None,
+            labels: Vec::new(),
             kind: $crate::instruction_kind!($($tokens)*)
         }
     };
@@ -1,71 +1,411 @@
+pub mod aarch64;
+pub mod dataflow;
+mod display;
+pub mod machine;
 mod macros;
-mod optimize;
+pub mod optimize;
+pub mod parse;
 pub mod portable;
+pub mod riscv64;
 pub mod x86_64;
 
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
 pub type Imm32 = i32;
 pub type Imm64 = i64;
 
+#[derive(Debug)]
 pub struct Address<I, R> {
     pub base: R,
     pub offset: I,
 }
 
+#[derive(Debug)]
 pub enum InstructionKind<R> {
     LoadImm { src: Imm64, dst: R },
     LoadAddr { src: Address<Imm32, R>, dst: R },
+    LoadSymbolAddr { src: Symbol, dst: R },
+    LoadGotAddr { src: Symbol, dst: R },
+    LoadEffectiveAddr { base: R, index: R, dst: R },
     Store { src: R, dst: Address<Imm32, R> },
     Mov { src: R, dst: R },
     Push(R),
     Pop(R),
     Add { src: R, dst: R },
     AddImm { src: Imm32, dst: R },
+    Mul { src: R, dst: R },
     SetIfLess { src1: R, src2: R, dst: R },
+    Compare { src1: R, src2: R },
+    CompareImm { src: R, imm: Imm32 },
+    Test { src1: R, src2: R },
     Jump(Label),
     JumpIfZero { src: R, target: Label },
+    JumpIf { cond: Condition, target: Label },
     Return,
     Call(R),
+    CallSymbol(Symbol),
     Nop,
+    Float(Precision, FloatKind<R>),
+}
+
+/// An instruction on floats of the precision given by [`InstructionKind::Float`].
+///
+/// `SetIfLess` sets `dst` to whether `src1 < src2`, which does not hold if either of them is NaN,
+/// and `Compare` compares two floats like [`InstructionKind::Compare`], see [`Condition`] for the
+/// conditions that can be checked afterwards. `FromInt` and `ToInt` convert from and to 64-bit
+/// integers, truncating towards zero, and `Convert` converts a float from the other precision.
+#[derive(Debug)]
+pub enum FloatKind<R> {
+    Add { src: R, dst: R },
+    Mul { src: R, dst: R },
+    SetIfLess { src1: R, src2: R, dst: R },
+    Compare { src1: R, src2: R },
+    FromInt { src: R, dst: R },
+    ToInt { src: R, dst: R },
+    Convert { src: R, dst: R },
 }
 
+/// The precision of the floats that an instruction operates on.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    /// IEEE 754 single precision, an `f32`.
+    Single,
+    /// IEEE 754 double precision, an `f64`.
+    Double,
+}
+
+/// A condition checked by [`InstructionKind::JumpIf`] on the flags set by the last
+/// [`InstructionKind::Compare`] of `src1` with `src2`, or of [`InstructionKind::CompareImm`] of
+/// `src` with `imm`. The comparisons of [`Condition::Less`] to [`Condition::LessOrEqual`] are
+/// signed and the ones of [`Condition::Above`] to [`Condition::BelowOrEqual`] are unsigned.
+///
+/// After an [`InstructionKind::Test`], which computes the bitwise and of its operands, only
+/// [`Condition::Equal`] and [`Condition::NotEqual`] are meaningful: they check whether the result
+/// is zero. After a test of a register with itself, [`Condition::Less`] and
+/// [`Condition::GreaterOrEqual`] check the sign of the register too.
+///
+/// After an [`FloatKind::Compare`], only the unsigned conditions are meaningful. The
+/// operands are unordered if either of them is NaN, which only [`Condition::Above`] and
+/// [`Condition::AboveOrEqual`] treat as false.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    /// `src1 == src2`.
+    Equal,
+    /// `src1 != src2`.
+    NotEqual,
+    /// `src1 < src2`.
+    Less,
+    /// `src1 >= src2`.
+    GreaterOrEqual,
+    /// `src1 > src2`.
+    Greater,
+    /// `src1 <= src2`.
+    LessOrEqual,
+    /// `src1 > src2`.
+    Above,
+    /// `src1 >= src2`.
+    AboveOrEqual,
+    /// `src1 < src2`.
+    Below,
+    /// `src1 <= src2`.
+    BelowOrEqual,
+}
+
+impl Condition {
+    /// Returns the condition that holds exactly when this one does not.
+    pub fn negate(self) -> Self {
+        match self {
+            Self::Equal => Self::NotEqual,
+            Self::NotEqual => Self::Equal,
+            Self::Less => Self::GreaterOrEqual,
+            Self::GreaterOrEqual => Self::Less,
+            Self::Greater => Self::LessOrEqual,
+            Self::LessOrEqual => Self::Greater,
+            Self::Above => Self::BelowOrEqual,
+            Self::AboveOrEqual => Self::Below,
+            Self::Below => Self::AboveOrEqual,
+            Self::BelowOrEqual => Self::Above,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Label(usize);
 
 #[derive(Default)]
 struct LabelGenerator(usize);
 
+/// A symbol defined outside of the current function.
+///
+/// The address of a symbol is unknown when assembling, so every use of a symbol produces a
+/// [`Relocation`] that must be resolved by the linker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol(usize);
+
+/// A location in the assembled code that must be patched with the address of a symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    /// The offset of the bytes to be patched, relative to the start of the assembled code.
+    pub offset: usize,
+    /// The name of the referenced symbol.
+    pub symbol: String,
+    pub kind: RelocationKind,
+    /// The constant to be added to the address of the symbol.
+    pub addend: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationKind {
+    /// A displacement used as the target of a call, relative to the patched location.
+    Branch,
+    /// A 32-bit displacement to the symbol, relative to the patched location.
+    Relative,
+    /// A 32-bit displacement to the global offset table entry of the symbol, relative to the
+    /// patched location.
+    GotRelative,
+    /// The distance in 4 KiB pages from the patched instruction to the symbol, the immediate of
+    /// an AArch64 `adrp`.
+    PageRelative,
+    /// The offset of the symbol inside its 4 KiB page, the immediate of an AArch64 `add`.
+    PageOffset,
+    /// The distance in 4 KiB pages from the patched instruction to the global offset table entry
+    /// of the symbol, the immediate of an AArch64 `adrp`.
+    GotPageRelative,
+    /// The offset of the global offset table entry of the symbol inside its 4 KiB page, the
+    /// immediate of an AArch64 `ldr`.
+    GotPageOffset,
+    /// The upper 20 bits of the displacement to the symbol, relative to the patched instruction,
+    /// the immediate of a RISC-V `auipc`.
+    PcRelativeHigh,
+    /// The upper 20 bits of the displacement to the global offset table entry of the symbol,
+    /// relative to the patched instruction, the immediate of a RISC-V `auipc`.
+    GotPcRelativeHigh,
+    /// The lower 12 bits of the displacement computed by the RISC-V `auipc` right before the
+    /// patched instruction, the immediate of an `addi` or an `ld`.
+    PcRelativeLow,
+}
+
+#[derive(Debug)]
+pub enum AssemblerError {
+    MissingLabelLocation(Label),
+    /// The distance to a label does not fit in the immediate of the instruction that jumps to it.
+    LabelOutOfRange(Label),
+}
+
+impl fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingLabelLocation(label) => {
+                write!(f, "location of label {label:?} is missing")
+            }
+            Self::LabelOutOfRange(label) => write!(f, "label {label:?} is out of range"),
+        }
+    }
+}
+
+impl Error for AssemblerError {}
+
+/// An instruction together with the labels bound to its position when it is added.
+#[derive(Debug)]
 pub struct Instruction<R> {
-    pub label: Option<Label>,
+    pub labels: Vec<Label>,
     pub kind: InstructionKind<R>,
 }
 
+/// Where a label points to.
+#[derive(Debug, Clone, Copy)]
+enum Binding {
+    Unbound,
+    /// The label points to the instruction with this index, or to the end of the function if the
+    /// index is the number of instructions.
+    Position(usize),
+    /// The label points wherever another label points.
+    Alias(Label),
+}
+
+/// The instructions of a function.
+///
+/// Labels are bound to positions instead of instructions: removing an instruction binds its labels
+/// to the next one, so instructions can be inserted and removed freely. Several labels can be bound
+/// to the same position, and a label can be made an alias of another one, forming a union-find
+/// where the root of every set holds the position.
+#[derive(Debug)]
 pub struct Instructions<R> {
-    instructions: Vec<Instruction<R>>,
-    labels_len: usize,
+    instructions: Vec<InstructionKind<R>>,
+    labels: Vec<Binding>,
+    symbols: Vec<String>,
 }
 
 impl<R> Instructions<R> {
     pub fn new() -> Self {
         Self {
             instructions: Vec::new(),
-            labels_len: 0,
+            labels: Vec::new(),
+            symbols: Vec::new(),
         }
     }
+
+    /// Adds a label that is not bound to any position yet.
     pub fn add_label(&mut self) -> Label {
-        let label = Label(self.labels_len);
-        self.labels_len += 1;
+        self.labels.push(Binding::Unbound);
+        Label(self.labels.len() - 1)
+    }
+
+    /// Binds `label` to the end of the function, which is the position of the next instruction
+    /// added.
+    pub fn bind_label(&mut self, label: Label) {
+        self.bind_label_at(label, self.instructions.len());
+    }
+
+    /// Binds `label` and its aliases to the position `index`.
+    pub fn bind_label_at(&mut self, label: Label, index: usize) {
+        assert!(index <= self.instructions.len(), "position out of bounds");
+        let root = self.resolve(label);
+        self.labels[root.0] = Binding::Position(index);
+    }
+
+    /// Returns the label that holds the position of `label`, following its aliases.
+    pub fn resolve(&self, mut label: Label) -> Label {
+        while let Binding::Alias(target) = self.labels[label.0] {
+            label = target;
+        }
         label
     }
 
+    /// Returns the position `label` is bound to, if any.
+    pub fn position(&self, label: Label) -> Option<usize> {
+        match self.labels[self.resolve(label).0] {
+            Binding::Position(index) => Some(index),
+            Binding::Unbound | Binding::Alias(_) => None,
+        }
+    }
+
+    /// Makes `label` point wherever `target` points from now on, even if `target` is bound to
+    /// another position later.
+    pub fn alias(&mut self, label: Label, target: Label) {
+        let root = self.resolve(label);
+        let target = self.resolve(target);
+        if root != target {
+            self.labels[root.0] = Binding::Alias(target);
+        }
+    }
+
+    /// Makes `label1` and `label2` point to the same position, which must be the position of
+    /// either of them if they are bound. Returns the label that holds the position.
+    pub fn union(&mut self, label1: Label, label2: Label) -> Label {
+        let (root1, root2) = (self.resolve(label1), self.resolve(label2));
+
+        match (self.position(root1), self.position(root2)) {
+            (Some(index1), Some(index2)) if index1 != index2 => {
+                panic!("cannot unite labels bound to different positions")
+            }
+            (None, Some(_)) => {
+                self.alias(root1, root2);
+                root2
+            }
+            _ => {
+                self.alias(root2, root1);
+                root1
+            }
+        }
+    }
+
+    /// Returns the labels bound to each position, including the end of the function. Aliases are
+    /// included and every list is sorted.
+    pub fn labels_by_position(&self) -> Vec<Vec<Label>> {
+        let mut labels = vec![Vec::new(); self.instructions.len() + 1];
+
+        for label in (0..self.labels.len()).map(Label) {
+            if let Some(index) = self.position(label) {
+                labels[index].push(label);
+            }
+        }
+
+        labels
+    }
+
+    /// Returns the [`Symbol`] for `name`, adding it if this is the first time it is used.
+    pub fn add_symbol(&mut self, name: &str) -> Symbol {
+        let index = match self.symbols.iter().position(|symbol| symbol == name) {
+            Some(index) => index,
+            None => {
+                self.symbols.push(name.to_owned());
+                self.symbols.len() - 1
+            }
+        };
+
+        Symbol(index)
+    }
+
+    pub fn symbol_name(&self, symbol: Symbol) -> &str {
+        &self.symbols[symbol.0]
+    }
+
+    /// Adds an instruction at the end of the function and binds its labels to it.
     pub fn add_instruction(&mut self, instruction: Instruction<R>) {
-        self.instructions.push(instruction)
+        self.insert(self.instructions.len(), instruction)
+    }
+
+    /// Inserts an instruction before the one at `index` and binds its labels to it. The labels
+    /// bound to `index` point to the new instruction.
+    pub fn insert(&mut self, index: usize, instruction: Instruction<R>) {
+        self.splice(index..index, vec![instruction.kind]);
+
+        for label in instruction.labels {
+            self.bind_label_at(label, index);
+        }
+    }
+
+    /// Removes the instruction at `index`. The labels bound to it point to the next instruction.
+    pub fn remove(&mut self, index: usize) -> InstructionKind<R> {
+        self.splice(index..index + 1, Vec::new()).remove(0)
+    }
+
+    /// Replaces the instructions in `range` with `replacement` and returns the removed ones. The
+    /// labels bound to positions in `range` point to the start of the replacement.
+    pub fn splice(
+        &mut self,
+        range: Range<usize>,
+        replacement: Vec<InstructionKind<R>>,
+    ) -> Vec<InstructionKind<R>> {
+        let inserted = replacement.len();
+        let removed = self
+            .instructions
+            .splice(range.clone(), replacement)
+            .collect::<Vec<_>>();
+
+        for binding in &mut self.labels {
+            if let Binding::Position(index) = binding {
+                if range.contains(index) {
+                    *index = range.start;
+                } else if *index >= range.end && *index > range.start {
+                    *index = *index - removed.len() + inserted;
+                }
+            }
+        }
+
+        removed
     }
 
     pub fn len(&self) -> usize {
         self.instructions.len()
     }
 
-    pub fn get_mut(&mut self, index: usize) -> Option<&mut Instruction<R>> {
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&InstructionKind<R>> {
+        self.instructions.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut InstructionKind<R>> {
         self.instructions.get_mut(index)
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = &InstructionKind<R>> {
+        self.instructions.iter()
+    }
 }
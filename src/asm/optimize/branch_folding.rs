@@ -0,0 +1,145 @@
+//! Simplifications of the control flow between the blocks of a [`MachineFunction`].
+use std::mem;
+
+use crate::asm::machine::{MachineBlockId, MachineCfg, MachineFunction, MachineTerminator};
+use crate::asm::Instructions;
+use crate::pass::Pass;
+
+/// Folds branches in the machine blocks of a function:
+/// - jumps to a block that only jumps somewhere else go there directly,
+/// - a conditional jump whose targets are the same block becomes a jump,
+/// - blocks that cannot be reached are removed,
+/// - a block that is only entered by a jump from another block is appended to it.
+///
+/// Jumps to the next block in the layout are removed when the blocks are emitted again.
+pub struct BranchFolding;
+
+impl<R: Copy> Pass<Instructions<R>> for BranchFolding {
+    fn name(&self) -> &'static str {
+        "branch-folding"
+    }
+
+    fn run(&self, instructions: &mut Instructions<R>) -> bool {
+        let len = instructions.len();
+        let mut func = MachineFunction::new(mem::replace(instructions, Instructions::new()));
+
+        let mut changed = thread_jumps(&mut func);
+        changed |= fold_conditionals(&mut func);
+        changed |= remove_unreachable(&mut func);
+        changed |= merge_blocks(&mut func);
+
+        *instructions = func.into_instructions();
+        changed || instructions.len() != len
+    }
+}
+
+/// Turns the conditional jumps on the flags whose targets are the same block into jumps. Jumps on
+/// the value of a register are kept, as they overwrite the flags.
+fn fold_conditionals<R: Copy>(func: &mut MachineFunction<R>) -> bool {
+    let mut changed = false;
+
+    for block in func.blocks.values_mut() {
+        if let MachineTerminator::JumpIf {
+            then_bb, else_bb, ..
+        } = block.terminator
+        {
+            if then_bb == else_bb {
+                block.terminator = MachineTerminator::Jump(then_bb);
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+/// Returns the block reached by following the jumps of empty blocks from `id`, or `id` itself if
+/// the jumps form a cycle.
+fn jump_destination<R: Copy>(func: &MachineFunction<R>, id: MachineBlockId) -> MachineBlockId {
+    let mut visited = vec![id];
+    let mut current = id;
+
+    while let Some(block) = func.blocks.get(&current) {
+        let MachineTerminator::Jump(target) = block.terminator else {
+            break;
+        };
+        if !block.instructions.is_empty() {
+            break;
+        }
+        if visited.contains(&target) {
+            return id;
+        }
+        visited.push(target);
+        current = target;
+    }
+
+    current
+}
+
+fn thread_jumps<R: Copy>(func: &mut MachineFunction<R>) -> bool {
+    let mut changed = false;
+
+    let ids = func.blocks.keys().copied().collect::<Vec<_>>();
+    for id in ids {
+        let targets = func.blocks[&id].terminator.successors();
+        let destinations = targets
+            .iter()
+            .map(|target| jump_destination(func, *target))
+            .collect::<Vec<_>>();
+
+        let terminator = &mut func.blocks.get_mut(&id).unwrap().terminator;
+        for (target, destination) in terminator.successors_mut().into_iter().zip(destinations) {
+            if *target != destination {
+                *target = destination;
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+fn remove_unreachable<R: Copy>(func: &mut MachineFunction<R>) -> bool {
+    let cfg = MachineCfg::new(func);
+    let len = func.layout.len();
+
+    func.layout.retain(|id| cfg.is_reachable(*id));
+    func.blocks.retain(|id, _| cfg.is_reachable(*id));
+
+    func.layout.len() != len
+}
+
+/// Appends the blocks that are only entered by a jump from another block to that block. The
+/// labels of the appended block are dropped, as no other jump can use them.
+fn merge_blocks<R: Copy>(func: &mut MachineFunction<R>) -> bool {
+    let mut changed = false;
+
+    'merge: loop {
+        let cfg = MachineCfg::new(func);
+
+        for id in func.layout.clone() {
+            let MachineTerminator::Jump(target) = func.blocks[&id].terminator else {
+                continue;
+            };
+            if target == id
+                || target == func.entry()
+                || cfg.predecessors(target) != [id]
+                || matches!(func.blocks[&target].terminator, MachineTerminator::Exit)
+            {
+                continue;
+            }
+
+            let appended = func.blocks.remove(&target).unwrap();
+            func.layout.retain(|id| *id != target);
+
+            let block = func.blocks.get_mut(&id).unwrap();
+            block.instructions.extend(appended.instructions);
+            block.terminator = appended.terminator;
+
+            changed = true;
+            continue 'merge;
+        }
+
+        return changed;
+    }
+}
@@ -0,0 +1,54 @@
+//! Optimization passes over assembly.
+mod branch_folding;
+mod peephole;
+
+use std::fmt::Display;
+
+use crate::asm::Instructions;
+use crate::pass::{Dump, OptLevel, Pass, Pipeline};
+
+use super::InstructionKind;
+
+pub use branch_folding::BranchFolding;
+pub use peephole::{patterns, Pattern, Peephole, RedundantLoads, Rewrite};
+
+/// Returns the assembly optimization passes in the order they are run.
+pub fn pipeline<R: Copy + Ord + 'static>() -> Pipeline<Instructions<R>> {
+    vec![
+        (OptLevel::O1, Box::new(RedundantLoads)),
+        (OptLevel::O1, Box::new(Peephole::default())),
+        (OptLevel::O1, Box::new(BranchFolding)),
+        (OptLevel::O1, Box::new(DeadJumps)),
+    ]
+}
+
+impl<R: Display> Dump for Instructions<R> {
+    fn dump(&self, name: &str) -> String {
+        format!("{name}:\n{self}")
+    }
+}
+
+/// Removes jumps to the next instruction.
+pub struct DeadJumps;
+
+impl<R> Pass<Instructions<R>> for DeadJumps {
+    fn name(&self) -> &'static str {
+        "dead-jumps"
+    }
+
+    fn run(&self, instructions: &mut Instructions<R>) -> bool {
+        let mut changed = false;
+
+        // Removing a jump only moves the instructions after it, which have been visited already.
+        for index in (0..instructions.len()).rev() {
+            if let Some(InstructionKind::Jump(target)) = instructions.get(index) {
+                if instructions.position(*target) == Some(index + 1) {
+                    instructions.remove(index);
+                    changed = true;
+                }
+            }
+        }
+
+        changed
+    }
+}
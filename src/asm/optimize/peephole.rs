@@ -0,0 +1,245 @@
+//! Peephole optimizations: rewrites of short windows of consecutive instructions.
+//!
+//! Each [`Pattern`] matches a fixed number of instructions and returns their replacement, so the
+//! rewrites are written as slice patterns over the kinds of the instructions:
+//! ```text
+//! mov a,b; add c,b  =>  lea a+c,b
+//! ```
+//! A window is only rewritten if the instructions after the first one have no label, as they
+//! could be reached without executing the whole window. The labels of the first instruction are
+//! bound to the start of the replacement.
+use std::collections::BTreeMap;
+
+use crate::asm::dataflow::{InstructionGraph, LiveFlags};
+use crate::asm::{Condition, Imm64, InstructionKind, Instructions};
+use crate::dataflow::solve;
+use crate::pass::Pass;
+
+/// Returns the replacement of the kinds of the instructions in a window, or `None` if the
+/// pattern does not match. The second argument is whether the flags may be read after the window.
+pub type Rewrite<R> = fn(&[&InstructionKind<R>], bool) -> Option<Vec<InstructionKind<R>>>;
+
+/// A rewrite of `len` consecutive instructions.
+pub struct Pattern<R> {
+    pub len: usize,
+    pub rewrite: Rewrite<R>,
+}
+
+/// Returns the patterns run by default.
+pub fn patterns<R: Copy + Eq>() -> Vec<Pattern<R>> {
+    vec![
+        // mov r,r => nothing
+        Pattern {
+            len: 1,
+            rewrite: |window, _| match window {
+                [InstructionKind::Mov { src, dst }] if src == dst => Some(vec![]),
+                _ => None,
+            },
+        },
+        // mov a,b; add c,b => lea a+c,b
+        //
+        // `lea` does not set the flags, so the flags of the `add` must not be read.
+        Pattern {
+            len: 2,
+            rewrite: |window, flags_live| match window {
+                [InstructionKind::Mov { src, dst }, InstructionKind::Add {
+                    src: other,
+                    dst: sum,
+                }] if !flags_live && dst == sum && src != dst && other != dst && src != other => {
+                    Some(vec![InstructionKind::LoadEffectiveAddr {
+                        base: *src,
+                        index: *other,
+                        dst: *dst,
+                    }])
+                }
+                _ => None,
+            },
+        },
+        // cmpi r,0; jcc target => test r,r; jcc target
+        //
+        // `test` has a shorter encoding. It sets the zero and sign flags like the comparison, but
+        // only those are the same on every target, so the jump must not read any other flag and
+        // the flags must not be read after it.
+        Pattern {
+            len: 2,
+            rewrite: |window, flags_live| match window {
+                [InstructionKind::CompareImm { src, imm: 0 }, InstructionKind::JumpIf { cond, target }]
+                    if !flags_live
+                        && matches!(
+                            cond,
+                            Condition::Equal
+                                | Condition::NotEqual
+                                | Condition::Less
+                                | Condition::GreaterOrEqual
+                        ) =>
+                {
+                    Some(vec![
+                        InstructionKind::Test {
+                            src1: *src,
+                            src2: *src,
+                        },
+                        InstructionKind::JumpIf {
+                            cond: *cond,
+                            target: *target,
+                        },
+                    ])
+                }
+                _ => None,
+            },
+        },
+    ]
+}
+
+/// Rewrites the instructions with a list of [`Pattern`]s until none of them matches.
+pub struct Peephole<R> {
+    patterns: Vec<Pattern<R>>,
+}
+
+impl<R> Peephole<R> {
+    pub fn new(patterns: Vec<Pattern<R>>) -> Self {
+        Self { patterns }
+    }
+}
+
+impl<R: Copy + Eq> Default for Peephole<R> {
+    fn default() -> Self {
+        Self::new(patterns())
+    }
+}
+
+impl<R: Copy> Pass<Instructions<R>> for Peephole<R> {
+    fn name(&self) -> &'static str {
+        "peephole"
+    }
+
+    fn run(&self, instructions: &mut Instructions<R>) -> bool {
+        let mut changed = false;
+        let mut facts = Facts::new(instructions);
+
+        let mut index = 0;
+        while index < instructions.len() {
+            if self.rewrite_at(instructions, index, &facts) {
+                // The replacement may match another pattern, so the same index is tried again.
+                changed = true;
+                facts = Facts::new(instructions);
+            } else {
+                index += 1;
+            }
+        }
+
+        changed
+    }
+}
+
+impl<R: Copy> Peephole<R> {
+    /// Rewrites the window starting at `start` with the first pattern that matches it. Returns
+    /// whether the instructions changed.
+    fn rewrite_at(&self, instructions: &mut Instructions<R>, start: usize, facts: &Facts) -> bool {
+        for pattern in &self.patterns {
+            let end = start + pattern.len;
+            let Some(window) = instructions.instructions.get(start..end) else {
+                continue;
+            };
+            if facts.labeled[start + 1..end].contains(&true) {
+                continue;
+            }
+
+            let kinds = window.iter().collect::<Vec<_>>();
+            let Some(replacement) = (pattern.rewrite)(&kinds, facts.flags_live[end - 1]) else {
+                continue;
+            };
+
+            instructions.splice(start..end, replacement);
+            return true;
+        }
+
+        false
+    }
+}
+
+/// What the patterns need to know about each instruction.
+struct Facts {
+    /// Whether the flags may be read after the instruction. The flags are assumed to be live after
+    /// unreachable instructions.
+    flags_live: Vec<bool>,
+    /// Whether a label is bound to the position of the instruction.
+    labeled: Vec<bool>,
+}
+
+impl Facts {
+    fn new<R: Copy>(instructions: &Instructions<R>) -> Self {
+        let results = solve(
+            &InstructionGraph::new(instructions),
+            &LiveFlags::new(instructions),
+        );
+
+        let flags_live = (0..instructions.len())
+            .map(|index| results.after.get(&index).copied().unwrap_or(true))
+            .collect();
+        let labeled = instructions
+            .labels_by_position()
+            .iter()
+            .map(|labels| !labels.is_empty())
+            .collect();
+
+        Self {
+            flags_live,
+            labeled,
+        }
+    }
+}
+
+/// Removes the `loadi` of a value that is already in the register.
+///
+/// The values are tracked along straight-line code: they are forgotten at every label, because
+/// the instruction can be reached from elsewhere, and at every call, because the callee can
+/// overwrite any register.
+pub struct RedundantLoads;
+
+impl<R: Copy + Ord> Pass<Instructions<R>> for RedundantLoads {
+    fn name(&self) -> &'static str {
+        "redundant-loads"
+    }
+
+    fn run(&self, instructions: &mut Instructions<R>) -> bool {
+        let labels = instructions.labels_by_position();
+        let mut values = BTreeMap::<R, Imm64>::new();
+        let mut redundant = Vec::new();
+
+        for (index, kind) in instructions.iter().enumerate() {
+            if !labels[index].is_empty() {
+                values.clear();
+            }
+
+            match *kind {
+                InstructionKind::LoadImm { src, dst } if values.get(&dst) == Some(&src) => {
+                    redundant.push(index);
+                }
+                InstructionKind::LoadImm { src, dst } => {
+                    values.insert(dst, src);
+                }
+                InstructionKind::Mov { src, dst } => match values.get(&src).copied() {
+                    Some(value) => {
+                        values.insert(dst, value);
+                    }
+                    None => {
+                        values.remove(&dst);
+                    }
+                },
+                InstructionKind::Call(_) | InstructionKind::CallSymbol(_) => values.clear(),
+                ref kind => {
+                    if let Some(def) = kind.def() {
+                        values.remove(&def);
+                    }
+                }
+            }
+        }
+
+        // Removing the instructions from the end keeps the indices of the remaining ones.
+        for index in redundant.iter().rev() {
+            instructions.remove(*index);
+        }
+
+        !redundant.is_empty()
+    }
+}
@@ -0,0 +1,397 @@
+//! Parser for the textual representation of assembly described in [`crate::asm::display`].
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::asm::{
+    Address, Condition, FloatKind, Imm32, Instruction, InstructionKind, Instructions, Label,
+    Precision,
+};
+use crate::parse::{ParseError, Parser, TokenKind};
+
+/// Parses a sequence of functions. Each function starts with a global label, such as `start:`,
+/// and every local label must start with a dot.
+pub fn parse_functions<R: FromStr>(
+    src: &str,
+) -> Result<Vec<(String, Instructions<R>)>, ParseError> {
+    let mut parser = Parser::new(src)?;
+    let mut functions = Vec::new();
+
+    while !parser.is_finished() {
+        let name = parser.expect_ident("function label")?;
+        if name.starts_with('.') {
+            return Err(parser.error(format!("local label `{name}` outside of a function")));
+        }
+        parser.expect_punct(':')?;
+
+        if functions.iter().any(|(function, _)| *function == name) {
+            return Err(parser.error(format!("function `{name}` is defined more than once")));
+        }
+
+        let instructions = FunctionParser {
+            parser: &mut parser,
+            instructions: Instructions::new(),
+            labels: BTreeMap::new(),
+        }
+        .parse()?;
+
+        functions.push((name, instructions));
+    }
+
+    Ok(functions)
+}
+
+const CONDITIONS: [Condition; 10] = [
+    Condition::Equal,
+    Condition::NotEqual,
+    Condition::Less,
+    Condition::GreaterOrEqual,
+    Condition::Greater,
+    Condition::LessOrEqual,
+    Condition::Above,
+    Condition::AboveOrEqual,
+    Condition::Below,
+    Condition::BelowOrEqual,
+];
+
+/// Returns the precision of a float instruction whose mnemonic ends with `suffix` for single
+/// precision.
+fn precision(mnemonic: &str, suffix: &str) -> Precision {
+    if mnemonic.ends_with(suffix) {
+        Precision::Single
+    } else {
+        Precision::Double
+    }
+}
+
+struct FunctionParser<'p, R> {
+    parser: &'p mut Parser,
+    instructions: Instructions<R>,
+    /// The labels used in the function and whether they have been defined.
+    labels: BTreeMap<String, (Label, Option<ParseError>)>,
+}
+
+impl<'p, R: FromStr> FunctionParser<'p, R> {
+    fn parse(mut self) -> Result<Instructions<R>, ParseError> {
+        loop {
+            match (self.parser.peek(), self.parser.peek_second()) {
+                (Some(TokenKind::Ident(ident)), Some(TokenKind::Punct(':')))
+                    if ident.starts_with('.') =>
+                {
+                    let name = self.parser.expect_ident("label")?;
+                    self.parser.expect_punct(':')?;
+
+                    let (label, undefined) = self.label(name.clone());
+                    let label = *label;
+                    if undefined.take().is_none() {
+                        return Err(self
+                            .parser
+                            .error(format!("label `{name}` is defined more than once")));
+                    }
+
+                    self.instructions.bind_label(label);
+                }
+                // A global label starts the next function.
+                (None, _) | (_, Some(TokenKind::Punct(':'))) => break,
+                _ => {
+                    let kind = self.parse_instruction()?;
+                    self.instructions.add_instruction(Instruction {
+                        labels: Vec::new(),
+                        kind,
+                    });
+                }
+            }
+        }
+
+        if let Some((_, Some(error))) = self.labels.into_values().find(|(_, error)| error.is_some())
+        {
+            return Err(error);
+        }
+
+        Ok(self.instructions)
+    }
+
+    /// Returns the label called `name` and the error to be reported if it is never defined.
+    fn label(&mut self, name: String) -> &mut (Label, Option<ParseError>) {
+        let error = self.parser.error(format!("label `{name}` is not defined"));
+        let instructions = &mut self.instructions;
+
+        self.labels
+            .entry(name)
+            .or_insert_with(|| (instructions.add_label(), Some(error)))
+    }
+
+    fn parse_register(&mut self) -> Result<R, ParseError> {
+        let error = self.parser.error("expected register");
+
+        match self.parser.peek() {
+            Some(TokenKind::Ident(ident)) => {
+                let reg = ident.parse().map_err(|_| error)?;
+                self.parser.expect_ident("register")?;
+                Ok(reg)
+            }
+            _ => Err(error),
+        }
+    }
+
+    fn parse_imm32(&mut self) -> Result<Imm32, ParseError> {
+        let error = self.parser.error("immediate does not fit in 32 bits");
+        let imm = self.parser.expect_number("immediate")?;
+        Imm32::try_from(imm).map_err(|_| error)
+    }
+
+    fn parse_address(&mut self) -> Result<Address<Imm32, R>, ParseError> {
+        let base = self.parse_register()?;
+        // Negative offsets are parsed as a single number.
+        self.parser.eat_punct('+');
+        let offset = self.parse_imm32()?;
+
+        Ok(Address { base, offset })
+    }
+
+    fn parse_label(&mut self) -> Result<Label, ParseError> {
+        let name = self.parser.expect_ident("label")?;
+        Ok(self.label(name).0)
+    }
+
+    fn parse_instruction(&mut self) -> Result<InstructionKind<R>, ParseError> {
+        let error = self.parser.error("unknown instruction");
+        let mnemonic = self.parser.expect_ident("instruction")?;
+
+        let kind = match mnemonic.as_str() {
+            "loadi" => {
+                let src = self.parser.expect_number("immediate")?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::LoadImm {
+                    src,
+                    dst: self.parse_register()?,
+                }
+            }
+            "load" if self.parser.eat_keyword("got") => {
+                let src = self.parser.expect_ident("symbol")?;
+                let src = self.instructions.add_symbol(&src);
+                self.parser.expect_punct(',')?;
+                InstructionKind::LoadGotAddr {
+                    src,
+                    dst: self.parse_register()?,
+                }
+            }
+            "load" => {
+                let src = self.parse_address()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::LoadAddr {
+                    src,
+                    dst: self.parse_register()?,
+                }
+            }
+            "lea" => match self.parse_register() {
+                Ok(base) => {
+                    self.parser.expect_punct('+')?;
+                    let index = self.parse_register()?;
+                    self.parser.expect_punct(',')?;
+                    InstructionKind::LoadEffectiveAddr {
+                        base,
+                        index,
+                        dst: self.parse_register()?,
+                    }
+                }
+                Err(_) => {
+                    let src = self.parser.expect_ident("register or symbol")?;
+                    let src = self.instructions.add_symbol(&src);
+                    self.parser.expect_punct(',')?;
+                    InstructionKind::LoadSymbolAddr {
+                        src,
+                        dst: self.parse_register()?,
+                    }
+                }
+            },
+            "store" => {
+                let src = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Store {
+                    src,
+                    dst: self.parse_address()?,
+                }
+            }
+            "mov" => {
+                let src = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Mov {
+                    src,
+                    dst: self.parse_register()?,
+                }
+            }
+            "push" => InstructionKind::Push(self.parse_register()?),
+            "pop" => InstructionKind::Pop(self.parse_register()?),
+            "add" => {
+                let src = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Add {
+                    src,
+                    dst: self.parse_register()?,
+                }
+            }
+            "addi" => {
+                let src = self.parse_imm32()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::AddImm {
+                    src,
+                    dst: self.parse_register()?,
+                }
+            }
+            "mul" => {
+                let src = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Mul {
+                    src,
+                    dst: self.parse_register()?,
+                }
+            }
+            "slt" => {
+                let src1 = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                let src2 = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::SetIfLess {
+                    src1,
+                    src2,
+                    dst: self.parse_register()?,
+                }
+            }
+            "cmp" => {
+                let src1 = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Compare {
+                    src1,
+                    src2: self.parse_register()?,
+                }
+            }
+            "cmpi" => {
+                let src = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::CompareImm {
+                    src,
+                    imm: self.parse_imm32()?,
+                }
+            }
+            "test" => {
+                let src1 = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Test {
+                    src1,
+                    src2: self.parse_register()?,
+                }
+            }
+            "jmp" => InstructionKind::Jump(self.parse_label()?),
+            "jz" => {
+                let src = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::JumpIfZero {
+                    src,
+                    target: self.parse_label()?,
+                }
+            }
+            "je" | "jne" | "jl" | "jge" | "jg" | "jle" | "ja" | "jae" | "jb" | "jbe" => {
+                let cond = CONDITIONS
+                    .into_iter()
+                    .find(|cond| cond.jump_mnemonic() == mnemonic)
+                    .unwrap();
+                InstructionKind::JumpIf {
+                    cond,
+                    target: self.parse_label()?,
+                }
+            }
+            "ret" => InstructionKind::Return,
+            "call" => match self.parse_register() {
+                Ok(target) => InstructionKind::Call(target),
+                Err(_) => {
+                    let target = self.parser.expect_ident("register or symbol")?;
+                    InstructionKind::CallSymbol(self.instructions.add_symbol(&target))
+                }
+            },
+            "nop" => InstructionKind::Nop,
+            "addsd" | "addss" => {
+                let src = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Float(
+                    precision(&mnemonic, "ss"),
+                    FloatKind::Add {
+                        src,
+                        dst: self.parse_register()?,
+                    },
+                )
+            }
+            "mulsd" | "mulss" => {
+                let src = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Float(
+                    precision(&mnemonic, "ss"),
+                    FloatKind::Mul {
+                        src,
+                        dst: self.parse_register()?,
+                    },
+                )
+            }
+            "sltsd" | "sltss" => {
+                let src1 = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                let src2 = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Float(
+                    precision(&mnemonic, "ss"),
+                    FloatKind::SetIfLess {
+                        src1,
+                        src2,
+                        dst: self.parse_register()?,
+                    },
+                )
+            }
+            "ucomisd" | "ucomiss" => {
+                let src1 = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Float(
+                    precision(&mnemonic, "ss"),
+                    FloatKind::Compare {
+                        src1,
+                        src2: self.parse_register()?,
+                    },
+                )
+            }
+            "cvtsi2sd" | "cvtsi2ss" => {
+                let src = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Float(
+                    precision(&mnemonic, "ss"),
+                    FloatKind::FromInt {
+                        src,
+                        dst: self.parse_register()?,
+                    },
+                )
+            }
+            "cvttsd2si" | "cvttss2si" => {
+                let src = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Float(
+                    precision(&mnemonic, "ss2si"),
+                    FloatKind::ToInt {
+                        src,
+                        dst: self.parse_register()?,
+                    },
+                )
+            }
+            "cvtsd2ss" | "cvtss2sd" => {
+                let src = self.parse_register()?;
+                self.parser.expect_punct(',')?;
+                InstructionKind::Float(
+                    precision(&mnemonic, "ss"),
+                    FloatKind::Convert {
+                        src,
+                        dst: self.parse_register()?,
+                    },
+                )
+            }
+            _ => return Err(error),
+        };
+
+        Ok(kind)
+    }
+}
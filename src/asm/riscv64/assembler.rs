@@ -0,0 +1,556 @@
+use std::collections::BTreeSet;
+
+use crate::asm::riscv64::register::Register;
+use crate::asm::{
+    Address, AssemblerError, Condition, Imm32, Imm64, InstructionKind, Instructions, Label,
+    Relocation, RelocationKind, Symbol,
+};
+
+/// The register used to hold immediates and addresses that do not fit in the instruction that
+/// uses them. This is `t6`, so the assembled code must not use it for anything else.
+const TEMPORARY_REGISTER: Register = Register::T6;
+
+const OP: u32 = 0b011_0011;
+const OP_IMM: u32 = 0b001_0011;
+const OP_IMM_32: u32 = 0b001_1011;
+const LOAD: u32 = 0b000_0011;
+const STORE: u32 = 0b010_0011;
+const BRANCH: u32 = 0b110_0011;
+const LUI: u32 = 0b011_0111;
+const AUIPC: u32 = 0b001_0111;
+const JAL: u32 = 0b110_1111;
+const JALR: u32 = 0b110_0111;
+
+/// The `funct3` field of the branches. Flipping the lowest bit of any of them negates the
+/// condition.
+const BEQ: u32 = 0b000;
+const BNE: u32 = 0b001;
+const BLT: u32 = 0b100;
+const BGE: u32 = 0b101;
+const BLTU: u32 = 0b110;
+const BGEU: u32 = 0b111;
+
+/// Assembles `instructions` into `buf`.
+///
+/// Returns the relocations that must be applied to `buf` to resolve the symbols used by the
+/// instructions.
+///
+/// A conditional branch can only jump 4 KiB away, so the branches whose labels are out of range
+/// are replaced by the opposite branch over a `jal` to the label, and the instructions are
+/// assembled again until every branch reaches its label. A replaced branch is never restored,
+/// so this always ends.
+pub fn assemble(
+    instructions: Instructions<Register>,
+    buf: &mut Vec<u8>,
+) -> Result<Vec<Relocation>, AssemblerError> {
+    let labels = instructions.labels_by_position();
+    let start = buf.len();
+    let mut long_branches = BTreeSet::new();
+
+    loop {
+        buf.truncate(start);
+
+        let mut asm = Assembler {
+            buf: &mut *buf,
+            label_locations: vec![None; instructions.labels.len()],
+            patches: Vec::with_capacity(instructions.labels.len()),
+            symbols: &instructions.symbols,
+            relocations: Vec::new(),
+            long_branches: &long_branches,
+            comparison: None,
+        };
+
+        for (index, (kind, labels)) in instructions.iter().zip(&labels).enumerate() {
+            asm.bind_labels(labels);
+            asm.assemble_instruction(index, kind);
+        }
+        // Labels bound to the end of the function.
+        asm.bind_labels(&labels[labels.len() - 1]);
+
+        let out_of_range = asm.out_of_range_branches()?;
+        if out_of_range.is_empty() {
+            return asm.finish();
+        }
+        long_branches.extend(out_of_range);
+    }
+}
+
+/// The operands of the last [`InstructionKind::Compare`] or [`InstructionKind::Test`].
+///
+/// RISC-V has no flags: the branches compare two registers themselves, so a comparison is not
+/// assembled until the conditional jump that reads it.
+#[derive(Clone, Copy)]
+enum Comparison {
+    Compare(Register, Register),
+    CompareImm(Register, Imm32),
+    Test(Register, Register),
+}
+
+impl Comparison {
+    fn reads(self, reg: Register) -> bool {
+        match self {
+            Self::Compare(src1, src2) | Self::Test(src1, src2) => reg == src1 || reg == src2,
+            Self::CompareImm(src, _) => reg == src,
+        }
+    }
+}
+
+struct Assembler<'asm> {
+    buf: &'asm mut Vec<u8>,
+    label_locations: Vec<Option<usize>>,
+    patches: Vec<Patch>,
+    symbols: &'asm [String],
+    relocations: Vec<Relocation>,
+    /// The indices of the conditional branches that jump over a `jal` to their label.
+    long_branches: &'asm BTreeSet<usize>,
+    comparison: Option<Comparison>,
+}
+
+impl<'asm> Assembler<'asm> {
+    /// Adds a new patch for a [`Label`] in the instruction that starts at the current location of
+    /// the instruction pointer and was assembled from the instruction at `index`.
+    fn add_patch(&mut self, label: Label, kind: PatchKind, index: usize) {
+        self.patches.push(Patch {
+            label,
+            start: self.buf.len(),
+            kind,
+            index,
+        })
+    }
+
+    /// Adds a new relocation for a [`Symbol`] in the instruction that starts at the current
+    /// location of the instruction pointer.
+    fn add_relocation(&mut self, symbol: Symbol, kind: RelocationKind) {
+        self.relocations.push(Relocation {
+            offset: self.buf.len(),
+            symbol: self.symbols[symbol.0].clone(),
+            kind,
+            // Every RISC-V relocation is relative to the start of the instruction.
+            addend: 0,
+        })
+    }
+
+    fn push_instruction(&mut self, instruction: u32) {
+        self.buf.extend_from_slice(&instruction.to_le_bytes())
+    }
+
+    /// Sets the location of `labels` to the current location of the instruction pointer.
+    ///
+    /// The pending comparison is dropped because it may not hold when jumping to the labels.
+    fn bind_labels(&mut self, labels: &[Label]) {
+        if !labels.is_empty() {
+            self.comparison = None;
+        }
+        for label in labels {
+            self.label_locations[label.0] = Some(self.buf.len());
+        }
+    }
+
+    /// Assembles the instruction at `index`.
+    pub fn assemble_instruction(&mut self, index: usize, kind: &InstructionKind<Register>) {
+        // An instruction that clobbers the flags or overwrites a compared register ends the
+        // pending comparison.
+        if let Some(comparison) = self.comparison {
+            if kind.clobbers_flags() || kind.def().is_some_and(|dst| comparison.reads(dst)) {
+                self.comparison = None;
+            }
+        }
+
+        match *kind {
+            InstructionKind::LoadImm { src, dst } => self.assemble_load_imm(src, dst),
+            InstructionKind::LoadAddr { ref src, dst } => {
+                self.assemble_memory(LOAD, dst, src.base, src.offset)
+            }
+            InstructionKind::LoadSymbolAddr { src, dst } => self.assemble_load_pc_relative(
+                src,
+                dst,
+                RelocationKind::PcRelativeHigh,
+                i_type(0, dst, 0b000, dst, OP_IMM),
+            ),
+            InstructionKind::LoadGotAddr { src, dst } => self.assemble_load_pc_relative(
+                src,
+                dst,
+                RelocationKind::GotPcRelativeHigh,
+                i_type(0, dst, 0b011, dst, LOAD),
+            ),
+            InstructionKind::LoadEffectiveAddr { base, index, dst } => {
+                self.assemble_add_registers(base, index, dst)
+            }
+            InstructionKind::Store { src, ref dst } => self.assemble_store(src, dst),
+            InstructionKind::Mov { src, dst } => self.assemble_mov(src, dst),
+            InstructionKind::Push(reg) => self.assemble_push(reg),
+            InstructionKind::Pop(reg) => self.assemble_pop(reg),
+            InstructionKind::Add { src, dst } => self.assemble_add_registers(dst, src, dst),
+            InstructionKind::AddImm { src, dst } => self.assemble_add_imm(src, dst),
+            InstructionKind::Mul { src, dst } => self.assemble_mul(src, dst),
+            InstructionKind::SetIfLess { src1, src2, dst } => {
+                self.assemble_set_if_less(src1, src2, dst)
+            }
+            InstructionKind::Compare { src1, src2 } => {
+                self.comparison = Some(Comparison::Compare(src1, src2))
+            }
+            InstructionKind::CompareImm { src, imm } => {
+                self.comparison = Some(Comparison::CompareImm(src, imm))
+            }
+            InstructionKind::Test { src1, src2 } => {
+                self.comparison = Some(Comparison::Test(src1, src2))
+            }
+            InstructionKind::Jump(target) => self.assemble_jump(target, index),
+            InstructionKind::JumpIfZero { src, target } => {
+                self.assemble_branch(BEQ, src, Register::Zero, target, index)
+            }
+            InstructionKind::JumpIf { cond, target } => self.assemble_jump_if(cond, target, index),
+            InstructionKind::Return => self.assemble_return(),
+            InstructionKind::Call(target) => self.assemble_call(target),
+            InstructionKind::CallSymbol(target) => self.assemble_call_symbol(target),
+            InstructionKind::Nop => {}
+            InstructionKind::Float(..) => {
+                panic!("floating-point instructions are not supported on RISC-V")
+            }
+        }
+    }
+
+    /// Assembles the shortest sequence of `lui`, `addi`, `addiw` and `slli` that loads `src`, the
+    /// same one chosen by the assemblers for the `li` pseudo-instruction.
+    ///
+    /// A 32-bit immediate is split into its upper 20 bits and its lower 12 bits, loaded by a `lui`
+    /// and an `addiw`. Any other immediate is split into its lower 12 bits and the rest, which is
+    /// loaded recursively without its trailing zeros and then shifted back into place.
+    fn assemble_load_imm(&mut self, src: Imm64, dst: Register) {
+        let lo12 = sign_extend(src, 12);
+
+        if let Ok(src) = i32::try_from(src) {
+            let hi20 = (src as u32).wrapping_add(0x800) >> 12;
+            if hi20 != 0 {
+                self.push_instruction(hi20 << 12 | dst.encode() << 7 | LUI);
+            }
+            if lo12 != 0 || hi20 == 0 {
+                let (rs1, opcode) = if hi20 != 0 {
+                    (dst, OP_IMM_32)
+                } else {
+                    (Register::Zero, OP_IMM)
+                };
+                self.push_instruction(i_type(lo12 as i32, rs1, 0b000, dst, opcode));
+            }
+            return;
+        }
+
+        let hi52 = (src as u64).wrapping_add(0x800) >> 12;
+        let mut shift = 12 + hi52.trailing_zeros();
+        let mut hi52 = sign_extend((hi52 >> (shift - 12)) as i64, 64 - shift);
+
+        // Shifting less leaves zeros in the lower 12 bits of the rest, which a single `lui` may be
+        // able to load.
+        if shift > 12 && sign_extend(hi52, 12) != hi52 && i32::try_from(hi52 << 12).is_ok() {
+            shift -= 12;
+            hi52 <<= 12;
+        }
+
+        self.assemble_load_imm(hi52, dst);
+        // slli dst,dst,shift
+        self.push_instruction(i_type(shift as i32, dst, 0b001, dst, OP_IMM));
+        if lo12 != 0 {
+            self.push_instruction(i_type(lo12 as i32, dst, 0b000, dst, OP_IMM));
+        }
+    }
+
+    /// Assembles a load into `reg` or a store of `reg` at `[base+offset]`. An offset that does
+    /// not fit in 12 bits is added to the base in the temporary register first.
+    fn assemble_memory(&mut self, opcode: u32, reg: Register, base: Register, offset: Imm32) {
+        let (base, offset) = if fits_in_12_bits(offset.into()) {
+            (base, offset)
+        } else {
+            self.assemble_load_imm(offset.into(), TEMPORARY_REGISTER);
+            self.assemble_add_registers(TEMPORARY_REGISTER, base, TEMPORARY_REGISTER);
+            (TEMPORARY_REGISTER, 0)
+        };
+
+        // ld reg,offset(base) or sd reg,offset(base)
+        let instruction = if opcode == STORE {
+            s_type(offset, reg, base, 0b011, opcode)
+        } else {
+            i_type(offset, base, 0b011, reg, opcode)
+        };
+        self.push_instruction(instruction);
+    }
+
+    /// Assembles an `auipc` of the upper bits of the distance to `src` followed by `instruction`,
+    /// which adds the lower bits or loads from them.
+    fn assemble_load_pc_relative(
+        &mut self,
+        src: Symbol,
+        dst: Register,
+        kind: RelocationKind,
+        instruction: u32,
+    ) {
+        // auipc dst,0
+        self.add_relocation(src, kind);
+        self.push_instruction(dst.encode() << 7 | AUIPC);
+
+        self.add_relocation(src, RelocationKind::PcRelativeLow);
+        self.push_instruction(instruction);
+    }
+
+    fn assemble_store(&mut self, src: Register, dst: &Address<Imm32, Register>) {
+        self.assemble_memory(STORE, dst.base, src, dst.offset)
+    }
+
+    /// Assembles `add dst,lhs,rhs`.
+    fn assemble_add_registers(&mut self, lhs: Register, rhs: Register, dst: Register) {
+        self.push_instruction(r_type(rhs, lhs, 0b000, dst));
+    }
+
+    fn assemble_mov(&mut self, src: Register, dst: Register) {
+        // addi dst,src,0
+        self.push_instruction(i_type(0, src, 0b000, dst, OP_IMM));
+    }
+
+    /// Assembles `addi sp,sp,-16` followed by `sd reg,0(sp)`. The stack pointer must stay aligned
+    /// to 16 bytes, so every push takes 16 bytes.
+    fn assemble_push(&mut self, reg: Register) {
+        assert!(reg != Register::Sp, "cannot push `sp`");
+        self.assemble_add_imm(-16, Register::Sp);
+        self.push_instruction(s_type(0, reg, Register::Sp, 0b011, STORE));
+    }
+
+    /// Assembles `ld reg,0(sp)` followed by `addi sp,sp,16`.
+    fn assemble_pop(&mut self, reg: Register) {
+        assert!(reg != Register::Sp, "cannot pop `sp`");
+        self.push_instruction(i_type(0, Register::Sp, 0b011, reg, LOAD));
+        self.assemble_add_imm(16, Register::Sp);
+    }
+
+    /// Assembles an `addi`, or loads the immediate into a register first if it does not fit in
+    /// 12 bits.
+    fn assemble_add_imm(&mut self, src: Imm32, dst: Register) {
+        if fits_in_12_bits(src.into()) {
+            self.push_instruction(i_type(src, dst, 0b000, dst, OP_IMM));
+        } else {
+            self.assemble_load_imm(src.into(), TEMPORARY_REGISTER);
+            self.assemble_add_registers(dst, TEMPORARY_REGISTER, dst);
+        }
+    }
+
+    /// Assembles `mul dst,dst,src`, from the M extension.
+    fn assemble_mul(&mut self, src: Register, dst: Register) {
+        self.push_instruction(1 << 25 | r_type(src, dst, 0b000, dst));
+    }
+
+    /// Assembles `slt dst,src1,src2`.
+    fn assemble_set_if_less(&mut self, src1: Register, src2: Register, dst: Register) {
+        self.push_instruction(r_type(src2, src1, 0b010, dst));
+    }
+
+    fn assemble_jump(&mut self, target: Label, index: usize) {
+        // jal zero,target
+        self.add_patch(target, PatchKind::Jump, index);
+        self.push_instruction(JAL);
+    }
+
+    /// Assembles the branch that compares the operands of the pending comparison as required by
+    /// `cond`.
+    fn assemble_jump_if(&mut self, cond: Condition, target: Label, index: usize) {
+        let comparison = match self
+            .comparison
+            .expect("a conditional jump must follow the comparison it reads")
+        {
+            // The branches only compare registers, so a non-zero immediate is loaded first.
+            Comparison::CompareImm(src, 0) => Comparison::Compare(src, Register::Zero),
+            Comparison::CompareImm(src, imm) => {
+                self.assemble_load_imm(imm.into(), TEMPORARY_REGISTER);
+                Comparison::Compare(src, TEMPORARY_REGISTER)
+            }
+            comparison => comparison,
+        };
+
+        let (funct3, rs1, rs2) = match (comparison, cond) {
+            (Comparison::Compare(src1, src2), Condition::Equal) => (BEQ, src1, src2),
+            (Comparison::Compare(src1, src2), Condition::NotEqual) => (BNE, src1, src2),
+            (Comparison::Compare(src1, src2), Condition::Less) => (BLT, src1, src2),
+            (Comparison::Compare(src1, src2), Condition::GreaterOrEqual) => (BGE, src1, src2),
+            (Comparison::Compare(src1, src2), Condition::Greater) => (BLT, src2, src1),
+            (Comparison::Compare(src1, src2), Condition::LessOrEqual) => (BGE, src2, src1),
+            (Comparison::Compare(src1, src2), Condition::Below) => (BLTU, src1, src2),
+            (Comparison::Compare(src1, src2), Condition::AboveOrEqual) => (BGEU, src1, src2),
+            (Comparison::Compare(src1, src2), Condition::Above) => (BLTU, src2, src1),
+            (Comparison::Compare(src1, src2), Condition::BelowOrEqual) => (BGEU, src2, src1),
+            (Comparison::Test(src1, src2), Condition::Equal | Condition::NotEqual) => {
+                let reg = if src1 == src2 {
+                    src1
+                } else {
+                    // and t6,src1,src2
+                    self.push_instruction(r_type(src2, src1, 0b111, TEMPORARY_REGISTER));
+                    TEMPORARY_REGISTER
+                };
+                let funct3 = if cond == Condition::Equal { BEQ } else { BNE };
+                (funct3, reg, Register::Zero)
+            }
+            (Comparison::Test(src1, src2), Condition::Less) if src1 == src2 => {
+                (BLT, src1, Register::Zero)
+            }
+            (Comparison::Test(src1, src2), Condition::GreaterOrEqual) if src1 == src2 => {
+                (BGE, src1, Register::Zero)
+            }
+            (Comparison::Test(..), _) => {
+                panic!("only equality and signs can be checked after a test, found {cond:?}")
+            }
+            (Comparison::CompareImm(..), _) => unreachable!("the immediate is loaded first"),
+        };
+
+        self.assemble_branch(funct3, rs1, rs2, target, index);
+    }
+
+    /// Assembles the branch with the given `funct3`, or the opposite branch over a `jal` if the
+    /// branch at `index` is out of range.
+    fn assemble_branch(
+        &mut self,
+        funct3: u32,
+        rs1: Register,
+        rs2: Register,
+        target: Label,
+        index: usize,
+    ) {
+        if self.long_branches.contains(&index) {
+            self.push_instruction(b_type(8, rs2, rs1, funct3 ^ 1));
+            self.assemble_jump(target, index);
+        } else {
+            self.add_patch(target, PatchKind::Branch, index);
+            self.push_instruction(b_type(0, rs2, rs1, funct3));
+        }
+    }
+
+    fn assemble_return(&mut self) {
+        // jalr zero,0(ra)
+        self.push_instruction(i_type(0, Register::Ra, 0b000, Register::Zero, JALR));
+    }
+
+    fn assemble_call(&mut self, target: Register) {
+        // jalr ra,0(target)
+        self.push_instruction(i_type(0, target, 0b000, Register::Ra, JALR));
+    }
+
+    fn assemble_call_symbol(&mut self, target: Symbol) {
+        // auipc ra,0; jalr ra,0(ra)
+        self.add_relocation(target, RelocationKind::Branch);
+        self.push_instruction(Register::Ra.encode() << 7 | AUIPC);
+        self.push_instruction(i_type(0, Register::Ra, 0b000, Register::Ra, JALR));
+    }
+
+    /// Returns the indices of the conditional branches that cannot reach their labels.
+    fn out_of_range_branches(&self) -> Result<Vec<usize>, AssemblerError> {
+        let mut out_of_range = Vec::new();
+
+        for patch in &self.patches {
+            if let PatchKind::Branch = patch.kind {
+                if !patch.kind.reaches(self.distance(patch)?) {
+                    out_of_range.push(patch.index);
+                }
+            }
+        }
+
+        Ok(out_of_range)
+    }
+
+    /// Returns the distance in bytes from the start of the patched instruction to its label.
+    fn distance(&self, patch: &Patch) -> Result<i64, AssemblerError> {
+        let label_location = self.label_locations[patch.label.0]
+            .ok_or(AssemblerError::MissingLabelLocation(patch.label))?;
+        Ok(label_location as i64 - patch.start as i64)
+    }
+
+    pub fn finish(&mut self) -> Result<Vec<Relocation>, AssemblerError> {
+        for patch in &self.patches {
+            let patch_end = patch.start + std::mem::size_of::<u32>();
+
+            let distance = self.distance(patch)?;
+            if !patch.kind.reaches(distance) {
+                return Err(AssemblerError::LabelOutOfRange(patch.label));
+            }
+
+            let mut instruction =
+                u32::from_le_bytes(self.buf[patch.start..patch_end].try_into().unwrap());
+            instruction |= match patch.kind {
+                PatchKind::Branch => b_immediate(distance as i32),
+                PatchKind::Jump => j_immediate(distance as i32),
+            };
+            self.buf[patch.start..patch_end].copy_from_slice(&instruction.to_le_bytes());
+        }
+
+        Ok(std::mem::take(&mut self.relocations))
+    }
+}
+
+/// The immediate field of an instruction that holds the distance to a label.
+enum PatchKind {
+    /// The 13-bit immediate of a conditional branch.
+    Branch,
+    /// The 21-bit immediate of `jal`.
+    Jump,
+}
+
+impl PatchKind {
+    /// Returns whether the immediate can hold `distance`, which is always even.
+    fn reaches(&self, distance: i64) -> bool {
+        let bits = match self {
+            Self::Branch => 13,
+            Self::Jump => 21,
+        };
+        sign_extend(distance, bits) == distance
+    }
+}
+
+struct Patch {
+    label: Label,
+    start: usize,
+    kind: PatchKind,
+    /// The index of the instruction that was assembled into the patched one.
+    index: usize,
+}
+
+/// Returns the lower `bits` bits of `value` sign extended to 64 bits.
+fn sign_extend(value: i64, bits: u32) -> i64 {
+    value << (64 - bits) >> (64 - bits)
+}
+
+fn fits_in_12_bits(imm: i64) -> bool {
+    sign_extend(imm, 12) == imm
+}
+
+/// Encodes a register to register operation with the `OP` opcode and a zero `funct7`, such as
+/// `add`.
+fn r_type(rs2: Register, rs1: Register, funct3: u32, rd: Register) -> u32 {
+    rs2.encode() << 20 | rs1.encode() << 15 | funct3 << 12 | rd.encode() << 7 | OP
+}
+
+fn i_type(imm: i32, rs1: Register, funct3: u32, rd: Register, opcode: u32) -> u32 {
+    (imm as u32 & 0xfff) << 20 | rs1.encode() << 15 | funct3 << 12 | rd.encode() << 7 | opcode
+}
+
+fn s_type(imm: i32, rs2: Register, rs1: Register, funct3: u32, opcode: u32) -> u32 {
+    let imm = imm as u32;
+    (imm >> 5 & 0x7f) << 25
+        | rs2.encode() << 20
+        | rs1.encode() << 15
+        | funct3 << 12
+        | (imm & 0x1f) << 7
+        | opcode
+}
+
+fn b_type(imm: i32, rs2: Register, rs1: Register, funct3: u32) -> u32 {
+    b_immediate(imm) | rs2.encode() << 20 | rs1.encode() << 15 | funct3 << 12 | BRANCH
+}
+
+/// Encodes the scattered immediate of a conditional branch.
+fn b_immediate(imm: i32) -> u32 {
+    let imm = imm as u32;
+    (imm >> 12 & 1) << 31 | (imm >> 5 & 0x3f) << 25 | (imm >> 1 & 0xf) << 8 | (imm >> 11 & 1) << 7
+}
+
+/// Encodes the scattered immediate of `jal`.
+fn j_immediate(imm: i32) -> u32 {
+    let imm = imm as u32;
+    (imm >> 20 & 1) << 31
+        | (imm >> 1 & 0x3ff) << 21
+        | (imm >> 11 & 1) << 20
+        | (imm >> 12 & 0xff) << 12
+}
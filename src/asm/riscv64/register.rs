@@ -0,0 +1,100 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// An integer register, named after its role in the calling convention. The variants are in the
+/// order of their encodings, from `x0` to `x31`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Register {
+    Zero,
+    Ra,
+    Sp,
+    Gp,
+    Tp,
+    T0,
+    T1,
+    T2,
+    S0,
+    S1,
+    A0,
+    A1,
+    A2,
+    A3,
+    A4,
+    A5,
+    A6,
+    A7,
+    S2,
+    S3,
+    S4,
+    S5,
+    S6,
+    S7,
+    S8,
+    S9,
+    S10,
+    S11,
+    T3,
+    T4,
+    T5,
+    T6,
+}
+
+impl Register {
+    pub const fn encode(self) -> u32 {
+        self as u32
+    }
+}
+
+const NAMES: [(Register, &str); 32] = [
+    (Register::Zero, "zero"),
+    (Register::Ra, "ra"),
+    (Register::Sp, "sp"),
+    (Register::Gp, "gp"),
+    (Register::Tp, "tp"),
+    (Register::T0, "t0"),
+    (Register::T1, "t1"),
+    (Register::T2, "t2"),
+    (Register::S0, "s0"),
+    (Register::S1, "s1"),
+    (Register::A0, "a0"),
+    (Register::A1, "a1"),
+    (Register::A2, "a2"),
+    (Register::A3, "a3"),
+    (Register::A4, "a4"),
+    (Register::A5, "a5"),
+    (Register::A6, "a6"),
+    (Register::A7, "a7"),
+    (Register::S2, "s2"),
+    (Register::S3, "s3"),
+    (Register::S4, "s4"),
+    (Register::S5, "s5"),
+    (Register::S6, "s6"),
+    (Register::S7, "s7"),
+    (Register::S8, "s8"),
+    (Register::S9, "s9"),
+    (Register::S10, "s10"),
+    (Register::S11, "s11"),
+    (Register::T3, "t3"),
+    (Register::T4, "t4"),
+    (Register::T5, "t5"),
+    (Register::T6, "t6"),
+];
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (_, name) = NAMES.iter().find(|(reg, _)| reg == self).unwrap();
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Register {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NAMES
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|(reg, _)| *reg)
+            .ok_or(())
+    }
+}
@@ -2,53 +2,49 @@ mod mod_rm;
 mod rex;
 mod sib;
 
-use std::error::Error;
-use std::fmt;
-
 use crate::asm::x86_64::register::Register;
-use crate::asm::{Address, Imm32, Imm64, Instruction, InstructionKind, Instructions, Label};
+use crate::asm::{
+    Address, AssemblerError, Condition, FloatKind, Imm32, Imm64, InstructionKind, Instructions,
+    Label, Precision, Relocation, RelocationKind, Symbol,
+};
 use mod_rm::ModRmBuilder;
 use rex::RexBuilder;
 use sib::{Scale, SibBuilder};
 
+/// Assembles `instructions` into `buf`.
+///
+/// Returns the relocations that must be applied to `buf` to resolve the symbols used by the
+/// instructions.
 pub fn assemble(
     instructions: Instructions<Register>,
     buf: &mut Vec<u8>,
-) -> Result<(), AssemblerError> {
+) -> Result<Vec<Relocation>, AssemblerError> {
+    let labels = instructions.labels_by_position();
+
     let mut asm = Assembler {
         buf,
-        label_locations: vec![None; instructions.labels_len],
-        patches: Vec::with_capacity(instructions.labels_len),
+        label_locations: vec![None; instructions.labels.len()],
+        patches: Vec::with_capacity(instructions.labels.len()),
+        symbols: instructions.symbols,
+        relocations: Vec::new(),
     };
 
-    for instruction in instructions.instructions {
-        asm.assemble_instruction(instruction);
+    for (kind, labels) in instructions.instructions.into_iter().zip(&labels) {
+        asm.bind_labels(labels);
+        asm.assemble_instruction(kind);
     }
+    // Labels bound to the end of the function.
+    asm.bind_labels(&labels[labels.len() - 1]);
 
     asm.finish()
 }
 
-#[derive(Debug)]
-pub enum AssemblerError {
-    MissingLabelLocation(Label),
-}
-
-impl fmt::Display for AssemblerError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::MissingLabelLocation(label) => {
-                write!(f, "location of label {label:?} is missing")
-            }
-        }
-    }
-}
-
-impl Error for AssemblerError {}
-
 struct Assembler<'asm> {
     buf: &'asm mut Vec<u8>,
     label_locations: Vec<Option<usize>>,
     patches: Vec<Patch>,
+    symbols: Vec<String>,
+    relocations: Vec<Relocation>,
 }
 
 impl<'asm> Assembler<'asm> {
@@ -64,6 +60,22 @@ impl<'asm> Assembler<'asm> {
         })
     }
 
+    /// Adds a new relocation in the current location of the instruction pointer for a
+    /// [`Symbol`].
+    ///
+    /// The `4` bytes following the current instruction pointer location will be overwritten by
+    /// the linker.
+    fn add_relocation(&mut self, symbol: Symbol, kind: RelocationKind) {
+        self.relocations.push(Relocation {
+            offset: self.buf.len(),
+            symbol: self.symbols[symbol.0].clone(),
+            kind,
+            // The location must be relative to the end of the instruction which matches the end
+            // of the relocation.
+            addend: -(std::mem::size_of::<i32>() as i64),
+        })
+    }
+
     fn push_byte(&mut self, byte: u8) {
         self.buf.push(byte)
     }
@@ -72,31 +84,50 @@ impl<'asm> Assembler<'asm> {
         self.buf.extend_from_slice(&bytes)
     }
 
-    /// Assembles an instruction.:
-    ///
-    /// If the instruction has a label, the previous location of the label will be overwritten.
-    pub fn assemble_instruction(&mut self, instruction: Instruction<Register>) {
-        if let Some(label) = instruction.label {
+    /// Sets the location of `labels` to the current location of the instruction pointer.
+    fn bind_labels(&mut self, labels: &[Label]) {
+        for label in labels {
             self.label_locations[label.0] = Some(self.buf.len());
         }
+    }
 
-        match instruction.kind {
+    /// Assembles an instruction.
+    pub fn assemble_instruction(&mut self, kind: InstructionKind<Register>) {
+        match kind {
             InstructionKind::LoadImm { src, dst } => self.assemble_load_imm::<true>(src, dst),
             InstructionKind::LoadAddr { src, dst } => self.assemble_load_addr(src, dst),
+            InstructionKind::LoadSymbolAddr { src, dst } => {
+                self.assemble_load_relative::<0x8d>(src, dst, RelocationKind::Relative)
+            }
+            InstructionKind::LoadGotAddr { src, dst } => {
+                self.assemble_load_relative::<0x8b>(src, dst, RelocationKind::GotRelative)
+            }
+            InstructionKind::LoadEffectiveAddr { base, index, dst } => {
+                self.assemble_load_effective_addr(base, index, dst)
+            }
             InstructionKind::Store { src, dst } => self.assemble_store(src, dst),
             InstructionKind::Mov { src, dst } => self.assemble_mov(src, dst),
             InstructionKind::Push(reg) => self.assemble_push(reg),
             InstructionKind::Pop(reg) => self.assemble_pop(reg),
             InstructionKind::Add { src, dst } => self.assemble_add(src, dst),
-            InstructionKind::AddImm { src, dst } => self.assemble_add_imm(src, dst),
+            InstructionKind::AddImm { src, dst } => self.assemble_binary_imm::<0x0, 0x05>(src, dst),
+            InstructionKind::Mul { src, dst } => self.assemble_mul(src, dst),
             InstructionKind::SetIfLess { src1, src2, dst } => {
                 self.assemble_set_if::<0x9c>(src1, src2, dst)
             }
+            InstructionKind::Compare { src1, src2 } => self.assemble_binary::<0x39>(src1, src2),
+            InstructionKind::CompareImm { src, imm } => {
+                self.assemble_binary_imm::<0x7, 0x3d>(imm, src)
+            }
+            InstructionKind::Test { src1, src2 } => self.assemble_binary::<0x85>(src1, src2),
             InstructionKind::Jump(target) => self.assemble_jump(target),
             InstructionKind::JumpIfZero { src, target } => self.assemble_jump_if_zero(src, target),
+            InstructionKind::JumpIf { cond, target } => self.assemble_jump_if(cond, target),
             InstructionKind::Return => self.assemble_return(),
             InstructionKind::Call(target) => self.assemble_call(target),
+            InstructionKind::CallSymbol(target) => self.assemble_call_symbol(target),
             InstructionKind::Nop => {}
+            InstructionKind::Float(precision, kind) => self.assemble_float(precision, kind),
         }
     }
 
@@ -119,8 +150,8 @@ impl<'asm> Assembler<'asm> {
                 .rm(dst.encode())
                 .build();
             self.push_bytes([0x31, mod_rm]);
-        } else if let Ok(src) = Imm32::try_from(src) {
-            // mov dst,imm32
+        } else if let Ok(src) = u32::try_from(src) {
+            // mov dst,imm32, which clears the upper 32 bits
             if dst.needs_extension() {
                 let rex_prefix = RexBuilder::new()
                     .set_w(false)
@@ -134,6 +165,23 @@ impl<'asm> Assembler<'asm> {
 
             self.push_byte(0xb8 + dst.encode());
             self.push_bytes(src.to_le_bytes());
+        } else if let Ok(src) = Imm32::try_from(src) {
+            // mov dst,imm32, which sign-extends the immediate
+            let rex_prefix = RexBuilder::new()
+                .set_w(true)
+                .set_r(false)
+                .set_x(false)
+                .set_b(dst.needs_extension())
+                .finish();
+
+            let mod_rm = ModRmBuilder::new()
+                .direct()
+                .reg(0x0)
+                .rm(dst.encode())
+                .build();
+
+            self.push_bytes([rex_prefix, 0xc7, mod_rm]);
+            self.push_bytes(src.to_le_bytes());
         } else {
             // mov dst,imm64
             let rex_prefix = RexBuilder::new()
@@ -178,6 +226,73 @@ impl<'asm> Assembler<'asm> {
         self.push_bytes(src.offset.to_le_bytes());
     }
 
+    /// Assembles an instruction whose source operand is a symbol addressed relative to the
+    /// instruction pointer: `lea dst,[rip+src]` or `mov dst,[rip+src]`.
+    fn assemble_load_relative<const OPCODE: u8>(
+        &mut self,
+        src: Symbol,
+        dst: Register,
+        kind: RelocationKind,
+    ) {
+        let rex_prefix = RexBuilder::new()
+            .set_w(true)
+            .set_r(dst.needs_extension())
+            .set_x(false)
+            .set_b(false)
+            .finish();
+
+        let mod_rm = ModRmBuilder::new().relative().reg(dst.encode()).build();
+
+        self.push_bytes([rex_prefix, OPCODE, mod_rm]);
+        self.add_relocation(src, kind);
+        self.push_bytes(0x0i32.to_le_bytes());
+    }
+
+    /// Assembles `lea dst,[base+index]`.
+    ///
+    /// The operands are swapped if needed because `rsp` cannot be an index and a base of `rbp` or
+    /// `r13` requires a displacement.
+    fn assemble_load_effective_addr(&mut self, base: Register, index: Register, dst: Register) {
+        let needs_displacement = |reg| matches!(reg, Register::Bp | Register::R13);
+
+        let (base, index) = if index == Register::Sp || needs_displacement(base) {
+            (index, base)
+        } else {
+            (base, index)
+        };
+        assert!(
+            index != Register::Sp,
+            "cannot add `rsp` to itself with `lea`"
+        );
+
+        let rex_prefix = RexBuilder::new()
+            .set_w(true)
+            .set_r(dst.needs_extension())
+            .set_x(index.needs_extension())
+            .set_b(base.needs_extension())
+            .finish();
+
+        let mod_rm = ModRmBuilder::new();
+        let mod_rm = if needs_displacement(base) {
+            mod_rm.displacement8()
+        } else {
+            mod_rm.indirect()
+        };
+        let mod_rm = mod_rm.reg(dst.encode()).rm(0b100).build();
+
+        let sib = SibBuilder::new()
+            .scale(Scale::One)
+            .index(index)
+            .base(base)
+            .build();
+
+        self.push_bytes([rex_prefix, 0x8d, mod_rm, sib]);
+
+        if needs_displacement(base) {
+            self.push_byte(0x0);
+        }
+    }
+
     fn assemble_store(&mut self, src: Register, dst: Address<Imm32, Register>) {
         let rex_prefix = RexBuilder::new()
             .set_w(true)
@@ -208,6 +323,16 @@ impl<'asm> Assembler<'asm> {
     }
 
     fn assemble_mov(&mut self, src: Register, dst: Register) {
+        match (src.is_xmm(), dst.is_xmm()) {
+            // movsd dst,src
+            (true, true) => return self.assemble_sse(Some(0xf2), false, 0x10, dst, src),
+            // movq dst,src
+            (false, true) => return self.assemble_sse(Some(0x66), true, 0x6e, dst, src),
+            // movq dst,src
+            (true, false) => return self.assemble_sse(Some(0x66), true, 0x7e, src, dst),
+            (false, false) => {}
+        }
+
         let rex_prefix = RexBuilder::new()
             .set_w(true)
             .set_r(src.needs_extension())
@@ -224,7 +349,15 @@ impl<'asm> Assembler<'asm> {
         self.push_bytes([rex_prefix, 0x89, mod_rm]);
     }
 
+    /// Assembles `push reg`, or `lea rsp,[rsp-8]` followed by `movsd [rsp],reg` for an SSE
+    /// register, which leaves the flags untouched too.
     fn assemble_push(&mut self, reg: Register) {
+        if reg.is_xmm() {
+            self.assemble_move_stack_pointer(-8);
+            self.assemble_sse_stack(0x11, reg);
+            return;
+        }
+
         if reg.needs_extension() {
             let rex_prefix = RexBuilder::new()
                 .set_w(false)
@@ -239,7 +372,15 @@ impl<'asm> Assembler<'asm> {
         self.push_byte(0x50 + reg.encode());
     }
 
+    /// Assembles `pop reg`, or `movsd reg,[rsp]` followed by `lea rsp,[rsp+8]` for an SSE
+    /// register.
     fn assemble_pop(&mut self, reg: Register) {
+        if reg.is_xmm() {
+            self.assemble_sse_stack(0x10, reg);
+            self.assemble_move_stack_pointer(8);
+            return;
+        }
+
         if reg.needs_extension() {
             let rex_prefix = RexBuilder::new()
                 .set_w(false)
@@ -271,41 +412,120 @@ impl<'asm> Assembler<'asm> {
         self.push_bytes([rex_prefix, 0x01, mod_rm]);
     }
 
-    fn assemble_add_imm(&mut self, src: i32, dst: Register) {
+    /// Assembles an instruction of the group with opcodes `0x83` and `0x81` whose `reg` field is
+    /// `EXTENSION`, such as `add` or `cmp`, with a register and an immediate. `RAX_OPCODE` is the
+    /// shorter opcode of the instruction with `rax` and a 32-bit immediate.
+    fn assemble_binary_imm<const EXTENSION: u8, const RAX_OPCODE: u8>(
+        &mut self,
+        imm: i32,
+        reg: Register,
+    ) {
         let rex_prefix = RexBuilder::new()
             .set_w(true)
             .set_r(false)
             .set_x(false)
-            .set_b(dst.needs_extension())
+            .set_b(reg.needs_extension())
             .finish();
 
-        if let Ok(src) = i8::try_from(src) {
-            // add dst,imm8
+        if let Ok(imm) = i8::try_from(imm) {
+            // op reg,imm8
             let mod_rm = ModRmBuilder::new()
                 .direct()
-                .reg(0x0)
-                .rm(dst.encode())
+                .reg(EXTENSION)
+                .rm(reg.encode())
                 .build();
 
             self.push_bytes([rex_prefix, 0x83, mod_rm]);
-            self.push_bytes(src.to_le_bytes());
-        } else if let Register::Ax = dst {
-            // add rax,imm32
-            self.push_bytes([rex_prefix, 0x05]);
-            self.push_bytes(src.to_le_bytes());
+            self.push_bytes(imm.to_le_bytes());
+        } else if let Register::Ax = reg {
+            // op rax,imm32
+            self.push_bytes([rex_prefix, RAX_OPCODE]);
+            self.push_bytes(imm.to_le_bytes());
         } else {
-            // add rax,imm32
+            // op reg,imm32
             let mod_rm = ModRmBuilder::new()
                 .direct()
-                .reg(0x0)
-                .rm(dst.encode())
+                .reg(EXTENSION)
+                .rm(reg.encode())
                 .build();
 
             self.push_bytes([rex_prefix, 0x81, mod_rm]);
-            self.push_bytes(src.to_le_bytes());
+            self.push_bytes(imm.to_le_bytes());
         }
     }
 
+    /// Assembles `lea rsp,[rsp+offset]`.
+    fn assemble_move_stack_pointer(&mut self, offset: i8) {
+        let rex_prefix = RexBuilder::new()
+            .set_w(true)
+            .set_r(false)
+            .set_x(false)
+            .set_b(false)
+            .finish();
+
+        let mod_rm = ModRmBuilder::new()
+            .displacement8()
+            .reg(Register::Sp.encode())
+            .rm(0b100)
+            .build();
+
+        let sib = SibBuilder::new()
+            .scale(Scale::One)
+            .index(Register::Sp)
+            .base(Register::Sp)
+            .build();
+
+        self.push_bytes([rex_prefix, 0x8d, mod_rm, sib]);
+        self.push_bytes(offset.to_le_bytes());
+    }
+
+    /// Assembles `movsd [rsp],reg` if `opcode` is `0x11` or `movsd reg,[rsp]` if it is `0x10`.
+    fn assemble_sse_stack(&mut self, opcode: u8, reg: Register) {
+        self.push_byte(0xf2);
+        if reg.needs_extension() {
+            let rex_prefix = RexBuilder::new()
+                .set_w(false)
+                .set_r(true)
+                .set_x(false)
+                .set_b(false)
+                .finish();
+
+            self.push_byte(rex_prefix);
+        }
+
+        let mod_rm = ModRmBuilder::new()
+            .indirect()
+            .reg(reg.encode())
+            .rm(0b100)
+            .build();
+
+        let sib = SibBuilder::new()
+            .scale(Scale::One)
+            .index(Register::Sp)
+            .base(Register::Sp)
+            .build();
+
+        self.push_bytes([0x0f, opcode, mod_rm, sib]);
+    }
+
+    /// Assembles `imul dst,src`.
+    fn assemble_mul(&mut self, src: Register, dst: Register) {
+        let rex_prefix = RexBuilder::new()
+            .set_w(true)
+            .set_r(dst.needs_extension())
+            .set_x(false)
+            .set_b(src.needs_extension())
+            .finish();
+
+        let mod_rm = ModRmBuilder::new()
+            .direct()
+            .reg(dst.encode())
+            .rm(src.encode())
+            .build();
+
+        self.push_bytes([rex_prefix, 0x0f, 0xaf, mod_rm]);
+    }
+
     fn assemble_set_if<const OPCODE: u8>(&mut self, src1: Register, src2: Register, dst: Register) {
         if dst != src1 && dst != src2 {
             // xor dst,dst
@@ -346,6 +566,11 @@ impl<'asm> Assembler<'asm> {
         }
 
         // setl dst
+        self.assemble_set::<OPCODE>(dst);
+    }
+
+    /// Assembles the `setcc` of the lowest byte of `dst` with opcode `0x0f OPCODE`.
+    fn assemble_set<const OPCODE: u8>(&mut self, dst: Register) {
         if !matches!(
             dst,
             Register::Ax | Register::Cx | Register::Bx | Register::Dx
@@ -369,6 +594,106 @@ impl<'asm> Assembler<'asm> {
         self.push_bytes([0x0f, OPCODE, mod_rm]);
     }
 
+    /// Assembles `xor dst,dst`, `ucomisd src2,src1` and `seta dst`, which is only set if the
+    /// operands are ordered.
+    fn assemble_float_set_if_less(
+        &mut self,
+        precision: Precision,
+        src1: Register,
+        src2: Register,
+        dst: Register,
+    ) {
+        self.assemble_load_imm::<true>(0x0, dst);
+        self.assemble_float_compare(precision, src2, src1);
+        self.assemble_set::<0x97>(dst);
+    }
+
+    /// Assembles a scalar SSE instruction, whose mandatory prefix selects the precision.
+    fn assemble_float(&mut self, precision: Precision, kind: FloatKind<Register>) {
+        let prefix = Some(scalar_prefix(precision));
+        match kind {
+            FloatKind::Add { src, dst } => self.assemble_sse(prefix, false, 0x58, dst, src),
+            FloatKind::Mul { src, dst } => self.assemble_sse(prefix, false, 0x59, dst, src),
+            FloatKind::SetIfLess { src1, src2, dst } => {
+                self.assemble_float_set_if_less(precision, src1, src2, dst)
+            }
+            FloatKind::Compare { src1, src2 } => self.assemble_float_compare(precision, src1, src2),
+            FloatKind::FromInt { src, dst } => self.assemble_sse(prefix, true, 0x2a, dst, src),
+            FloatKind::ToInt { src, dst } => self.assemble_sse(prefix, true, 0x2c, dst, src),
+            FloatKind::Convert { src, dst } => {
+                // The prefix is the one of the precision of the source.
+                let prefix = match precision {
+                    Precision::Single => scalar_prefix(Precision::Double),
+                    Precision::Double => scalar_prefix(Precision::Single),
+                };
+                self.assemble_sse(Some(prefix), false, 0x5a, dst, src)
+            }
+        }
+    }
+
+    /// Assembles `ucomisd src1,src2` or `ucomiss src1,src2`.
+    fn assemble_float_compare(&mut self, precision: Precision, src1: Register, src2: Register) {
+        let prefix = match precision {
+            Precision::Single => None,
+            Precision::Double => Some(0x66),
+        };
+        self.assemble_sse(prefix, false, 0x2e, src1, src2)
+    }
+
+    /// Assembles an SSE instruction between two registers: the mandatory `prefix`, a REX prefix
+    /// if `w` is set or a register needs an extension, `0x0f OPCODE` and a ModR/M byte with `reg`
+    /// and `rm`.
+    fn assemble_sse(
+        &mut self,
+        prefix: Option<u8>,
+        w: bool,
+        opcode: u8,
+        reg: Register,
+        rm: Register,
+    ) {
+        if let Some(prefix) = prefix {
+            self.push_byte(prefix);
+        }
+
+        if w || reg.needs_extension() || rm.needs_extension() {
+            let rex_prefix = RexBuilder::new()
+                .set_w(w)
+                .set_r(reg.needs_extension())
+                .set_x(false)
+                .set_b(rm.needs_extension())
+                .finish();
+
+            self.push_byte(rex_prefix);
+        }
+
+        let mod_rm = ModRmBuilder::new()
+            .direct()
+            .reg(reg.encode())
+            .rm(rm.encode())
+            .build();
+
+        self.push_bytes([0x0f, opcode, mod_rm]);
+    }
+
+    /// Assembles an instruction that only sets the flags from two registers: `cmp src1,src2` or
+    /// `test src1,src2`.
+    fn assemble_binary<const OPCODE: u8>(&mut self, src1: Register, src2: Register) {
+        let rex_prefix = RexBuilder::new()
+            .set_w(true)
+            .set_r(src2.needs_extension())
+            .set_x(false)
+            .set_b(src1.needs_extension())
+            .finish();
+
+        let mod_rm = ModRmBuilder::new()
+            .direct()
+            .reg(src2.encode())
+            .rm(src1.encode())
+            .build();
+
+        self.push_bytes([rex_prefix, OPCODE, mod_rm]);
+    }
+
     fn assemble_jump(&mut self, target: Label) {
         self.push_byte(0xe9);
         self.add_patch(target);
@@ -404,6 +729,26 @@ impl<'asm> Assembler<'asm> {
         self.push_bytes(0x0i32.to_le_bytes());
     }
 
+    fn assemble_jump_if(&mut self, cond: Condition, target: Label) {
+        let opcode = match cond {
+            Condition::Equal => 0x84,
+            Condition::NotEqual => 0x85,
+            Condition::Less => 0x8c,
+            Condition::GreaterOrEqual => 0x8d,
+            Condition::LessOrEqual => 0x8e,
+            Condition::Greater => 0x8f,
+            Condition::Above => 0x87,
+            Condition::AboveOrEqual => 0x83,
+            Condition::Below => 0x82,
+            Condition::BelowOrEqual => 0x86,
+        };
+
+        // jcc target
+        self.push_bytes([0x0f, opcode]);
+        self.add_patch(target);
+        self.push_bytes(0x0i32.to_le_bytes());
+    }
+
     fn assemble_return(&mut self) {
         self.push_byte(0xc3);
     }
@@ -429,7 +774,13 @@ impl<'asm> Assembler<'asm> {
         self.push_bytes([0xff, mod_rm]);
     }
 
-    pub fn finish(&mut self) -> Result<(), AssemblerError> {
+    fn assemble_call_symbol(&mut self, target: Symbol) {
+        self.push_byte(0xe8);
+        self.add_relocation(target, RelocationKind::Branch);
+        self.push_bytes(0x0i32.to_le_bytes());
+    }
+
+    pub fn finish(&mut self) -> Result<Vec<Relocation>, AssemblerError> {
         for patch in &self.patches {
             // The value to be patched is an `i32`.
             let patch_end = patch.start + std::mem::size_of::<i32>();
@@ -444,7 +795,15 @@ impl<'asm> Assembler<'asm> {
             self.buf[patch.start..patch_end].copy_from_slice(&label_location.to_le_bytes());
         }
 
-        Ok(())
+        Ok(std::mem::take(&mut self.relocations))
+    }
+}
+
+/// Returns the mandatory prefix of the scalar SSE instructions on floats of `precision`.
+fn scalar_prefix(precision: Precision) -> u8 {
+    match precision {
+        Precision::Single => 0xf3,
+        Precision::Double => 0xf2,
     }
 }
 
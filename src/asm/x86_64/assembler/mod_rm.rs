@@ -28,6 +28,11 @@ impl<const REG: bool, const RM: bool> ModRmBuilder<false, REG, RM> {
         ModRmBuilder(self.0 | (0b10 << 6))
     }
 
+    /// Set the register-indirect addressing mode with an 8-bit displacement.
+    pub const fn displacement8(self) -> ModRmBuilder<true, REG, RM> {
+        ModRmBuilder(self.0 | (0b01 << 6))
+    }
+
     /// Set the register-indirect addressing mode without displacement.
     ///
     /// This mode is also used for instruction-pointer-relative addressing when the `rm` field is
@@ -1,5 +1,6 @@
 mod assembler;
 mod register;
 
-pub use assembler::{assemble, AssemblerError};
+pub use crate::asm::AssemblerError;
+pub use assembler::assemble;
 pub use register::Register;
@@ -1,4 +1,7 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Register {
     Ax,
     Cx,
@@ -16,19 +19,35 @@ pub enum Register {
     R13,
     R14,
     R15,
+    Xmm0,
+    Xmm1,
+    Xmm2,
+    Xmm3,
+    Xmm4,
+    Xmm5,
+    Xmm6,
+    Xmm7,
+    Xmm8,
+    Xmm9,
+    Xmm10,
+    Xmm11,
+    Xmm12,
+    Xmm13,
+    Xmm14,
+    Xmm15,
 }
 
 impl Register {
     pub const fn encode(self) -> u8 {
         match self {
-            Register::Ax | Register::R8 => 0,
-            Register::Cx | Register::R9 => 1,
-            Register::Dx | Register::R10 => 2,
-            Register::Bx | Register::R11 => 3,
-            Register::Sp | Register::R12 => 4,
-            Register::Bp | Register::R13 => 5,
-            Register::Si | Register::R14 => 6,
-            Register::Di | Register::R15 => 7,
+            Register::Ax | Register::R8 | Register::Xmm0 | Register::Xmm8 => 0,
+            Register::Cx | Register::R9 | Register::Xmm1 | Register::Xmm9 => 1,
+            Register::Dx | Register::R10 | Register::Xmm2 | Register::Xmm10 => 2,
+            Register::Bx | Register::R11 | Register::Xmm3 | Register::Xmm11 => 3,
+            Register::Sp | Register::R12 | Register::Xmm4 | Register::Xmm12 => 4,
+            Register::Bp | Register::R13 | Register::Xmm5 | Register::Xmm13 => 5,
+            Register::Si | Register::R14 | Register::Xmm6 | Register::Xmm14 => 6,
+            Register::Di | Register::R15 | Register::Xmm7 | Register::Xmm15 => 7,
         }
     }
 
@@ -41,7 +60,15 @@ impl Register {
             | Register::Sp
             | Register::Bp
             | Register::Si
-            | Register::Di => false,
+            | Register::Di
+            | Register::Xmm0
+            | Register::Xmm1
+            | Register::Xmm2
+            | Register::Xmm3
+            | Register::Xmm4
+            | Register::Xmm5
+            | Register::Xmm6
+            | Register::Xmm7 => false,
             Register::R8
             | Register::R9
             | Register::R10
@@ -49,7 +76,92 @@ impl Register {
             | Register::R12
             | Register::R13
             | Register::R14
-            | Register::R15 => true,
+            | Register::R15
+            | Register::Xmm8
+            | Register::Xmm9
+            | Register::Xmm10
+            | Register::Xmm11
+            | Register::Xmm12
+            | Register::Xmm13
+            | Register::Xmm14
+            | Register::Xmm15 => true,
         }
     }
+
+    /// Returns whether this is one of the SSE registers, which hold floats.
+    pub const fn is_xmm(self) -> bool {
+        matches!(
+            self,
+            Register::Xmm0
+                | Register::Xmm1
+                | Register::Xmm2
+                | Register::Xmm3
+                | Register::Xmm4
+                | Register::Xmm5
+                | Register::Xmm6
+                | Register::Xmm7
+                | Register::Xmm8
+                | Register::Xmm9
+                | Register::Xmm10
+                | Register::Xmm11
+                | Register::Xmm12
+                | Register::Xmm13
+                | Register::Xmm14
+                | Register::Xmm15
+        )
+    }
+}
+
+const NAMES: [(Register, &str); 32] = [
+    (Register::Ax, "rax"),
+    (Register::Cx, "rcx"),
+    (Register::Dx, "rdx"),
+    (Register::Bx, "rbx"),
+    (Register::Sp, "rsp"),
+    (Register::Bp, "rbp"),
+    (Register::Si, "rsi"),
+    (Register::Di, "rdi"),
+    (Register::R8, "r8"),
+    (Register::R9, "r9"),
+    (Register::R10, "r10"),
+    (Register::R11, "r11"),
+    (Register::R12, "r12"),
+    (Register::R13, "r13"),
+    (Register::R14, "r14"),
+    (Register::R15, "r15"),
+    (Register::Xmm0, "xmm0"),
+    (Register::Xmm1, "xmm1"),
+    (Register::Xmm2, "xmm2"),
+    (Register::Xmm3, "xmm3"),
+    (Register::Xmm4, "xmm4"),
+    (Register::Xmm5, "xmm5"),
+    (Register::Xmm6, "xmm6"),
+    (Register::Xmm7, "xmm7"),
+    (Register::Xmm8, "xmm8"),
+    (Register::Xmm9, "xmm9"),
+    (Register::Xmm10, "xmm10"),
+    (Register::Xmm11, "xmm11"),
+    (Register::Xmm12, "xmm12"),
+    (Register::Xmm13, "xmm13"),
+    (Register::Xmm14, "xmm14"),
+    (Register::Xmm15, "xmm15"),
+];
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (_, name) = NAMES.iter().find(|(reg, _)| reg == self).unwrap();
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Register {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NAMES
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|(reg, _)| *reg)
+            .ok_or(())
+    }
 }
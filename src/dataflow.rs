@@ -0,0 +1,184 @@
+//! A worklist solver for dataflow analyses, shared by MIR and assembly.
+//!
+//! The built-in analyses live next to the IR they analyze, in [`crate::mir::dataflow`] and
+//! [`crate::asm::dataflow`].
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// A directed graph whose nodes are the units of an analysis, such as MIR blocks or assembly
+/// instructions.
+pub trait Graph {
+    type Node: Copy + Ord;
+
+    /// Returns the nodes reachable from the entry node in reverse postorder, starting with the
+    /// entry node.
+    fn nodes(&self) -> &[Self::Node];
+
+    fn successors(&self, node: Self::Node) -> &[Self::Node];
+
+    fn predecessors(&self, node: Self::Node) -> &[Self::Node];
+}
+
+/// A join-semilattice of finite height.
+pub trait Lattice: Clone + Eq {
+    /// Sets `self` to the least upper bound of `self` and `other`. Returns whether `self` changed.
+    fn join(&mut self, other: &Self) -> bool;
+}
+
+/// Sets are joined with their union.
+impl<T: Ord + Clone> Lattice for BTreeSet<T> {
+    fn join(&mut self, other: &Self) -> bool {
+        let len = self.len();
+        self.extend(other.iter().cloned());
+        self.len() != len
+    }
+}
+
+/// Booleans are joined with a logical or.
+impl Lattice for bool {
+    fn join(&mut self, other: &Self) -> bool {
+        let changed = !*self && *other;
+        *self |= *other;
+        changed
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The state flows from the entry node along the edges of the graph.
+    Forward,
+    /// The state flows from the nodes without successors against the edges of the graph.
+    Backward,
+}
+
+pub trait Analysis {
+    type Node: Copy + Ord;
+    type Domain: Lattice;
+
+    const DIRECTION: Direction;
+
+    /// Returns the state before the entry node of a forward analysis or after the nodes without
+    /// successors of a backward analysis.
+    fn boundary(&self) -> Self::Domain;
+
+    /// Returns the least element of the lattice, used as the initial state of every other node.
+    fn bottom(&self) -> Self::Domain;
+
+    /// Applies the effect of `node` to `state`, in the direction of the analysis.
+    fn transfer(&self, node: Self::Node, state: &mut Self::Domain);
+}
+
+/// The fixpoint of an analysis. Both maps are in program order, regardless of the direction of
+/// the analysis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Results<N, D> {
+    /// The state before each node.
+    pub before: BTreeMap<N, D>,
+    /// The state after each node.
+    pub after: BTreeMap<N, D>,
+}
+
+/// Computes the fixpoint of `analysis` over the reachable nodes of `graph`.
+pub fn solve<G, A>(graph: &G, analysis: &A) -> Results<G::Node, A::Domain>
+where
+    G: Graph,
+    A: Analysis<Node = G::Node>,
+{
+    let forward = A::DIRECTION == Direction::Forward;
+
+    // The states flowing into each node, following the direction of the analysis.
+    let mut inputs = graph
+        .nodes()
+        .iter()
+        .map(|node| {
+            let is_boundary = if forward {
+                Some(node) == graph.nodes().first()
+            } else {
+                graph.successors(*node).is_empty()
+            };
+
+            let state = if is_boundary {
+                analysis.boundary()
+            } else {
+                analysis.bottom()
+            };
+
+            (*node, state)
+        })
+        .collect::<BTreeMap<_, _>>();
+    let mut outputs = BTreeMap::new();
+
+    // Visiting the nodes in reverse postorder for forward analyses and in postorder for backward
+    // ones reduces the number of iterations needed to reach the fixpoint.
+    let mut worklist = graph.nodes().to_vec();
+    if !forward {
+        worklist.reverse();
+    }
+    let mut worklist = VecDeque::from(worklist);
+    let mut queued = worklist.iter().copied().collect::<BTreeSet<_>>();
+
+    while let Some(node) = worklist.pop_front() {
+        queued.remove(&node);
+
+        let mut state = inputs[&node].clone();
+        analysis.transfer(node, &mut state);
+
+        let next = if forward {
+            graph.successors(node)
+        } else {
+            graph.predecessors(node)
+        };
+
+        for next in next {
+            // Unreachable predecessors are not part of the analysis.
+            if let Some(input) = inputs.get_mut(next) {
+                if input.join(&state) && queued.insert(*next) {
+                    worklist.push_back(*next);
+                }
+            }
+        }
+
+        outputs.insert(node, state);
+    }
+
+    if forward {
+        Results {
+            before: inputs,
+            after: outputs,
+        }
+    } else {
+        Results {
+            before: outputs,
+            after: inputs,
+        }
+    }
+}
+
+/// Returns the nodes reachable from `entry` in reverse postorder.
+pub(crate) fn reverse_postorder<'a, N: Copy + Ord + 'a>(
+    entry: N,
+    successors: impl Fn(N) -> &'a [N],
+) -> Vec<N> {
+    // Iterative depth-first search, a node is added to the postorder once all its successors have
+    // been visited.
+    let mut postorder = Vec::new();
+    let mut visited = BTreeSet::from([entry]);
+    let mut stack = vec![(entry, 0)];
+
+    while let Some((node, next)) = stack.last_mut() {
+        match successors(*node).get(*next) {
+            Some(succ) => {
+                *next += 1;
+                if visited.insert(*succ) {
+                    stack.push((*succ, 0));
+                }
+            }
+            None => {
+                postorder.push(*node);
+                stack.pop();
+            }
+        }
+    }
+
+    postorder.reverse();
+    postorder
+}
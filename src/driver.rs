@@ -0,0 +1,612 @@
+//! The `pijama` command-line compiler driver.
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::asm::{optimize, parse::parse_functions, AssemblerError, InstructionKind, Instructions};
+use crate::emit::{Arch, EmitError, Format, ObjectEmitter, Visibility};
+use crate::mir::{
+    self, optimize::Unroll, parse::parse_module, Function, Module, Operand, Statement, Ty,
+};
+use crate::mir_lowering::{self, LowerError, LowerOptions, RelocationModel};
+use crate::parse::ParseError;
+use crate::pass::{OptLevel, PassManager, PassOptions, PassStatistics};
+use crate::profile::{self, Instrumentation, Profile};
+use crate::target::{Aarch64, RegisterClass, Riscv64, Target, X86_64Windows, X86_64};
+
+pub const USAGE: &str = "\
+Usage: pijama [OPTIONS] INPUT...
+
+Inputs ending in `.mir` are parsed as MIR, inputs ending in `.s` or `.asm` are parsed as
+assembly and any other input is passed to the linker when emitting an executable.
+
+Options:
+    -o PATH                 Write the output to PATH
+    -O0, -O1, -O2           Set the optimization level (default: -O1)
+    --target TARGET         Generate code for TARGET: x86_64-linux, x86_64-macos,
+                            x86_64-windows, aarch64-linux or riscv64-linux
+                            (default: the host)
+    --emit KINDS            Comma separated list of outputs to emit: mir, asm, obj or exe
+                            (default: obj)
+    --pic                   Generate position-independent code
+    --visibility VISIBILITY Give the functions defined in the inputs VISIBILITY: default,
+                            hidden or protected (default: default)
+    --unroll-factor N       Unroll the loops that cannot be fully unrolled N times at -O2,
+                            1 disables it (default: 4)
+    --profile-generate PATH Count how many times each block runs and write the counts to PATH
+                            when the program exits, or to the path in the `PIJAMA_PROFILE`
+                            environment variable if it is set
+    --profile-use PATH      Optimize using the counts in the profile at PATH
+    --enable-pass PASSES    Run the comma separated PASSES even if the optimization level
+                            does not include them
+    --disable-pass PASSES   Never run the comma separated PASSES
+    --dump-after PASSES     Print the IR to the standard error after each one of the comma
+                            separated PASSES, `all` prints it after every pass
+    --time-passes           Print how long each pass took to the standard error
+    -h, --help              Print this message
+";
+
+/// The kinds of output that can be emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Emit {
+    Mir,
+    Asm,
+    Obj,
+    Exe,
+}
+
+impl Emit {
+    fn extension(self, format: Format) -> &'static str {
+        match (self, format) {
+            (Emit::Mir, _) => "mir",
+            (Emit::Asm, _) => "s",
+            (Emit::Obj, Format::Coff) => "obj",
+            (Emit::Obj, Format::Elf | Format::MachO) => "o",
+            (Emit::Exe, Format::Coff) => "exe",
+            (Emit::Exe, Format::Elf | Format::MachO) => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub inputs: Vec<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub passes: PassOptions,
+    pub format: Format,
+    pub arch: Arch,
+    pub emit: Vec<Emit>,
+    pub relocation_model: RelocationModel,
+    /// The visibility of the functions defined in the inputs.
+    pub visibility: Visibility,
+    /// The number of copies of a loop body made by the `unroll` pass.
+    pub unroll_factor: u32,
+    /// The default path of the profile written by an instrumented program.
+    pub profile_generate: Option<PathBuf>,
+    /// The profile that guides the optimizations.
+    pub profile_use: Option<PathBuf>,
+}
+
+impl Options {
+    /// Parses the command-line arguments, without the name of the program.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, DriverError> {
+        let mut options = Options {
+            inputs: Vec::new(),
+            output: None,
+            passes: PassOptions::default(),
+            format: Format::host(),
+            arch: Arch::host(),
+            emit: Vec::new(),
+            relocation_model: RelocationModel::Static,
+            visibility: Visibility::Default,
+            unroll_factor: Unroll::default().factor,
+            profile_generate: None,
+            profile_use: None,
+        };
+
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Options with values can be written as `--option=value` or `--option value`.
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_owned())),
+                _ => (arg.as_str(), None),
+            };
+
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| DriverError::Usage(format!("missing value for `{name}`")))
+            };
+
+            match name {
+                "-o" => options.output = Some(value()?.into()),
+                "-O0" => options.passes.opt_level = OptLevel::O0,
+                "-O1" => options.passes.opt_level = OptLevel::O1,
+                "-O2" => options.passes.opt_level = OptLevel::O2,
+                "--target" => {
+                    (options.format, options.arch) = match value()?.as_str() {
+                        "x86_64-linux" | "x86_64-unknown-linux-gnu" => (Format::Elf, Arch::X86_64),
+                        "x86_64-macos" | "x86_64-apple-darwin" => (Format::MachO, Arch::X86_64),
+                        "x86_64-windows" | "x86_64-pc-windows-msvc" => (Format::Coff, Arch::X86_64),
+                        "aarch64-linux" | "aarch64-unknown-linux-gnu" => {
+                            (Format::Elf, Arch::Aarch64)
+                        }
+                        "riscv64-linux" | "riscv64-unknown-linux-gnu" => {
+                            (Format::Elf, Arch::Riscv64)
+                        }
+                        target => {
+                            return Err(DriverError::Usage(format!("unknown target `{target}`")))
+                        }
+                    }
+                }
+                "--emit" => {
+                    for kind in value()?.split(',') {
+                        let kind = match kind {
+                            "mir" => Emit::Mir,
+                            "asm" => Emit::Asm,
+                            "obj" => Emit::Obj,
+                            "exe" => Emit::Exe,
+                            kind => {
+                                return Err(DriverError::Usage(format!(
+                                    "unknown output kind `{kind}`"
+                                )))
+                            }
+                        };
+
+                        if !options.emit.contains(&kind) {
+                            options.emit.push(kind);
+                        }
+                    }
+                }
+                "--pic" => options.relocation_model = RelocationModel::Pic,
+                "--visibility" => {
+                    options.visibility = match value()?.as_str() {
+                        "default" => Visibility::Default,
+                        "hidden" => Visibility::Hidden,
+                        "protected" => Visibility::Protected,
+                        visibility => {
+                            return Err(DriverError::Usage(format!(
+                                "unknown visibility `{visibility}`"
+                            )))
+                        }
+                    }
+                }
+                "--unroll-factor" => {
+                    let factor = value()?;
+                    options.unroll_factor = match factor.parse() {
+                        Ok(factor) if factor > 0 => factor,
+                        _ => {
+                            return Err(DriverError::Usage(format!(
+                                "invalid unroll factor `{factor}`"
+                            )))
+                        }
+                    }
+                }
+                "--profile-generate" => options.profile_generate = Some(value()?.into()),
+                "--profile-use" => options.profile_use = Some(value()?.into()),
+                "--enable-pass" => options
+                    .passes
+                    .enabled
+                    .extend(value()?.split(',').map(str::to_owned)),
+                "--disable-pass" => options
+                    .passes
+                    .disabled
+                    .extend(value()?.split(',').map(str::to_owned)),
+                "--dump-after" => options
+                    .passes
+                    .dump_after
+                    .extend(value()?.split(',').map(str::to_owned)),
+                "--time-passes" => options.passes.time_passes = true,
+                _ if arg.starts_with('-') => {
+                    return Err(DriverError::Usage(format!("unknown option `{arg}`")))
+                }
+                _ => options.inputs.push(arg.into()),
+            }
+        }
+
+        if options.inputs.is_empty() {
+            return Err(DriverError::Usage("no input files".to_owned()));
+        }
+
+        if options.emit.is_empty() {
+            options.emit.push(Emit::Obj);
+        }
+        options.emit.sort();
+
+        Ok(options)
+    }
+
+    /// Returns the path where the output of kind `emit` must be written or `None` if it must be
+    /// written to the standard output.
+    fn output_path(&self, emit: Emit) -> Option<PathBuf> {
+        match &self.output {
+            Some(output) if output == Path::new("-") => None,
+            Some(output) if self.emit.len() == 1 => Some(output.clone()),
+            Some(output) => Some(output.with_extension(emit.extension(self.format))),
+            // Text is written to the standard output by default.
+            None if matches!(emit, Emit::Mir | Emit::Asm) => None,
+            None => {
+                let stem = self.inputs[0].file_stem().unwrap_or(OsStr::new("a"));
+                Some(Path::new(stem).with_extension(emit.extension(self.format)))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DriverError {
+    /// The command-line arguments are invalid.
+    Usage(String),
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, ParseError),
+    /// A function uses floats, but the target has no float registers.
+    Floats(String),
+    /// A function cannot be lowered to the instructions of the target.
+    Lower(String, LowerError),
+    Assembler(String, AssemblerError),
+    Emit(EmitError),
+    /// The linker could not be run or failed.
+    Link(String),
+}
+
+impl DriverError {
+    /// The exit code of the process when this error happens.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            DriverError::Usage(_) => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for DriverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DriverError::Usage(message) => write!(f, "{message}"),
+            DriverError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            DriverError::Parse(path, err) => write!(f, "{}:{err}", path.display()),
+            DriverError::Floats(name) => {
+                write!(f, "`{name}` uses floats, which the target does not support")
+            }
+            DriverError::Lower(name, err) => write!(f, "cannot lower `{name}`: {err}"),
+            DriverError::Assembler(name, err) => write!(f, "cannot assemble `{name}`: {err}"),
+            DriverError::Emit(err) => write!(f, "{err}"),
+            DriverError::Link(message) => write!(f, "linking failed: {message}"),
+        }
+    }
+}
+
+impl Error for DriverError {}
+
+impl From<EmitError> for DriverError {
+    fn from(err: EmitError) -> Self {
+        DriverError::Emit(err)
+    }
+}
+
+/// Compiles the inputs according to `options`.
+pub fn run(options: &Options) -> Result<(), DriverError> {
+    match options.arch {
+        Arch::X86_64 if options.format == Format::Coff => compile::<X86_64Windows>(options),
+        Arch::X86_64 => compile::<X86_64>(options),
+        Arch::Aarch64 => compile::<Aarch64>(options),
+        Arch::Riscv64 => compile::<Riscv64>(options),
+    }
+}
+
+fn compile<T: Target>(options: &Options) -> Result<(), DriverError> {
+    let mut module_passes = PassManager::new(mir::optimize::module_pipeline(), &options.passes);
+    let registers = |class| {
+        T::ALLOCATABLE_REGISTERS
+            .iter()
+            .filter(|reg| T::register_class(**reg) == class)
+            .count()
+    };
+    let unroll = Unroll {
+        factor: options.unroll_factor,
+        general_registers: registers(RegisterClass::General),
+        float_registers: registers(RegisterClass::Float),
+    };
+    let mut mir_passes = PassManager::new(mir::optimize::pipeline(unroll), &options.passes);
+    let mut asm_passes = PassManager::new(optimize::pipeline(), &options.passes);
+
+    let passes = &options.passes;
+    if let Some(name) = passes
+        .enabled
+        .iter()
+        .chain(&passes.disabled)
+        .chain(passes.dump_after.iter().filter(|name| *name != "all"))
+        .find(|name| {
+            !module_passes.contains(name)
+                && !mir_passes.contains(name)
+                && !asm_passes.contains(name)
+        })
+    {
+        return Err(DriverError::Usage(format!("unknown pass `{name}`")));
+    }
+
+    let mut module = Module::default();
+    let mut asm_functions = Vec::new();
+    let mut linker_inputs = Vec::new();
+
+    for input in &options.inputs {
+        let read = || fs::read_to_string(input).map_err(|err| DriverError::Io(input.clone(), err));
+
+        match input.extension().and_then(OsStr::to_str) {
+            Some("mir") => {
+                let parsed =
+                    parse_module(&read()?).map_err(|err| DriverError::Parse(input.clone(), err))?;
+
+                for (name, func) in parsed.functions {
+                    if module.functions.insert(name.clone(), func).is_some() {
+                        return Err(DriverError::Emit(EmitError::DuplicateSymbol(name)));
+                    }
+                }
+            }
+            Some("s" | "asm") => asm_functions.extend(
+                parse_functions::<T::Register>(&read()?)
+                    .map_err(|err| DriverError::Parse(input.clone(), err))?,
+            ),
+            _ => linker_inputs.push(input.clone()),
+        }
+    }
+
+    // Only the MIR is needed when it is the only output.
+    let lower = options.emit.iter().any(|emit| *emit != Emit::Mir);
+
+    // The floats would reach the instruction selection or the assembler, which cannot handle them
+    // without float registers.
+    if lower && registers(RegisterClass::Float) == 0 {
+        let name = module
+            .functions
+            .iter()
+            .find(|(_, func)| uses_floats(func))
+            .map(|(name, _)| name)
+            .or_else(|| {
+                asm_functions
+                    .iter()
+                    .find(|(_, instructions)| {
+                        instructions
+                            .iter()
+                            .any(|kind| matches!(kind, InstructionKind::Float(..)))
+                    })
+                    .map(|(name, _)| name)
+            });
+        if let Some(name) = name {
+            return Err(DriverError::Floats(name.clone()));
+        }
+    }
+
+    if !linker_inputs.is_empty() && !options.emit.contains(&Emit::Exe) {
+        return Err(DriverError::Usage(format!(
+            "`{}` can only be used when emitting an executable",
+            linker_inputs[0].display()
+        )));
+    }
+
+    if let Some(path) = &options.profile_use {
+        let src = fs::read_to_string(path).map_err(|err| DriverError::Io(path.clone(), err))?;
+        let profile = Profile::parse(&src).map_err(|err| DriverError::Parse(path.clone(), err))?;
+        profile.apply(&mut module);
+    }
+
+    // The counters are assigned before any optimization, so they count the blocks of the input.
+    let instrumentation = options
+        .profile_generate
+        .as_ref()
+        .map(|path| (Instrumentation::new(&mut module), path.as_path()));
+
+    module_passes.run("module", &mut module);
+    eprint!("{}", module_passes.take_dumps());
+
+    for (name, func) in &mut module.functions {
+        mir_passes.run(name, func);
+        eprint!("{}", mir_passes.take_dumps());
+    }
+
+    if options.emit.contains(&Emit::Mir) {
+        if !asm_functions.is_empty() {
+            return Err(DriverError::Usage(
+                "cannot emit MIR from assembly inputs".to_owned(),
+            ));
+        }
+
+        write_output(options, Emit::Mir, module.to_string().as_bytes())?;
+    }
+
+    // Functions with hidden or protected visibility cannot be preempted, so their addresses do not
+    // need to be loaded from the global offset table.
+    let local_symbols = match options.visibility {
+        Visibility::Default => BTreeSet::new(),
+        Visibility::Hidden | Visibility::Protected => module
+            .functions
+            .keys()
+            .chain(asm_functions.iter().map(|(name, _)| name))
+            .cloned()
+            .collect(),
+    };
+    let lower_options = LowerOptions {
+        relocation_model: options.relocation_model,
+        local_symbols,
+    };
+
+    let mut functions = Vec::new();
+    if lower {
+        for (name, func) in &module.functions {
+            let mut instructions = mir_lowering::lower_function::<T>(func, &lower_options)
+                .map_err(|err| DriverError::Lower(name.clone(), err))?;
+            asm_passes.run(name, &mut instructions);
+            eprint!("{}", asm_passes.take_dumps());
+            functions.push((name.clone(), instructions));
+        }
+        functions.extend(asm_functions);
+    }
+
+    if options.passes.time_passes {
+        eprint!(
+            "{}",
+            timing_report(
+                module_passes
+                    .statistics()
+                    .chain(mir_passes.statistics())
+                    .chain(asm_passes.statistics())
+            )
+        );
+    }
+
+    if options.emit.contains(&Emit::Asm) {
+        let mut text = String::new();
+        for (name, instructions) in &functions {
+            text += &format!("{name}:\n{instructions}");
+        }
+
+        write_output(options, Emit::Asm, text.as_bytes())?;
+    }
+
+    if !options.emit.contains(&Emit::Obj) && !options.emit.contains(&Emit::Exe) {
+        return Ok(());
+    }
+
+    let object = emit_object::<T>(options, functions, instrumentation.as_ref())?;
+
+    if options.emit.contains(&Emit::Obj) {
+        write_output(options, Emit::Obj, &object)?;
+    }
+
+    if options.emit.contains(&Emit::Exe) {
+        link(options, &object, &linker_inputs, instrumentation.is_some())?;
+    }
+
+    Ok(())
+}
+
+/// Returns whether `func` has a float local or reads a float constant.
+fn uses_floats(func: &Function) -> bool {
+    let is_float = |ty: &Ty| RegisterClass::of(ty) == RegisterClass::Float;
+
+    func.local_types.values().any(is_float)
+        || func
+            .basic_blocks
+            .values()
+            .flat_map(|bb_data| &bb_data.statements)
+            .flat_map(Statement::operands)
+            .any(|operand| matches!(operand, Operand::Constant(literal) if is_float(&literal.ty)))
+}
+
+/// Formats the statistics of the passes as a table.
+fn timing_report<'a>(statistics: impl Iterator<Item = &'a PassStatistics>) -> String {
+    let mut report = format!(
+        "{:>12}  {:>6}  {:>7}  pass\n",
+        "time (ms)", "runs", "changed"
+    );
+    let mut total = Duration::ZERO;
+
+    for statistics in statistics {
+        report += &format!(
+            "{:>12.3}  {:>6}  {:>7}  {}\n",
+            statistics.time.as_secs_f64() * 1000.0,
+            statistics.runs,
+            statistics.changes,
+            statistics.name
+        );
+        total += statistics.time;
+    }
+
+    report += &format!("{:>12.3}  total\n", total.as_secs_f64() * 1000.0);
+    report
+}
+
+fn emit_object<T: Target>(
+    options: &Options,
+    functions: Vec<(String, Instructions<T::Register>)>,
+    instrumentation: Option<&(Instrumentation, &Path)>,
+) -> Result<Vec<u8>, DriverError> {
+    let mut emitter = ObjectEmitter::<T>::for_target(options.format)?;
+
+    if let Some((instrumentation, path)) = instrumentation {
+        instrumentation.emit(&mut emitter, path)?;
+    }
+
+    for (name, instructions) in functions {
+        let mut code = Vec::new();
+        let relocations = T::assemble(instructions, &mut code)
+            .map_err(|err| DriverError::Assembler(name.clone(), err))?;
+
+        emitter.add_function(&name, options.visibility, &code, relocations)?;
+    }
+
+    Ok(emitter.write()?)
+}
+
+fn write_output(options: &Options, emit: Emit, bytes: &[u8]) -> Result<(), DriverError> {
+    match options.output_path(emit) {
+        Some(path) => fs::write(&path, bytes).map_err(|err| DriverError::Io(path, err)),
+        None => io::stdout()
+            .write_all(bytes)
+            .map_err(|err| DriverError::Io("<stdout>".into(), err)),
+    }
+}
+
+/// Links the object file with the system C compiler, which can be overridden with the `CC`
+/// environment variable. An instrumented object is linked with the profile runtime.
+fn link(
+    options: &Options,
+    object: &[u8],
+    linker_inputs: &[PathBuf],
+    instrumented: bool,
+) -> Result<(), DriverError> {
+    if options.format != Format::host() || options.arch != Arch::host() {
+        return Err(DriverError::Usage(
+            "executables can only be emitted for the host target".to_owned(),
+        ));
+    }
+
+    let output = options
+        .output_path(Emit::Exe)
+        .ok_or_else(|| DriverError::Usage("executables cannot be written to stdout".to_owned()))?;
+
+    let object_path = std::env::temp_dir().join(format!("pijama-{}.o", std::process::id()));
+    fs::write(&object_path, object).map_err(|err| DriverError::Io(object_path.clone(), err))?;
+
+    let runtime_path =
+        std::env::temp_dir().join(format!("pijama-{}-profile.c", std::process::id()));
+    if instrumented {
+        fs::write(&runtime_path, profile::RUNTIME)
+            .map_err(|err| DriverError::Io(runtime_path.clone(), err))?;
+    }
+
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_owned());
+    let result = Command::new(&cc)
+        .arg(&object_path)
+        .args(instrumented.then_some(&runtime_path))
+        .args(linker_inputs)
+        .arg("-o")
+        .arg(&output)
+        .output();
+
+    // The temporary files are not needed anymore, even if linking failed.
+    let _ = fs::remove_file(&object_path);
+    if instrumented {
+        let _ = fs::remove_file(&runtime_path);
+    }
+
+    let result = result.map_err(|err| DriverError::Link(format!("cannot run `{cc}`: {err}")))?;
+
+    if !result.status.success() {
+        return Err(DriverError::Link(
+            String::from_utf8_lossy(&result.stderr)
+                .trim_end()
+                .to_owned(),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,391 @@
+use std::{error::Error as StdError, fmt, io, marker::PhantomData};
+
+use object::{
+    elf,
+    write::{self, Object, SectionId, StandardSection, SymbolId, SymbolSection},
+    BinaryFormat, Endianness, SectionKind, SymbolFlags, SymbolKind, SymbolScope,
+};
+
+use crate::asm::{Relocation, RelocationKind};
+use crate::target::{Target, X86_64};
+
+/// The architectures that code can be emitted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arch {
+    X86_64,
+    Aarch64,
+    Riscv64,
+}
+
+impl Arch {
+    /// The architecture of the host.
+    pub const fn host() -> Self {
+        if cfg!(target_arch = "aarch64") {
+            Self::Aarch64
+        } else if cfg!(target_arch = "riscv64") {
+            Self::Riscv64
+        } else {
+            Self::X86_64
+        }
+    }
+}
+
+/// The object file formats that can be emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The ELF format used on Linux.
+    Elf,
+    /// The Mach-O format used on macOS.
+    MachO,
+    /// The PE/COFF format used on Windows.
+    Coff,
+}
+
+impl Format {
+    /// The object file format of the host.
+    pub const fn host() -> Self {
+        if cfg!(target_os = "macos") {
+            Self::MachO
+        } else if cfg!(target_os = "windows") {
+            Self::Coff
+        } else {
+            Self::Elf
+        }
+    }
+
+    const fn binary_format(self) -> BinaryFormat {
+        match self {
+            Self::Elf => BinaryFormat::Elf,
+            Self::MachO => BinaryFormat::MachO,
+            Self::Coff => BinaryFormat::Coff,
+        }
+    }
+
+    /// Returns the name of the symbol that must be used as the target of a relocation.
+    fn relocation_target(self, relocation: &Relocation) -> String {
+        match (self, relocation.kind) {
+            (Self::Coff, RelocationKind::GotRelative) => format!("__imp_{}", relocation.symbol),
+            _ => relocation.symbol.clone(),
+        }
+    }
+
+    /// Returns the scope and flags used by this format for a symbol with the given visibility.
+    fn symbol_scope(
+        self,
+        visibility: Visibility,
+        kind: SymbolKind,
+    ) -> Result<(SymbolScope, SymbolFlags<SectionId>), EmitError> {
+        match (self, visibility) {
+            (_, Visibility::Default) => Ok((SymbolScope::Dynamic, SymbolFlags::None)),
+            // `object` uses the `STV_HIDDEN` visibility on ELF and the private external bit on
+            // Mach-O for symbols in the linkage scope.
+            (_, Visibility::Hidden) => Ok((SymbolScope::Linkage, SymbolFlags::None)),
+            (Self::Elf, Visibility::Protected) => {
+                let st_type = match kind {
+                    SymbolKind::Text => elf::STT_FUNC,
+                    _ => elf::STT_OBJECT,
+                };
+
+                Ok((
+                    SymbolScope::Dynamic,
+                    SymbolFlags::Elf {
+                        st_info: (elf::STB_GLOBAL << 4) + (st_type & 0xf),
+                        st_other: elf::STV_PROTECTED & 0x3,
+                    },
+                ))
+            }
+            // Symbols cannot be preempted on Windows.
+            (Self::Coff, Visibility::Protected) => Ok((SymbolScope::Dynamic, SymbolFlags::None)),
+            (Self::MachO, Visibility::Protected) => {
+                Err(EmitError::UnsupportedVisibility(visibility))
+            }
+        }
+    }
+}
+
+/// The visibility of a symbol when linking a shared library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    /// The symbol is exported and can be preempted by a definition in another module.
+    Default,
+    /// The symbol is not exported from the shared library.
+    Hidden,
+    /// The symbol is exported but it cannot be preempted.
+    Protected,
+}
+
+#[derive(Debug)]
+pub enum EmitError {
+    /// A function with the same name was already added.
+    DuplicateSymbol(String),
+    /// The visibility cannot be represented by the format.
+    UnsupportedVisibility(Visibility),
+    /// The format cannot be emitted for the architecture.
+    UnsupportedArch(Format, Arch),
+    /// A relocation points outside of the code of its function.
+    InvalidRelocation(Relocation),
+    Object(write::Error),
+}
+
+impl fmt::Display for EmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateSymbol(name) => write!(f, "symbol `{name}` is defined more than once"),
+            Self::UnsupportedVisibility(visibility) => {
+                write!(
+                    f,
+                    "visibility {visibility:?} is not supported by this format"
+                )
+            }
+            Self::UnsupportedArch(format, arch) => {
+                write!(f, "format {format:?} is not supported on {arch:?}")
+            }
+            Self::InvalidRelocation(relocation) => write!(
+                f,
+                "relocation for symbol `{}` at offset {} is out of bounds",
+                relocation.symbol, relocation.offset
+            ),
+            Self::Object(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl StdError for EmitError {}
+
+impl From<write::Error> for EmitError {
+    fn from(err: write::Error) -> Self {
+        Self::Object(err)
+    }
+}
+
+/// Emitter for relocatable object files with the code of target `T`.
+pub struct ObjectEmitter<'a, T = X86_64> {
+    object: Object<'a>,
+    format: Format,
+    text: SectionId,
+    data: SectionId,
+    bss: SectionId,
+    /// Relocations are added after all the functions so every symbol is already defined when
+    /// they are resolved. The first field is the offset of the function inside the text section.
+    relocations: Vec<(u64, Relocation)>,
+    target: PhantomData<T>,
+}
+
+impl<'a> ObjectEmitter<'a> {
+    /// Returns an emitter of x86-64 code, which can be emitted in every format.
+    pub fn new(format: Format) -> Self {
+        Self::new_unchecked(format)
+    }
+}
+
+impl<'a, T: Target> ObjectEmitter<'a, T> {
+    /// Returns an emitter of code for `T`, if the target can be emitted as `format`.
+    pub fn for_target(format: Format) -> Result<Self, EmitError> {
+        if T::FORMATS.contains(&format) {
+            Ok(Self::new_unchecked(format))
+        } else {
+            Err(EmitError::UnsupportedArch(format, T::ARCH))
+        }
+    }
+
+    fn new_unchecked(format: Format) -> Self {
+        let mut object = Object::new(format.binary_format(), T::ARCHITECTURE, Endianness::Little);
+
+        // The section name depends on the format: `.text` on ELF and COFF and `__text` inside
+        // the `__TEXT` segment on Mach-O.
+        let text = object.section_id(StandardSection::Text);
+        let data = object.section_id(StandardSection::Data);
+        let bss = object.section_id(StandardSection::UninitializedData);
+
+        if format == Format::Elf {
+            // Without this section the linker assumes that the code needs an executable stack.
+            object.add_section(Vec::new(), b".note.GNU-stack".to_vec(), SectionKind::Other);
+        }
+
+        Self {
+            object,
+            format,
+            text,
+            data,
+            bss,
+            relocations: Vec::new(),
+            target: PhantomData,
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn arch(&self) -> Arch {
+        T::ARCH
+    }
+
+    /// Adds the assembled `code` of a function as a global symbol called `name`.
+    ///
+    /// The name must not be mangled, the leading underscore required by Mach-O is added
+    /// automatically.
+    pub fn add_function(
+        &mut self,
+        name: &str,
+        visibility: Visibility,
+        code: &[u8],
+        relocations: Vec<Relocation>,
+    ) -> Result<(), EmitError> {
+        if let Some(relocation) = relocations
+            .iter()
+            .find(|relocation| relocation.offset + std::mem::size_of::<i32>() > code.len())
+        {
+            return Err(EmitError::InvalidRelocation(relocation.clone()));
+        }
+
+        let symbol_id = self.define_symbol(name, visibility, SymbolKind::Text, code.len())?;
+        let offset = self.object.add_symbol_data(symbol_id, self.text, code, 16);
+
+        self.relocations.extend(
+            relocations
+                .into_iter()
+                .map(|relocation| (offset, relocation)),
+        );
+
+        Ok(())
+    }
+
+    /// Adds `data` as a global writable symbol called `name`.
+    pub fn add_data(
+        &mut self,
+        name: &str,
+        visibility: Visibility,
+        data: &[u8],
+    ) -> Result<(), EmitError> {
+        let symbol_id = self.define_symbol(name, visibility, SymbolKind::Data, data.len())?;
+        self.object.add_symbol_data(symbol_id, self.data, data, 8);
+
+        Ok(())
+    }
+
+    /// Adds `size` bytes of zero-initialized writable memory as a global symbol called `name`.
+    pub fn add_bss(
+        &mut self,
+        name: &str,
+        visibility: Visibility,
+        size: usize,
+    ) -> Result<(), EmitError> {
+        let symbol_id = self.define_symbol(name, visibility, SymbolKind::Data, size)?;
+        self.object
+            .add_symbol_bss(symbol_id, self.bss, size as u64, 8);
+
+        Ok(())
+    }
+
+    fn define_symbol(
+        &mut self,
+        name: &str,
+        visibility: Visibility,
+        kind: SymbolKind,
+        size: usize,
+    ) -> Result<SymbolId, EmitError> {
+        if self.object.symbol_id(name.as_bytes()).is_some() {
+            return Err(EmitError::DuplicateSymbol(name.to_owned()));
+        }
+
+        let (scope, flags) = self.format.symbol_scope(visibility, kind)?;
+
+        let section = match kind {
+            SymbolKind::Text => self.text,
+            _ => self.data,
+        };
+
+        Ok(self.object.add_symbol(write::Symbol {
+            name: name.as_bytes().to_vec(),
+            // It seems that `object` ignores this value so we can leave it be zero.
+            value: 0,
+            size: size as u64,
+            kind,
+            scope,
+            weak: false,
+            section: SymbolSection::Section(section),
+            flags,
+        }))
+    }
+
+    /// Returns the symbol called `name`, adding it as an undefined symbol if it does not exist.
+    fn symbol(&mut self, name: &str, kind: SymbolKind) -> SymbolId {
+        if let Some(symbol_id) = self.object.symbol_id(name.as_bytes()) {
+            return symbol_id;
+        }
+
+        self.object.add_symbol(write::Symbol {
+            name: name.as_bytes().to_vec(),
+            value: 0,
+            size: 0,
+            kind,
+            scope: SymbolScope::Dynamic,
+            weak: false,
+            section: SymbolSection::Undefined,
+            flags: SymbolFlags::None,
+        })
+    }
+
+    /// Resolves the relocations of every function added so far.
+    fn add_relocations(&mut self) -> Result<(), EmitError> {
+        for (offset, relocation) in std::mem::take(&mut self.relocations) {
+            let kind = match relocation.kind {
+                RelocationKind::Branch => SymbolKind::Text,
+                RelocationKind::Relative
+                | RelocationKind::GotRelative
+                | RelocationKind::PageRelative
+                | RelocationKind::PageOffset
+                | RelocationKind::GotPageRelative
+                | RelocationKind::GotPageOffset
+                | RelocationKind::PcRelativeHigh
+                | RelocationKind::GotPcRelativeHigh => SymbolKind::Data,
+                RelocationKind::PcRelativeLow => SymbolKind::Label,
+            };
+            let symbol = if kind == SymbolKind::Label {
+                // The low bits are computed from the distance found by the `auipc` before the
+                // patched instruction, so the relocation points to a local label at the `auipc`.
+                let auipc = offset + relocation.offset as u64 - 4;
+                self.object.add_symbol(write::Symbol {
+                    name: format!(".Lpcrel_hi{auipc}").into_bytes(),
+                    value: auipc,
+                    size: 0,
+                    kind,
+                    scope: SymbolScope::Compilation,
+                    weak: false,
+                    section: SymbolSection::Section(self.text),
+                    flags: SymbolFlags::None,
+                })
+            } else {
+                self.symbol(&self.format.relocation_target(&relocation), kind)
+            };
+            let (kind, encoding) = T::relocation(self.format, relocation.kind);
+
+            self.object.add_relocation(
+                self.text,
+                write::Relocation {
+                    offset: offset + relocation.offset as u64,
+                    size: 32,
+                    kind,
+                    encoding,
+                    symbol,
+                    addend: relocation.addend,
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the object file into a `Vec`.
+    pub fn write(mut self) -> Result<Vec<u8>, EmitError> {
+        self.add_relocations()?;
+        Ok(self.object.write()?)
+    }
+
+    /// Writes the object file into `w`.
+    pub fn write_stream<W: io::Write>(mut self, w: W) -> Result<(), Box<dyn StdError>> {
+        self.add_relocations()?;
+        self.object.write_stream(w)
+    }
+}
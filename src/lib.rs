@@ -1,3 +1,10 @@
 pub mod asm;
+pub mod dataflow;
+pub mod driver;
+pub mod emit;
 pub mod mir;
 pub mod mir_lowering;
+pub mod parse;
+pub mod pass;
+pub mod profile;
+pub mod target;
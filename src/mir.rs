@@ -1,16 +1,28 @@
 mod bb;
+pub mod cfg;
+pub mod dataflow;
+mod display;
 mod func;
+pub mod induction;
+mod module;
+pub mod optimize;
+pub mod parse;
 mod statement;
 mod terminator;
 
-pub use bb::{BasicBlock, BasicBlockId};
-pub use func::Function;
+use std::collections::BTreeMap;
+
+pub use bb::{BasicBlock, BasicBlockId, BlockProfile};
+pub use display::DisplayFunction;
+pub use func::{Function, InlineHint};
+pub use module::Module;
 pub use statement::Statement;
 pub use terminator::Terminator;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Local(usize);
 
+#[derive(Debug, Clone)]
 pub enum Rvalue {
     Use(Operand),
     BinaryOp {
@@ -18,27 +30,159 @@ pub enum Rvalue {
         lhs: Operand,
         rhs: Operand,
     },
+    /// The address of a symbol defined in this module or in another one.
+    SymbolAddr(String),
+    /// The value returned by a function defined in this module or in another one.
+    Call {
+        callee: String,
+        args: Vec<Operand>,
+    },
+    /// The value of `operand` converted to `ty`. Floats are converted to integers by truncating
+    /// them towards zero and the result is unspecified if it does not fit. Integers and booleans
+    /// are converted to each other by keeping their bits.
+    Cast {
+        operand: Operand,
+        ty: Ty,
+    },
 }
 
+impl Rvalue {
+    /// Returns the operands read by this rvalue.
+    pub fn operands(&self) -> impl Iterator<Item = &Operand> {
+        let (first, second, rest) = match self {
+            Rvalue::Use(operand) | Rvalue::Cast { operand, .. } => (Some(operand), None, &[][..]),
+            Rvalue::BinaryOp { lhs, rhs, .. } => (Some(lhs), Some(rhs), &[][..]),
+            Rvalue::SymbolAddr(_) => (None, None, &[][..]),
+            Rvalue::Call { args, .. } => (None, None, &args[..]),
+        };
+
+        first.into_iter().chain(second).chain(rest)
+    }
+
+    pub fn operands_mut(&mut self) -> impl Iterator<Item = &mut Operand> {
+        let (first, second, rest) = match self {
+            Rvalue::Use(operand) | Rvalue::Cast { operand, .. } => {
+                (Some(operand), None, &mut [][..])
+            }
+            Rvalue::BinaryOp { lhs, rhs, .. } => (Some(lhs), Some(rhs), &mut [][..]),
+            Rvalue::SymbolAddr(_) => (None, None, &mut [][..]),
+            Rvalue::Call { args, .. } => (None, None, &mut args[..]),
+        };
+
+        first.into_iter().chain(second).chain(rest)
+    }
+
+    /// Returns whether evaluating this rvalue can do something other than computing its value,
+    /// in which case it cannot be removed even if its value is never read.
+    pub fn has_side_effects(&self) -> bool {
+        matches!(self, Rvalue::Call { .. })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum BinOp {
     Add,
+    Mul,
     Lt,
 }
 
-#[derive(Clone)]
+impl BinOp {
+    /// Returns whether swapping the operands of this operation does not change its result.
+    pub fn is_commutative(&self) -> bool {
+        match self {
+            BinOp::Add | BinOp::Mul => true,
+            BinOp::Lt => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum Operand {
     Local(Local),
     Constant(Literal),
 }
 
-#[derive(Clone)]
+impl Operand {
+    /// Returns the local read by this operand, if any.
+    pub fn local(&self) -> Option<Local> {
+        match self {
+            Operand::Local(local) => Some(*local),
+            Operand::Constant(_) => None,
+        }
+    }
+
+    /// Returns the type of this operand given the types of the locals.
+    pub fn ty(&self, local_types: &BTreeMap<Local, Ty>) -> Ty {
+        match self {
+            Operand::Local(local) => local_types[local],
+            Operand::Constant(literal) => literal.ty,
+        }
+    }
+}
+
+/// A constant of type `ty`. Integers are stored as 64-bit two's complement numbers, booleans as `0`
+/// or `1` and floats as their IEEE 754 bits, so the bits of an `f32` are the lower 32 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Literal {
-    pub data: u32,
+    pub data: u64,
     pub ty: Ty,
 }
 
-#[derive(Clone)]
+impl Literal {
+    pub fn int(value: i64) -> Self {
+        Self {
+            data: value as u64,
+            ty: Ty::Int,
+        }
+    }
+
+    pub fn bool(value: bool) -> Self {
+        Self {
+            data: value.into(),
+            ty: Ty::Bool,
+        }
+    }
+
+    pub fn f32(value: f32) -> Self {
+        Self {
+            data: value.to_bits().into(),
+            ty: Ty::F32,
+        }
+    }
+
+    pub fn f64(value: f64) -> Self {
+        Self {
+            data: value.to_bits(),
+            ty: Ty::F64,
+        }
+    }
+
+    /// Returns the value of a float constant.
+    pub fn float(&self) -> f64 {
+        match self.ty {
+            Ty::F32 => f32::from_bits(self.data as u32).into(),
+            Ty::F64 => f64::from_bits(self.data),
+            Ty::Int | Ty::Bool => panic!("the constant is not a float"),
+        }
+    }
+
+    /// Returns the bits of this constant as they are held in a 64-bit register, where `f32`s only
+    /// use the lower 32 bits.
+    pub fn bits(&self) -> i64 {
+        self.data as i64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Ty {
     Int,
     Bool,
+    F32,
+    F64,
+}
+
+impl Ty {
+    pub fn is_float(self) -> bool {
+        matches!(self, Ty::F32 | Ty::F64)
+    }
 }
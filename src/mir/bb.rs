@@ -4,7 +4,29 @@ use crate::mir::terminator::Terminator;
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct BasicBlockId(pub(super) usize);
 
+#[derive(Debug, Clone)]
 pub struct BasicBlock {
     pub statements: Vec<Statement>,
     pub terminator: Terminator,
+    /// Whether the block is unlikely to be executed, written as `#[cold]` before the block. Cold
+    /// blocks are placed at the end of the function.
+    pub cold: bool,
+    pub profile: BlockProfile,
+}
+
+/// How often a block runs, see [`crate::profile`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockProfile {
+    /// The counters incremented every time the block runs in an instrumented build. Blocks merged
+    /// into this one keep their counters.
+    pub counters: Vec<usize>,
+    /// The number of times the block ran according to the profile used to compile it.
+    pub count: Option<u64>,
+}
+
+impl BasicBlock {
+    /// Returns whether the block starts with phis, which only happens in SSA form.
+    pub fn has_phis(&self) -> bool {
+        self.statements.first().is_some_and(Statement::is_phi)
+    }
 }
@@ -0,0 +1,302 @@
+//! Analyses of the control flow graph of a [`Function`].
+//!
+//! Blocks that cannot be reached from the entry block are ignored by every analysis except
+//! [`Cfg::successors`] and [`Cfg::predecessors`].
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::dataflow::reverse_postorder;
+use crate::mir::{BasicBlockId, Function};
+
+/// The edges between the blocks of a function.
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    entry: BasicBlockId,
+    successors: BTreeMap<BasicBlockId, Vec<BasicBlockId>>,
+    predecessors: BTreeMap<BasicBlockId, Vec<BasicBlockId>>,
+    reverse_postorder: Vec<BasicBlockId>,
+}
+
+impl Cfg {
+    pub fn new(func: &Function) -> Self {
+        let entry = *func
+            .basic_blocks
+            .keys()
+            .next()
+            .expect("function without blocks");
+
+        let mut successors = BTreeMap::new();
+        let mut predecessors = func
+            .basic_blocks
+            .keys()
+            .map(|bb| (*bb, Vec::new()))
+            .collect::<BTreeMap<_, _>>();
+
+        for (bb, bb_data) in &func.basic_blocks {
+            let targets = bb_data.terminator.successors().collect::<Vec<_>>();
+            for target in &targets {
+                predecessors.get_mut(target).unwrap().push(*bb);
+            }
+            successors.insert(*bb, targets);
+        }
+
+        let reverse_postorder = reverse_postorder(entry, |bb| &successors[&bb]);
+
+        Self {
+            entry,
+            successors,
+            predecessors,
+            reverse_postorder,
+        }
+    }
+
+    pub fn entry(&self) -> BasicBlockId {
+        self.entry
+    }
+
+    pub fn successors(&self, bb: BasicBlockId) -> &[BasicBlockId] {
+        &self.successors[&bb]
+    }
+
+    pub fn predecessors(&self, bb: BasicBlockId) -> &[BasicBlockId] {
+        &self.predecessors[&bb]
+    }
+
+    /// Returns the blocks reachable from the entry block in reverse postorder, where every block
+    /// comes before its successors unless the edge between them is a back edge.
+    pub fn reverse_postorder(&self) -> &[BasicBlockId] {
+        &self.reverse_postorder
+    }
+
+    pub fn is_reachable(&self, bb: BasicBlockId) -> bool {
+        self.reverse_postorder.contains(&bb)
+    }
+}
+
+/// The dominator tree of a function, computed with the algorithm from "A Simple, Fast Dominance
+/// Algorithm" by Cooper, Harvey and Kennedy.
+#[derive(Debug, Clone)]
+pub struct Dominators {
+    entry: BasicBlockId,
+    /// The immediate dominator of every reachable block, the entry block is its own immediate
+    /// dominator.
+    idoms: BTreeMap<BasicBlockId, BasicBlockId>,
+    children: BTreeMap<BasicBlockId, Vec<BasicBlockId>>,
+}
+
+impl Dominators {
+    pub fn new(cfg: &Cfg) -> Self {
+        let order = cfg
+            .reverse_postorder()
+            .iter()
+            .enumerate()
+            .map(|(index, bb)| (*bb, index))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut idoms = BTreeMap::from([(cfg.entry(), cfg.entry())]);
+
+        let intersect = |idoms: &BTreeMap<_, _>, mut lhs, mut rhs| {
+            while lhs != rhs {
+                while order[&lhs] > order[&rhs] {
+                    lhs = idoms[&lhs];
+                }
+                while order[&rhs] > order[&lhs] {
+                    rhs = idoms[&rhs];
+                }
+            }
+            lhs
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for bb in &cfg.reverse_postorder()[1..] {
+                let mut processed = cfg
+                    .predecessors(*bb)
+                    .iter()
+                    .filter(|pred| idoms.contains_key(pred));
+
+                let first = *processed.next().unwrap();
+                let idom = processed.fold(first, |idom, pred| intersect(&idoms, idom, *pred));
+
+                if idoms.insert(*bb, idom) != Some(idom) {
+                    changed = true;
+                }
+            }
+        }
+
+        let mut children = BTreeMap::<_, Vec<_>>::new();
+        for bb in cfg.reverse_postorder() {
+            children.insert(*bb, Vec::new());
+            if *bb != cfg.entry() {
+                children.get_mut(&idoms[bb]).unwrap().push(*bb);
+            }
+        }
+
+        Self {
+            entry: cfg.entry(),
+            idoms,
+            children,
+        }
+    }
+
+    /// Returns the immediate dominator of `bb` or `None` if `bb` is the entry block or it is
+    /// unreachable.
+    pub fn immediate_dominator(&self, bb: BasicBlockId) -> Option<BasicBlockId> {
+        if bb == self.entry {
+            None
+        } else {
+            self.idoms.get(&bb).copied()
+        }
+    }
+
+    /// Returns the blocks immediately dominated by `bb`.
+    pub fn children(&self, bb: BasicBlockId) -> &[BasicBlockId] {
+        self.children.get(&bb).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns whether every path from the entry block to `bb` goes through `dominator`. Every
+    /// block dominates itself.
+    pub fn dominates(&self, dominator: BasicBlockId, mut bb: BasicBlockId) -> bool {
+        if !self.idoms.contains_key(&bb) {
+            return false;
+        }
+
+        loop {
+            if bb == dominator {
+                return true;
+            }
+            match self.immediate_dominator(bb) {
+                Some(idom) => bb = idom,
+                None => return false,
+            }
+        }
+    }
+
+    /// Returns the dominance frontier of every reachable block: the blocks where the dominance of
+    /// a block ends.
+    pub fn frontiers(&self, cfg: &Cfg) -> BTreeMap<BasicBlockId, BTreeSet<BasicBlockId>> {
+        let mut frontiers = cfg
+            .reverse_postorder()
+            .iter()
+            .map(|bb| (*bb, BTreeSet::new()))
+            .collect::<BTreeMap<_, _>>();
+
+        for bb in cfg.reverse_postorder() {
+            let preds = cfg.predecessors(*bb);
+            // The entry block has an implicit predecessor, the caller, so a back edge to it makes
+            // it a join point too.
+            if preds.len() + usize::from(*bb == cfg.entry()) < 2 {
+                continue;
+            }
+
+            // The walk up from the predecessors of the entry block goes up to the entry block
+            // itself, since nothing dominates it.
+            let idom = self.immediate_dominator(*bb);
+            for pred in preds.iter().filter(|pred| self.idoms.contains_key(pred)) {
+                let mut runner = Some(*pred);
+                while runner != idom {
+                    let block = runner.unwrap();
+                    frontiers.get_mut(&block).unwrap().insert(*bb);
+                    runner = self.immediate_dominator(block);
+                }
+            }
+        }
+
+        frontiers
+    }
+}
+
+/// A natural loop, formed by the back edges to a header that dominates their sources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    pub header: BasicBlockId,
+    /// The sources of the back edges to the header.
+    pub latches: Vec<BasicBlockId>,
+    /// The blocks of the loop, including the header.
+    pub blocks: BTreeSet<BasicBlockId>,
+    /// The index of the innermost loop containing this one.
+    pub parent: Option<usize>,
+    /// The number of loops containing this one, plus one.
+    pub depth: usize,
+}
+
+/// The natural loops of a function. Loops with the same header are merged into one.
+#[derive(Debug, Clone)]
+pub struct Loops {
+    /// The loops sorted so that every loop comes after the loops that contain it.
+    loops: Vec<Loop>,
+}
+
+impl Loops {
+    pub fn new(cfg: &Cfg, dominators: &Dominators) -> Self {
+        let mut loops = Vec::<Loop>::new();
+
+        for header in cfg.reverse_postorder() {
+            let latches = cfg
+                .predecessors(*header)
+                .iter()
+                .copied()
+                .filter(|pred| dominators.dominates(*header, *pred))
+                .collect::<Vec<_>>();
+
+            if latches.is_empty() {
+                continue;
+            }
+
+            // Every block that reaches a latch without going through the header is in the loop.
+            let mut blocks = BTreeSet::from([*header]);
+            let mut stack = latches.clone();
+            while let Some(bb) = stack.pop() {
+                if blocks.insert(bb) {
+                    stack.extend(
+                        cfg.predecessors(bb)
+                            .iter()
+                            .filter(|pred| cfg.is_reachable(**pred)),
+                    );
+                }
+            }
+
+            loops.push(Loop {
+                header: *header,
+                latches,
+                blocks,
+                parent: None,
+                depth: 1,
+            });
+        }
+
+        // Outer loops contain more blocks than the loops nested in them.
+        loops.sort_by_key(|l| std::cmp::Reverse(l.blocks.len()));
+
+        for index in 0..loops.len() {
+            let parent = (0..index)
+                .rev()
+                .find(|parent| loops[*parent].blocks.contains(&loops[index].header));
+
+            if let Some(parent) = parent {
+                loops[index].parent = Some(parent);
+                loops[index].depth = loops[parent].depth + 1;
+            }
+        }
+
+        Self { loops }
+    }
+
+    pub fn loops(&self) -> &[Loop] {
+        &self.loops
+    }
+
+    /// Returns the innermost loop containing `bb`.
+    pub fn innermost(&self, bb: BasicBlockId) -> Option<&Loop> {
+        self.loops
+            .iter()
+            .filter(|l| l.blocks.contains(&bb))
+            .max_by_key(|l| l.depth)
+    }
+
+    /// Returns the number of loops containing `bb`.
+    pub fn depth(&self, bb: BasicBlockId) -> usize {
+        self.innermost(bb).map_or(0, |l| l.depth)
+    }
+}
@@ -0,0 +1,217 @@
+//! Dataflow analyses over MIR, where every node is a block of the function's [`Cfg`].
+//!
+//! The results only hold the state at the boundaries of each block. The state at a particular
+//! statement is found by applying the effects of the statements before it, in the direction of
+//! the analysis.
+//!
+//! The arguments of a phi are read at the end of the corresponding predecessor, so liveness
+//! counts them as uses of that predecessor and not of the block that contains the phi.
+use std::collections::BTreeSet;
+
+use crate::dataflow::{Analysis, Direction, Graph};
+use crate::mir::{cfg::Cfg, BasicBlockId, Function, Local, Operand, Statement, Terminator};
+
+impl Graph for Cfg {
+    type Node = BasicBlockId;
+
+    fn nodes(&self) -> &[BasicBlockId] {
+        self.reverse_postorder()
+    }
+
+    fn successors(&self, bb: BasicBlockId) -> &[BasicBlockId] {
+        Cfg::successors(self, bb)
+    }
+
+    fn predecessors(&self, bb: BasicBlockId) -> &[BasicBlockId] {
+        Cfg::predecessors(self, bb)
+    }
+}
+
+/// Computes the locals whose current value may be read later.
+pub struct Liveness<'a> {
+    func: &'a Function,
+}
+
+impl<'a> Liveness<'a> {
+    pub fn new(func: &'a Function) -> Self {
+        Self { func }
+    }
+
+    /// Updates the locals that are live after `statement` to the ones live before it. A phi only
+    /// kills its destination, see [`Liveness::edge_effect`].
+    pub fn statement_effect(statement: &Statement, live: &mut BTreeSet<Local>) {
+        live.remove(&statement.lhs());
+        if let Statement::Assign { rhs, .. } = statement {
+            live.extend(rhs.operands().filter_map(Operand::local));
+        }
+    }
+
+    /// Adds the arguments that the phis of the successors of `bb` read when they are entered from
+    /// `bb` to the locals live at the end of `bb`.
+    pub fn edge_effect(func: &Function, bb: BasicBlockId, live: &mut BTreeSet<Local>) {
+        for target in func.basic_blocks[&bb].terminator.successors() {
+            for statement in &func.basic_blocks[&target].statements {
+                if let Statement::Phi { args, .. } = statement {
+                    live.extend(
+                        args.iter()
+                            .filter(|(pred, _)| *pred == bb)
+                            .filter_map(|(_, operand)| operand.local()),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Updates the locals that are live after `terminator` to the ones live before it.
+    pub fn terminator_effect(terminator: &Terminator, live: &mut BTreeSet<Local>) {
+        match terminator {
+            Terminator::Jump(_) => {}
+            // The return value is read by the caller.
+            Terminator::Return => {
+                live.insert(Local(0));
+            }
+            Terminator::JumpIf { cond, .. } => live.extend(cond.local()),
+        }
+    }
+}
+
+impl<'a> Analysis for Liveness<'a> {
+    type Node = BasicBlockId;
+    type Domain = BTreeSet<Local>;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn boundary(&self) -> BTreeSet<Local> {
+        BTreeSet::new()
+    }
+
+    fn bottom(&self) -> BTreeSet<Local> {
+        BTreeSet::new()
+    }
+
+    fn transfer(&self, bb: BasicBlockId, live: &mut BTreeSet<Local>) {
+        let bb_data = &self.func.basic_blocks[&bb];
+
+        Self::edge_effect(self.func, bb, live);
+        Self::terminator_effect(&bb_data.terminator, live);
+        for statement in bb_data.statements.iter().rev() {
+            Self::statement_effect(statement, live);
+        }
+    }
+}
+
+/// The place where a local is assigned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DefinitionSite {
+    /// The local is an argument, assigned by the caller.
+    Argument,
+    /// The statement with this index in this block.
+    Statement(BasicBlockId, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Definition {
+    pub local: Local,
+    pub site: DefinitionSite,
+}
+
+/// Computes the assignments that may have produced the current value of each local.
+pub struct ReachingDefinitions<'a> {
+    func: &'a Function,
+}
+
+impl<'a> ReachingDefinitions<'a> {
+    pub fn new(func: &'a Function) -> Self {
+        Self { func }
+    }
+
+    /// Updates the definitions that reach the statement with index `index` in `bb` to the ones
+    /// that reach the next statement.
+    pub fn statement_effect(
+        bb: BasicBlockId,
+        index: usize,
+        statement: &Statement,
+        definitions: &mut BTreeSet<Definition>,
+    ) {
+        let lhs = statement.lhs();
+        definitions.retain(|definition| definition.local != lhs);
+        definitions.insert(Definition {
+            local: lhs,
+            site: DefinitionSite::Statement(bb, index),
+        });
+    }
+}
+
+impl<'a> Analysis for ReachingDefinitions<'a> {
+    type Node = BasicBlockId;
+    type Domain = BTreeSet<Definition>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self) -> BTreeSet<Definition> {
+        (1..=self.func.args_len)
+            .map(|local| Definition {
+                local: Local(local),
+                site: DefinitionSite::Argument,
+            })
+            .collect()
+    }
+
+    fn bottom(&self) -> BTreeSet<Definition> {
+        BTreeSet::new()
+    }
+
+    fn transfer(&self, bb: BasicBlockId, definitions: &mut BTreeSet<Definition>) {
+        for (index, statement) in self.func.basic_blocks[&bb].statements.iter().enumerate() {
+            Self::statement_effect(bb, index, statement, definitions);
+        }
+    }
+}
+
+/// Computes the locals that have been assigned in every path that reaches a point.
+///
+/// The state is the complement of that: the set of locals that may still be uninitialized, which
+/// turns the intersection of the paths into a union so it can share the lattice of the other
+/// analyses.
+pub struct DefiniteInit<'a> {
+    func: &'a Function,
+}
+
+impl<'a> DefiniteInit<'a> {
+    pub fn new(func: &'a Function) -> Self {
+        Self { func }
+    }
+
+    /// Updates the locals that may be uninitialized before `statement` to the ones that may be
+    /// uninitialized after it.
+    pub fn statement_effect(statement: &Statement, maybe_uninit: &mut BTreeSet<Local>) {
+        maybe_uninit.remove(&statement.lhs());
+    }
+}
+
+impl<'a> Analysis for DefiniteInit<'a> {
+    type Node = BasicBlockId;
+    type Domain = BTreeSet<Local>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    /// Only the arguments are initialized when the function starts.
+    fn boundary(&self) -> BTreeSet<Local> {
+        self.func
+            .local_types
+            .keys()
+            .copied()
+            .filter(|local| !(1..=self.func.args_len).contains(&local.0))
+            .collect()
+    }
+
+    fn bottom(&self) -> BTreeSet<Local> {
+        BTreeSet::new()
+    }
+
+    fn transfer(&self, bb: BasicBlockId, maybe_uninit: &mut BTreeSet<Local>) {
+        for statement in &self.func.basic_blocks[&bb].statements {
+            Self::statement_effect(statement, maybe_uninit);
+        }
+    }
+}
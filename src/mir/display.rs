@@ -0,0 +1,232 @@
+//! Textual representation of MIR.
+//!
+//! The syntax follows the one used in the notes:
+//! ```text
+//! fn duplicate(_1: int) -> int {
+//!     let _2: int
+//!     let _3: bool
+//!
+//!     bb0: _0 = USE 0
+//!          _2 = USE 0
+//!          JUMP bb1
+//!
+//!     bb1: _3 = _2 < _1
+//!          JUMP IF _3 THEN bb2 ELSE bb3
+//!
+//!     bb2: _0 = _0 + 2
+//!          _2 = _2 + 1
+//!          JUMP bb1
+//!
+//!     bb3: RETURN
+//! }
+//! ```
+//!
+//! In SSA form, phis are written as `_4 = PHI(bb0: _2, bb2: _5)`. Calls are written as
+//! `_2 = CALL start()` and the inlining attributes of a function, such as `#[inline(never)]`, are
+//! written in the line before the function. Blocks that are unlikely to run are marked with
+//! `#[cold]` in the line before the block.
+//!
+//! Float constants always have a fractional part and `f32` constants have a suffix, as in `1.5`
+//! and `1.5f32`. Conversions are written as `_2 = CAST _1 AS f64`.
+use std::fmt;
+
+use crate::mir::{
+    BasicBlockId, BinOp, Function, InlineHint, Literal, Local, Module, Operand, Rvalue, Statement,
+    Terminator, Ty,
+};
+
+impl fmt::Display for Local {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "_{}", self.0)
+    }
+}
+
+impl fmt::Display for BasicBlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Int => write!(f, "int"),
+            Ty::Bool => write!(f, "bool"),
+            Ty::F32 => write!(f, "f32"),
+            Ty::F64 => write!(f, "f64"),
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ty {
+            Ty::Int => write!(f, "{}", self.data as i64),
+            Ty::Bool => write!(f, "{}", self.data != 0),
+            Ty::F32 => write!(f, "{}f32", DisplayFloat(f32::from_bits(self.data as u32))),
+            Ty::F64 => write!(f, "{}", DisplayFloat(f64::from_bits(self.data))),
+        }
+    }
+}
+
+/// Displays a float with a fractional part, so that it is not parsed back as an integer.
+struct DisplayFloat<F>(F);
+
+impl<F: fmt::Display> fmt::Display for DisplayFloat<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let float = self.0.to_string();
+        if float.contains(['.', 'N', 'i']) {
+            write!(f, "{float}")
+        } else {
+            write!(f, "{float}.0")
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Local(local) => write!(f, "{local}"),
+            Operand::Constant(literal) => write!(f, "{literal}"),
+        }
+    }
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinOp::Add => write!(f, "+"),
+            BinOp::Mul => write!(f, "*"),
+            BinOp::Lt => write!(f, "<"),
+        }
+    }
+}
+
+impl fmt::Display for Rvalue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rvalue::Use(operand) => write!(f, "USE {operand}"),
+            Rvalue::BinaryOp { op, lhs, rhs } => write!(f, "{lhs} {op} {rhs}"),
+            Rvalue::SymbolAddr(name) => write!(f, "ADDR {name}"),
+            Rvalue::Cast { operand, ty } => write!(f, "CAST {operand} AS {ty}"),
+            Rvalue::Call { callee, args } => {
+                write!(f, "CALL {callee}(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Assign { lhs, rhs } => write!(f, "{lhs} = {rhs}"),
+            Statement::Phi { lhs, args } => {
+                write!(f, "{lhs} = PHI(")?;
+                for (i, (bb, operand)) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{bb}: {operand}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Jump(bb) => write!(f, "JUMP {bb}"),
+            Terminator::Return => write!(f, "RETURN"),
+            Terminator::JumpIf {
+                cond,
+                then_bb,
+                else_bb,
+            } => write!(f, "JUMP IF {cond} THEN {then_bb} ELSE {else_bb}"),
+        }
+    }
+}
+
+/// Helper to display a [`Function`] with its name.
+pub struct DisplayFunction<'a> {
+    name: &'a str,
+    func: &'a Function,
+}
+
+impl Function {
+    pub fn display<'a>(&'a self, name: &'a str) -> DisplayFunction<'a> {
+        DisplayFunction { name, func: self }
+    }
+}
+
+impl<'a> fmt::Display for DisplayFunction<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let func = self.func;
+        let mut locals = func.local_types.iter();
+
+        match func.inline {
+            InlineHint::Auto => {}
+            InlineHint::Hint => writeln!(f, "#[inline]")?,
+            InlineHint::Always => writeln!(f, "#[inline(always)]")?,
+            InlineHint::Never => writeln!(f, "#[inline(never)]")?,
+        }
+
+        write!(f, "fn {}(", self.name)?;
+        // The first local is the return value, which is always called `_0`.
+        let (_, output_ty) = locals.next().unwrap();
+        for (i, (local, ty)) in locals.by_ref().take(func.args_len).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{local}: {ty}")?;
+        }
+        writeln!(f, ") -> {output_ty} {{")?;
+
+        let mut has_locals = false;
+        for (local, ty) in locals {
+            writeln!(f, "    let {local}: {ty}")?;
+            has_locals = true;
+        }
+
+        for (i, (bb, bb_data)) in func.basic_blocks.iter().enumerate() {
+            if has_locals || i > 0 {
+                writeln!(f)?;
+            }
+
+            if bb_data.cold {
+                writeln!(f, "    #[cold]")?;
+            }
+            let prefix = format!("{bb}: ");
+            write!(f, "    {prefix}")?;
+            let indent = " ".repeat(prefix.len() + 4);
+
+            for statement in &bb_data.statements {
+                write!(f, "{statement}\n{indent}")?;
+            }
+            writeln!(f, "{}", bb_data.terminator)?;
+        }
+
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, func)) in self.functions.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "{}", func.display(name))?;
+        }
+
+        Ok(())
+    }
+}
@@ -2,10 +2,26 @@ use crate::mir::bb::{BasicBlock, BasicBlockId};
 use crate::mir::{Local, Ty};
 use std::collections::BTreeMap;
 
+#[derive(Debug, Clone)]
 pub struct Function {
     pub args_len: usize,
     pub basic_blocks: BTreeMap<BasicBlockId, BasicBlock>,
     pub local_types: BTreeMap<Local, Ty>,
+    pub inline: InlineHint,
+}
+
+/// Whether calls to a function should be inlined, written as an attribute before the function.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InlineHint {
+    /// The inliner decides based on the size of the function.
+    #[default]
+    Auto,
+    /// `#[inline]`: larger functions are inlined.
+    Hint,
+    /// `#[inline(always)]`: the function is inlined regardless of its size.
+    Always,
+    /// `#[inline(never)]`: the function is never inlined.
+    Never,
 }
 
 impl Function {
@@ -16,6 +32,30 @@ impl Function {
             local_types: BTreeMap::default(),
         }
     }
+
+    /// Adds a local after the last one.
+    pub fn add_local(&mut self, ty: Ty) -> Local {
+        let local = Local(
+            self.local_types
+                .keys()
+                .next_back()
+                .map_or(0, |local| local.0 + 1),
+        );
+        self.local_types.insert(local, ty);
+        local
+    }
+
+    /// Adds a block after the last one.
+    pub fn add_block(&mut self, bb_data: BasicBlock) -> BasicBlockId {
+        let bb = BasicBlockId(
+            self.basic_blocks
+                .keys()
+                .next_back()
+                .map_or(0, |bb| bb.0 + 1),
+        );
+        self.basic_blocks.insert(bb, bb_data);
+        bb
+    }
 }
 
 pub struct FunctionBuilder {
@@ -57,6 +97,7 @@ impl FunctionBuilder {
                 .map(|(k, v)| (k, v.expect("missing block")))
                 .collect(),
             local_types: self.local_types,
+            inline: InlineHint::Auto,
         }
     }
 }
@@ -0,0 +1,268 @@
+//! Induction variables of the natural loops of a function in SSA form.
+//!
+//! A basic induction variable is a phi of the loop header that is increased by a constant on
+//! every iteration, which is what `i = i + 1` becomes in SSA form. A derived induction variable
+//! adds a constant to another induction variable. Integers wrap around on overflow, so steps and
+//! offsets are `u64`s and subtracting a constant is adding a large one.
+//!
+//! Only loops with a single latch and a single block entering the header from outside the loop
+//! are analyzed.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::mir::{
+    cfg::{Cfg, Loop},
+    BasicBlockId, BinOp, Function, Local, Operand, Rvalue, Statement, Terminator, Ty,
+};
+
+#[derive(Debug, Clone)]
+pub enum InductionVariable {
+    /// A phi of the header whose value is `init` in the first iteration and `next` in the
+    /// following ones, where `next` is the phi plus `step`.
+    Basic {
+        init: Operand,
+        step: u64,
+        next: Local,
+    },
+    /// The value of the basic induction variable `base` plus `offset`.
+    Derived { base: Local, offset: u64 },
+}
+
+/// The induction variables of a loop.
+#[derive(Debug, Clone)]
+pub struct InductionVariables {
+    entering: BasicBlockId,
+    latch: BasicBlockId,
+    variables: BTreeMap<Local, InductionVariable>,
+}
+
+impl InductionVariables {
+    /// Finds the induction variables of `l`. Returns `None` if the loop has more than one latch or
+    /// more than one block entering it.
+    pub fn new(func: &Function, cfg: &Cfg, l: &Loop) -> Option<Self> {
+        let [latch] = l.latches[..] else {
+            return None;
+        };
+        let entering = entering_block(cfg, l)?;
+
+        // The phis that could be basic induction variables, with their initial and next values.
+        let mut candidates = BTreeMap::new();
+        for statement in &func.basic_blocks[&l.header].statements {
+            let Statement::Phi { lhs, args } = statement else {
+                break;
+            };
+
+            let arg = |bb| {
+                args.iter()
+                    .find(|(pred, _)| *pred == bb)
+                    .map(|(_, arg)| arg)
+            };
+            if let (Some(init), Some(Operand::Local(next))) = (arg(entering), arg(latch)) {
+                candidates.insert(*lhs, (init.clone(), *next));
+            }
+        }
+
+        // Every local is assigned before it is read in SSA form, so visiting the blocks in reverse
+        // postorder finds the offsets of the operands of an addition before the addition.
+        let mut derived = BTreeMap::<Local, (Local, u64)>::new();
+        for bb in cfg.reverse_postorder() {
+            if !l.blocks.contains(bb) {
+                continue;
+            }
+
+            for statement in &func.basic_blocks[bb].statements {
+                let Statement::Assign {
+                    lhs,
+                    rhs:
+                        Rvalue::BinaryOp {
+                            op: BinOp::Add,
+                            lhs: operand1,
+                            rhs: operand2,
+                        },
+                } = statement
+                else {
+                    continue;
+                };
+
+                let (local, constant) = match (operand1, operand2) {
+                    (Operand::Local(local), Operand::Constant(literal))
+                    | (Operand::Constant(literal), Operand::Local(local))
+                        if literal.ty == Ty::Int =>
+                    {
+                        (*local, literal.data)
+                    }
+                    _ => continue,
+                };
+
+                let base = if candidates.contains_key(&local) {
+                    Some((local, 0))
+                } else {
+                    derived.get(&local).copied()
+                };
+
+                if let Some((base, offset)) = base {
+                    derived.insert(*lhs, (base, offset.wrapping_add(constant)));
+                }
+            }
+        }
+
+        let mut variables = BTreeMap::new();
+        for (phi, (init, next)) in candidates {
+            if let Some((base, step)) = derived.get(&next) {
+                if *base == phi {
+                    let step = *step;
+                    variables.insert(phi, InductionVariable::Basic { init, step, next });
+                }
+            }
+        }
+
+        for (local, (base, offset)) in derived {
+            if variables.contains_key(&base) {
+                variables.insert(local, InductionVariable::Derived { base, offset });
+            }
+        }
+
+        Some(Self {
+            entering,
+            latch,
+            variables,
+        })
+    }
+
+    /// Returns the only block outside the loop that jumps to the header.
+    pub fn entering(&self) -> BasicBlockId {
+        self.entering
+    }
+
+    /// Returns the only block inside the loop that jumps to the header.
+    pub fn latch(&self) -> BasicBlockId {
+        self.latch
+    }
+
+    pub fn get(&self, local: Local) -> Option<&InductionVariable> {
+        self.variables.get(&local)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Local, &InductionVariable)> {
+        self.variables
+            .iter()
+            .map(|(local, variable)| (*local, variable))
+    }
+}
+
+/// The number of iterations of a counted loop: a loop whose header is its only exit, ending in
+/// `JUMP IF cond THEN body ELSE exit` with `cond = iv < bound`, where `iv` is a basic induction
+/// variable with a step of 1 and `bound` is not assigned in the loop.
+///
+/// The header is executed once more than the rest of the loop, to leave it.
+#[derive(Debug, Clone)]
+pub struct TripCount {
+    pub iv: Local,
+    pub bound: Operand,
+    /// The result of the comparison, assigned in the header.
+    pub cond: Local,
+    /// The block outside the loop executed after the header.
+    pub exit: BasicBlockId,
+}
+
+impl TripCount {
+    pub fn new(func: &Function, l: &Loop, ivs: &InductionVariables) -> Option<Self> {
+        let exits_elsewhere = l.blocks.iter().any(|bb| {
+            *bb != l.header
+                && func.basic_blocks[bb]
+                    .terminator
+                    .successors()
+                    .any(|target| !l.blocks.contains(&target))
+        });
+        if exits_elsewhere {
+            return None;
+        }
+
+        let Terminator::JumpIf {
+            cond: Operand::Local(cond),
+            then_bb,
+            else_bb,
+        } = func.basic_blocks[&l.header].terminator
+        else {
+            return None;
+        };
+        if !l.blocks.contains(&then_bb) || l.blocks.contains(&else_bb) {
+            return None;
+        }
+
+        let (iv, bound) = func.basic_blocks[&l.header]
+            .statements
+            .iter()
+            .find_map(|statement| match statement {
+                Statement::Assign {
+                    lhs,
+                    rhs:
+                        Rvalue::BinaryOp {
+                            op: BinOp::Lt,
+                            lhs: Operand::Local(iv),
+                            rhs: bound,
+                        },
+                } if *lhs == cond => Some((*iv, bound.clone())),
+                _ => None,
+            })?;
+
+        let Some(InductionVariable::Basic { step: 1, .. }) = ivs.get(iv) else {
+            return None;
+        };
+
+        if let Operand::Local(bound) = bound {
+            if assigned_locals(func, l).contains(&bound) {
+                return None;
+            }
+        }
+
+        Some(Self {
+            iv,
+            bound,
+            cond,
+            exit: else_bb,
+        })
+    }
+
+    /// Returns the number of iterations if both the initial value of the induction variable and
+    /// the bound are constants. Comparisons are signed.
+    pub fn constant(&self, ivs: &InductionVariables) -> Option<u64> {
+        let Some(InductionVariable::Basic {
+            init: Operand::Constant(init),
+            ..
+        }) = ivs.get(self.iv)
+        else {
+            return None;
+        };
+        let Operand::Constant(bound) = &self.bound else {
+            return None;
+        };
+
+        if init.bits() < bound.bits() {
+            Some(bound.data.wrapping_sub(init.data))
+        } else {
+            Some(0)
+        }
+    }
+}
+
+/// Returns the only block outside `l` that jumps to its header, if there is one.
+pub fn entering_block(cfg: &Cfg, l: &Loop) -> Option<BasicBlockId> {
+    let mut entering = cfg
+        .predecessors(l.header)
+        .iter()
+        .filter(|pred| !l.blocks.contains(pred) && cfg.is_reachable(**pred));
+
+    match (entering.next(), entering.next()) {
+        (Some(bb), None) => Some(*bb),
+        _ => None,
+    }
+}
+
+/// Returns the locals assigned by the statements of `l`.
+pub fn assigned_locals(func: &Function, l: &Loop) -> BTreeSet<Local> {
+    l.blocks
+        .iter()
+        .flat_map(|bb| &func.basic_blocks[bb].statements)
+        .map(Statement::lhs)
+        .collect()
+}
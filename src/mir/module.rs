@@ -0,0 +1,9 @@
+use std::collections::BTreeMap;
+
+use crate::mir::Function;
+
+/// A collection of functions that are compiled together into a single object file.
+#[derive(Debug, Default)]
+pub struct Module {
+    pub functions: BTreeMap<String, Function>,
+}
@@ -0,0 +1,318 @@
+//! Sparse conditional constant propagation.
+//!
+//! Every local starts as [`Value::Undefined`] and can only move down the lattice towards
+//! [`Value::Overdefined`]. Only the successors of a block that can actually be taken are visited,
+//! so a constant assigned in a branch that is never executed does not pollute the values of the
+//! rest of the function.
+//!
+//! In SSA form, the value of a phi is the meet of its arguments over the edges that can be taken,
+//! which lets the pass see through loops whose induction variables stay constant.
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::mir::{
+    BasicBlockId, BinOp, Function, Literal, Local, Operand, Rvalue, Statement, Terminator, Ty,
+};
+use crate::pass::Pass;
+
+use super::ssa::prune_phi_args;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value {
+    /// The local has not been assigned yet in any path that reaches this point.
+    Undefined,
+    Constant(Literal),
+    /// The local can hold more than one value.
+    Overdefined,
+}
+
+impl Value {
+    fn meet(self, other: Self) -> Self {
+        match (self, other) {
+            (Value::Undefined, value) | (value, Value::Undefined) => value,
+            (Value::Constant(lhs), Value::Constant(rhs)) if lhs == rhs => self,
+            _ => Value::Overdefined,
+        }
+    }
+}
+
+/// The values of the locals at a point of the function.
+type State = BTreeMap<Local, Value>;
+
+pub struct ConstProp;
+
+impl Pass<Function> for ConstProp {
+    fn name(&self) -> &'static str {
+        "const-prop"
+    }
+
+    fn run(&self, func: &mut Function) -> bool {
+        let (states, edges) = solve(func);
+        let mut changed = false;
+
+        for (bb, in_state) in &states {
+            let mut state = in_state.clone();
+            eval_phis(func, *bb, &edges, &mut state);
+            let bb_data = func.basic_blocks.get_mut(bb).unwrap();
+
+            for statement in &mut bb_data.statements {
+                let Statement::Assign { lhs, rhs } = statement else {
+                    // The phis were evaluated above, their arguments are read before any of them
+                    // is assigned. The phis themselves are left for dead code elimination.
+                    for operand in statement.operands_mut() {
+                        changed |= replace_operand(&func.local_types, in_state, operand);
+                    }
+                    continue;
+                };
+                let value = eval_rvalue(&state, rhs);
+
+                match (value, &mut *rhs) {
+                    (_, Rvalue::Use(Operand::Constant(_))) => {}
+                    (Value::Constant(literal), _) => {
+                        *rhs = Rvalue::Use(constant(&func.local_types, *lhs, literal));
+                        changed = true;
+                    }
+                    (_, Rvalue::Use(operand) | Rvalue::Cast { operand, .. }) => {
+                        changed |= replace_operand(&func.local_types, &state, operand);
+                    }
+                    (_, Rvalue::BinaryOp { lhs, rhs, .. }) => {
+                        changed |= replace_operand(&func.local_types, &state, lhs);
+                        changed |= replace_operand(&func.local_types, &state, rhs);
+                    }
+                    (_, Rvalue::SymbolAddr(_)) => {}
+                    (_, Rvalue::Call { args, .. }) => {
+                        for arg in args {
+                            changed |= replace_operand(&func.local_types, &state, arg);
+                        }
+                    }
+                }
+
+                state.insert(*lhs, value);
+            }
+
+            if let Terminator::JumpIf {
+                cond,
+                then_bb,
+                else_bb,
+            } = &bb_data.terminator
+            {
+                if let Value::Constant(cond) = eval(&state, cond) {
+                    let target = if cond.data != 0 { *then_bb } else { *else_bb };
+                    bb_data.terminator = Terminator::Jump(target);
+                    changed = true;
+                }
+            }
+        }
+
+        // Blocks that were never visited cannot be executed.
+        let len = func.basic_blocks.len();
+        func.basic_blocks.retain(|bb, _| states.contains_key(bb));
+        changed |= func.basic_blocks.len() != len;
+
+        changed | prune_phi_args(func)
+    }
+}
+
+/// The edges between blocks that can be taken.
+type Edges = BTreeSet<(BasicBlockId, BasicBlockId)>;
+
+/// Returns the state at the start of every block that can be executed, before its phis, and the
+/// edges that can be taken.
+fn solve(func: &Function) -> (BTreeMap<BasicBlockId, State>, Edges) {
+    let entry = match func.basic_blocks.keys().next() {
+        Some(entry) => *entry,
+        None => return (BTreeMap::new(), Edges::new()),
+    };
+
+    // The return value and the locals are undefined when the function starts, but the arguments
+    // can have any value.
+    let entry_state = func
+        .local_types
+        .keys()
+        .map(|local| {
+            if (1..=func.args_len).contains(&local.0) {
+                (*local, Value::Overdefined)
+            } else {
+                (*local, Value::Undefined)
+            }
+        })
+        .collect::<State>();
+
+    let mut states = BTreeMap::from([(entry, entry_state)]);
+    let mut edges = Edges::new();
+    let mut worklist = vec![entry];
+
+    while let Some(bb) = worklist.pop() {
+        let bb_data = &func.basic_blocks[&bb];
+        let mut state = states[&bb].clone();
+
+        eval_phis(func, bb, &edges, &mut state);
+        for statement in &bb_data.statements {
+            if let Statement::Assign { lhs, rhs } = statement {
+                state.insert(*lhs, eval_rvalue(&state, rhs));
+            }
+        }
+
+        let successors = match &bb_data.terminator {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Return => vec![],
+            Terminator::JumpIf {
+                cond,
+                then_bb,
+                else_bb,
+            } => match eval(&state, cond) {
+                Value::Constant(Literal { data: 0, .. }) => vec![*else_bb],
+                Value::Constant(_) => vec![*then_bb],
+                // An undefined condition is treated as unknown so the terminator is still valid
+                // after the rewrite.
+                Value::Undefined | Value::Overdefined => vec![*then_bb, *else_bb],
+            },
+        };
+
+        for target in successors {
+            // A new edge changes the value of the phis of the target even if its state does not
+            // change.
+            let new_edge = edges.insert((bb, target));
+
+            let changed = match states.entry(target) {
+                Entry::Vacant(entry) => {
+                    entry.insert(state.clone());
+                    true
+                }
+                Entry::Occupied(mut entry) => {
+                    let mut changed = false;
+                    for (local, value) in entry.get_mut() {
+                        let new_value = value.meet(state[local]);
+                        changed |= new_value != *value;
+                        *value = new_value;
+                    }
+                    changed
+                }
+            };
+
+            if new_edge || changed {
+                worklist.push(target);
+            }
+        }
+    }
+
+    (states, edges)
+}
+
+/// Assigns the values of the phis at the start of `bb` to `state`, reading the arguments of the
+/// edges in `edges`. All the phis are evaluated before any of them is assigned.
+fn eval_phis(func: &Function, bb: BasicBlockId, edges: &Edges, state: &mut State) {
+    let values = func.basic_blocks[&bb]
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Phi { lhs, args } => {
+                let value = args
+                    .iter()
+                    .filter(|(pred, _)| edges.contains(&(*pred, bb)))
+                    .fold(Value::Undefined, |value, (_, operand)| {
+                        value.meet(eval(state, operand))
+                    });
+                Some((*lhs, value))
+            }
+            Statement::Assign { .. } => None,
+        })
+        .collect::<Vec<_>>();
+
+    state.extend(values);
+}
+
+fn eval(state: &State, operand: &Operand) -> Value {
+    match operand {
+        Operand::Local(local) => state[local],
+        Operand::Constant(literal) => Value::Constant(*literal),
+    }
+}
+
+fn eval_rvalue(state: &State, rvalue: &Rvalue) -> Value {
+    match rvalue {
+        Rvalue::Use(operand) => eval(state, operand),
+        Rvalue::BinaryOp { op, lhs, rhs } => match (eval(state, lhs), eval(state, rhs)) {
+            (Value::Constant(lhs), Value::Constant(rhs)) => Value::Constant(fold(op, lhs, rhs)),
+            (Value::Overdefined, _) | (_, Value::Overdefined) => Value::Overdefined,
+            _ => Value::Undefined,
+        },
+        Rvalue::Cast { operand, ty } => match eval(state, operand) {
+            Value::Constant(literal) => Value::Constant(cast(literal, *ty)),
+            value => value,
+        },
+        Rvalue::SymbolAddr(_) | Rvalue::Call { .. } => Value::Overdefined,
+    }
+}
+
+/// Evaluates a binary operation over two constants of the same type, integers wrap around on
+/// overflow.
+fn fold(op: &BinOp, lhs: Literal, rhs: Literal) -> Literal {
+    match lhs.ty {
+        Ty::Int | Ty::Bool => {
+            let (lhs, rhs) = (lhs.bits(), rhs.bits());
+            match op {
+                BinOp::Add => Literal::int(lhs.wrapping_add(rhs)),
+                BinOp::Mul => Literal::int(lhs.wrapping_mul(rhs)),
+                BinOp::Lt => Literal::bool(lhs < rhs),
+            }
+        }
+        Ty::F32 => {
+            let (lhs, rhs) = (lhs.float() as f32, rhs.float() as f32);
+            match op {
+                BinOp::Add => Literal::f32(lhs + rhs),
+                BinOp::Mul => Literal::f32(lhs * rhs),
+                BinOp::Lt => Literal::bool(lhs < rhs),
+            }
+        }
+        Ty::F64 => {
+            let (lhs, rhs) = (lhs.float(), rhs.float());
+            match op {
+                BinOp::Add => Literal::f64(lhs + rhs),
+                BinOp::Mul => Literal::f64(lhs * rhs),
+                BinOp::Lt => Literal::bool(lhs < rhs),
+            }
+        }
+    }
+}
+
+/// Converts a constant to `ty`.
+fn cast(literal: Literal, ty: Ty) -> Literal {
+    match (literal.ty, ty) {
+        (Ty::Int | Ty::Bool, Ty::F32) => Literal::f32(literal.bits() as f32),
+        (Ty::Int | Ty::Bool, Ty::F64) => Literal::f64(literal.bits() as f64),
+        (Ty::F32 | Ty::F64, Ty::F32) => Literal::f32(literal.float() as f32),
+        (Ty::F32 | Ty::F64, Ty::F64) => Literal::f64(literal.float()),
+        (Ty::F32 | Ty::F64, Ty::Int | Ty::Bool) => Literal {
+            ty,
+            ..Literal::int(literal.float() as i64)
+        },
+        (Ty::Int | Ty::Bool, Ty::Int | Ty::Bool) => Literal { ty, ..literal },
+    }
+}
+
+/// Builds a constant operand with the type of `local`.
+fn constant(local_types: &BTreeMap<Local, Ty>, local: Local, literal: Literal) -> Operand {
+    Operand::Constant(Literal {
+        data: literal.data,
+        ty: local_types[&local],
+    })
+}
+
+/// Replaces `operand` by a constant if it is a local with a known value.
+fn replace_operand(
+    local_types: &BTreeMap<Local, Ty>,
+    state: &State,
+    operand: &mut Operand,
+) -> bool {
+    match *operand {
+        Operand::Local(local) => match state[&local] {
+            Value::Constant(literal) => {
+                *operand = constant(local_types, local, literal);
+                true
+            }
+            Value::Undefined | Value::Overdefined => false,
+        },
+        Operand::Constant(_) => false,
+    }
+}
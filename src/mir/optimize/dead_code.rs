@@ -0,0 +1,117 @@
+//! Removal of assignments to locals that are never read, unless the assigned value has side
+//! effects.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::dataflow;
+use crate::mir::{cfg::Cfg, dataflow::Liveness, Function, Local, Operand, Statement, Terminator};
+use crate::pass::Pass;
+
+pub struct DeadCode;
+
+impl Pass<Function> for DeadCode {
+    fn name(&self) -> &'static str {
+        "dce"
+    }
+
+    fn run(&self, func: &mut Function) -> bool {
+        let mut changed = false;
+
+        // Removing an assignment can make the assignments to the locals it reads dead in other
+        // blocks, so liveness is recomputed until nothing else can be removed.
+        loop {
+            let results = dataflow::solve(&Cfg::new(func), &Liveness::new(func));
+            let mut live_out = results.after;
+            for (bb, live) in &mut live_out {
+                Liveness::edge_effect(func, *bb, live);
+            }
+
+            let mut removed = false;
+
+            for (bb, bb_data) in &mut func.basic_blocks {
+                // Unreachable blocks are not analyzed.
+                let Some(mut live) = live_out.remove(bb) else {
+                    continue;
+                };
+
+                Liveness::terminator_effect(&bb_data.terminator, &mut live);
+
+                // Walk the statements backwards, keeping only the ones whose destination is live.
+                let statements = std::mem::take(&mut bb_data.statements);
+                let len = statements.len();
+                let mut kept = statements
+                    .into_iter()
+                    .rev()
+                    .filter(|statement| {
+                        let is_live = live.contains(&statement.lhs())
+                            || matches!(statement, Statement::Assign { rhs, .. } if rhs.has_side_effects());
+                        if is_live {
+                            Liveness::statement_effect(statement, &mut live);
+                        }
+                        is_live
+                    })
+                    .collect::<Vec<_>>();
+                kept.reverse();
+
+                removed |= kept.len() != len;
+                bb_data.statements = kept;
+            }
+
+            if !removed {
+                break;
+            }
+            changed = true;
+        }
+
+        changed | compact_locals(func)
+    }
+}
+
+/// Removes the locals that are never mentioned and renumbers the rest so their ids are
+/// consecutive. The return value and the arguments are always kept. Returns whether any local was
+/// removed.
+pub(super) fn compact_locals(func: &mut Function) -> bool {
+    let mut used = (0..=func.args_len).map(Local).collect::<BTreeSet<_>>();
+
+    for bb_data in func.basic_blocks.values() {
+        for statement in &bb_data.statements {
+            used.insert(statement.lhs());
+            used.extend(statement.operands().filter_map(Operand::local));
+        }
+        Liveness::terminator_effect(&bb_data.terminator, &mut used);
+    }
+
+    if used.len() == func.local_types.len() {
+        return false;
+    }
+
+    let new_locals = used
+        .iter()
+        .enumerate()
+        .map(|(index, local)| (*local, Local(index)))
+        .collect::<BTreeMap<_, _>>();
+
+    let rename = |operand: &mut Operand| {
+        if let Operand::Local(local) = operand {
+            *local = new_locals[local];
+        }
+    };
+
+    for bb_data in func.basic_blocks.values_mut() {
+        for statement in &mut bb_data.statements {
+            let lhs = statement.lhs_mut();
+            *lhs = new_locals[lhs];
+            statement.operands_mut().for_each(rename);
+        }
+
+        if let Terminator::JumpIf { cond, .. } = &mut bb_data.terminator {
+            rename(cond);
+        }
+    }
+
+    func.local_types = std::mem::take(&mut func.local_types)
+        .into_iter()
+        .filter_map(|(local, ty)| Some((*new_locals.get(&local)?, ty)))
+        .collect();
+
+    true
+}
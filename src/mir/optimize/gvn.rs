@@ -0,0 +1,161 @@
+//! Global value numbering.
+//!
+//! The pass walks the dominator tree giving a number to the value of every assignment. A binary
+//! operation over the same values as another one in a dominating statement is replaced by a copy
+//! of the local that holds the result of the first one. The operands of commutative operations are
+//! sorted first, so `_2 + _3` and `_3 + _2` get the same number.
+//!
+//! Only the locals assigned by a single statement are numbered, and only after that statement, as
+//! other locals may hold a different value when the operation is repeated. In SSA form, this is
+//! every local except the return value.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::mir::{
+    cfg::{Cfg, Dominators},
+    BasicBlockId, BinOp, Function, Literal, Local, Operand, Rvalue, Statement,
+};
+use crate::pass::Pass;
+
+pub struct Gvn;
+
+impl Pass<Function> for Gvn {
+    fn name(&self) -> &'static str {
+        "gvn"
+    }
+
+    fn run(&self, func: &mut Function) -> bool {
+        if func.basic_blocks.is_empty() {
+            return false;
+        }
+
+        let cfg = Cfg::new(func);
+        let dominators = Dominators::new(&cfg);
+
+        // The arguments are assigned by the caller.
+        let mut assignments = (1..=func.args_len)
+            .map(|local| (Local(local), 1))
+            .collect::<BTreeMap<_, usize>>();
+        for bb_data in func.basic_blocks.values() {
+            for statement in &bb_data.statements {
+                *assignments.entry(statement.lhs()).or_default() += 1;
+            }
+        }
+
+        let assigned_once = assignments
+            .into_iter()
+            .filter(|(_, count)| *count == 1)
+            .map(|(local, _)| local)
+            .collect::<BTreeSet<_>>();
+
+        let mut numbering = Numbering {
+            values: (1..=func.args_len)
+                .map(Local)
+                .filter(|local| assigned_once.contains(local))
+                .map(|local| (local, Value::Local(local)))
+                .collect(),
+            assigned_once,
+            expressions: BTreeMap::new(),
+            changed: false,
+        };
+
+        numbering.visit(func, cfg.entry(), &dominators);
+        numbering.changed
+    }
+}
+
+/// A value number, which is either the first local that holds the value or a constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Value {
+    Local(Local),
+    Constant(Literal),
+}
+
+type Expression = (BinOp, Value, Value);
+
+struct Numbering {
+    assigned_once: BTreeSet<Local>,
+    /// The values of the locals assigned in the dominators of the current statement.
+    values: BTreeMap<Local, Value>,
+    /// The local that holds the result of every binary operation computed in the dominators of the
+    /// current statement.
+    expressions: BTreeMap<Expression, Local>,
+    changed: bool,
+}
+
+impl Numbering {
+    fn value(&self, operand: &Operand) -> Option<Value> {
+        match operand {
+            Operand::Local(local) => self.values.get(local).copied(),
+            Operand::Constant(literal) => Some(Value::Constant(*literal)),
+        }
+    }
+
+    /// Returns the value assigned to `lhs` by `rhs`, replacing `rhs` by a copy if its value was
+    /// already computed.
+    fn number(&mut self, lhs: Local, rhs: &mut Rvalue, computed: &mut Vec<Expression>) -> Value {
+        match rhs {
+            Rvalue::Use(operand) => self.value(operand).unwrap_or(Value::Local(lhs)),
+            Rvalue::BinaryOp {
+                op,
+                lhs: first,
+                rhs: second,
+            } => {
+                let (Some(mut first), Some(mut second)) = (self.value(first), self.value(second))
+                else {
+                    return Value::Local(lhs);
+                };
+
+                if op.is_commutative() && second < first {
+                    std::mem::swap(&mut first, &mut second);
+                }
+
+                let expression = (*op, first, second);
+                match self.expressions.get(&expression) {
+                    Some(holder) => {
+                        let holder = *holder;
+                        *rhs = Rvalue::Use(Operand::Local(holder));
+                        self.changed = true;
+                        self.values[&holder]
+                    }
+                    None => {
+                        self.expressions.insert(expression, lhs);
+                        computed.push(expression);
+                        Value::Local(lhs)
+                    }
+                }
+            }
+            Rvalue::SymbolAddr(_) | Rvalue::Call { .. } | Rvalue::Cast { .. } => Value::Local(lhs),
+        }
+    }
+
+    fn visit(&mut self, func: &mut Function, bb: BasicBlockId, dominators: &Dominators) {
+        let mut assigned = Vec::new();
+        let mut computed = Vec::new();
+
+        for statement in &mut func.basic_blocks.get_mut(&bb).unwrap().statements {
+            let lhs = statement.lhs();
+            if !self.assigned_once.contains(&lhs) {
+                continue;
+            }
+
+            let value = match statement {
+                Statement::Assign { rhs, .. } => self.number(lhs, rhs, &mut computed),
+                Statement::Phi { .. } => Value::Local(lhs),
+            };
+            self.values.insert(lhs, value);
+            assigned.push(lhs);
+        }
+
+        for child in dominators.children(bb) {
+            self.visit(func, *child, dominators);
+        }
+
+        // The values of this block are not available in the blocks it does not dominate.
+        for local in assigned {
+            self.values.remove(&local);
+        }
+        for expression in computed {
+            self.expressions.remove(&expression);
+        }
+    }
+}
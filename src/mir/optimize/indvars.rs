@@ -0,0 +1,303 @@
+//! Replacement of counted loops by the closed form of their induction variables.
+//!
+//! A loop whose statements only update induction variables and compute its exit condition has
+//! no effect other than the final values of those variables. If the loop has a
+//! [`TripCount`] and its counter starts at a constant, the value of a basic induction variable
+//! after the loop is `init + step * trips`, so the loop is replaced by that computation.
+//!
+//! MIR has no multiplication, so `step * trips` is computed with additions by doubling, which
+//! takes a number of additions logarithmic in `step` instead of one per iteration.
+use std::collections::BTreeSet;
+
+use crate::mir::{
+    cfg::{Cfg, Dominators, Loop, Loops},
+    induction::{assigned_locals, InductionVariable, InductionVariables, TripCount},
+    BasicBlock, BinOp, BlockProfile, Function, Literal, Local, Operand, Rvalue, Statement,
+    Terminator, Ty,
+};
+use crate::pass::Pass;
+
+use super::ssa::rename_phi_preds;
+
+pub struct IndVars;
+
+impl Pass<Function> for IndVars {
+    fn name(&self) -> &'static str {
+        "indvars"
+    }
+
+    fn run(&self, func: &mut Function) -> bool {
+        if func.basic_blocks.is_empty() {
+            return false;
+        }
+
+        // Replacing a loop removes its blocks and the loops nested in it, so the loops are found
+        // again for every header.
+        let headers = loops(func)
+            .loops()
+            .iter()
+            .rev()
+            .map(|l| l.header)
+            .collect::<Vec<_>>();
+
+        let mut changed = false;
+
+        for header in headers {
+            let cfg = Cfg::new(func);
+            let loops = loops(func);
+            let Some(l) = loops.loops().iter().find(|l| l.header == header) else {
+                continue;
+            };
+
+            changed |= replace_loop(func, &cfg, l);
+        }
+
+        changed
+    }
+}
+
+fn loops(func: &Function) -> Loops {
+    let cfg = Cfg::new(func);
+    Loops::new(&cfg, &Dominators::new(&cfg))
+}
+
+/// Replaces `l` by the closed form of its induction variables if possible. Returns whether the loop
+/// was replaced.
+fn replace_loop(func: &mut Function, cfg: &Cfg, l: &Loop) -> bool {
+    let Some(ivs) = InductionVariables::new(func, cfg, l) else {
+        return false;
+    };
+    let Some(trip_count) = TripCount::new(func, l, &ivs) else {
+        return false;
+    };
+    let Some(InductionVariable::Basic {
+        init: Operand::Constant(counter_init),
+        ..
+    }) = ivs.get(trip_count.iv)
+    else {
+        return false;
+    };
+    let counter_init = counter_init.data;
+
+    // Every statement must be a phi of a basic induction variable or an addition that computes an
+    // induction variable, except the exit condition.
+    let only_induction_variables = l
+        .blocks
+        .iter()
+        .flat_map(|bb| &func.basic_blocks[bb].statements)
+        .all(|statement| match statement {
+            Statement::Phi { lhs, .. } => {
+                matches!(ivs.get(*lhs), Some(InductionVariable::Basic { .. }))
+            }
+            Statement::Assign { lhs, .. } => *lhs == trip_count.cond || ivs.get(*lhs).is_some(),
+        });
+    if !only_induction_variables {
+        return false;
+    }
+
+    // The locals read after the loop must have a closed form: they can only be the phis of the
+    // header and the derived induction variables assigned by it.
+    let header_locals = func.basic_blocks[&l.header]
+        .statements
+        .iter()
+        .map(Statement::lhs)
+        .filter(|local| *local != trip_count.cond)
+        .collect::<BTreeSet<_>>();
+    let assigned_in_loop = assigned_locals(func, l);
+    let read_outside = func
+        .basic_blocks
+        .iter()
+        .filter(|(bb, _)| !l.blocks.contains(bb))
+        .flat_map(|(_, bb_data)| {
+            let cond = match &bb_data.terminator {
+                Terminator::JumpIf { cond, .. } => Some(cond),
+                Terminator::Jump(_) | Terminator::Return => None,
+            };
+            bb_data
+                .statements
+                .iter()
+                .flat_map(Statement::operands)
+                .chain(cond)
+        })
+        .filter_map(Operand::local)
+        .any(|local| assigned_in_loop.contains(&local) && !header_locals.contains(&local));
+    if read_outside {
+        return false;
+    }
+
+    let ty = func.local_types[&trip_count.iv];
+    let header = l.header;
+    let exit = trip_count.exit;
+
+    // The header is kept, as the block that jumps to the exit, and the rest of the loop is
+    // removed.
+    let header_statements =
+        std::mem::take(&mut func.basic_blocks.get_mut(&header).unwrap().statements);
+    func.basic_blocks
+        .retain(|bb, _| *bb == header || !l.blocks.contains(bb));
+
+    let mut builder = Builder {
+        func,
+        ty,
+        statements: Vec::new(),
+    };
+
+    if let Some(trips) = trip_count.constant(&ivs) {
+        // Every value is known when the header is executed.
+        for (local, iv) in ivs.iter() {
+            if let InductionVariable::Basic { init, step, .. } = iv {
+                let offset = builder.constant(step.wrapping_mul(trips));
+                builder.add(Some(local), init.clone(), offset);
+            }
+        }
+        builder.derived(&ivs, &header_statements);
+
+        let header_bb = BasicBlock {
+            statements: builder.statements,
+            terminator: Terminator::Jump(exit),
+            cold: false,
+            profile: BlockProfile::default(),
+        };
+        builder.func.basic_blocks.insert(header, header_bb);
+    } else {
+        // The loop runs `bound - init` times if the counter starts below the bound and never
+        // otherwise, so the values are selected by phis after a check of the first iteration.
+        let init = builder.constant(counter_init);
+        let cond = Statement::Assign {
+            lhs: trip_count.cond,
+            rhs: Rvalue::BinaryOp {
+                op: BinOp::Lt,
+                lhs: init,
+                rhs: trip_count.bound.clone(),
+            },
+        };
+
+        let minus_init = builder.constant(counter_init.wrapping_neg());
+        let trips = builder.add(None, trip_count.bound.clone(), minus_init);
+
+        let mut values = Vec::new();
+        for (local, iv) in ivs.iter() {
+            if let InductionVariable::Basic { init, step, .. } = iv {
+                // The counter stops at the bound.
+                let value = if local == trip_count.iv {
+                    trip_count.bound.clone()
+                } else {
+                    let offset = builder.multiply(trips.clone(), *step);
+                    builder.add(None, init.clone(), offset)
+                };
+                values.push((local, init.clone(), value));
+            }
+        }
+
+        let loop_statements = std::mem::take(&mut builder.statements);
+        builder.derived(&ivs, &header_statements);
+        let derived = builder.statements;
+
+        let func = builder.func;
+        let loop_bb = func.add_block(BasicBlock {
+            statements: loop_statements,
+            terminator: Terminator::Return,
+            cold: false,
+            profile: BlockProfile::default(),
+        });
+        let join_bb = func.add_block(BasicBlock {
+            statements: values
+                .into_iter()
+                .map(|(lhs, init, value)| Statement::Phi {
+                    lhs,
+                    args: vec![(header, init), (loop_bb, value)],
+                })
+                .chain(derived)
+                .collect(),
+            terminator: Terminator::Jump(exit),
+            cold: false,
+            profile: BlockProfile::default(),
+        });
+        func.basic_blocks.get_mut(&loop_bb).unwrap().terminator = Terminator::Jump(join_bb);
+        rename_phi_preds(func, exit, header, join_bb);
+
+        let header_bb = BasicBlock {
+            statements: vec![cond],
+            terminator: Terminator::JumpIf {
+                cond: Operand::Local(trip_count.cond),
+                then_bb: loop_bb,
+                else_bb: join_bb,
+            },
+            cold: false,
+            profile: BlockProfile::default(),
+        };
+        func.basic_blocks.insert(header, header_bb);
+    }
+
+    true
+}
+
+/// Builds the statements that compute the closed forms.
+struct Builder<'a> {
+    func: &'a mut Function,
+    /// The type of the induction variables.
+    ty: Ty,
+    statements: Vec<Statement>,
+}
+
+impl<'a> Builder<'a> {
+    fn constant(&self, data: u64) -> Operand {
+        Operand::Constant(Literal { data, ty: self.ty })
+    }
+
+    /// Returns the sum of two operands, assigned to `lhs` or to a new local if `lhs` is `None`.
+    /// No statement is added if the sum is one of the operands and `lhs` is `None`.
+    fn add(&mut self, lhs: Option<Local>, operand1: Operand, operand2: Operand) -> Operand {
+        let rhs = match (operand1, operand2) {
+            (Operand::Constant(lhs), Operand::Constant(rhs)) => {
+                Rvalue::Use(self.constant(lhs.data.wrapping_add(rhs.data)))
+            }
+            (Operand::Constant(Literal { data: 0, .. }), operand)
+            | (operand, Operand::Constant(Literal { data: 0, .. })) => Rvalue::Use(operand),
+            (lhs, rhs) => Rvalue::BinaryOp {
+                op: BinOp::Add,
+                lhs,
+                rhs,
+            },
+        };
+
+        match (lhs, rhs) {
+            (None, Rvalue::Use(operand)) => operand,
+            (lhs, rhs) => {
+                let lhs = lhs.unwrap_or_else(|| self.func.add_local(self.ty));
+                self.statements.push(Statement::Assign { lhs, rhs });
+                Operand::Local(lhs)
+            }
+        }
+    }
+
+    /// Returns `operand` times `factor`, computed by doubling the operand and adding it once for
+    /// every bit of `factor`.
+    fn multiply(&mut self, operand: Operand, factor: u64) -> Operand {
+        if factor == 0 {
+            return self.constant(0);
+        }
+
+        let mut product = operand.clone();
+        for bit in (0..factor.ilog2()).rev() {
+            product = self.add(None, product.clone(), product);
+            if factor & (1 << bit) != 0 {
+                product = self.add(None, product, operand.clone());
+            }
+        }
+
+        product
+    }
+
+    /// Adds the derived induction variables assigned by `statements`, which must come after the
+    /// assignments of the basic induction variables.
+    fn derived(&mut self, ivs: &InductionVariables, statements: &[Statement]) {
+        for statement in statements {
+            let lhs = statement.lhs();
+            if let Some(InductionVariable::Derived { base, offset }) = ivs.get(lhs) {
+                let offset = self.constant(*offset);
+                self.add(Some(lhs), Operand::Local(*base), offset);
+            }
+        }
+    }
+}
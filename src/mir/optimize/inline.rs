@@ -0,0 +1,346 @@
+//! Inlining of calls to functions defined in the same module.
+//!
+//! Callees are visited before their callers, so the calls inside a callee are inlined before the
+//! callee itself is inlined. A call is only inlined once: the calls that come from an inlined
+//! callee are not considered again, and a function is never inlined into a function it can
+//! call, which would never end for recursive functions.
+//!
+//! Whether a call is inlined depends on the [`InlineHint`] of the callee and on its size. When
+//! the module was compiled with a profile, calls that never ran are only inlined if the callee
+//! is marked with `#[inline(always)]`, and hot calls are inlined as if the callee was marked with
+//! `#[inline]`. Calls that don't pass as many arguments as the callee takes are never inlined,
+//! since the missing arguments would have no value and the extra operands would be lost.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::mir::{
+    BasicBlock, BasicBlockId, BlockProfile, Function, InlineHint, Local, Module, Operand, Rvalue,
+    Statement, Terminator,
+};
+use crate::pass::Pass;
+
+use super::ssa::rename_phi_preds;
+
+/// The size of the largest function that is inlined without an attribute, such as a function
+/// with a single block of three statements.
+const INLINE_THRESHOLD: usize = 4;
+
+/// The size of the largest function marked with `#[inline]` that is inlined.
+const INLINE_HINT_THRESHOLD: usize = 32;
+
+/// The number of times a call must run according to the profile to be considered hot.
+const HOT_CALL_COUNT: u64 = 1000;
+
+pub struct Inliner;
+
+impl Pass<Module> for Inliner {
+    fn name(&self) -> &'static str {
+        "inline"
+    }
+
+    fn run(&self, module: &mut Module) -> bool {
+        let callees = module
+            .functions
+            .iter()
+            .map(|(name, func)| (name.clone(), callees(func)))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut changed = false;
+
+        for name in postorder(&callees) {
+            // A function that can call the caller would be inlined into itself eventually.
+            let can_inline = |callee: &str| {
+                callees.contains_key(callee) && !reachable(&callees, callee).contains(name.as_str())
+            };
+
+            let mut caller = module.functions.remove(&name).unwrap();
+            changed |= inline_calls(module, &mut caller, can_inline);
+            module.functions.insert(name, caller);
+        }
+
+        changed
+    }
+}
+
+/// Returns the functions called by `func`.
+fn callees(func: &Function) -> BTreeSet<String> {
+    func.basic_blocks
+        .values()
+        .flat_map(|bb_data| &bb_data.statements)
+        .filter_map(|statement| match statement {
+            Statement::Assign {
+                rhs: Rvalue::Call { callee, .. },
+                ..
+            } => Some(callee.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the functions that can be reached by a chain of calls from `name`, not including
+/// `name` unless it is recursive.
+fn reachable<'a>(callees: &'a BTreeMap<String, BTreeSet<String>>, name: &str) -> BTreeSet<&'a str> {
+    let mut reachable = BTreeSet::new();
+    let mut stack = callees
+        .get(name)
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+
+    while let Some(name) = stack.pop() {
+        if reachable.insert(name) {
+            stack.extend(callees.get(name).into_iter().flatten().map(String::as_str));
+        }
+    }
+
+    reachable
+}
+
+/// Returns the functions of the module so that every function comes after the functions it
+/// calls, except for the calls that form a cycle.
+fn postorder(callees: &BTreeMap<String, BTreeSet<String>>) -> Vec<String> {
+    let mut postorder = Vec::new();
+    let mut visited = BTreeSet::new();
+
+    for root in callees.keys() {
+        if !visited.insert(root) {
+            continue;
+        }
+
+        let mut stack = vec![(root, callees[root].iter())];
+        while let Some((name, next)) = stack.last_mut() {
+            match next.next() {
+                Some(callee) => {
+                    if let Some((callee, _)) = callees.get_key_value(callee) {
+                        if visited.insert(callee) {
+                            stack.push((callee, callees[callee].iter()));
+                        }
+                    }
+                }
+                None => {
+                    postorder.push((*name).clone());
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    postorder
+}
+
+/// Returns the number of statements and terminators of `func`.
+fn size(func: &Function) -> usize {
+    func.basic_blocks
+        .values()
+        .map(|bb_data| bb_data.statements.len() + 1)
+        .sum()
+}
+
+/// Returns whether a call to `callee` should be inlined, `count` is the number of times the call
+/// ran according to the profile.
+fn should_inline(callee: &Function, count: Option<u64>) -> bool {
+    let hot = count.is_some_and(|count| count >= HOT_CALL_COUNT);
+    match callee.inline {
+        InlineHint::Always => true,
+        InlineHint::Never => false,
+        _ if count == Some(0) => false,
+        InlineHint::Hint => size(callee) <= INLINE_HINT_THRESHOLD,
+        InlineHint::Auto if hot => size(callee) <= INLINE_HINT_THRESHOLD,
+        InlineHint::Auto => size(callee) <= INLINE_THRESHOLD,
+    }
+}
+
+/// Inlines the calls of `caller` to the functions of `module` accepted by `can_inline`. Returns
+/// whether any call was inlined.
+fn inline_calls(module: &Module, caller: &mut Function, can_inline: impl Fn(&str) -> bool) -> bool {
+    let mut changed = false;
+
+    // The blocks of the inlined callees are not added to the worklist, but the rest of the block
+    // after each inlined call is.
+    let mut worklist = caller
+        .basic_blocks
+        .keys()
+        .rev()
+        .copied()
+        .collect::<Vec<_>>();
+
+    while let Some(bb) = worklist.pop() {
+        let count = caller.basic_blocks[&bb].profile.count;
+        let call = caller.basic_blocks[&bb]
+            .statements
+            .iter()
+            .enumerate()
+            .find_map(|(index, statement)| match statement {
+                Statement::Assign {
+                    rhs: Rvalue::Call { callee, args },
+                    ..
+                } if can_inline(callee)
+                    && args.len() == module.functions[callee].args_len
+                    && should_inline(&module.functions[callee], count) =>
+                {
+                    Some((index, &module.functions[callee]))
+                }
+                _ => None,
+            });
+
+        if let Some((index, callee)) = call {
+            worklist.push(inline_call(caller, bb, index, callee));
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+/// Replaces the call in the statement with index `index` of `bb` by the body of `callee`. The
+/// statements after the call are moved to a new block, which is returned.
+///
+/// The arguments that the callee never assigns are replaced by the operands of the call, and the
+/// return value is assigned directly to the destination of the call when no argument reads it.
+/// The rest of the locals of the callee get new locals in the caller.
+fn inline_call(
+    caller: &mut Function,
+    bb: BasicBlockId,
+    index: usize,
+    callee: &Function,
+) -> BasicBlockId {
+    let bb_data = caller.basic_blocks.get_mut(&bb).unwrap();
+    let mut rest = bb_data.statements.split_off(index);
+    let Statement::Assign {
+        lhs,
+        rhs: Rvalue::Call { args, .. },
+    } = rest.remove(0)
+    else {
+        unreachable!("the statement is not a call")
+    };
+
+    let assigned = callee
+        .basic_blocks
+        .values()
+        .flat_map(|bb_data| &bb_data.statements)
+        .map(Statement::lhs)
+        .collect::<BTreeSet<_>>();
+
+    let mut operands = BTreeMap::new();
+    let mut copies = Vec::new();
+    for (arg, operand) in args.into_iter().enumerate() {
+        let arg = Local(arg + 1);
+        if assigned.contains(&arg) {
+            copies.push((arg, operand));
+        } else {
+            operands.insert(arg, operand);
+        }
+    }
+
+    let reads_lhs = operands
+        .values()
+        .chain(copies.iter().map(|(_, operand)| operand))
+        .any(|operand| operand.local() == Some(lhs));
+
+    let mut locals = BTreeMap::new();
+    for (local, ty) in &callee.local_types {
+        if operands.contains_key(local) {
+            continue;
+        }
+
+        let new_local = if local.0 == 0 && !reads_lhs {
+            lhs
+        } else {
+            caller.add_local(*ty)
+        };
+        locals.insert(*local, new_local);
+    }
+
+    let rename = |operand: &mut Operand| {
+        if let Operand::Local(local) = operand {
+            match operands.get(local) {
+                Some(new_operand) => *operand = new_operand.clone(),
+                None => *local = locals[local],
+            }
+        }
+    };
+
+    // The blocks of the callee go after the ones of the caller, followed by the block that
+    // continues after the call.
+    let first = caller.basic_blocks.keys().next_back().unwrap().0 + 1;
+    let blocks = callee
+        .basic_blocks
+        .keys()
+        .enumerate()
+        .map(|(offset, callee_bb)| (*callee_bb, BasicBlockId(first + offset)))
+        .collect::<BTreeMap<_, _>>();
+    let continuation = BasicBlockId(first + blocks.len());
+
+    let bb_data = caller.basic_blocks.get_mut(&bb).unwrap();
+    bb_data
+        .statements
+        .extend(copies.into_iter().map(|(arg, operand)| Statement::Assign {
+            lhs: locals[&arg],
+            rhs: Rvalue::Use(operand),
+        }));
+
+    let entry = blocks[callee.basic_blocks.keys().next().unwrap()];
+    let terminator = std::mem::replace(&mut bb_data.terminator, Terminator::Jump(entry));
+    let cold = bb_data.cold;
+    // The counters of the block are only incremented once, before the call.
+    let profile = BlockProfile {
+        counters: Vec::new(),
+        count: bb_data.profile.count,
+    };
+
+    // The successors of the caller's block are now entered from the continuation.
+    for succ in terminator.successors() {
+        rename_phi_preds(caller, succ, bb, continuation);
+    }
+
+    let return_value = locals[&Local(0)];
+    if return_value != lhs {
+        rest.insert(
+            0,
+            Statement::Assign {
+                lhs,
+                rhs: Rvalue::Use(Operand::Local(return_value)),
+            },
+        );
+    }
+    caller.basic_blocks.insert(
+        continuation,
+        BasicBlock {
+            statements: rest,
+            terminator,
+            cold,
+            profile,
+        },
+    );
+
+    for (callee_bb, bb_data) in &callee.basic_blocks {
+        let mut bb_data = bb_data.clone();
+
+        for statement in &mut bb_data.statements {
+            let lhs = statement.lhs_mut();
+            *lhs = locals[lhs];
+            statement.operands_mut().for_each(rename);
+
+            if let Statement::Phi { args, .. } = statement {
+                for (pred, _) in args {
+                    *pred = blocks[pred];
+                }
+            }
+        }
+
+        match &mut bb_data.terminator {
+            Terminator::Return => bb_data.terminator = Terminator::Jump(continuation),
+            Terminator::JumpIf { cond, .. } => rename(cond),
+            Terminator::Jump(_) => {}
+        }
+        for target in bb_data.terminator.successors_mut() {
+            if *target != continuation {
+                *target = blocks[target];
+            }
+        }
+
+        caller.basic_blocks.insert(blocks[callee_bb], bb_data);
+    }
+
+    continuation
+}
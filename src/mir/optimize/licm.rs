@@ -0,0 +1,264 @@
+//! Loop invariant code motion.
+//!
+//! An assignment is invariant in a loop if every local it reads is either assigned outside the
+//! loop or by another invariant assignment, so it computes the same value in every iteration. Such
+//! assignments are moved to the preheader of the loop, a block that jumps to the header and is
+//! executed once before the loop is entered.
+//!
+//! Only locals assigned by a single statement are moved, and only by assignments without side
+//! effects, as the moved statement is executed even if the loop would not have reached it. In SSA
+//! form, this is every assignment except the calls and the ones to the return value.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::mir::{
+    cfg::{Cfg, Dominators, Loop, Loops},
+    induction::assigned_locals,
+    BasicBlock, BasicBlockId, BlockProfile, Function, Local, Operand, Statement, Terminator,
+};
+use crate::pass::Pass;
+
+pub struct Licm;
+
+impl Pass<Function> for Licm {
+    fn name(&self) -> &'static str {
+        "licm"
+    }
+
+    fn run(&self, func: &mut Function) -> bool {
+        if func.basic_blocks.is_empty() {
+            return false;
+        }
+
+        // Inner loops go first, so the assignments moved out of them can be moved out of the
+        // loops that contain them as well. Inserting a preheader changes the loops that contain
+        // it, so they are found again for every header.
+        let headers = loops(func)
+            .loops()
+            .iter()
+            .rev()
+            .map(|l| l.header)
+            .collect::<Vec<_>>();
+
+        let mut changed = false;
+
+        for header in headers {
+            let cfg = Cfg::new(func);
+            let loops = loops(func);
+            let l = loops.loops().iter().find(|l| l.header == header).unwrap();
+
+            // A block before the entry block cannot be inserted.
+            if header == cfg.entry() {
+                continue;
+            }
+
+            let invariant = invariant_statements(func, &cfg, l);
+            if invariant.is_empty() {
+                continue;
+            }
+
+            let mut moved = Vec::new();
+            for (bb, indices) in invariant {
+                let bb_data = func.basic_blocks.get_mut(&bb).unwrap();
+                let (hoisted, kept) = std::mem::take(&mut bb_data.statements)
+                    .into_iter()
+                    .enumerate()
+                    .partition::<Vec<_>, _>(|(index, _)| indices.contains(index));
+
+                moved.extend(hoisted.into_iter().map(|(_, statement)| statement));
+                bb_data.statements = kept.into_iter().map(|(_, statement)| statement).collect();
+            }
+            let moved = order_by_dependencies(moved);
+
+            let preheader = insert_preheader(func, &cfg, l);
+            func.basic_blocks
+                .get_mut(&preheader)
+                .unwrap()
+                .statements
+                .extend(moved);
+            changed = true;
+        }
+
+        changed
+    }
+}
+
+fn loops(func: &Function) -> Loops {
+    let cfg = Cfg::new(func);
+    Loops::new(&cfg, &Dominators::new(&cfg))
+}
+
+/// Returns the indices of the invariant statements of every block of `l`.
+fn invariant_statements(
+    func: &Function,
+    cfg: &Cfg,
+    l: &Loop,
+) -> BTreeMap<BasicBlockId, BTreeSet<usize>> {
+    // The arguments are assigned by the caller.
+    let mut assignments = (1..=func.args_len)
+        .map(|local| (Local(local), 1))
+        .collect::<BTreeMap<_, usize>>();
+    for bb_data in func.basic_blocks.values() {
+        for statement in &bb_data.statements {
+            *assignments.entry(statement.lhs()).or_default() += 1;
+        }
+    }
+
+    let assigned_in_loop = assigned_locals(func, l);
+    let mut invariant_locals = BTreeSet::new();
+    let mut invariant = BTreeMap::<_, BTreeSet<_>>::new();
+
+    // An invariant statement can read a local assigned by an invariant statement that comes later
+    // in a loop that is not in SSA form, so the loop is scanned until nothing else is found.
+    let mut found = true;
+    while found {
+        found = false;
+
+        for bb in cfg.reverse_postorder() {
+            if !l.blocks.contains(bb) {
+                continue;
+            }
+
+            for (index, statement) in func.basic_blocks[bb].statements.iter().enumerate() {
+                let Statement::Assign { lhs, rhs } = statement else {
+                    continue;
+                };
+
+                let is_invariant = assignments[lhs] == 1
+                    && !rhs.has_side_effects()
+                    && !invariant_locals.contains(lhs)
+                    && rhs.operands().all(|operand| match operand {
+                        Operand::Local(local) => {
+                            !assigned_in_loop.contains(local) || invariant_locals.contains(local)
+                        }
+                        Operand::Constant(_) => true,
+                    });
+
+                if is_invariant {
+                    invariant_locals.insert(*lhs);
+                    invariant.entry(*bb).or_default().insert(index);
+                    found = true;
+                }
+            }
+        }
+    }
+
+    invariant
+}
+
+/// Sorts `statements` so every statement comes after the ones assigning the locals it reads.
+fn order_by_dependencies(mut statements: Vec<Statement>) -> Vec<Statement> {
+    let mut ordered = Vec::with_capacity(statements.len());
+
+    while !statements.is_empty() {
+        let pending = statements
+            .iter()
+            .map(Statement::lhs)
+            .collect::<BTreeSet<_>>();
+
+        let (ready, rest) = statements.into_iter().partition::<Vec<_>, _>(|statement| {
+            statement
+                .operands()
+                .filter_map(Operand::local)
+                .all(|local| !pending.contains(&local))
+        });
+
+        ordered.extend(ready);
+        statements = rest;
+    }
+
+    ordered
+}
+
+/// Returns the preheader of `l`, inserting a new one if the loop does not have one.
+///
+/// The preheader is the only block outside the loop that jumps to the header, and it does not jump
+/// anywhere else. If there are many blocks entering the loop, the arguments that the phis of the
+/// header receive from them are merged by phis in the new preheader.
+pub(super) fn insert_preheader(func: &mut Function, cfg: &Cfg, l: &Loop) -> BasicBlockId {
+    let entering = cfg
+        .predecessors(l.header)
+        .iter()
+        .copied()
+        .filter(|pred| !l.blocks.contains(pred))
+        .collect::<BTreeSet<_>>();
+
+    if let [pred] = entering.iter().copied().collect::<Vec<_>>()[..] {
+        if let Terminator::Jump(_) = func.basic_blocks[&pred].terminator {
+            return pred;
+        }
+    }
+
+    let mut statements = Vec::new();
+    let header_data = &func.basic_blocks[&l.header];
+    let phis = header_data
+        .statements
+        .iter()
+        .enumerate()
+        .filter_map(|(index, statement)| match statement {
+            Statement::Phi { lhs, args } => Some((index, *lhs, args.clone())),
+            Statement::Assign { .. } => None,
+        })
+        .collect::<Vec<_>>();
+
+    let preheader = func.add_block(BasicBlock {
+        statements: Vec::new(),
+        terminator: Terminator::Jump(l.header),
+        cold: false,
+        profile: BlockProfile::default(),
+    });
+
+    for (index, lhs, args) in phis {
+        let outside = args
+            .iter()
+            .filter(|(pred, _)| entering.contains(pred))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let arg = if let [(_, arg)] = &outside[..] {
+            arg.clone()
+        } else {
+            let local = func.add_local(func.local_types[&lhs]);
+            statements.push(Statement::Phi {
+                lhs: local,
+                args: outside,
+            });
+            Operand::Local(local)
+        };
+
+        // The argument from the preheader takes the place of the first argument from outside the
+        // loop.
+        let mut new_args = Vec::new();
+        let mut arg = Some(arg);
+        for (pred, operand) in args {
+            if !entering.contains(&pred) {
+                new_args.push((pred, operand));
+            } else if let Some(arg) = arg.take() {
+                new_args.push((preheader, arg));
+            }
+        }
+
+        let statement = &mut func.basic_blocks.get_mut(&l.header).unwrap().statements[index];
+        *statement = Statement::Phi {
+            lhs,
+            args: new_args,
+        };
+    }
+
+    func.basic_blocks.get_mut(&preheader).unwrap().statements = statements;
+
+    for pred in entering {
+        for target in func
+            .basic_blocks
+            .get_mut(&pred)
+            .unwrap()
+            .terminator
+            .successors_mut()
+        {
+            if *target == l.header {
+                *target = preheader;
+            }
+        }
+    }
+
+    preheader
+}
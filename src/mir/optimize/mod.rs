@@ -0,0 +1,67 @@
+//! Optimization passes over MIR functions.
+mod const_prop;
+mod dead_code;
+mod gvn;
+mod indvars;
+mod inline;
+mod licm;
+mod placement;
+mod simplify_cfg;
+mod ssa;
+mod unreachable_blocks;
+mod unroll;
+
+use crate::mir::{Function, Module};
+use crate::pass::{Dump, OptLevel, Pipeline};
+
+pub use const_prop::ConstProp;
+pub use dead_code::DeadCode;
+pub use gvn::Gvn;
+pub use indvars::IndVars;
+pub use inline::Inliner;
+pub use licm::Licm;
+pub use placement::BlockPlacement;
+pub use simplify_cfg::SimplifyCfg;
+pub use ssa::{IntoSsa, OutOfSsa};
+pub use unreachable_blocks::UnreachableBlocks;
+pub use unroll::Unroll;
+
+/// Returns the MIR optimization passes in the order they are run.
+///
+/// At `-O2` the passes between `ssa` and `out-of-ssa` run on the function in SSA form, and
+/// `const-prop` runs again after `unroll` to fold the copies of the loops that were fully
+/// unrolled.
+pub fn pipeline(unroll: Unroll) -> Pipeline<Function> {
+    vec![
+        (OptLevel::O2, Box::new(IntoSsa)),
+        (OptLevel::O1, Box::new(ConstProp)),
+        (OptLevel::O2, Box::new(Licm)),
+        (OptLevel::O2, Box::new(IndVars)),
+        (OptLevel::O2, Box::new(unroll)),
+        (OptLevel::O2, Box::new(ConstProp)),
+        (OptLevel::O2, Box::new(Gvn)),
+        (OptLevel::O1, Box::new(UnreachableBlocks)),
+        (OptLevel::O1, Box::new(DeadCode)),
+        (OptLevel::O2, Box::new(OutOfSsa)),
+        (OptLevel::O1, Box::new(SimplifyCfg)),
+        (OptLevel::O1, Box::new(BlockPlacement)),
+    ]
+}
+
+/// Returns the MIR optimization passes that work on a whole module, which run before the passes
+/// of [`pipeline`].
+pub fn module_pipeline() -> Pipeline<Module> {
+    vec![(OptLevel::O1, Box::new(Inliner))]
+}
+
+impl Dump for Module {
+    fn dump(&self, _name: &str) -> String {
+        self.to_string()
+    }
+}
+
+impl Dump for Function {
+    fn dump(&self, name: &str) -> String {
+        format!("{}\n", self.display(name))
+    }
+}
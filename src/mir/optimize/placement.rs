@@ -0,0 +1,121 @@
+//! Ordering of the blocks of a function for code layout.
+//!
+//! Blocks are lowered in the order of their ids, so the pass renumbers them. The blocks are laid
+//! out in chains, where each block is followed by its most likely successor so that the lowering
+//! can fall through to it. A block only follows its successor once every block that can reach it
+//! without going through a loop's back edge has been placed, which keeps the order of a loop
+//! header, its body and its exit. Blocks marked as cold, or that never ran according to the
+//! profile, are placed at the end of the function.
+//!
+//! The likely successor of a `JUMP IF` is found with the following rules:
+//! - A cold successor is unlikely.
+//! - If the profile has a count for both successors, the one that ran more often is likely.
+//! - A successor that leaves the innermost loop containing the block is unlikely.
+//! - Otherwise, the `THEN` block is likely.
+use std::collections::BTreeSet;
+
+use crate::mir::cfg::{Cfg, Dominators, Loops};
+use crate::mir::{BasicBlockId, Function, Terminator};
+use crate::pass::Pass;
+
+use super::unreachable_blocks::renumber_blocks;
+
+pub struct BlockPlacement;
+
+impl Pass<Function> for BlockPlacement {
+    fn name(&self) -> &'static str {
+        "block-placement"
+    }
+
+    fn run(&self, func: &mut Function) -> bool {
+        let order = block_order(func);
+        renumber_blocks(func, &order)
+    }
+}
+
+/// Returns whether `bb` is marked as cold or never ran according to the profile.
+fn is_cold(func: &Function, bb: BasicBlockId) -> bool {
+    let bb_data = &func.basic_blocks[&bb];
+    bb_data.cold || bb_data.profile.count == Some(0)
+}
+
+/// Returns the successors of `bb`, the most likely one first.
+fn likely_successors(func: &Function, loops: &Loops, bb: BasicBlockId) -> Vec<BasicBlockId> {
+    let Terminator::JumpIf {
+        then_bb, else_bb, ..
+    } = func.basic_blocks[&bb].terminator
+    else {
+        return func.basic_blocks[&bb].terminator.successors().collect();
+    };
+
+    let cold = |bb: BasicBlockId| is_cold(func, bb);
+    let count = |bb: BasicBlockId| func.basic_blocks[&bb].profile.count;
+    let leaves_loop = |target: BasicBlockId| {
+        loops
+            .innermost(bb)
+            .is_some_and(|l| !l.blocks.contains(&target))
+    };
+
+    let else_is_likely = if cold(then_bb) != cold(else_bb) {
+        cold(then_bb)
+    } else if let (Some(then_count), Some(else_count)) = (count(then_bb), count(else_bb)) {
+        else_count > then_count
+    } else {
+        leaves_loop(then_bb) && !leaves_loop(else_bb)
+    };
+
+    if else_is_likely {
+        vec![else_bb, then_bb]
+    } else {
+        vec![then_bb, else_bb]
+    }
+}
+
+/// Returns the blocks of `func` in the order they should be laid out, starting with the entry
+/// block.
+fn block_order(func: &Function) -> Vec<BasicBlockId> {
+    let cfg = Cfg::new(func);
+    let dominators = Dominators::new(&cfg);
+    let loops = Loops::new(&cfg, &dominators);
+
+    let cold = |bb: &BasicBlockId| is_cold(func, *bb);
+    // Back edges and edges from unreachable blocks do not delay the placement of their target.
+    let ready = |bb: BasicBlockId, placed: &BTreeSet<BasicBlockId>| {
+        cfg.predecessors(bb).iter().all(|pred| {
+            placed.contains(pred) || !cfg.is_reachable(*pred) || dominators.dominates(bb, *pred)
+        })
+    };
+
+    let rpo = cfg.reverse_postorder();
+    let starts = std::iter::once(cfg.entry())
+        .chain(rpo.iter().copied().filter(|bb| !cold(bb)))
+        .chain(rpo.iter().copied().filter(cold))
+        .chain(func.basic_blocks.keys().copied());
+
+    let mut order = Vec::new();
+    let mut placed = BTreeSet::new();
+
+    for start in starts {
+        if placed.contains(&start) {
+            continue;
+        }
+
+        let mut bb = start;
+        loop {
+            placed.insert(bb);
+            order.push(bb);
+
+            let next = likely_successors(func, &loops, bb)
+                .into_iter()
+                .find(|succ| {
+                    !placed.contains(succ) && cold(succ) == cold(&start) && ready(*succ, &placed)
+                });
+            match next {
+                Some(next) => bb = next,
+                None => break,
+            }
+        }
+    }
+
+    order
+}
@@ -0,0 +1,179 @@
+//! Control flow graph simplification.
+//!
+//! The pass repeats the following rewrites until none of them applies:
+//! - A `JUMP IF` whose targets are the same block becomes a `JUMP`.
+//! - Jumps to a block without statements that only jumps somewhere else go straight to the final
+//!   target, and jumps to a block without statements that only returns become a `RETURN`.
+//! - The blocks that cannot be reached anymore are removed.
+//! - A block that is only reached by a `JUMP` from another block is merged into it.
+//!
+//! Blocks with phis are never skipped by a jump nor merged into another block, since their phis
+//! depend on the predecessor they are entered from.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::mir::{BasicBlockId, Function, Statement, Terminator};
+use crate::pass::Pass;
+
+use super::UnreachableBlocks;
+
+pub struct SimplifyCfg;
+
+impl Pass<Function> for SimplifyCfg {
+    fn name(&self) -> &'static str {
+        "simplify-cfg"
+    }
+
+    fn run(&self, func: &mut Function) -> bool {
+        let mut changed = false;
+
+        // The unreachable blocks are removed before merging, since they would still count as
+        // predecessors of the blocks they jump to.
+        loop {
+            let simplified = fold_branches(func)
+                | thread_jumps(func)
+                | UnreachableBlocks.run(func)
+                | merge_blocks(func);
+            if !simplified {
+                break;
+            }
+            changed = true;
+        }
+
+        changed
+    }
+}
+
+fn fold_branches(func: &mut Function) -> bool {
+    let mut changed = false;
+
+    for bb_data in func.basic_blocks.values_mut() {
+        if let Terminator::JumpIf {
+            then_bb, else_bb, ..
+        } = bb_data.terminator
+        {
+            if then_bb == else_bb {
+                bb_data.terminator = Terminator::Jump(then_bb);
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+/// Returns the block reached by following the jumps that start at `bb` through blocks without
+/// statements.
+fn jump_destination(func: &Function, mut bb: BasicBlockId) -> BasicBlockId {
+    let mut visited = BTreeSet::from([bb]);
+
+    while let Some(bb_data) = func.basic_blocks.get(&bb) {
+        match bb_data.terminator {
+            // Empty infinite loops are left alone.
+            Terminator::Jump(target)
+                if bb_data.statements.is_empty()
+                    && !func.basic_blocks[&target].has_phis()
+                    && visited.insert(target) =>
+            {
+                bb = target
+            }
+            _ => break,
+        }
+    }
+
+    bb
+}
+
+fn thread_jumps(func: &mut Function) -> bool {
+    let mut changed = false;
+    let blocks = func.basic_blocks.keys().copied().collect::<Vec<_>>();
+
+    for bb in blocks {
+        let targets = func.basic_blocks[&bb]
+            .terminator
+            .successors()
+            .map(|target| jump_destination(func, target))
+            .collect::<Vec<_>>();
+
+        let returns = |target: &BasicBlockId| {
+            let target_data = &func.basic_blocks[target];
+            target_data.statements.is_empty()
+                && matches!(target_data.terminator, Terminator::Return)
+        };
+        let returns = matches!(&targets[..], [target] if returns(target));
+
+        let terminator = &mut func.basic_blocks.get_mut(&bb).unwrap().terminator;
+
+        if returns {
+            *terminator = Terminator::Return;
+            changed = true;
+            continue;
+        }
+
+        for (successor, target) in terminator.successors_mut().zip(targets) {
+            if *successor != target {
+                *successor = target;
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+fn merge_blocks(func: &mut Function) -> bool {
+    let mut changed = false;
+
+    let mut predecessors = BTreeMap::<BasicBlockId, usize>::new();
+    for bb_data in func.basic_blocks.values() {
+        for target in bb_data.terminator.successors() {
+            *predecessors.entry(target).or_default() += 1;
+        }
+    }
+
+    let entry = func.basic_blocks.keys().next().copied();
+    let blocks = func.basic_blocks.keys().copied().collect::<Vec<_>>();
+
+    for bb in blocks {
+        // The block could have been merged into a previous one.
+        while let Some(Terminator::Jump(target)) = func
+            .basic_blocks
+            .get(&bb)
+            .map(|bb_data| &bb_data.terminator)
+        {
+            let target = *target;
+            if target == bb
+                || Some(target) == entry
+                || predecessors[&target] != 1
+                || func.basic_blocks[&target].has_phis()
+            {
+                break;
+            }
+
+            let target_data = func.basic_blocks.remove(&target).unwrap();
+
+            // The phis of the successors are now entered from `bb`.
+            for succ in target_data.terminator.successors() {
+                for statement in &mut func.basic_blocks.get_mut(&succ).unwrap().statements {
+                    if let Statement::Phi { args, .. } = statement {
+                        for (pred, _) in args {
+                            if *pred == target {
+                                *pred = bb;
+                            }
+                        }
+                    }
+                }
+            }
+
+            let bb_data = func.basic_blocks.get_mut(&bb).unwrap();
+            bb_data.statements.extend(target_data.statements);
+            bb_data.terminator = target_data.terminator;
+            bb_data
+                .profile
+                .counters
+                .extend(target_data.profile.counters);
+            changed = true;
+        }
+    }
+
+    changed
+}
@@ -0,0 +1,608 @@
+//! Conversion of functions into and out of static single assignment (SSA) form.
+//!
+//! In SSA form every local is assigned by a single statement, and the values that depend on the
+//! path taken to reach a block are selected by the phis at the start of the block. The return
+//! value is the only exception: it is assigned right before every `RETURN`, from the local that
+//! holds its value at that point.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::dataflow;
+use crate::mir::{
+    cfg::{Cfg, Dominators},
+    dataflow::Liveness,
+    BasicBlock, BasicBlockId, BlockProfile, Function, Local, Operand, Rvalue, Statement,
+    Terminator,
+};
+use crate::pass::Pass;
+
+use super::dead_code::compact_locals;
+use super::unreachable_blocks::renumber_blocks;
+
+/// Converts a function into SSA form.
+///
+/// Phis are placed at the iterated dominance frontiers of the assignments to each local, as in
+/// "Efficiently Computing Static Single Assignment Form and the Control Dependence Graph" by
+/// Cytron et al., but only where the local is live. The first assignment to a local keeps its id
+/// and the rest get new locals. A local read before any assignment reaches it keeps its id.
+///
+/// A phi in the entry block would have no argument for the values coming from the caller, so
+/// when the entry block is also the target of a jump a new empty entry block is added before it.
+pub struct IntoSsa;
+
+impl Pass<Function> for IntoSsa {
+    fn name(&self) -> &'static str {
+        "ssa"
+    }
+
+    fn run(&self, func: &mut Function) -> bool {
+        // A function with phis is already in SSA form.
+        if func.basic_blocks.is_empty() || func.basic_blocks.values().any(BasicBlock::has_phis) {
+            return false;
+        }
+
+        let added_entry = add_entry_block(func);
+
+        let cfg = Cfg::new(func);
+        let dominators = Dominators::new(&cfg);
+        let locals_len = func.local_types.len();
+
+        let placed = place_phis(func, &cfg, &dominators);
+        Renamer::new(func).rename(cfg.entry(), &cfg, &dominators);
+
+        added_entry || placed || func.local_types.len() != locals_len
+    }
+}
+
+/// Adds an empty block that jumps to the entry block before it if the entry block has
+/// predecessors. Returns whether the block was added.
+fn add_entry_block(func: &mut Function) -> bool {
+    let cfg = Cfg::new(func);
+    if cfg.predecessors(cfg.entry()).is_empty() {
+        return false;
+    }
+
+    let entry = func.add_block(BasicBlock {
+        statements: Vec::new(),
+        terminator: Terminator::Jump(cfg.entry()),
+        cold: false,
+        profile: BlockProfile::default(),
+    });
+
+    let mut order = vec![entry];
+    order.extend(func.basic_blocks.keys().copied().filter(|bb| *bb != entry));
+    renumber_blocks(func, &order);
+
+    true
+}
+
+/// Inserts a phi for every local that needs one, with the original local as the argument of every
+/// predecessor. Returns whether any phi was inserted.
+fn place_phis(func: &mut Function, cfg: &Cfg, dominators: &Dominators) -> bool {
+    let frontiers = dominators.frontiers(cfg);
+    let live_in = dataflow::solve(cfg, &Liveness::new(func)).before;
+
+    // The arguments are assigned by the caller before the entry block.
+    let mut def_blocks = (1..=func.args_len)
+        .map(|local| (Local(local), BTreeSet::from([cfg.entry()])))
+        .collect::<BTreeMap<_, _>>();
+    for bb in cfg.reverse_postorder() {
+        for statement in &func.basic_blocks[bb].statements {
+            def_blocks.entry(statement.lhs()).or_default().insert(*bb);
+        }
+    }
+
+    let mut phis = BTreeMap::<BasicBlockId, Vec<Local>>::new();
+    for (local, blocks) in &def_blocks {
+        // A phi is an assignment too, so the frontiers of the blocks that get one need phis as
+        // well.
+        let mut worklist = blocks.iter().copied().collect::<Vec<_>>();
+        let mut placed = BTreeSet::new();
+
+        while let Some(bb) = worklist.pop() {
+            for frontier in &frontiers[&bb] {
+                if live_in[frontier].contains(local) && placed.insert(*frontier) {
+                    phis.entry(*frontier).or_default().push(*local);
+                    worklist.push(*frontier);
+                }
+            }
+        }
+    }
+
+    let placed = !phis.is_empty();
+
+    for (bb, locals) in phis {
+        let preds = cfg
+            .predecessors(bb)
+            .iter()
+            .copied()
+            .filter(|pred| cfg.is_reachable(*pred))
+            .collect::<BTreeSet<_>>();
+
+        let statements = &mut func.basic_blocks.get_mut(&bb).unwrap().statements;
+        statements.splice(
+            0..0,
+            locals.into_iter().map(|local| Statement::Phi {
+                lhs: local,
+                args: preds
+                    .iter()
+                    .map(|pred| (*pred, Operand::Local(local)))
+                    .collect(),
+            }),
+        );
+    }
+
+    placed
+}
+
+/// Renames the assignments of a function so every local is assigned once, walking the dominator
+/// tree so the current version of every local is known at each statement.
+struct Renamer<'a> {
+    func: &'a mut Function,
+    /// The versions of every original local that are visible at the current block, the last one
+    /// is the current one.
+    versions: BTreeMap<Local, Vec<Local>>,
+    /// The original locals whose id is already used by a version.
+    assigned: BTreeSet<Local>,
+}
+
+impl<'a> Renamer<'a> {
+    fn new(func: &'a mut Function) -> Self {
+        let versions = func
+            .local_types
+            .keys()
+            .map(|local| (*local, vec![*local]))
+            .collect();
+
+        // The return value is assigned right before returning and the arguments are assigned by
+        // the caller, so their ids are never reused.
+        let assigned = (0..=func.args_len).map(Local).collect();
+
+        Self {
+            func,
+            versions,
+            assigned,
+        }
+    }
+
+    fn current(&self, local: Local) -> Local {
+        *self.versions[&local].last().unwrap()
+    }
+
+    fn rename_operand(&self, operand: &mut Operand) {
+        if let Operand::Local(local) = operand {
+            *local = self.current(*local);
+        }
+    }
+
+    /// Returns the local for a new assignment to `local` and makes it its current version.
+    fn new_version(&mut self, local: Local) -> Local {
+        let version = if self.assigned.insert(local) {
+            local
+        } else {
+            let ty = self.func.local_types[&local];
+            self.func.add_local(ty)
+        };
+
+        self.versions.get_mut(&local).unwrap().push(version);
+        version
+    }
+
+    fn rename(&mut self, bb: BasicBlockId, cfg: &Cfg, dominators: &Dominators) {
+        let mut defined = Vec::new();
+        let bb_data = self.func.basic_blocks.get_mut(&bb).unwrap();
+        let mut statements = std::mem::take(&mut bb_data.statements);
+
+        for statement in &mut statements {
+            // The arguments of the phis are renamed from their predecessors.
+            if !statement.is_phi() {
+                statement
+                    .operands_mut()
+                    .for_each(|operand| self.rename_operand(operand));
+            }
+
+            let lhs = statement.lhs_mut();
+            defined.push(*lhs);
+            *lhs = self.new_version(*lhs);
+        }
+
+        let bb_data = self.func.basic_blocks.get_mut(&bb).unwrap();
+        match &mut bb_data.terminator {
+            Terminator::Jump(_) => {}
+            Terminator::JumpIf { cond, .. } => {
+                if let Operand::Local(local) = cond {
+                    *local = *self.versions[local].last().unwrap();
+                }
+            }
+            Terminator::Return => {
+                let value = *self.versions[&Local(0)].last().unwrap();
+                if value != Local(0) {
+                    statements.push(Statement::Assign {
+                        lhs: Local(0),
+                        rhs: Rvalue::Use(Operand::Local(value)),
+                    });
+                }
+            }
+        }
+        bb_data.statements = statements;
+
+        let successors = cfg.successors(bb).iter().collect::<BTreeSet<_>>();
+        for succ in successors {
+            for statement in &mut self.func.basic_blocks.get_mut(succ).unwrap().statements {
+                if let Statement::Phi { args, .. } = statement {
+                    for (pred, operand) in args {
+                        if *pred == bb {
+                            if let Operand::Local(local) = operand {
+                                *local = *self.versions[local].last().unwrap();
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for child in dominators.children(bb) {
+            self.rename(*child, cfg, dominators);
+        }
+
+        for local in defined {
+            self.versions.get_mut(&local).unwrap().pop();
+        }
+    }
+}
+
+/// Takes a function out of SSA form.
+///
+/// The edges from blocks with more than one successor to blocks with phis are split, and every
+/// phi is replaced by copies at the end of its predecessors. Then, the locals related by a copy
+/// are merged when their values are never live at the same time, which removes most of the
+/// copies. Finally, every other local of the same type that is never live at the same time as
+/// another one shares its id, so that the function has no more locals than values live at once.
+pub struct OutOfSsa;
+
+impl Pass<Function> for OutOfSsa {
+    fn name(&self) -> &'static str {
+        "out-of-ssa"
+    }
+
+    fn run(&self, func: &mut Function) -> bool {
+        let mut changed = false;
+
+        if func.basic_blocks.values().any(BasicBlock::has_phis) {
+            split_critical_edges(func);
+            insert_copies(func);
+            changed = true;
+        }
+
+        changed |= coalesce_copies(func);
+        changed |= share_locals(func);
+        changed | compact_locals(func)
+    }
+}
+
+/// Removes the arguments of the phis whose predecessor no longer jumps to the block of the phi.
+/// Returns whether any argument was removed.
+pub(super) fn prune_phi_args(func: &mut Function) -> bool {
+    let edges = func
+        .basic_blocks
+        .iter()
+        .flat_map(|(bb, bb_data)| bb_data.terminator.successors().map(|target| (*bb, target)))
+        .collect::<BTreeSet<_>>();
+
+    let mut changed = false;
+
+    for (bb, bb_data) in &mut func.basic_blocks {
+        for statement in &mut bb_data.statements {
+            if let Statement::Phi { args, .. } = statement {
+                let len = args.len();
+                args.retain(|(pred, _)| edges.contains(&(*pred, *bb)));
+                changed |= args.len() != len;
+            }
+        }
+    }
+
+    changed
+}
+
+/// Replaces `old` by `new` in the predecessors of the phis of `bb`.
+pub(super) fn rename_phi_preds(
+    func: &mut Function,
+    bb: BasicBlockId,
+    old: BasicBlockId,
+    new: BasicBlockId,
+) {
+    for statement in &mut func.basic_blocks.get_mut(&bb).unwrap().statements {
+        if let Statement::Phi { args, .. } = statement {
+            for (pred, _) in args {
+                if *pred == old {
+                    *pred = new;
+                }
+            }
+        }
+    }
+}
+
+/// Splits the edges from a `JUMP IF` to a block with phis, so the copies that replace the phis can
+/// be placed at the end of a block that only jumps to the block of the phis.
+fn split_critical_edges(func: &mut Function) {
+    let mut edges = Vec::new();
+
+    for (bb, bb_data) in &mut func.basic_blocks {
+        if let Terminator::JumpIf {
+            then_bb, else_bb, ..
+        } = bb_data.terminator
+        {
+            if then_bb == else_bb {
+                bb_data.terminator = Terminator::Jump(then_bb);
+            } else {
+                edges.extend([(*bb, then_bb), (*bb, else_bb)]);
+            }
+        }
+    }
+
+    for (bb, target) in edges {
+        if !func.basic_blocks[&target].has_phis() {
+            continue;
+        }
+
+        let new_bb = func.add_block(BasicBlock {
+            statements: Vec::new(),
+            terminator: Terminator::Jump(target),
+            cold: false,
+            profile: BlockProfile::default(),
+        });
+
+        for successor in func
+            .basic_blocks
+            .get_mut(&bb)
+            .unwrap()
+            .terminator
+            .successors_mut()
+        {
+            if *successor == target {
+                *successor = new_bb;
+            }
+        }
+
+        for statement in &mut func.basic_blocks.get_mut(&target).unwrap().statements {
+            if let Statement::Phi { args, .. } = statement {
+                for (pred, _) in args {
+                    if *pred == bb {
+                        *pred = new_bb;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Replaces the phis by copies at the end of their predecessors.
+fn insert_copies(func: &mut Function) {
+    let mut copies = BTreeMap::<BasicBlockId, Vec<(Local, Operand)>>::new();
+
+    for bb_data in func.basic_blocks.values_mut() {
+        let (phis, statements) = std::mem::take(&mut bb_data.statements)
+            .into_iter()
+            .partition::<Vec<_>, _>(Statement::is_phi);
+        bb_data.statements = statements;
+
+        for phi in phis {
+            if let Statement::Phi { lhs, args } = phi {
+                for (pred, operand) in args {
+                    copies.entry(pred).or_default().push((lhs, operand));
+                }
+            }
+        }
+    }
+
+    for (pred, copies) in copies {
+        let statements = sequentialize(func, copies);
+        func.basic_blocks
+            .get_mut(&pred)
+            .unwrap()
+            .statements
+            .extend(statements);
+    }
+}
+
+/// Orders copies that happen in parallel, where every source is read before any destination is
+/// written, into a sequence of assignments.
+fn sequentialize(func: &mut Function, copies: Vec<(Local, Operand)>) -> Vec<Statement> {
+    let mut pending = copies
+        .into_iter()
+        .filter(|(lhs, operand)| operand.local() != Some(*lhs))
+        .collect::<Vec<_>>();
+    let mut statements = Vec::new();
+
+    while !pending.is_empty() {
+        // A copy can be done once no other pending copy reads its destination.
+        let ready = pending.iter().position(|(lhs, _)| {
+            pending
+                .iter()
+                .all(|(_, operand)| operand.local() != Some(*lhs))
+        });
+
+        match ready {
+            Some(index) => {
+                let (lhs, operand) = pending.remove(index);
+                statements.push(Statement::Assign {
+                    lhs,
+                    rhs: Rvalue::Use(operand),
+                });
+            }
+            // Every destination is read by another copy, so the copies form cycles. Saving one of
+            // the destinations in a new local breaks its cycle.
+            None => {
+                let lhs = pending[0].0;
+                let temp = func.add_local(func.local_types[&lhs]);
+                statements.push(Statement::Assign {
+                    lhs: temp,
+                    rhs: Rvalue::Use(Operand::Local(lhs)),
+                });
+
+                for (_, operand) in &mut pending {
+                    if operand.local() == Some(lhs) {
+                        *operand = Operand::Local(temp);
+                    }
+                }
+            }
+        }
+    }
+
+    statements
+}
+
+/// Returns the copy of a local done by `statement`, as its destination and source.
+fn copy(statement: &Statement) -> Option<(Local, Local)> {
+    match statement {
+        Statement::Assign {
+            lhs,
+            rhs: Rvalue::Use(Operand::Local(rhs)),
+        } => Some((*lhs, *rhs)),
+        _ => None,
+    }
+}
+
+/// Returns the pairs of locals that cannot share an id because one of them is assigned while the
+/// other one is live.
+fn interference(func: &Function) -> BTreeMap<Local, BTreeSet<Local>> {
+    let mut edges = BTreeMap::<Local, BTreeSet<Local>>::new();
+    let mut add_edge = |lhs: Local, rhs: Local| {
+        if lhs != rhs {
+            edges.entry(lhs).or_default().insert(rhs);
+            edges.entry(rhs).or_default().insert(lhs);
+        }
+    };
+
+    // The arguments are assigned at the same time by the caller, and they cannot be merged with
+    // the return value because neither of them can be renamed.
+    for lhs in 0..=func.args_len {
+        for rhs in 0..=func.args_len {
+            add_edge(Local(lhs), Local(rhs));
+        }
+    }
+
+    let cfg = Cfg::new(func);
+    let results = dataflow::solve(&cfg, &Liveness::new(func));
+
+    for (bb, live_out) in &results.after {
+        let bb_data = &func.basic_blocks[bb];
+        let mut live = live_out.clone();
+        Liveness::edge_effect(func, *bb, &mut live);
+        Liveness::terminator_effect(&bb_data.terminator, &mut live);
+
+        for statement in bb_data.statements.iter().rev() {
+            // The destination of a copy holds the same value as its source, so they do not
+            // interfere.
+            let source = copy(statement).map(|(_, rhs)| rhs);
+
+            for local in &live {
+                if Some(*local) != source {
+                    add_edge(statement.lhs(), *local);
+                }
+            }
+            Liveness::statement_effect(statement, &mut live);
+        }
+    }
+
+    edges
+}
+
+/// Merges the locals related by copies that do not interfere, keeping the smallest id, and
+/// removes the copies of a local to itself. Returns whether anything changed.
+fn coalesce_copies(func: &mut Function) -> bool {
+    let interference = interference(func);
+
+    let mut classes = func
+        .local_types
+        .keys()
+        .map(|local| (*local, *local))
+        .collect::<BTreeMap<_, _>>();
+    let mut members = func
+        .local_types
+        .keys()
+        .map(|local| (*local, BTreeSet::from([*local])))
+        .collect::<BTreeMap<_, _>>();
+
+    for bb_data in func.basic_blocks.values() {
+        for (lhs, rhs) in bb_data.statements.iter().filter_map(copy) {
+            let (lhs, rhs) = (classes[&lhs], classes[&rhs]);
+            if lhs == rhs {
+                continue;
+            }
+
+            let interferes = members[&lhs].iter().any(|local| {
+                interference
+                    .get(local)
+                    .is_some_and(|edges| !edges.is_disjoint(&members[&rhs]))
+            });
+            if interferes {
+                continue;
+            }
+
+            let (kept, merged) = (lhs.min(rhs), lhs.max(rhs));
+            let merged = members.remove(&merged).unwrap();
+            for local in &merged {
+                classes.insert(*local, kept);
+            }
+            members.get_mut(&kept).unwrap().extend(merged);
+        }
+    }
+
+    rename_locals(func, &classes)
+}
+
+/// Merges every local into the first local of the same type that does not interfere with it or
+/// with the locals already merged into it. Returns whether anything changed.
+fn share_locals(func: &mut Function) -> bool {
+    let interference = interference(func);
+
+    let mut classes = BTreeMap::new();
+    let mut members = BTreeMap::<Local, BTreeSet<Local>>::new();
+
+    for (local, ty) in &func.local_types {
+        let edges = interference.get(local);
+        let class = members
+            .iter()
+            .find(|(class, members)| {
+                func.local_types[*class] == *ty
+                    && edges.is_none_or(|edges| edges.is_disjoint(members))
+            })
+            .map_or(*local, |(class, _)| *class);
+
+        classes.insert(*local, class);
+        members.entry(class).or_default().insert(*local);
+    }
+
+    rename_locals(func, &classes)
+}
+
+/// Replaces every local by its class in `classes` and removes the copies of a local to itself.
+/// Returns whether anything changed.
+fn rename_locals(func: &mut Function, classes: &BTreeMap<Local, Local>) -> bool {
+    let mut changed = false;
+    let rename = |operand: &mut Operand| {
+        if let Operand::Local(local) = operand {
+            *local = classes[local];
+        }
+    };
+
+    for bb_data in func.basic_blocks.values_mut() {
+        for statement in &mut bb_data.statements {
+            let lhs = statement.lhs_mut();
+            *lhs = classes[lhs];
+            statement.operands_mut().for_each(rename);
+        }
+
+        if let Terminator::JumpIf { cond, .. } = &mut bb_data.terminator {
+            rename(cond);
+        }
+
+        let len = bb_data.statements.len();
+        bb_data
+            .statements
+            .retain(|statement| !matches!(copy(statement), Some((lhs, rhs)) if lhs == rhs));
+        changed |= bb_data.statements.len() != len;
+    }
+
+    changed | classes.iter().any(|(local, class)| local != class)
+}
@@ -0,0 +1,59 @@
+//! Removal of the blocks that cannot be reached from the entry block.
+use std::collections::BTreeMap;
+
+use crate::mir::{cfg::Cfg, BasicBlockId, Function, Statement};
+use crate::pass::Pass;
+
+use super::ssa::prune_phi_args;
+
+pub struct UnreachableBlocks;
+
+impl Pass<Function> for UnreachableBlocks {
+    fn name(&self) -> &'static str {
+        "unreachable-blocks"
+    }
+
+    fn run(&self, func: &mut Function) -> bool {
+        let cfg = Cfg::new(func);
+
+        let len = func.basic_blocks.len();
+        func.basic_blocks.retain(|bb, _| cfg.is_reachable(*bb));
+
+        let removed = func.basic_blocks.len() != len;
+        let order = func.basic_blocks.keys().copied().collect::<Vec<_>>();
+        removed | prune_phi_args(func) | renumber_blocks(func, &order)
+    }
+}
+
+/// Renumbers the blocks so their ids are consecutive and follow `order`, which must contain every
+/// block. Returns whether any id changed.
+pub(super) fn renumber_blocks(func: &mut Function, order: &[BasicBlockId]) -> bool {
+    let new_ids = order
+        .iter()
+        .enumerate()
+        .map(|(index, bb)| (*bb, BasicBlockId(index)))
+        .collect::<BTreeMap<_, _>>();
+
+    if new_ids.iter().all(|(old, new)| old == new) {
+        return false;
+    }
+
+    func.basic_blocks = std::mem::take(&mut func.basic_blocks)
+        .into_iter()
+        .map(|(bb, mut bb_data)| {
+            for target in bb_data.terminator.successors_mut() {
+                *target = new_ids[target];
+            }
+            for statement in &mut bb_data.statements {
+                if let Statement::Phi { args, .. } = statement {
+                    for (pred, _) in args {
+                        *pred = new_ids[pred];
+                    }
+                }
+            }
+            (new_ids[&bb], bb_data)
+        })
+        .collect();
+
+    true
+}
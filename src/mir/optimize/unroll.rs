@@ -0,0 +1,523 @@
+//! Unrolling of counted loops in SSA form.
+//!
+//! A loop with a [`TripCount`] that is a small constant is fully unrolled: it is replaced by a
+//! copy of its body for every iteration, followed by the header, which no longer jumps back. Other
+//! counted loops are unrolled by a factor: a guard after the header checks that there are at least
+//! that many iterations left, in which case a copy of that many iterations runs without checking
+//! the exit condition in between. The original loop runs the remaining iterations.
+//!
+//! When the function was compiled with a profile, loops that never ran are not unrolled, and loops
+//! are not unrolled by a factor when they run fewer iterations than the factor on average.
+//!
+//! Copying an iteration gives a new local to every local assigned in it. The phis of the header
+//! are not copied, their values are the values of the arguments from the latch in the previous
+//! copy. A loop is not unrolled if the function would then need more locals of a register class
+//! than there are registers of that class.
+use std::collections::BTreeMap;
+
+use crate::mir::{
+    cfg::{Cfg, Dominators, Loop, Loops},
+    induction::{entering_block, InductionVariable, InductionVariables, TripCount},
+    BasicBlock, BasicBlockId, BinOp, BlockProfile, Function, Literal, Local, Operand, Rvalue,
+    Statement, Terminator, Ty,
+};
+use crate::pass::Pass;
+use crate::target::RegisterClass;
+
+use super::ssa::{rename_phi_preds, OutOfSsa};
+use super::DeadCode;
+
+/// The largest number of statements and terminators that unrolling a loop can produce.
+const UNROLL_THRESHOLD: usize = 64;
+
+pub struct Unroll {
+    /// The number of iterations in every copy of a loop that is not fully unrolled. A factor of 1
+    /// only unrolls loops fully.
+    pub factor: u32,
+    /// The number of registers that can hold integers and booleans.
+    pub general_registers: usize,
+    /// The number of registers that can hold floats.
+    pub float_registers: usize,
+}
+
+impl Default for Unroll {
+    fn default() -> Self {
+        Self {
+            factor: 4,
+            general_registers: usize::MAX,
+            float_registers: usize::MAX,
+        }
+    }
+}
+
+impl Pass<Function> for Unroll {
+    fn name(&self) -> &'static str {
+        "unroll"
+    }
+
+    fn run(&self, func: &mut Function) -> bool {
+        if func.basic_blocks.is_empty() {
+            return false;
+        }
+
+        let headers = loops(func)
+            .loops()
+            .iter()
+            .map(|l| l.header)
+            .collect::<Vec<_>>();
+
+        let mut changed = false;
+
+        // Unrolling a loop adds blocks to the loops that contain it, so the loops are found again
+        // for every header.
+        for header in headers {
+            let cfg = Cfg::new(func);
+            let loops = loops(func);
+            let Some(index) = loops.loops().iter().position(|l| l.header == header) else {
+                continue;
+            };
+
+            // Only innermost loops are unrolled.
+            if loops.loops().iter().any(|l| l.parent == Some(index)) {
+                continue;
+            }
+
+            changed |= self.unroll_loop(func, &cfg, &loops.loops()[index]);
+        }
+
+        changed
+    }
+}
+
+fn loops(func: &Function) -> Loops {
+    let cfg = Cfg::new(func);
+    Loops::new(&cfg, &Dominators::new(&cfg))
+}
+
+impl Unroll {
+    /// Unrolls `l` fully or by the factor if possible. Returns whether the loop was unrolled.
+    fn unroll_loop(&self, func: &mut Function, cfg: &Cfg, l: &Loop) -> bool {
+        let Some(ivs) = InductionVariables::new(func, cfg, l) else {
+            return false;
+        };
+        let Some(trip_count) = TripCount::new(func, l, &ivs) else {
+            return false;
+        };
+
+        // The header must be followed by the rest of the iteration.
+        if ivs.latch() == l.header {
+            return false;
+        }
+
+        if func.basic_blocks[&l.header].profile.count == Some(0) {
+            return false;
+        }
+
+        let size = l
+            .blocks
+            .iter()
+            .map(|bb| func.basic_blocks[bb].statements.len() + 1)
+            .sum::<usize>();
+
+        let original = l
+            .blocks
+            .iter()
+            .map(|bb| (*bb, func.basic_blocks[bb].clone()))
+            .collect::<BTreeMap<_, _>>();
+        let copier = Copier {
+            l,
+            original,
+            latch: ivs.latch(),
+        };
+
+        if let Some(trips) = trip_count.constant(&ivs) {
+            if (trips as usize).saturating_mul(size) <= UNROLL_THRESHOLD {
+                let mut unrolled = func.clone();
+                copier.unroll_fully(&mut unrolled, &ivs, &trip_count, trips);
+                if self.fits_in_registers(&unrolled) {
+                    *func = unrolled;
+                    return true;
+                }
+            }
+        }
+
+        // The guard compares the counter with `bound - (factor - 1)`, which only overflows if the
+        // bound is close to the smallest integer. The guard is only reached if the counter is
+        // below the bound, so that cannot happen when the counter starts far enough from it.
+        let Some(InductionVariable::Basic {
+            init: Operand::Constant(init),
+            ..
+        }) = ivs.get(trip_count.iv)
+        else {
+            return false;
+        };
+        let fits = init.bits() >= i64::MIN + i64::from(self.factor) - 1;
+
+        if self.factor > 1
+            && fits
+            && !self.runs_few_iterations(func, cfg, l)
+            && (self.factor as usize).saturating_mul(size) <= UNROLL_THRESHOLD
+        {
+            let mut unrolled = func.clone();
+            copier.unroll_by(&mut unrolled, &trip_count, self.factor);
+            if self.fits_in_registers(&unrolled) {
+                *func = unrolled;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Returns whether the locals of `func` fit in the registers of their class once the dead
+    /// assignments are removed and the function is taken out of SSA form.
+    fn fits_in_registers(&self, func: &Function) -> bool {
+        let mut func = func.clone();
+        DeadCode.run(&mut func);
+        OutOfSsa.run(&mut func);
+
+        let (mut general, mut float) = (0, 0);
+        for ty in func.local_types.values() {
+            match RegisterClass::of(ty) {
+                RegisterClass::General => general += 1,
+                RegisterClass::Float => float += 1,
+            }
+        }
+        general <= self.general_registers && float <= self.float_registers
+    }
+
+    /// Returns whether `l` runs fewer iterations than the factor every time it is entered,
+    /// according to the profile.
+    fn runs_few_iterations(&self, func: &Function, cfg: &Cfg, l: &Loop) -> bool {
+        let count = |bb: BasicBlockId| func.basic_blocks[&bb].profile.count;
+        let (Some(header), Some(entering)) =
+            (count(l.header), entering_block(cfg, l).and_then(count))
+        else {
+            return false;
+        };
+
+        // The header runs once more than the body every time the loop is entered.
+        let iterations = header.saturating_sub(entering);
+        iterations < entering.saturating_mul(u64::from(self.factor))
+    }
+}
+
+/// The copy of an iteration.
+struct Iteration {
+    /// The first block of the copy.
+    entry: BasicBlockId,
+    /// The copy of the latch, which jumps to the header.
+    latch: BasicBlockId,
+    /// The values of the locals of the loop in the copy.
+    values: BTreeMap<Local, Operand>,
+}
+
+/// Copies the iterations of a loop.
+struct Copier<'a> {
+    l: &'a Loop,
+    /// The blocks of the loop before unrolling it.
+    original: BTreeMap<BasicBlockId, BasicBlock>,
+    latch: BasicBlockId,
+}
+
+impl<'a> Copier<'a> {
+    /// The block executed after the header when the loop continues.
+    fn body_entry(&self) -> BasicBlockId {
+        match self.original[&self.l.header].terminator {
+            Terminator::JumpIf { then_bb, .. } => then_bb,
+            _ => unreachable!("the header of a counted loop ends in a `JUMP IF`"),
+        }
+    }
+
+    /// Returns the arguments of the phis of the header from `pred`.
+    fn phi_args(&self, pred: BasicBlockId) -> Vec<(Local, Operand)> {
+        self.original[&self.l.header]
+            .statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Phi { lhs, args } => args
+                    .iter()
+                    .find(|(arg_pred, _)| *arg_pred == pred)
+                    .map(|(_, arg)| (*lhs, arg.clone())),
+                Statement::Assign { .. } => None,
+            })
+            .collect()
+    }
+
+    /// Replaces the loop by a copy of every iteration, followed by the header.
+    fn unroll_fully(
+        &self,
+        func: &mut Function,
+        ivs: &InductionVariables,
+        trip_count: &TripCount,
+        trips: u64,
+    ) {
+        let header = self.l.header;
+
+        // The body of the loop is replaced by the copies.
+        func.basic_blocks
+            .retain(|bb, _| *bb == header || !self.l.blocks.contains(bb));
+
+        let mut phis = self.phi_args(ivs.entering()).into_iter().collect();
+        let mut pred = ivs.entering();
+
+        for _ in 0..trips {
+            let iteration = self.copy_iteration(func, phis, None);
+            retarget(func, pred, header, iteration.entry);
+            pred = iteration.latch;
+            phis = self.next_phis(&iteration.values);
+        }
+
+        // The phis of the header are left with their values after the last iteration, and it
+        // always leaves the loop.
+        let header_data = func.basic_blocks.get_mut(&header).unwrap();
+        for statement in &mut header_data.statements {
+            if let Statement::Phi { lhs, .. } = statement {
+                *statement = Statement::Assign {
+                    lhs: *lhs,
+                    rhs: Rvalue::Use(phis[lhs].clone()),
+                };
+            }
+        }
+        header_data.terminator = Terminator::Jump(trip_count.exit);
+    }
+
+    /// Adds a guard after the header that runs `factor` iterations without checking the exit
+    /// condition if there are enough iterations left.
+    fn unroll_by(&self, func: &mut Function, trip_count: &TripCount, factor: u32) {
+        let header = self.l.header;
+        let body_entry = self.body_entry();
+
+        // `iv < bound - (factor - 1)`, the header already checked `iv < bound`.
+        let offset = u64::from(factor).wrapping_sub(1).wrapping_neg();
+        let (limit, mut statements) = match &trip_count.bound {
+            Operand::Constant(literal) => (
+                Operand::Constant(Literal {
+                    data: literal.data.wrapping_add(offset),
+                    ty: Ty::Int,
+                }),
+                Vec::new(),
+            ),
+            bound => {
+                let limit = func.add_local(Ty::Int);
+                let statement = Statement::Assign {
+                    lhs: limit,
+                    rhs: Rvalue::BinaryOp {
+                        op: BinOp::Add,
+                        lhs: bound.clone(),
+                        rhs: Operand::Constant(Literal {
+                            data: offset,
+                            ty: Ty::Int,
+                        }),
+                    },
+                };
+                (Operand::Local(limit), vec![statement])
+            }
+        };
+
+        let cond = func.add_local(Ty::Bool);
+        statements.push(Statement::Assign {
+            lhs: cond,
+            rhs: Rvalue::BinaryOp {
+                op: BinOp::Lt,
+                lhs: Operand::Local(trip_count.iv),
+                rhs: limit,
+            },
+        });
+
+        let guard = func.add_block(BasicBlock {
+            statements,
+            terminator: Terminator::Return,
+            cold: false,
+            profile: BlockProfile::default(),
+        });
+        retarget(func, header, body_entry, guard);
+        rename_phi_preds(func, body_entry, header, guard);
+
+        // The first copy starts with the values of the header.
+        let mut iteration = self.copy_iteration(func, BTreeMap::new(), Some(guard));
+        func.basic_blocks.get_mut(&guard).unwrap().terminator = Terminator::JumpIf {
+            cond: Operand::Local(cond),
+            then_bb: iteration.entry,
+            else_bb: body_entry,
+        };
+
+        for _ in 1..factor {
+            let phis = self.next_phis(&iteration.values);
+            let next = self.copy_iteration(func, phis, None);
+            retarget(func, iteration.latch, header, next.entry);
+            iteration = next;
+        }
+
+        // The last copy goes back to the header.
+        let args = self.phi_args(self.latch);
+        for statement in &mut func.basic_blocks.get_mut(&header).unwrap().statements {
+            if let Statement::Phi {
+                lhs,
+                args: phi_args,
+            } = statement
+            {
+                let arg = args
+                    .iter()
+                    .find(|(phi, _)| phi == lhs)
+                    .map(|(_, arg)| value(&iteration.values, arg))
+                    .unwrap();
+                phi_args.push((iteration.latch, arg));
+            }
+        }
+    }
+
+    /// Returns the values of the phis of the header in the iteration after the one whose locals
+    /// have `values`.
+    fn next_phis(&self, values: &BTreeMap<Local, Operand>) -> BTreeMap<Local, Operand> {
+        self.phi_args(self.latch)
+            .into_iter()
+            .map(|(phi, arg)| (phi, value(values, &arg)))
+            .collect()
+    }
+
+    /// Adds a copy of an iteration of the loop whose latch jumps to the header, with the phis of
+    /// the header set to `phis`.
+    ///
+    /// If `entered_from` is set, the header is not copied: the copy starts after it, reading the
+    /// locals of the header itself, and it is entered from that block.
+    fn copy_iteration(
+        &self,
+        func: &mut Function,
+        phis: BTreeMap<Local, Operand>,
+        entered_from: Option<BasicBlockId>,
+    ) -> Iteration {
+        let header = self.l.header;
+        let body_entry = self.body_entry();
+
+        let blocks = self
+            .original
+            .keys()
+            .filter(|bb| **bb != header)
+            .map(|bb| {
+                let new_bb = func.add_block(BasicBlock {
+                    statements: Vec::new(),
+                    terminator: Terminator::Return,
+                    cold: false,
+                    profile: BlockProfile::default(),
+                });
+                (*bb, new_bb)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let header_copy = entered_from.is_none().then(|| {
+            func.add_block(BasicBlock {
+                statements: Vec::new(),
+                terminator: Terminator::Jump(blocks[&body_entry]),
+                cold: false,
+                profile: BlockProfile::default(),
+            })
+        });
+        let entry = header_copy.unwrap_or(blocks[&body_entry]);
+        let body_pred = entered_from.or(header_copy).unwrap();
+
+        let mut values = phis;
+        for (bb, bb_data) in &self.original {
+            if *bb == header && header_copy.is_none() {
+                continue;
+            }
+
+            for statement in &bb_data.statements {
+                if *bb == header && statement.is_phi() {
+                    continue;
+                }
+
+                let lhs = statement.lhs();
+                let new_local = func.add_local(func.local_types[&lhs]);
+                values.insert(lhs, Operand::Local(new_local));
+            }
+        }
+
+        let copy_statement = |statement: &Statement| {
+            let mut statement = statement.clone();
+            for operand in statement.operands_mut() {
+                if let Operand::Local(local) = operand {
+                    *operand = value(&values, &Operand::Local(*local));
+                }
+            }
+
+            let lhs = statement.lhs_mut();
+            *lhs = values[lhs].local().unwrap();
+
+            if let Statement::Phi { args, .. } = &mut statement {
+                for (pred, _) in args {
+                    *pred = if *pred == header {
+                        body_pred
+                    } else {
+                        blocks[pred]
+                    };
+                }
+            }
+
+            statement
+        };
+
+        if let Some(header_copy) = header_copy {
+            func.basic_blocks.get_mut(&header_copy).unwrap().statements = self.original[&header]
+                .statements
+                .iter()
+                .filter(|statement| !statement.is_phi())
+                .map(copy_statement)
+                .collect();
+        }
+
+        for (bb, new_bb) in &blocks {
+            let bb_data = &self.original[bb];
+            let mut terminator = bb_data.terminator.clone();
+            for target in terminator.successors_mut() {
+                if *target != header {
+                    *target = blocks[target];
+                }
+            }
+            if let Terminator::JumpIf { cond, .. } = &mut terminator {
+                *cond = value(&values, cond);
+            }
+
+            let statements = bb_data.statements.iter().map(copy_statement).collect();
+            func.basic_blocks.insert(
+                *new_bb,
+                BasicBlock {
+                    statements,
+                    terminator,
+                    cold: bb_data.cold,
+                    profile: bb_data.profile.clone(),
+                },
+            );
+        }
+
+        Iteration {
+            entry,
+            latch: blocks[&self.latch],
+            values,
+        }
+    }
+}
+
+/// Returns the value of `operand` in a copy whose locals have `values`. The locals assigned
+/// outside the loop keep their value.
+fn value(values: &BTreeMap<Local, Operand>, operand: &Operand) -> Operand {
+    match operand {
+        Operand::Local(local) => values.get(local).unwrap_or(operand).clone(),
+        Operand::Constant(_) => operand.clone(),
+    }
+}
+
+/// Makes `bb` jump to `new` instead of `old`.
+fn retarget(func: &mut Function, bb: BasicBlockId, old: BasicBlockId, new: BasicBlockId) {
+    for target in func
+        .basic_blocks
+        .get_mut(&bb)
+        .unwrap()
+        .terminator
+        .successors_mut()
+    {
+        if *target == old {
+            *target = new;
+        }
+    }
+}
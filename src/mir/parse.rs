@@ -0,0 +1,379 @@
+//! Parser for the textual representation of MIR described in [`crate::mir::display`].
+use std::collections::BTreeMap;
+
+use crate::mir::{
+    func::FunctionBuilder, BasicBlock, BasicBlockId, BinOp, BlockProfile, Function, InlineHint,
+    Literal, Local, Module, Operand, Rvalue, Statement, Terminator, Ty,
+};
+use crate::parse::{ParseError, Parser, TokenKind};
+
+/// Parses a module containing zero or more functions.
+pub fn parse_module(src: &str) -> Result<Module, ParseError> {
+    let mut parser = Parser::new(src)?;
+    let mut module = Module::default();
+
+    while !parser.is_finished() {
+        let error = parser.error("function is defined more than once");
+        let (name, func) = parse_function(&mut parser)?;
+
+        if module.functions.insert(name, func).is_some() {
+            return Err(error);
+        }
+    }
+
+    Ok(module)
+}
+
+/// The name of a block and the error to be reported if it is not defined.
+type Target = (String, ParseError);
+
+/// A statement whose blocks have not been resolved yet.
+enum RawStatement {
+    Statement(Statement),
+    Phi {
+        lhs: Local,
+        args: Vec<(Target, Operand)>,
+    },
+}
+
+/// A terminator whose targets have not been resolved yet.
+enum RawTerminator {
+    Jump(Target),
+    Return,
+    JumpIf {
+        cond: Operand,
+        then_bb: Target,
+        else_bb: Target,
+    },
+}
+
+struct FunctionParser<'p> {
+    parser: &'p mut Parser,
+    builder: FunctionBuilder,
+    locals: BTreeMap<String, Local>,
+}
+
+fn parse_inline_hint(parser: &mut Parser) -> Result<InlineHint, ParseError> {
+    if !parser.eat_punct('#') {
+        return Ok(InlineHint::Auto);
+    }
+
+    parser.expect_punct('[')?;
+    parser.expect_keyword("inline")?;
+
+    let hint = if parser.eat_punct('(') {
+        let hint = if parser.eat_keyword("always") {
+            InlineHint::Always
+        } else if parser.eat_keyword("never") {
+            InlineHint::Never
+        } else {
+            return Err(parser.error("expected `always` or `never`"));
+        };
+        parser.expect_punct(')')?;
+        hint
+    } else {
+        InlineHint::Hint
+    };
+
+    parser.expect_punct(']')?;
+    Ok(hint)
+}
+
+fn parse_cold(parser: &mut Parser) -> Result<bool, ParseError> {
+    if !parser.eat_punct('#') {
+        return Ok(false);
+    }
+
+    parser.expect_punct('[')?;
+    parser.expect_keyword("cold")?;
+    parser.expect_punct(']')?;
+    Ok(true)
+}
+
+fn parse_function(parser: &mut Parser) -> Result<(String, Function), ParseError> {
+    let inline = parse_inline_hint(parser)?;
+    parser.expect_keyword("fn")?;
+    let name = parser.expect_ident("function name")?;
+
+    parser.expect_punct('(')?;
+    let mut args = Vec::new();
+    while !parser.eat_punct(')') {
+        if !args.is_empty() {
+            parser.expect_punct(',')?;
+        }
+        let local = parser.expect_ident("argument")?;
+        parser.expect_punct(':')?;
+        args.push((local, parse_ty(parser)?));
+    }
+
+    parser.expect_arrow()?;
+    let output_ty = parse_ty(parser)?;
+
+    let mut ctx = FunctionParser {
+        parser,
+        builder: Function::builder(args.len()),
+        locals: BTreeMap::new(),
+    };
+
+    ctx.declare_local("_0".to_owned(), output_ty)?;
+    for (local, ty) in args {
+        ctx.declare_local(local, ty)?;
+    }
+
+    ctx.parser.expect_punct('{')?;
+
+    while ctx.parser.eat_keyword("let") {
+        let local = ctx.parser.expect_ident("local")?;
+        ctx.parser.expect_punct(':')?;
+        let ty = parse_ty(ctx.parser)?;
+        ctx.declare_local(local, ty)?;
+    }
+
+    let mut blocks = Vec::new();
+    let mut block_ids = BTreeMap::new();
+
+    while !ctx.parser.eat_punct('}') {
+        let cold = parse_cold(ctx.parser)?;
+        let error = ctx.parser.error("block is defined more than once");
+        let bb_name = ctx.parser.expect_ident("block")?;
+        ctx.parser.expect_punct(':')?;
+
+        let bb = ctx.builder.add_block();
+        if block_ids.insert(bb_name, bb).is_some() {
+            return Err(error);
+        }
+
+        let mut statements = Vec::new();
+        while matches!(ctx.parser.peek_second(), Some(TokenKind::Punct('='))) {
+            statements.push(ctx.parse_statement()?);
+        }
+        let terminator = ctx.parse_terminator()?;
+
+        blocks.push((bb, statements, terminator, cold));
+    }
+
+    if blocks.is_empty() {
+        return Err(ctx.parser.error(format!("function `{name}` has no blocks")));
+    }
+
+    let resolve = |(name, error): Target| -> Result<BasicBlockId, ParseError> {
+        block_ids.get(&name).copied().ok_or(ParseError {
+            message: format!("block `{name}` is not defined"),
+            ..error
+        })
+    };
+
+    let mut builder = ctx.builder;
+
+    for (bb, statements, terminator, cold) in blocks {
+        let terminator = match terminator {
+            RawTerminator::Jump(target) => Terminator::Jump(resolve(target)?),
+            RawTerminator::Return => Terminator::Return,
+            RawTerminator::JumpIf {
+                cond,
+                then_bb,
+                else_bb,
+            } => Terminator::JumpIf {
+                cond,
+                then_bb: resolve(then_bb)?,
+                else_bb: resolve(else_bb)?,
+            },
+        };
+
+        let statements = statements
+            .into_iter()
+            .map(|statement| match statement {
+                RawStatement::Statement(statement) => Ok(statement),
+                RawStatement::Phi { lhs, args } => Ok(Statement::Phi {
+                    lhs,
+                    args: args
+                        .into_iter()
+                        .map(|(bb, operand)| Ok((resolve(bb)?, operand)))
+                        .collect::<Result<_, ParseError>>()?,
+                }),
+            })
+            .collect::<Result<_, ParseError>>()?;
+
+        *builder.block_mut(bb) = Some(BasicBlock {
+            statements,
+            terminator,
+            cold,
+            profile: BlockProfile::default(),
+        });
+    }
+
+    let mut func = builder.finish();
+    func.inline = inline;
+
+    Ok((name, func))
+}
+
+fn parse_ty(parser: &mut Parser) -> Result<Ty, ParseError> {
+    if parser.eat_keyword("int") {
+        Ok(Ty::Int)
+    } else if parser.eat_keyword("bool") {
+        Ok(Ty::Bool)
+    } else if parser.eat_keyword("f32") {
+        Ok(Ty::F32)
+    } else if parser.eat_keyword("f64") {
+        Ok(Ty::F64)
+    } else {
+        Err(parser.error("expected type"))
+    }
+}
+
+impl<'p> FunctionParser<'p> {
+    fn declare_local(&mut self, name: String, ty: Ty) -> Result<(), ParseError> {
+        if self.locals.contains_key(&name) {
+            return Err(self
+                .parser
+                .error(format!("local `{name}` is declared more than once")));
+        }
+
+        let local = self.builder.add_local(ty);
+        self.locals.insert(name, local);
+
+        Ok(())
+    }
+
+    fn parse_local(&mut self) -> Result<Local, ParseError> {
+        let error = self.parser.error("");
+        let name = self.parser.expect_ident("local")?;
+
+        self.locals.get(&name).copied().ok_or(ParseError {
+            message: format!("local `{name}` is not declared"),
+            ..error
+        })
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, ParseError> {
+        if self.parser.eat_keyword("true") {
+            return Ok(Operand::Constant(Literal {
+                data: 1,
+                ty: Ty::Bool,
+            }));
+        }
+
+        if self.parser.eat_keyword("false") {
+            return Ok(Operand::Constant(Literal {
+                data: 0,
+                ty: Ty::Bool,
+            }));
+        }
+
+        if let Some(TokenKind::Number(_)) = self.parser.peek() {
+            let number = self.parser.expect_number("integer")?;
+            return Ok(Operand::Constant(Literal::int(number)));
+        }
+
+        if let Some(TokenKind::Float(float)) = self.parser.peek() {
+            let error = self.parser.error(format!("invalid float `{float}`"));
+            let literal = match float.strip_suffix("f32") {
+                Some(float) => float.parse().map(Literal::f32),
+                None => float
+                    .strip_suffix("f64")
+                    .unwrap_or(float)
+                    .parse()
+                    .map(Literal::f64),
+            }
+            .map_err(|_| error)?;
+            self.parser.bump();
+
+            return Ok(Operand::Constant(literal));
+        }
+
+        Ok(Operand::Local(self.parse_local()?))
+    }
+
+    fn parse_statement(&mut self) -> Result<RawStatement, ParseError> {
+        let lhs = self.parse_local()?;
+        self.parser.expect_punct('=')?;
+
+        if self.parser.eat_keyword("PHI") {
+            self.parser.expect_punct('(')?;
+            let mut args = Vec::new();
+            while !self.parser.eat_punct(')') {
+                if !args.is_empty() {
+                    self.parser.expect_punct(',')?;
+                }
+                let bb = self.parse_target()?;
+                self.parser.expect_punct(':')?;
+                args.push((bb, self.parse_operand()?));
+            }
+
+            return Ok(RawStatement::Phi { lhs, args });
+        }
+
+        let rhs = if self.parser.eat_keyword("USE") {
+            Rvalue::Use(self.parse_operand()?)
+        } else if self.parser.eat_keyword("CAST") {
+            let operand = self.parse_operand()?;
+            self.parser.expect_keyword("AS")?;
+            let ty = parse_ty(self.parser)?;
+
+            Rvalue::Cast { operand, ty }
+        } else if self.parser.eat_keyword("ADDR") {
+            Rvalue::SymbolAddr(self.parser.expect_ident("symbol")?)
+        } else if self.parser.eat_keyword("CALL") {
+            let callee = self.parser.expect_ident("function name")?;
+            self.parser.expect_punct('(')?;
+            let mut args = Vec::new();
+            while !self.parser.eat_punct(')') {
+                if !args.is_empty() {
+                    self.parser.expect_punct(',')?;
+                }
+                args.push(self.parse_operand()?);
+            }
+
+            Rvalue::Call { callee, args }
+        } else {
+            let lhs = self.parse_operand()?;
+            let op = if self.parser.eat_punct('+') {
+                BinOp::Add
+            } else if self.parser.eat_punct('*') {
+                BinOp::Mul
+            } else if self.parser.eat_punct('<') {
+                BinOp::Lt
+            } else {
+                return Err(self.parser.error("expected binary operator"));
+            };
+            let rhs = self.parse_operand()?;
+
+            Rvalue::BinaryOp { op, lhs, rhs }
+        };
+
+        Ok(RawStatement::Statement(Statement::Assign { lhs, rhs }))
+    }
+
+    fn parse_target(&mut self) -> Result<Target, ParseError> {
+        let error = self.parser.error("");
+        let name = self.parser.expect_ident("block")?;
+        Ok((name, error))
+    }
+
+    fn parse_terminator(&mut self) -> Result<RawTerminator, ParseError> {
+        if self.parser.eat_keyword("RETURN") {
+            return Ok(RawTerminator::Return);
+        }
+
+        if !self.parser.eat_keyword("JUMP") {
+            return Err(self.parser.error("expected statement or terminator"));
+        }
+
+        if self.parser.eat_keyword("IF") {
+            let cond = self.parse_operand()?;
+            self.parser.expect_keyword("THEN")?;
+            let then_bb = self.parse_target()?;
+            self.parser.expect_keyword("ELSE")?;
+            let else_bb = self.parse_target()?;
+
+            Ok(RawTerminator::JumpIf {
+                cond,
+                then_bb,
+                else_bb,
+            })
+        } else {
+            Ok(RawTerminator::Jump(self.parse_target()?))
+        }
+    }
+}
@@ -1,5 +1,58 @@
-use crate::mir::{Local, Rvalue};
+use crate::mir::{BasicBlockId, Local, Operand, Rvalue};
 
+#[derive(Debug, Clone)]
 pub enum Statement {
-    Assign { lhs: Local, rhs: Rvalue },
+    Assign {
+        lhs: Local,
+        rhs: Rvalue,
+    },
+    /// Assigns the operand that corresponds to the predecessor the block was entered from. Phis
+    /// only appear in SSA form, before any other statement of the block.
+    Phi {
+        lhs: Local,
+        args: Vec<(BasicBlockId, Operand)>,
+    },
+}
+
+impl Statement {
+    /// Returns the local assigned by this statement.
+    pub fn lhs(&self) -> Local {
+        match self {
+            Statement::Assign { lhs, .. } | Statement::Phi { lhs, .. } => *lhs,
+        }
+    }
+
+    pub fn lhs_mut(&mut self) -> &mut Local {
+        match self {
+            Statement::Assign { lhs, .. } | Statement::Phi { lhs, .. } => lhs,
+        }
+    }
+
+    pub fn is_phi(&self) -> bool {
+        matches!(self, Statement::Phi { .. })
+    }
+
+    /// Returns the operands read by this statement. The operands of a phi are read at the end of
+    /// the corresponding predecessor.
+    pub fn operands(&self) -> impl Iterator<Item = &Operand> {
+        let (rhs, args) = match self {
+            Statement::Assign { rhs, .. } => (Some(rhs), None),
+            Statement::Phi { args, .. } => (None, Some(args)),
+        };
+
+        rhs.into_iter()
+            .flat_map(Rvalue::operands)
+            .chain(args.into_iter().flatten().map(|(_, operand)| operand))
+    }
+
+    pub fn operands_mut(&mut self) -> impl Iterator<Item = &mut Operand> {
+        let (rhs, args) = match self {
+            Statement::Assign { rhs, .. } => (Some(rhs), None),
+            Statement::Phi { args, .. } => (None, Some(args)),
+        };
+
+        rhs.into_iter()
+            .flat_map(Rvalue::operands_mut)
+            .chain(args.into_iter().flatten().map(|(_, operand)| operand))
+    }
 }
@@ -1,5 +1,6 @@
 use crate::mir::{bb::BasicBlockId, Operand};
 
+#[derive(Debug, Clone)]
 pub enum Terminator {
     Jump(BasicBlockId),
     Return,
@@ -9,3 +10,30 @@ pub enum Terminator {
         else_bb: BasicBlockId,
     },
 }
+
+impl Terminator {
+    /// Returns the blocks that can be executed after this terminator.
+    pub fn successors(&self) -> impl Iterator<Item = BasicBlockId> {
+        let (first, second) = match *self {
+            Terminator::Jump(target) => (Some(target), None),
+            Terminator::Return => (None, None),
+            Terminator::JumpIf {
+                then_bb, else_bb, ..
+            } => (Some(then_bb), Some(else_bb)),
+        };
+
+        first.into_iter().chain(second)
+    }
+
+    pub fn successors_mut(&mut self) -> impl Iterator<Item = &mut BasicBlockId> {
+        let (first, second) = match self {
+            Terminator::Jump(target) => (Some(target), None),
+            Terminator::Return => (None, None),
+            Terminator::JumpIf {
+                then_bb, else_bb, ..
+            } => (Some(then_bb), Some(else_bb)),
+        };
+
+        first.into_iter().chain(second)
+    }
+}
@@ -0,0 +1,522 @@
+//! Instruction selection by tiling expression trees.
+//!
+//! The statements of a block whose value is only read once, by a later statement or by the
+//! terminator of the same block, are folded into that use, which turns the block into a list of
+//! [`Tree`]s. A target describes its instructions with [`Rule`]s: a [`Pattern`] matched against
+//! the root of a tree, the cost of the instructions and a function that emits them. The subtrees
+//! that a pattern leaves uncovered are selected recursively, and every tree is covered with the
+//! rules of lowest total cost.
+//!
+//! A folded statement is computed where its value is read instead of where it was, so a statement
+//! is only folded if none of the locals it reads are assigned in between. A subtree that a rule
+//! needs in a register is computed in the register of the local it was assigned to, which is not
+//! read anywhere else.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::asm::Label;
+use crate::mir::{BasicBlock, BinOp, Literal, Local, Operand, Rvalue, Statement, Terminator, Ty};
+
+/// An expression whose leaves are the operands of the statements of a block.
+#[derive(Debug, Clone)]
+pub enum Tree<'a> {
+    Leaf(&'a Operand),
+    BinaryOp {
+        op: BinOp,
+        /// The type of the operands.
+        ty: Ty,
+        lhs: Box<Tree<'a>>,
+        rhs: Box<Tree<'a>>,
+        /// The local assigned by the statement of the operation.
+        local: Local,
+    },
+    /// A conversion that involves a float. Booleans are converted like the integers `0` and `1`,
+    /// so they are written as [`Ty::Int`], and the other conversions are not trees of their own
+    /// because they keep the bits of the operand.
+    Cast {
+        operand: Box<Tree<'a>>,
+        from: Ty,
+        to: Ty,
+        /// The local assigned by the statement of the conversion.
+        local: Local,
+    },
+}
+
+impl<'a> Tree<'a> {
+    /// Returns the locals read by the leaves of this tree.
+    fn leaves(&self) -> BTreeSet<Local> {
+        match self {
+            Tree::Leaf(operand) => operand.local().into_iter().collect(),
+            Tree::BinaryOp { lhs, rhs, .. } => &lhs.leaves() | &rhs.leaves(),
+            Tree::Cast { operand, .. } => operand.leaves(),
+        }
+    }
+
+    /// Returns the locals assigned by the operations of this tree.
+    fn homes(&self) -> BTreeSet<Local> {
+        match self {
+            Tree::Leaf(_) => BTreeSet::new(),
+            Tree::BinaryOp {
+                lhs, rhs, local, ..
+            } => {
+                let mut homes = &lhs.homes() | &rhs.homes();
+                homes.insert(*local);
+                homes
+            }
+            Tree::Cast { operand, local, .. } => {
+                let mut homes = operand.homes();
+                homes.insert(*local);
+                homes
+            }
+        }
+    }
+}
+
+/// The trees of a block.
+#[derive(Debug)]
+pub struct BlockTrees<'a> {
+    /// The tree of every statement that assigns a `USE`, a binary operation or a conversion,
+    /// indexed by the position of the statement, or `None` if the statement is folded into a
+    /// later one.
+    pub statements: BTreeMap<usize, Option<Tree<'a>>>,
+    /// The tree of the condition of the terminator.
+    pub cond: Option<Tree<'a>>,
+}
+
+impl<'a> BlockTrees<'a> {
+    /// Builds the trees of `bb_data`, where `local_types` are the types of the locals of the
+    /// function and `read_counts` is the number of times each local is read in it.
+    pub fn new(
+        bb_data: &'a BasicBlock,
+        local_types: &BTreeMap<Local, Ty>,
+        read_counts: &BTreeMap<Local, usize>,
+    ) -> Self {
+        let statements = &bb_data.statements;
+        let cond = match &bb_data.terminator {
+            Terminator::JumpIf { cond, .. } => Some(cond),
+            Terminator::Jump(_) | Terminator::Return => None,
+        };
+
+        // The trees of the folded statements that have not been read yet.
+        let mut pending = BTreeMap::new();
+
+        let mut trees = BTreeMap::new();
+        for (index, statement) in statements.iter().enumerate() {
+            let Statement::Assign { lhs, rhs } = statement else {
+                continue;
+            };
+            let tree = match rhs {
+                Rvalue::Use(operand) => fold(&mut pending, operand),
+                Rvalue::BinaryOp { op, lhs: x, rhs: y } => Tree::BinaryOp {
+                    op: *op,
+                    ty: x.ty(local_types),
+                    lhs: Box::new(fold(&mut pending, x)),
+                    rhs: Box::new(fold(&mut pending, y)),
+                    local: *lhs,
+                },
+                Rvalue::Cast { operand, ty } => {
+                    let (from, to) = (operand.ty(local_types), *ty);
+                    if !from.is_float() && !to.is_float() {
+                        fold(&mut pending, operand)
+                    } else {
+                        let as_int = |ty| if ty == Ty::Bool { Ty::Int } else { ty };
+                        Tree::Cast {
+                            operand: Box::new(fold(&mut pending, operand)),
+                            from: as_int(from),
+                            to: as_int(to),
+                            local: *lhs,
+                        }
+                    }
+                }
+                Rvalue::SymbolAddr(_) | Rvalue::Call { .. } => continue,
+            };
+
+            if can_fold(statements, cond, index, &tree, read_counts) {
+                pending.insert(*lhs, tree);
+                trees.insert(index, None);
+            } else {
+                trees.insert(index, Some(tree));
+            }
+        }
+
+        let cond = cond.map(|cond| fold(&mut pending, cond));
+        Self {
+            statements: trees,
+            cond,
+        }
+    }
+}
+
+/// Returns the tree of `operand`, which is the tree of the statement folded into it if it reads a
+/// local in `pending`.
+fn fold<'a>(pending: &mut BTreeMap<Local, Tree<'a>>, operand: &'a Operand) -> Tree<'a> {
+    match operand.local().and_then(|local| pending.remove(&local)) {
+        Some(tree) => tree,
+        None => Tree::Leaf(operand),
+    }
+}
+
+/// Returns whether the statement with index `index`, which computes `tree`, can be folded into
+/// the only statement or terminator condition that reads the local it assigns.
+fn can_fold(
+    statements: &[Statement],
+    cond: Option<&Operand>,
+    index: usize,
+    tree: &Tree,
+    read_counts: &BTreeMap<Local, usize>,
+) -> bool {
+    let local = statements[index].lhs();
+    if read_counts.get(&local) != Some(&1) {
+        return false;
+    }
+
+    let reads = |statement: &Statement| statement.operands().any(|op| op.local() == Some(local));
+    let use_index = match statements[index + 1..].iter().position(reads) {
+        Some(offset) => index + 1 + offset,
+        None if cond.and_then(Operand::local) == Some(local) => statements.len(),
+        None => return false,
+    };
+
+    // The operands of the tree must keep their values until they are read.
+    let mut locals = tree.leaves();
+    locals.insert(local);
+    let assigned = statements[index + 1..use_index]
+        .iter()
+        .any(|statement| locals.contains(&statement.lhs()));
+    if assigned {
+        return false;
+    }
+
+    let Some(statement) = statements.get(use_index) else {
+        return true;
+    };
+
+    // Only trees can be folded into, and the subtrees are computed in the registers of their
+    // locals, which must not be the destination of the tree they are folded into.
+    let mut homes = tree.homes();
+    homes.insert(local);
+    matches!(
+        statement,
+        Statement::Assign {
+            rhs: Rvalue::Use(_) | Rvalue::BinaryOp { .. } | Rvalue::Cast { .. },
+            ..
+        }
+    ) && !homes.contains(&statement.lhs())
+}
+
+/// A pattern that matches the root of a tree.
+#[derive(Debug)]
+pub enum Pattern {
+    /// A value in a register: a local or a subtree computed in the register of its local.
+    Reg,
+    /// A value computed in the register where the rule puts its result, which lets a rule
+    /// overwrite one of its operands. It cannot be the whole pattern of a rule.
+    Dst,
+    /// Any integer or boolean constant.
+    Imm,
+    /// A constant that fits in a signed byte.
+    Imm8,
+    /// A constant whose absolute value fits in 12 bits.
+    Imm12,
+    /// A constant that fits in 12 bits as a signed number.
+    Simm12,
+    /// The constant zero.
+    Zero,
+    /// Any float constant, matched as its bits.
+    FloatImm,
+    /// An operation over integers or booleans.
+    BinaryOp(BinOp, &'static Pattern, &'static Pattern),
+    /// An operation over floats of the given type.
+    FloatOp(Ty, BinOp, &'static Pattern, &'static Pattern),
+    /// A conversion between the given types, where booleans are written as [`Ty::Int`].
+    Cast(Ty, Ty, &'static Pattern),
+}
+
+/// A value matched by a leaf of a pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value<R> {
+    Reg(R),
+    Imm(i64),
+}
+
+impl<R: Copy> Value<R> {
+    pub fn reg(self) -> R {
+        match self {
+            Value::Reg(reg) => reg,
+            Value::Imm(_) => panic!("the value is not in a register"),
+        }
+    }
+
+    pub fn imm(self) -> i64 {
+        match self {
+            Value::Imm(imm) => imm,
+            Value::Reg(_) => panic!("the value is not a constant"),
+        }
+    }
+}
+
+/// The targets of a conditional jump.
+#[derive(Debug, Clone, Copy)]
+pub struct Branch {
+    pub then_label: Label,
+    pub else_label: Label,
+    /// Whether the `ELSE` block is lowered right after the jump.
+    pub else_is_next: bool,
+}
+
+/// Instructions that compute a tree matched by a pattern. `G` is the goal of the rule: the
+/// register that receives the value of the tree or the [`Branch`] taken depending on it.
+pub struct Rule<C, R, G> {
+    pub pattern: Pattern,
+    /// An estimate of the size of the instructions in bytes.
+    pub cost: u32,
+    /// Emits the instructions into `C` given the values matched by the leaves of the pattern, in
+    /// order.
+    pub emit: fn(&mut C, &[Value<R>], G),
+}
+
+/// The rules of a target.
+pub struct Rules<C: 'static, R: 'static> {
+    /// Rules that compute a tree in a register.
+    pub values: &'static [Rule<C, R, R>],
+    /// Rules that jump depending on whether a tree is not zero.
+    pub branches: &'static [Rule<C, R, Branch>],
+}
+
+/// How a tree is covered by rules.
+#[derive(Debug)]
+pub struct Cover<R> {
+    /// The index of the rule that covers the root, or `None` if the tree is already in its
+    /// register.
+    rule: Option<usize>,
+    cost: u32,
+    values: Vec<Value<R>>,
+    /// The subtrees computed before the rule and their registers.
+    subtrees: Vec<(Cover<R>, R)>,
+}
+
+/// The values and subtrees matched by a pattern.
+struct Match<R> {
+    values: Vec<Value<R>>,
+    subtrees: Vec<(Cover<R>, R)>,
+    /// The subtree computed in the destination, which is computed after the others.
+    dst_subtree: Option<Cover<R>>,
+}
+
+impl<C, R: Copy + Eq> Rules<C, R> {
+    /// Covers `tree` to compute it in `dst`.
+    pub fn select_value(
+        &self,
+        registers: &BTreeMap<Local, R>,
+        tree: &Tree,
+        dst: R,
+    ) -> Option<Cover<R>> {
+        if let Tree::Leaf(Operand::Local(local)) = tree {
+            if registers[local] == dst {
+                return Some(Cover {
+                    rule: None,
+                    cost: 0,
+                    values: Vec::new(),
+                    subtrees: Vec::new(),
+                });
+            }
+        }
+
+        self.select(self.values, registers, tree, Some(dst))
+    }
+
+    /// Covers `tree` to jump depending on whether it is not zero.
+    pub fn select_branch(&self, registers: &BTreeMap<Local, R>, tree: &Tree) -> Option<Cover<R>> {
+        self.select(self.branches, registers, tree, None)
+    }
+
+    pub fn emit_value(&self, ctx: &mut C, cover: &Cover<R>, dst: R) {
+        self.emit(ctx, self.values, cover, dst)
+    }
+
+    pub fn emit_branch(&self, ctx: &mut C, cover: &Cover<R>, branch: Branch) {
+        self.emit(ctx, self.branches, cover, branch)
+    }
+
+    fn select<G>(
+        &self,
+        rules: &[Rule<C, R, G>],
+        registers: &BTreeMap<Local, R>,
+        tree: &Tree,
+        dst: Option<R>,
+    ) -> Option<Cover<R>> {
+        let mut best: Option<Cover<R>> = None;
+
+        for (index, rule) in rules.iter().enumerate() {
+            // A rule that is a single register only covers a local, matching it with an operation
+            // would select the same tree again.
+            let leaf = matches!(tree, Tree::Leaf(_));
+            if matches!(rule.pattern, Pattern::Reg | Pattern::Dst) && !leaf {
+                continue;
+            }
+
+            let mut m = Match {
+                values: Vec::new(),
+                subtrees: Vec::new(),
+                dst_subtree: None,
+            };
+            if !self.matches(&rule.pattern, registers, tree, dst, &mut m) {
+                continue;
+            }
+
+            // The value of the destination is overwritten by the subtree computed in it, so no
+            // other operand of the rule can read it.
+            if let (Some(dst), Some(_)) = (dst, &m.dst_subtree) {
+                let reads = m.values.iter().filter(|value| **value == Value::Reg(dst));
+                if reads.count() > 1 {
+                    continue;
+                }
+            }
+
+            let mut subtrees = m.subtrees;
+            subtrees.extend(m.dst_subtree.zip(dst));
+            let cost = rule.cost + subtrees.iter().map(|(cover, _)| cover.cost).sum::<u32>();
+
+            if best.as_ref().is_none_or(|best| cost < best.cost) {
+                best = Some(Cover {
+                    rule: Some(index),
+                    cost,
+                    values: m.values,
+                    subtrees,
+                });
+            }
+        }
+
+        best
+    }
+
+    fn matches(
+        &self,
+        pattern: &Pattern,
+        registers: &BTreeMap<Local, R>,
+        tree: &Tree,
+        dst: Option<R>,
+        m: &mut Match<R>,
+    ) -> bool {
+        let constant = match tree {
+            Tree::Leaf(Operand::Constant(literal)) => Some(literal),
+            Tree::Leaf(Operand::Local(_)) | Tree::BinaryOp { .. } | Tree::Cast { .. } => None,
+        };
+
+        match (pattern, tree) {
+            (
+                Pattern::BinaryOp(op, lhs_pattern, rhs_pattern),
+                Tree::BinaryOp {
+                    op: tree_op,
+                    ty,
+                    lhs,
+                    rhs,
+                    ..
+                },
+            ) => {
+                op == tree_op
+                    && !ty.is_float()
+                    && self.matches(lhs_pattern, registers, lhs, dst, m)
+                    && self.matches(rhs_pattern, registers, rhs, dst, m)
+            }
+            (
+                Pattern::FloatOp(pattern_ty, op, lhs_pattern, rhs_pattern),
+                Tree::BinaryOp {
+                    op: tree_op,
+                    ty,
+                    lhs,
+                    rhs,
+                    ..
+                },
+            ) => {
+                op == tree_op
+                    && pattern_ty == ty
+                    && self.matches(lhs_pattern, registers, lhs, dst, m)
+                    && self.matches(rhs_pattern, registers, rhs, dst, m)
+            }
+            (
+                Pattern::Cast(pattern_from, pattern_to, operand_pattern),
+                Tree::Cast {
+                    operand, from, to, ..
+                },
+            ) => {
+                pattern_from == from
+                    && pattern_to == to
+                    && self.matches(operand_pattern, registers, operand, dst, m)
+            }
+            (
+                Pattern::BinaryOp(..) | Pattern::FloatOp(..) | Pattern::Cast(..),
+                Tree::Leaf(_) | Tree::BinaryOp { .. } | Tree::Cast { .. },
+            ) => false,
+            (Pattern::Reg, Tree::Leaf(Operand::Local(local))) => {
+                m.values.push(Value::Reg(registers[local]));
+                true
+            }
+            (Pattern::Reg, Tree::BinaryOp { local, .. } | Tree::Cast { local, .. }) => {
+                let reg = registers[local];
+                let Some(cover) = self.select_value(registers, tree, reg) else {
+                    return false;
+                };
+                m.values.push(Value::Reg(reg));
+                m.subtrees.push((cover, reg));
+                true
+            }
+            (Pattern::Dst, _) => {
+                let Some(dst) = dst else {
+                    return false;
+                };
+                if m.dst_subtree.is_some() {
+                    return false;
+                }
+                let Some(cover) = self.select_value(registers, tree, dst) else {
+                    return false;
+                };
+                m.values.push(Value::Reg(dst));
+                // A local that is already in the destination does not need any instruction.
+                if cover.rule.is_some() {
+                    m.dst_subtree = Some(cover);
+                }
+                true
+            }
+            (Pattern::FloatImm, _) => match constant.filter(|literal| literal.ty.is_float()) {
+                Some(literal) => {
+                    m.values.push(Value::Imm(literal.bits()));
+                    true
+                }
+                None => false,
+            },
+            (Pattern::Imm, _)
+            | (Pattern::Imm8, _)
+            | (Pattern::Imm12, _)
+            | (Pattern::Simm12, _)
+            | (Pattern::Zero, _) => {
+                let fits = |imm: &i64| match pattern {
+                    Pattern::Imm8 => i8::try_from(*imm).is_ok(),
+                    Pattern::Imm12 => imm.unsigned_abs() < 1 << 12,
+                    Pattern::Simm12 => (-(1 << 11)..1 << 11).contains(imm),
+                    Pattern::Zero => *imm == 0,
+                    _ => true,
+                };
+                let constant = constant
+                    .filter(|literal| !literal.ty.is_float())
+                    .map(Literal::bits);
+                match constant.filter(fits) {
+                    Some(imm) => {
+                        m.values.push(Value::Imm(imm));
+                        true
+                    }
+                    None => false,
+                }
+            }
+            (Pattern::Reg, Tree::Leaf(Operand::Constant(_))) => false,
+        }
+    }
+
+    fn emit<G>(&self, ctx: &mut C, rules: &[Rule<C, R, G>], cover: &Cover<R>, goal: G) {
+        for (subtree, reg) in &cover.subtrees {
+            self.emit_value(ctx, subtree, *reg);
+        }
+        if let Some(rule) = cover.rule {
+            (rules[rule].emit)(ctx, &cover.values, goal);
+        }
+    }
+}
@@ -1,42 +1,109 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+
+pub(crate) mod aarch64;
+pub mod isel;
+pub(crate) mod riscv64;
+pub(crate) mod x86_64;
 
 use crate::{
-    asm::{x86_64::Register, Imm32, Instruction, Instructions, Label},
+    asm::{Imm32, Instruction, Instructions, Label},
     code,
-    mir::{
-        BasicBlock, BasicBlockId, BinOp, Function, Local, Operand, Rvalue, Statement, Terminator,
-        Ty,
-    },
+    mir::{BasicBlock, BasicBlockId, Function, Local, Operand, Rvalue, Statement, Terminator, Ty},
+    profile::COUNTERS_SYMBOL,
+    target::{RegisterClass, Target},
 };
 
-pub fn lower_function(func: &Function) -> Instructions<Register> {
-    const AVAILABLE_REGISTERS: [Register; 5] = [
-        Register::Ax,
-        Register::Di,
-        Register::Si,
-        Register::Dx,
-        Register::Cx,
-    ];
-
-    if func.args_len > 3 {
-        todo!("cannot lower function with {} arguments", func.args_len);
-    }
+use isel::{BlockTrees, Branch, Tree};
 
-    if func.local_types.len() > 5 {
-        todo!(
-            "cannot lower function with {} locals",
-            func.local_types.len()
-        );
+/// How the lowered code accesses symbols.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RelocationModel {
+    /// Code that is linked at a fixed address, every symbol is accessed directly.
+    #[default]
+    Static,
+    /// Position-independent code suitable for shared libraries. Symbols that could be preempted
+    /// by another module are accessed through the global offset table.
+    Pic,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LowerOptions {
+    pub relocation_model: RelocationModel,
+    /// Symbols defined in the same module that cannot be preempted because they have hidden or
+    /// protected visibility. These can be accessed relative to the instruction pointer even in
+    /// position-independent code.
+    pub local_symbols: BTreeSet<String>,
+}
+
+/// A function that cannot be lowered because the target does not have enough registers for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LowerError {
+    /// The function has more arguments of a class than there are argument registers of that
+    /// class.
+    TooManyArguments(usize),
+    /// The function has more locals of a class than there are registers of that class.
+    TooManyLocals(usize),
+    /// A call passes more arguments of a class than there are argument registers of that class.
+    TooManyCallArguments { callee: String, args: usize },
+}
+
+impl fmt::Display for LowerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyArguments(args) => write!(f, "not enough registers for {args} arguments"),
+            Self::TooManyLocals(locals) => write!(f, "not enough registers for {locals} locals"),
+            Self::TooManyCallArguments { callee, args } => write!(
+                f,
+                "not enough registers for the {args} arguments of the call to `{callee}`"
+            ),
+        }
     }
+}
 
-    let mut instructions = Instructions::new();
+impl Error for LowerError {}
 
-    let local_registers = func
-        .local_types
-        .keys()
-        .copied()
-        .zip(AVAILABLE_REGISTERS)
-        .collect();
+/// Lowers `func` to the instructions of target `T`.
+///
+/// Every local lives in a register for the whole function. A function saves the callee-saved
+/// registers assigned to its locals when it starts, and the link register too if it calls other
+/// functions, and restores them before returning.
+pub fn lower_function<T: Target>(
+    func: &Function,
+    options: &LowerOptions,
+) -> Result<Instructions<T::Register>, LowerError> {
+    let local_registers = allocate_registers::<T>(func)?;
+    let return_register = *local_registers
+        .values()
+        .next()
+        .expect("the function has no return value");
+
+    let calls = func
+        .basic_blocks
+        .values()
+        .flat_map(|bb_data| &bb_data.statements)
+        .any(|statement| {
+            matches!(
+                statement,
+                Statement::Assign {
+                    rhs: Rvalue::Call { .. },
+                    ..
+                }
+            )
+        });
+
+    let mut saved_registers = T::LINK_REGISTER
+        .filter(|_| calls)
+        .into_iter()
+        .collect::<Vec<_>>();
+    saved_registers.extend(
+        T::CALLEE_SAVED_REGISTERS
+            .iter()
+            .filter(|reg| local_registers.values().any(|local_reg| local_reg == *reg)),
+    );
+
+    let mut instructions = Instructions::new();
 
     let block_labels = func
         .basic_blocks
@@ -45,160 +112,331 @@ pub fn lower_function(func: &Function) -> Instructions<Register> {
         .map(|label| (label, instructions.add_label()))
         .collect();
 
-    let mut ctx = LowerCtx {
+    let mut ctx = LowerCtx::<T> {
+        options,
         local_registers,
+        local_types: &func.local_types,
         block_labels,
+        read_counts: read_counts(func),
+        return_register,
+        saved_registers,
         instructions,
     };
 
-    for (bb, bb_data) in &func.basic_blocks {
-        ctx.lower_block(*bb, bb_data);
+    // The registers are saved before the first block, which can be the target of a jump.
+    for reg in ctx.saved_registers.clone() {
+        ctx.add_instruction(code!(push { reg }));
     }
 
-    ctx.instructions
-}
+    let mut blocks = func.basic_blocks.iter().peekable();
+    while let Some((bb, bb_data)) = blocks.next() {
+        let next = blocks.peek().map(|(next, _)| **next);
+        ctx.lower_block(*bb, bb_data, next)?;
+    }
 
-enum AsmOperand {
-    Reg(Register),
-    Imm32(Imm32),
+    Ok(ctx.instructions)
 }
 
-struct LowerCtx {
-    local_registers: BTreeMap<Local, Register>,
-    block_labels: BTreeMap<BasicBlockId, Label>,
-    instructions: Instructions<Register>,
+/// Assigns a register to every local of `func`.
+///
+/// The arguments stay in the registers they are passed in, and every other local takes the first
+/// register of its class in [`Target::ALLOCATABLE_REGISTERS`] that is still free.
+fn allocate_registers<T: Target>(
+    func: &Function,
+) -> Result<BTreeMap<Local, T::Register>, LowerError> {
+    // The locals are sorted, so `_0` comes first and it is followed by the arguments.
+    let (args, locals): (Vec<_>, Vec<_>) = func
+        .local_types
+        .iter()
+        .enumerate()
+        .partition(|(index, _)| (1..=func.args_len).contains(index));
+
+    let arg_registers =
+        T::argument_registers(args.iter().map(|(_, (_, ty))| RegisterClass::of(ty)))
+            .ok_or(LowerError::TooManyArguments(func.args_len))?;
+    let mut registers = args
+        .iter()
+        .map(|(_, (local, _))| **local)
+        .zip(arg_registers)
+        .collect::<BTreeMap<_, _>>();
+
+    for (_, (local, ty)) in locals {
+        let class = RegisterClass::of(ty);
+        let reg = T::ALLOCATABLE_REGISTERS
+            .iter()
+            .copied()
+            .find(|reg| {
+                T::register_class(*reg) == class && !registers.values().any(|used| used == reg)
+            })
+            .ok_or(LowerError::TooManyLocals(func.local_types.len()))?;
+        registers.insert(*local, reg);
+    }
+
+    Ok(registers)
 }
 
-impl LowerCtx {
-    fn lower_operand(&self, operand: &Operand) -> AsmOperand {
-        match operand {
-            Operand::Local(ref local) => AsmOperand::Reg(self.local_registers[local]),
-            Operand::Constant(ref literal) => match literal.ty {
-                Ty::Int => AsmOperand::Imm32(literal.data as Imm32),
-                Ty::Bool => AsmOperand::Imm32(literal.data as Imm32),
-            },
+/// Returns the number of times each local is read by the statements and terminators of `func`.
+/// Every `RETURN` reads `_0`.
+fn read_counts(func: &Function) -> BTreeMap<Local, usize> {
+    let mut counts = BTreeMap::new();
+    // The locals are sorted, so `_0` comes first.
+    let return_local = func.local_types.keys().next().copied();
+
+    for bb_data in func.basic_blocks.values() {
+        let terminator_read = match &bb_data.terminator {
+            Terminator::JumpIf { cond, .. } => cond.local(),
+            Terminator::Return => return_local,
+            Terminator::Jump(_) => None,
+        };
+
+        let locals = bb_data
+            .statements
+            .iter()
+            .flat_map(Statement::operands)
+            .filter_map(Operand::local)
+            .chain(terminator_read);
+        for local in locals {
+            *counts.entry(local).or_insert(0) += 1;
         }
     }
 
-    fn lower_terminator(&mut self, terminator: &Terminator) {
-        match terminator {
-            Terminator::Jump(ref bb) => self
+    counts
+}
+
+struct LowerCtx<'a, T: Target> {
+    options: &'a LowerOptions,
+    local_registers: BTreeMap<Local, T::Register>,
+    local_types: &'a BTreeMap<Local, Ty>,
+    block_labels: BTreeMap<BasicBlockId, Label>,
+    /// The number of times each local is read in the function.
+    read_counts: BTreeMap<Local, usize>,
+    /// The register of `_0`.
+    return_register: T::Register,
+    /// The registers pushed when the function starts, in order.
+    saved_registers: Vec<T::Register>,
+    instructions: Instructions<T::Register>,
+}
+
+impl<'a, T: Target> LowerCtx<'a, T> {
+    /// Lowers `terminator`, where `next` is the block lowered after this one, and `cond` is the
+    /// tree of its condition if it is a conditional jump.
+    fn lower_terminator(
+        &mut self,
+        terminator: &Terminator,
+        cond: Option<&Tree>,
+        next: Option<BasicBlockId>,
+    ) {
+        match (terminator, cond) {
+            (Terminator::Jump(ref bb), _) => self
                 .instructions
                 .add_instruction(code!( jmp { self.block_labels[bb] } )),
-            Terminator::Return => self.add_instruction(code! { ret }),
-            Terminator::JumpIf {
-                ref cond,
-                ref then_bb,
-                ref else_bb,
-            } => match self.lower_operand(cond) {
-                AsmOperand::Reg(cond) => {
-                    self.add_instruction(code!(jz { cond }, { self.block_labels[else_bb] }));
-                    self.add_instruction(code!(jmp { self.block_labels[then_bb] }));
+            (Terminator::Return, _) => {
+                let ret = self.return_register;
+                let target_ret = T::return_register(T::register_class(ret));
+                if ret != target_ret {
+                    self.add_instruction(code!(mov { ret }, { target_ret }));
                 }
-                AsmOperand::Imm32(cond) => {
-                    let bb = match cond {
-                        0 => else_bb,
-                        _ => then_bb,
-                    };
-                    self.add_instruction(code!(jmp { self.block_labels[bb] }))
+                for reg in self.saved_registers.clone().into_iter().rev() {
+                    self.add_instruction(code!(pop { reg }));
                 }
-            },
+                self.add_instruction(code! { ret })
+            }
+            (
+                Terminator::JumpIf {
+                    ref then_bb,
+                    ref else_bb,
+                    ..
+                },
+                Some(cond),
+            ) => {
+                let branch = Branch {
+                    then_label: self.block_labels[then_bb],
+                    else_label: self.block_labels[else_bb],
+                    else_is_next: next == Some(*else_bb),
+                };
+                let cover = T::rules()
+                    .select_branch(&self.local_registers, cond)
+                    .unwrap_or_else(|| panic!("cannot select instructions for {cond:?}"));
+                T::rules().emit_branch(&mut self.instructions, &cover, branch);
+            }
+            (Terminator::JumpIf { .. }, None) => unreachable!("the condition has no tree"),
         }
     }
 
-    fn lower_statement(&mut self, statement: &Statement) {
+    /// Computes `tree` in the register of `lhs`.
+    fn lower_tree(&mut self, lhs: Local, tree: &Tree) {
+        let dst = self.local_registers[&lhs];
+        let cover = T::rules()
+            .select_value(&self.local_registers, tree, dst)
+            .unwrap_or_else(|| panic!("cannot select instructions for {tree:?}"));
+        T::rules().emit_value(&mut self.instructions, &cover, dst);
+    }
+
+    /// Lowers a statement that is not computed by a tree.
+    fn lower_statement(&mut self, statement: &Statement) -> Result<(), LowerError> {
         match statement {
             Statement::Assign { ref lhs, ref rhs } => {
                 let lhs = self.local_registers[lhs];
 
                 match rhs {
-                    Rvalue::Use(ref operand) => match self.lower_operand(operand) {
-                        AsmOperand::Reg(rhs) => self.add_instruction(code!(mov { rhs }, { lhs })),
-                        AsmOperand::Imm32(rhs) => {
-                            self.add_instruction(code!(loadi { rhs.into() }, { lhs }))
-                        }
-                    },
-                    Rvalue::BinaryOp {
-                        ref op,
-                        lhs: ref lhs_op,
-                        rhs: ref rhs_op,
-                    } => match (self.lower_operand(lhs_op), self.lower_operand(rhs_op)) {
-                        (AsmOperand::Reg(lhs_op), AsmOperand::Reg(rhs_op)) => match op {
-                            BinOp::Add => {
-                                if lhs == lhs_op {
-                                    // lhs = lhs + rhs_op -> lhs += rhs_op
-                                    self.add_instruction(code!(add { rhs_op }, { lhs }));
-                                } else if lhs == rhs_op {
-                                    // lhs = lhs_op + lhs -> lhs += lhs_op
-                                    self.add_instruction(code!(add { lhs_op }, { lhs }));
-                                } else {
-                                    // lhs = lhs_op + rhs_op -> lhs = lhs_op; lhs += rhs_op
-                                    self.add_instruction(code!(mov { lhs_op }, { lhs }));
-                                    self.add_instruction(code!(add { rhs_op }, { lhs }));
-                                }
-                            }
-                            BinOp::Lt => {
-                                self.add_instruction(code!(slt { lhs_op }, { rhs_op }, { lhs }))
-                            }
-                        },
-                        (AsmOperand::Reg(lhs_op), AsmOperand::Imm32(rhs_op)) => match op {
-                            BinOp::Add => {
-                                if lhs == lhs_op {
-                                    // lhs = lhs + rhs_op -> lhs += rhs_op
-                                    self.add_instruction(code!(addi { rhs_op }, { lhs }));
-                                } else {
-                                    // lhs = lhs_op + rhs_op -> lhs = lhs_op; lhs += rhs_op
-                                    self.add_instruction(code!(mov { lhs_op }, { lhs }));
-                                    self.add_instruction(code!(addi { rhs_op }, { lhs }));
-                                }
-                            }
-                            BinOp::Lt => todo!(),
-                        },
-                        (AsmOperand::Imm32(lhs_op), AsmOperand::Reg(rhs_op)) => match op {
-                            BinOp::Add => {
-                                if lhs == rhs_op {
-                                    // lhs = lhs_op + lhs -> lhs += lhs_op
-                                    self.add_instruction(code!(addi { lhs_op }, { lhs }));
-                                } else {
-                                    // lhs = lhs_op + rhs_op -> lhs = lhs_op; lhs += rhs_op
-                                    self.add_instruction(code!(loadi { lhs_op.into() }, { lhs }));
-                                    self.add_instruction(code!(add { rhs_op }, { lhs }));
-                                }
-                            }
-                            BinOp::Lt => todo!(),
-                        },
-                        (AsmOperand::Imm32(lhs_op), AsmOperand::Imm32(rhs_op)) => match op {
-                            BinOp::Add => {
-                                // lhs = lhs_op + rhs_op -> lhs = lhs_op; lhs += rhs_op
-                                self.add_instruction(code!(loadi { lhs_op.into() }, { lhs }));
-                                self.add_instruction(code!(addi { rhs_op }, { lhs }));
-                            }
-                            BinOp::Lt => {
-                                let imm = if lhs_op < rhs_op { 1 } else { 0 };
-                                self.add_instruction(code!(loadi { imm }, { lhs }));
-                            }
-                        },
-                    },
+                    Rvalue::SymbolAddr(ref name) => {
+                        self.lower_symbol_addr(name, lhs);
+                        Ok(())
+                    }
+                    Rvalue::Call {
+                        ref callee,
+                        ref args,
+                    } => self.lower_call(callee, args, lhs),
+                    Rvalue::Use(_) | Rvalue::BinaryOp { .. } | Rvalue::Cast { .. } => {
+                        unreachable!("the statement is lowered as a tree")
+                    }
                 }
             }
+            Statement::Phi { .. } => {
+                panic!("cannot lower phis, the function must be taken out of SSA form first")
+            }
+        }
+    }
+
+    /// Increments the profile counter with index `counter`. The counters are hidden, so they are
+    /// accessed directly even in position-independent code.
+    fn lower_counter_increment(&mut self, counter: usize) {
+        let (addr, value) = (T::SCRATCH_REGISTER, T::COUNTER_REGISTER);
+        let offset = Imm32::try_from(8 * counter).expect("too many profile counters");
+
+        let sym = self.instructions.add_symbol(COUNTERS_SYMBOL);
+        self.add_instruction(code!(lea sym { sym }, { addr }));
+        self.add_instruction(code!(load { addr } + { offset }, { value }));
+        self.add_instruction(code!(addi { 1 }, { value }));
+        self.add_instruction(code!(store { addr }, { value } + { offset }));
+    }
+
+    fn lower_symbol_addr(&mut self, name: &str, dst: T::Register) {
+        let sym = self.instructions.add_symbol(name);
+
+        match self.options.relocation_model {
+            RelocationModel::Pic if !self.options.local_symbols.contains(name) => {
+                self.add_instruction(code!(load got { sym }, { dst }))
+            }
+            RelocationModel::Static | RelocationModel::Pic => {
+                self.add_instruction(code!(lea sym { sym }, { dst }))
+            }
         }
     }
 
-    fn lower_block(&mut self, bb: BasicBlockId, bb_data: &BasicBlock) {
-        let index = self.instructions.len();
+    /// Calls `callee` following the calling convention of the target. The arguments are passed in
+    /// the registers given by [`Target::argument_registers`], and the shadow space of the target
+    /// is reserved right before the call. The registers of the other locals that are not
+    /// callee-saved are saved on the stack around the call.
+    fn lower_call(
+        &mut self,
+        callee: &str,
+        args: &[Operand],
+        dst: T::Register,
+    ) -> Result<(), LowerError> {
+        let arg_registers = T::argument_registers(
+            args.iter()
+                .map(|arg| RegisterClass::of(&arg.ty(self.local_types))),
+        )
+        .ok_or_else(|| LowerError::TooManyCallArguments {
+            callee: callee.to_owned(),
+            args: args.len(),
+        })?;
+
+        let saved = self
+            .local_registers
+            .values()
+            .copied()
+            .filter(|reg| *reg != dst && !T::CALLEE_SAVED_REGISTERS.contains(reg))
+            .collect::<Vec<_>>();
+
+        for reg in &saved {
+            self.add_instruction(code!(push { *reg }));
+        }
+
+        // The stack must be aligned at the call. Without a link register, the return address
+        // pushed by the call that started this function is on the stack too.
+        let return_address = if T::LINK_REGISTER.is_none() { 1 } else { 0 };
+        let pushes = return_address + self.saved_registers.len() + saved.len();
+        let pushed = T::PUSH_SIZE * i32::try_from(pushes).expect("too many pushes");
+        let padding = (T::STACK_ALIGNMENT - pushed % T::STACK_ALIGNMENT) % T::STACK_ALIGNMENT;
+        if padding != 0 {
+            self.add_instruction(code!(addi { -padding }, { T::STACK_POINTER }));
+        }
+
+        // Pushing every argument before popping them into their registers means that no argument
+        // is overwritten before it is read.
+        for arg in args {
+            match arg {
+                Operand::Local(local) => {
+                    let reg = self.local_registers[local];
+                    self.add_instruction(code!(push { reg }));
+                }
+                Operand::Constant(literal) => {
+                    // Float constants are pushed as their bits and popped into a float register.
+                    let scratch = T::SCRATCH_REGISTER;
+                    self.add_instruction(code!(loadi { literal.bits() }, { scratch }));
+                    self.add_instruction(code!(push { scratch }));
+                }
+            }
+        }
+        for reg in arg_registers.iter().rev() {
+            self.add_instruction(code!(pop { *reg }));
+        }
+
+        // The shadow space is a multiple of the stack alignment, so the stack stays aligned.
+        if T::SHADOW_SPACE != 0 {
+            self.add_instruction(code!(addi { -T::SHADOW_SPACE }, { T::STACK_POINTER }));
+        }
 
-        for statement in &bb_data.statements {
-            self.lower_statement(statement);
+        let sym = self.instructions.add_symbol(callee);
+        self.add_instruction(code!(call sym { sym }));
+
+        // The return value is kept in the scratch register while the saved registers, which may
+        // include the return register, are restored.
+        let class = T::register_class(dst);
+        let scratch = T::scratch_register(class);
+        self.add_instruction(code!(mov { T::return_register(class) }, { scratch }));
+        let reserved = padding + T::SHADOW_SPACE;
+        if reserved != 0 {
+            self.add_instruction(code!(addi { reserved }, { T::STACK_POINTER }));
+        }
+        for reg in saved.iter().rev() {
+            self.add_instruction(code!(pop { *reg }));
         }
+        self.add_instruction(code!(mov { scratch }, { dst }));
 
-        self.lower_terminator(&bb_data.terminator);
+        Ok(())
+    }
 
-        if let Some(instruction) = self.instructions.get_mut(index) {
-            instruction.label = Some(self.block_labels[&bb]);
+    fn lower_block(
+        &mut self,
+        bb: BasicBlockId,
+        bb_data: &BasicBlock,
+        next: Option<BasicBlockId>,
+    ) -> Result<(), LowerError> {
+        self.instructions.bind_label(self.block_labels[&bb]);
+        for counter in &bb_data.profile.counters {
+            self.lower_counter_increment(*counter);
         }
+
+        let trees = BlockTrees::new(bb_data, self.local_types, &self.read_counts);
+
+        for (i, statement) in bb_data.statements.iter().enumerate() {
+            match trees.statements.get(&i) {
+                Some(Some(tree)) => self.lower_tree(statement.lhs(), tree),
+                // The statement is folded into a later one.
+                Some(None) => {}
+                None => self.lower_statement(statement)?,
+            }
+        }
+
+        self.lower_terminator(&bb_data.terminator, trees.cond.as_ref(), next);
+
+        Ok(())
     }
 
-    fn add_instruction(&mut self, instruction: Instruction<Register>) {
+    fn add_instruction(&mut self, instruction: Instruction<T::Register>) {
         self.instructions.add_instruction(instruction)
     }
 }
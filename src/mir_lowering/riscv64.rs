@@ -0,0 +1,260 @@
+//! The instruction selection rules of RISC-V.
+//!
+//! The costs are the sizes of the instructions, which are 4 bytes each. A comparison costs nothing
+//! by itself because the branches compare registers.
+use crate::{
+    asm::{riscv64::Register, Condition, Imm32, Instructions},
+    code,
+    mir::BinOp,
+    target::{Riscv64, Target},
+};
+
+use super::isel::{Branch, Pattern, Rule, Rules, Value};
+
+type ValueRule = Rule<Instructions<Register>, Register, Register>;
+type BranchRule = Rule<Instructions<Register>, Register, Branch>;
+
+const fn add(lhs: &'static Pattern, rhs: &'static Pattern) -> Pattern {
+    Pattern::BinaryOp(BinOp::Add, lhs, rhs)
+}
+
+const fn mul(lhs: &'static Pattern, rhs: &'static Pattern) -> Pattern {
+    Pattern::BinaryOp(BinOp::Mul, lhs, rhs)
+}
+
+const fn lt(lhs: &'static Pattern, rhs: &'static Pattern) -> Pattern {
+    Pattern::BinaryOp(BinOp::Lt, lhs, rhs)
+}
+
+pub(crate) static RULES: Rules<Instructions<Register>, Register> = Rules {
+    values: VALUE_RULES,
+    branches: BRANCH_RULES,
+};
+
+static VALUE_RULES: &[ValueRule] = &[
+    Rule {
+        pattern: Pattern::Reg,
+        cost: 4,
+        emit: |instructions, values, dst| {
+            instructions.add_instruction(code!(mov { values[0].reg() }, { dst }))
+        },
+    },
+    Rule {
+        pattern: Pattern::Simm12,
+        cost: 4,
+        emit: load_imm,
+    },
+    Rule {
+        pattern: Pattern::Imm,
+        cost: 8,
+        emit: load_imm,
+    },
+    Rule {
+        pattern: add(&Pattern::Reg, &Pattern::Reg),
+        cost: 4,
+        emit: |instructions, values, dst| {
+            let (base, index) = (values[0].reg(), values[1].reg());
+            instructions.add_instruction(code!(lea { base } + { index }, { dst }))
+        },
+    },
+    Rule {
+        pattern: add(&Pattern::Dst, &Pattern::Simm12),
+        cost: 4,
+        emit: |instructions, values, dst| add_imm(instructions, values[1], dst),
+    },
+    Rule {
+        pattern: add(&Pattern::Dst, &Pattern::Imm),
+        cost: 12,
+        emit: |instructions, values, dst| add_imm(instructions, values[1], dst),
+    },
+    Rule {
+        pattern: add(&Pattern::Simm12, &Pattern::Dst),
+        cost: 4,
+        emit: |instructions, values, dst| add_imm(instructions, values[0], dst),
+    },
+    Rule {
+        pattern: add(&Pattern::Imm, &Pattern::Dst),
+        cost: 12,
+        emit: |instructions, values, dst| add_imm(instructions, values[0], dst),
+    },
+    Rule {
+        pattern: add(&Pattern::Imm, &Pattern::Imm),
+        cost: 8,
+        emit: |instructions, values, dst| {
+            let sum = values[0].imm().wrapping_add(values[1].imm());
+            instructions.add_instruction(code!(loadi { sum }, { dst }))
+        },
+    },
+    Rule {
+        pattern: mul(&Pattern::Dst, &Pattern::Reg),
+        cost: 4,
+        emit: |instructions, values, dst| {
+            instructions.add_instruction(code!(mul { values[1].reg() }, { dst }))
+        },
+    },
+    Rule {
+        pattern: mul(&Pattern::Reg, &Pattern::Dst),
+        cost: 4,
+        emit: |instructions, values, dst| {
+            instructions.add_instruction(code!(mul { values[0].reg() }, { dst }))
+        },
+    },
+    Rule {
+        pattern: mul(&Pattern::Dst, &Pattern::Imm),
+        cost: 12,
+        emit: |instructions, values, dst| {
+            let scratch = load_scratch(instructions, values[1]);
+            instructions.add_instruction(code!(mul { scratch }, { dst }))
+        },
+    },
+    Rule {
+        pattern: mul(&Pattern::Imm, &Pattern::Dst),
+        cost: 12,
+        emit: |instructions, values, dst| {
+            let scratch = load_scratch(instructions, values[0]);
+            instructions.add_instruction(code!(mul { scratch }, { dst }))
+        },
+    },
+    Rule {
+        pattern: mul(&Pattern::Imm, &Pattern::Imm),
+        cost: 8,
+        emit: |instructions, values, dst| {
+            let product = values[0].imm().wrapping_mul(values[1].imm());
+            instructions.add_instruction(code!(loadi { product }, { dst }))
+        },
+    },
+    Rule {
+        pattern: lt(&Pattern::Reg, &Pattern::Reg),
+        cost: 4,
+        emit: |instructions, values, dst| {
+            let (lhs, rhs) = (values[0].reg(), values[1].reg());
+            instructions.add_instruction(code!(slt { lhs }, { rhs }, { dst }))
+        },
+    },
+    Rule {
+        pattern: lt(&Pattern::Reg, &Pattern::Imm),
+        cost: 12,
+        emit: |instructions, values, dst| {
+            let scratch = load_scratch(instructions, values[1]);
+            instructions.add_instruction(code!(slt { values[0].reg() }, { scratch }, { dst }))
+        },
+    },
+    Rule {
+        pattern: lt(&Pattern::Imm, &Pattern::Reg),
+        cost: 12,
+        emit: |instructions, values, dst| {
+            let scratch = load_scratch(instructions, values[0]);
+            instructions.add_instruction(code!(slt { scratch }, { values[1].reg() }, { dst }))
+        },
+    },
+    Rule {
+        pattern: lt(&Pattern::Imm, &Pattern::Imm),
+        cost: 4,
+        emit: |instructions, values, dst| {
+            let less = i64::from(values[0].imm() < values[1].imm());
+            instructions.add_instruction(code!(loadi { less }, { dst }))
+        },
+    },
+];
+
+static BRANCH_RULES: &[BranchRule] = &[
+    Rule {
+        pattern: Pattern::Reg,
+        cost: 4,
+        emit: |instructions, values, branch| {
+            let cond = values[0].reg();
+            if branch.else_is_next {
+                instructions.add_instruction(code!(test { cond }, { cond }));
+                jump_if(instructions, Condition::NotEqual, branch);
+            } else {
+                instructions.add_instruction(code!(jz { cond }, { branch.else_label }));
+                instructions.add_instruction(code!(jmp { branch.then_label }));
+            }
+        },
+    },
+    Rule {
+        pattern: Pattern::Imm,
+        cost: 4,
+        emit: |instructions, values, branch| {
+            let target = match values[0].imm() {
+                0 => branch.else_label,
+                _ => branch.then_label,
+            };
+            instructions.add_instruction(code!(jmp { target }))
+        },
+    },
+    Rule {
+        pattern: lt(&Pattern::Reg, &Pattern::Reg),
+        cost: 4,
+        emit: |instructions, values, branch| {
+            let (lhs, rhs) = (values[0].reg(), values[1].reg());
+            instructions.add_instruction(code!(cmp { lhs }, { rhs }));
+            jump_if(instructions, Condition::Less, branch);
+        },
+    },
+    Rule {
+        pattern: lt(&Pattern::Reg, &Pattern::Imm),
+        cost: 12,
+        emit: |instructions, values, branch| {
+            let scratch = load_scratch(instructions, values[1]);
+            instructions.add_instruction(code!(cmp { values[0].reg() }, { scratch }));
+            jump_if(instructions, Condition::Less, branch);
+        },
+    },
+    Rule {
+        pattern: lt(&Pattern::Imm, &Pattern::Reg),
+        cost: 12,
+        emit: |instructions, values, branch| {
+            let scratch = load_scratch(instructions, values[0]);
+            instructions.add_instruction(code!(cmp { scratch }, { values[1].reg() }));
+            jump_if(instructions, Condition::Less, branch);
+        },
+    },
+    Rule {
+        pattern: lt(&Pattern::Imm, &Pattern::Imm),
+        cost: 4,
+        emit: |instructions, values, branch| {
+            let target = if values[0].imm() < values[1].imm() {
+                branch.then_label
+            } else {
+                branch.else_label
+            };
+            instructions.add_instruction(code!(jmp { target }))
+        },
+    },
+];
+
+fn load_imm(instructions: &mut Instructions<Register>, values: &[Value<Register>], dst: Register) {
+    instructions.add_instruction(code!(loadi { values[0].imm() }, { dst }))
+}
+
+/// Adds a constant to `dst`, loading it in the scratch register first if it does not fit in 32
+/// bits.
+fn add_imm(instructions: &mut Instructions<Register>, imm: Value<Register>, dst: Register) {
+    match Imm32::try_from(imm.imm()) {
+        Ok(imm) => instructions.add_instruction(code!(addi { imm }, { dst })),
+        Err(_) => {
+            let scratch = load_scratch(instructions, imm);
+            instructions.add_instruction(code!(add { scratch }, { dst }))
+        }
+    }
+}
+
+/// Loads a constant in the scratch register for an instruction that only takes registers.
+fn load_scratch(instructions: &mut Instructions<Register>, imm: Value<Register>) -> Register {
+    let scratch = Riscv64::SCRATCH_REGISTER;
+    instructions.add_instruction(code!(loadi { imm.imm() }, { scratch }));
+    scratch
+}
+
+/// Jumps to the `THEN` block if `cond` holds and to the `ELSE` block otherwise. The conditional
+/// jump is inverted so that it does not jump to the block lowered next.
+fn jump_if(instructions: &mut Instructions<Register>, cond: Condition, branch: Branch) {
+    let (cond, target, other) = if branch.else_is_next {
+        (cond, branch.then_label, branch.else_label)
+    } else {
+        (cond.negate(), branch.else_label, branch.then_label)
+    };
+    instructions.add_instruction(code!(jcc { cond }, { target }));
+    instructions.add_instruction(code!(jmp { other }));
+}
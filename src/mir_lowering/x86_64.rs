@@ -0,0 +1,758 @@
+//! The instruction selection rules of x86-64.
+//!
+//! The costs are the sizes of the encoded instructions. Immediates that fit in a byte have shorter
+//! encodings, and `lea` adds two registers into a third one in a single instruction.
+//!
+//! Float constants are loaded into the general scratch register and moved into an XMM register,
+//! because SSE instructions have no immediate operands.
+use std::marker::PhantomData;
+
+use crate::{
+    asm::{x86_64::Register, Condition, Imm32, Instructions, Precision},
+    code,
+    mir::{BinOp, Ty},
+    target::{RegisterClass, Target, X86_64},
+};
+
+use super::isel::{Branch, Pattern, Rule, Rules, Value};
+
+type ValueRule = Rule<Instructions<Register>, Register, Register>;
+type BranchRule = Rule<Instructions<Register>, Register, Branch>;
+
+const fn add(lhs: &'static Pattern, rhs: &'static Pattern) -> Pattern {
+    Pattern::BinaryOp(BinOp::Add, lhs, rhs)
+}
+
+const fn mul(lhs: &'static Pattern, rhs: &'static Pattern) -> Pattern {
+    Pattern::BinaryOp(BinOp::Mul, lhs, rhs)
+}
+
+const fn lt(lhs: &'static Pattern, rhs: &'static Pattern) -> Pattern {
+    Pattern::BinaryOp(BinOp::Lt, lhs, rhs)
+}
+
+const fn fadd(ty: Ty, lhs: &'static Pattern, rhs: &'static Pattern) -> Pattern {
+    Pattern::FloatOp(ty, BinOp::Add, lhs, rhs)
+}
+
+const fn fmul(ty: Ty, lhs: &'static Pattern, rhs: &'static Pattern) -> Pattern {
+    Pattern::FloatOp(ty, BinOp::Mul, lhs, rhs)
+}
+
+const fn flt(ty: Ty, lhs: &'static Pattern, rhs: &'static Pattern) -> Pattern {
+    Pattern::FloatOp(ty, BinOp::Lt, lhs, rhs)
+}
+
+/// The rules of the x86-64 target `T`, which decides the float scratch register. The System V and
+/// the Windows conventions do not agree on any SSE register that is free to clobber and not
+/// assigned to locals.
+pub(crate) struct X86_64Rules<T>(PhantomData<T>);
+
+impl<T: Target<Register = Register>> X86_64Rules<T> {
+    pub(crate) const RULES: &'static Rules<Instructions<Register>, Register> = &Rules {
+        values: Self::VALUE_RULES,
+        branches: Self::BRANCH_RULES,
+    };
+
+    const VALUE_RULES: &'static [ValueRule] = &[
+        Rule {
+            pattern: Pattern::Reg,
+            cost: 3,
+            emit: |instructions, values, dst| {
+                instructions.add_instruction(code!(mov { values[0].reg() }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Zero,
+            cost: 2,
+            emit: load_imm,
+        },
+        Rule {
+            pattern: Pattern::Imm,
+            cost: 5,
+            emit: load_imm,
+        },
+        Rule {
+            pattern: add(&Pattern::Dst, &Pattern::Reg),
+            cost: 3,
+            emit: |instructions, values, dst| {
+                instructions.add_instruction(code!(add { values[1].reg() }, { dst }))
+            },
+        },
+        Rule {
+            pattern: add(&Pattern::Reg, &Pattern::Dst),
+            cost: 3,
+            emit: |instructions, values, dst| {
+                instructions.add_instruction(code!(add { values[0].reg() }, { dst }))
+            },
+        },
+        Rule {
+            pattern: add(&Pattern::Dst, &Pattern::Imm8),
+            cost: 4,
+            emit: |instructions, values, dst| add_imm(instructions, values[1], dst),
+        },
+        Rule {
+            pattern: add(&Pattern::Dst, &Pattern::Imm),
+            cost: 7,
+            emit: |instructions, values, dst| add_imm(instructions, values[1], dst),
+        },
+        Rule {
+            pattern: add(&Pattern::Imm8, &Pattern::Dst),
+            cost: 4,
+            emit: |instructions, values, dst| add_imm(instructions, values[0], dst),
+        },
+        Rule {
+            pattern: add(&Pattern::Imm, &Pattern::Dst),
+            cost: 7,
+            emit: |instructions, values, dst| add_imm(instructions, values[0], dst),
+        },
+        Rule {
+            pattern: add(&Pattern::Reg, &Pattern::Reg),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                let (base, index) = (values[0].reg(), values[1].reg());
+                instructions.add_instruction(code!(lea { base } + { index }, { dst }))
+            },
+        },
+        Rule {
+            pattern: add(&Pattern::Imm, &Pattern::Imm),
+            cost: 5,
+            emit: |instructions, values, dst| {
+                let sum = values[0].imm().wrapping_add(values[1].imm());
+                instructions.add_instruction(code!(loadi { sum }, { dst }))
+            },
+        },
+        Rule {
+            pattern: lt(&Pattern::Reg, &Pattern::Reg),
+            cost: 10,
+            emit: |instructions, values, dst| {
+                let (lhs, rhs) = (values[0].reg(), values[1].reg());
+                instructions.add_instruction(code!(slt { lhs }, { rhs }, { dst }))
+            },
+        },
+        Rule {
+            pattern: lt(&Pattern::Reg, &Pattern::Imm),
+            cost: 15,
+            emit: |instructions, values, dst| {
+                let scratch = load_scratch(instructions, values[1]);
+                instructions.add_instruction(code!(slt { values[0].reg() }, { scratch }, { dst }))
+            },
+        },
+        Rule {
+            pattern: lt(&Pattern::Imm, &Pattern::Reg),
+            cost: 15,
+            emit: |instructions, values, dst| {
+                let scratch = load_scratch(instructions, values[0]);
+                instructions.add_instruction(code!(slt { scratch }, { values[1].reg() }, { dst }))
+            },
+        },
+        Rule {
+            pattern: lt(&Pattern::Imm, &Pattern::Imm),
+            cost: 5,
+            emit: |instructions, values, dst| {
+                let less = i64::from(values[0].imm() < values[1].imm());
+                instructions.add_instruction(code!(loadi { less }, { dst }))
+            },
+        },
+        Rule {
+            pattern: mul(&Pattern::Dst, &Pattern::Reg),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                instructions.add_instruction(code!(mul { values[1].reg() }, { dst }))
+            },
+        },
+        Rule {
+            pattern: mul(&Pattern::Reg, &Pattern::Dst),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                instructions.add_instruction(code!(mul { values[0].reg() }, { dst }))
+            },
+        },
+        Rule {
+            pattern: mul(&Pattern::Dst, &Pattern::Imm),
+            cost: 9,
+            emit: |instructions, values, dst| {
+                let scratch = load_scratch(instructions, values[1]);
+                instructions.add_instruction(code!(mul { scratch }, { dst }))
+            },
+        },
+        Rule {
+            pattern: mul(&Pattern::Imm, &Pattern::Dst),
+            cost: 9,
+            emit: |instructions, values, dst| {
+                let scratch = load_scratch(instructions, values[0]);
+                instructions.add_instruction(code!(mul { scratch }, { dst }))
+            },
+        },
+        Rule {
+            pattern: mul(&Pattern::Imm, &Pattern::Imm),
+            cost: 5,
+            emit: |instructions, values, dst| {
+                let product = values[0].imm().wrapping_mul(values[1].imm());
+                instructions.add_instruction(code!(loadi { product }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::FloatImm,
+            cost: 15,
+            emit: |instructions, values, dst| {
+                let scratch = load_scratch(instructions, values[0]);
+                instructions.add_instruction(code!(mov { scratch }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fadd(Ty::F64, &Pattern::Dst, &Pattern::Reg),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                let src = values[1].reg();
+                instructions.add_instruction(code!(addsd { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fadd(Ty::F64, &Pattern::Reg, &Pattern::Dst),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                let src = values[0].reg();
+                instructions.add_instruction(code!(addsd { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fadd(Ty::F64, &Pattern::Dst, &Pattern::FloatImm),
+            cost: 19,
+            emit: |instructions, values, dst| {
+                let src = load_float_scratch::<T>(instructions, values[1]);
+                instructions.add_instruction(code!(addsd { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fadd(Ty::F64, &Pattern::FloatImm, &Pattern::Dst),
+            cost: 19,
+            emit: |instructions, values, dst| {
+                let src = load_float_scratch::<T>(instructions, values[0]);
+                instructions.add_instruction(code!(addsd { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fmul(Ty::F64, &Pattern::Dst, &Pattern::Reg),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                let src = values[1].reg();
+                instructions.add_instruction(code!(mulsd { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fmul(Ty::F64, &Pattern::Reg, &Pattern::Dst),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                let src = values[0].reg();
+                instructions.add_instruction(code!(mulsd { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fmul(Ty::F64, &Pattern::Dst, &Pattern::FloatImm),
+            cost: 19,
+            emit: |instructions, values, dst| {
+                let src = load_float_scratch::<T>(instructions, values[1]);
+                instructions.add_instruction(code!(mulsd { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fmul(Ty::F64, &Pattern::FloatImm, &Pattern::Dst),
+            cost: 19,
+            emit: |instructions, values, dst| {
+                let src = load_float_scratch::<T>(instructions, values[0]);
+                instructions.add_instruction(code!(mulsd { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F64, &Pattern::Reg, &Pattern::Reg),
+            cost: 11,
+            emit: |instructions, values, dst| {
+                let (src1, src2) = (values[0].reg(), values[1].reg());
+                instructions.add_instruction(code!(sltsd { src1 }, { src2 }, { dst }))
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F64, &Pattern::Reg, &Pattern::FloatImm),
+            cost: 26,
+            emit: |instructions, values, dst| {
+                let (src1, src2) = (
+                    values[0].reg(),
+                    load_float_scratch::<T>(instructions, values[1]),
+                );
+                instructions.add_instruction(code!(sltsd { src1 }, { src2 }, { dst }))
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F64, &Pattern::FloatImm, &Pattern::Reg),
+            cost: 26,
+            emit: |instructions, values, dst| {
+                let (src1, src2) = (
+                    load_float_scratch::<T>(instructions, values[0]),
+                    values[1].reg(),
+                );
+                instructions.add_instruction(code!(sltsd { src1 }, { src2 }, { dst }))
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F64, &Pattern::FloatImm, &Pattern::FloatImm),
+            cost: 5,
+            emit: |instructions, values, dst| {
+                let less = i64::from(float_less(Precision::Double, values[0], values[1]));
+                instructions.add_instruction(code!(loadi { less }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Cast(Ty::Int, Ty::F64, &Pattern::Reg),
+            cost: 5,
+            emit: |instructions, values, dst| {
+                let src = values[0].reg();
+                instructions.add_instruction(code!(cvtsi2sd { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Cast(Ty::Int, Ty::F64, &Pattern::Imm),
+            cost: 10,
+            emit: |instructions, values, dst| {
+                let src = load_scratch(instructions, values[0]);
+                instructions.add_instruction(code!(cvtsi2sd { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Cast(Ty::F64, Ty::Int, &Pattern::Reg),
+            cost: 5,
+            emit: |instructions, values, dst| {
+                let src = values[0].reg();
+                instructions.add_instruction(code!(cvttsd2si { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Cast(Ty::F64, Ty::Int, &Pattern::FloatImm),
+            cost: 20,
+            emit: |instructions, values, dst| {
+                let src = load_float_scratch::<T>(instructions, values[0]);
+                instructions.add_instruction(code!(cvttsd2si { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Cast(Ty::F32, Ty::F64, &Pattern::Reg),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                let src = values[0].reg();
+                instructions.add_instruction(code!(cvtss2sd { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Cast(Ty::F32, Ty::F64, &Pattern::FloatImm),
+            cost: 19,
+            emit: |instructions, values, dst| {
+                let src = load_float_scratch::<T>(instructions, values[0]);
+                instructions.add_instruction(code!(cvtss2sd { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fadd(Ty::F32, &Pattern::Dst, &Pattern::Reg),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                let src = values[1].reg();
+                instructions.add_instruction(code!(addss { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fadd(Ty::F32, &Pattern::Reg, &Pattern::Dst),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                let src = values[0].reg();
+                instructions.add_instruction(code!(addss { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fadd(Ty::F32, &Pattern::Dst, &Pattern::FloatImm),
+            cost: 19,
+            emit: |instructions, values, dst| {
+                let src = load_float_scratch::<T>(instructions, values[1]);
+                instructions.add_instruction(code!(addss { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fadd(Ty::F32, &Pattern::FloatImm, &Pattern::Dst),
+            cost: 19,
+            emit: |instructions, values, dst| {
+                let src = load_float_scratch::<T>(instructions, values[0]);
+                instructions.add_instruction(code!(addss { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fmul(Ty::F32, &Pattern::Dst, &Pattern::Reg),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                let src = values[1].reg();
+                instructions.add_instruction(code!(mulss { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fmul(Ty::F32, &Pattern::Reg, &Pattern::Dst),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                let src = values[0].reg();
+                instructions.add_instruction(code!(mulss { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fmul(Ty::F32, &Pattern::Dst, &Pattern::FloatImm),
+            cost: 19,
+            emit: |instructions, values, dst| {
+                let src = load_float_scratch::<T>(instructions, values[1]);
+                instructions.add_instruction(code!(mulss { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: fmul(Ty::F32, &Pattern::FloatImm, &Pattern::Dst),
+            cost: 19,
+            emit: |instructions, values, dst| {
+                let src = load_float_scratch::<T>(instructions, values[0]);
+                instructions.add_instruction(code!(mulss { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F32, &Pattern::Reg, &Pattern::Reg),
+            cost: 11,
+            emit: |instructions, values, dst| {
+                let (src1, src2) = (values[0].reg(), values[1].reg());
+                instructions.add_instruction(code!(sltss { src1 }, { src2 }, { dst }))
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F32, &Pattern::Reg, &Pattern::FloatImm),
+            cost: 26,
+            emit: |instructions, values, dst| {
+                let (src1, src2) = (
+                    values[0].reg(),
+                    load_float_scratch::<T>(instructions, values[1]),
+                );
+                instructions.add_instruction(code!(sltss { src1 }, { src2 }, { dst }))
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F32, &Pattern::FloatImm, &Pattern::Reg),
+            cost: 26,
+            emit: |instructions, values, dst| {
+                let (src1, src2) = (
+                    load_float_scratch::<T>(instructions, values[0]),
+                    values[1].reg(),
+                );
+                instructions.add_instruction(code!(sltss { src1 }, { src2 }, { dst }))
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F32, &Pattern::FloatImm, &Pattern::FloatImm),
+            cost: 5,
+            emit: |instructions, values, dst| {
+                let less = i64::from(float_less(Precision::Single, values[0], values[1]));
+                instructions.add_instruction(code!(loadi { less }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Cast(Ty::Int, Ty::F32, &Pattern::Reg),
+            cost: 5,
+            emit: |instructions, values, dst| {
+                let src = values[0].reg();
+                instructions.add_instruction(code!(cvtsi2ss { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Cast(Ty::Int, Ty::F32, &Pattern::Imm),
+            cost: 10,
+            emit: |instructions, values, dst| {
+                let src = load_scratch(instructions, values[0]);
+                instructions.add_instruction(code!(cvtsi2ss { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Cast(Ty::F32, Ty::Int, &Pattern::Reg),
+            cost: 5,
+            emit: |instructions, values, dst| {
+                let src = values[0].reg();
+                instructions.add_instruction(code!(cvttss2si { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Cast(Ty::F32, Ty::Int, &Pattern::FloatImm),
+            cost: 20,
+            emit: |instructions, values, dst| {
+                let src = load_float_scratch::<T>(instructions, values[0]);
+                instructions.add_instruction(code!(cvttss2si { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Cast(Ty::F64, Ty::F32, &Pattern::Reg),
+            cost: 4,
+            emit: |instructions, values, dst| {
+                let src = values[0].reg();
+                instructions.add_instruction(code!(cvtsd2ss { src }, { dst }))
+            },
+        },
+        Rule {
+            pattern: Pattern::Cast(Ty::F64, Ty::F32, &Pattern::FloatImm),
+            cost: 19,
+            emit: |instructions, values, dst| {
+                let src = load_float_scratch::<T>(instructions, values[0]);
+                instructions.add_instruction(code!(cvtsd2ss { src }, { dst }))
+            },
+        },
+    ];
+
+    const BRANCH_RULES: &'static [BranchRule] = &[
+        Rule {
+            pattern: Pattern::Reg,
+            cost: 8,
+            emit: |instructions, values, branch| {
+                let cond = values[0].reg();
+                if branch.else_is_next {
+                    instructions.add_instruction(code!(test { cond }, { cond }));
+                    jump_if(instructions, Condition::NotEqual, branch);
+                } else {
+                    instructions.add_instruction(code!(jz { cond }, { branch.else_label }));
+                    instructions.add_instruction(code!(jmp { branch.then_label }));
+                }
+            },
+        },
+        Rule {
+            pattern: Pattern::Imm,
+            cost: 5,
+            emit: |instructions, values, branch| {
+                let target = match values[0].imm() {
+                    0 => branch.else_label,
+                    _ => branch.then_label,
+                };
+                instructions.add_instruction(code!(jmp { target }))
+            },
+        },
+        Rule {
+            pattern: lt(&Pattern::Reg, &Pattern::Reg),
+            cost: 9,
+            emit: |instructions, values, branch| {
+                let (lhs, rhs) = (values[0].reg(), values[1].reg());
+                instructions.add_instruction(code!(cmp { lhs }, { rhs }));
+                jump_if(instructions, Condition::Less, branch);
+            },
+        },
+        Rule {
+            pattern: lt(&Pattern::Reg, &Pattern::Imm),
+            cost: 13,
+            emit: |instructions, values, branch| {
+                compare_imm(instructions, values[0].reg(), values[1]);
+                jump_if(instructions, Condition::Less, branch);
+            },
+        },
+        Rule {
+            pattern: lt(&Pattern::Imm, &Pattern::Reg),
+            cost: 13,
+            emit: |instructions, values, branch| {
+                compare_imm(instructions, values[1].reg(), values[0]);
+                jump_if(instructions, Condition::Greater, branch);
+            },
+        },
+        Rule {
+            pattern: lt(&Pattern::Imm, &Pattern::Imm),
+            cost: 5,
+            emit: |instructions, values, branch| {
+                let target = if values[0].imm() < values[1].imm() {
+                    branch.then_label
+                } else {
+                    branch.else_label
+                };
+                instructions.add_instruction(code!(jmp { target }))
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F64, &Pattern::Reg, &Pattern::Reg),
+            cost: 10,
+            emit: |instructions, values, branch| {
+                float_jump_if_less(
+                    instructions,
+                    Precision::Double,
+                    values[0].reg(),
+                    values[1].reg(),
+                    branch,
+                )
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F64, &Pattern::Reg, &Pattern::FloatImm),
+            cost: 25,
+            emit: |instructions, values, branch| {
+                let scratch = load_float_scratch::<T>(instructions, values[1]);
+                float_jump_if_less(
+                    instructions,
+                    Precision::Double,
+                    values[0].reg(),
+                    scratch,
+                    branch,
+                )
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F64, &Pattern::FloatImm, &Pattern::Reg),
+            cost: 25,
+            emit: |instructions, values, branch| {
+                let scratch = load_float_scratch::<T>(instructions, values[0]);
+                float_jump_if_less(
+                    instructions,
+                    Precision::Double,
+                    scratch,
+                    values[1].reg(),
+                    branch,
+                )
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F64, &Pattern::FloatImm, &Pattern::FloatImm),
+            cost: 5,
+            emit: |instructions, values, branch| {
+                let target = if float_less(Precision::Double, values[0], values[1]) {
+                    branch.then_label
+                } else {
+                    branch.else_label
+                };
+                instructions.add_instruction(code!(jmp { target }))
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F32, &Pattern::Reg, &Pattern::Reg),
+            cost: 10,
+            emit: |instructions, values, branch| {
+                float_jump_if_less(
+                    instructions,
+                    Precision::Single,
+                    values[0].reg(),
+                    values[1].reg(),
+                    branch,
+                )
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F32, &Pattern::Reg, &Pattern::FloatImm),
+            cost: 25,
+            emit: |instructions, values, branch| {
+                let scratch = load_float_scratch::<T>(instructions, values[1]);
+                float_jump_if_less(
+                    instructions,
+                    Precision::Single,
+                    values[0].reg(),
+                    scratch,
+                    branch,
+                )
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F32, &Pattern::FloatImm, &Pattern::Reg),
+            cost: 25,
+            emit: |instructions, values, branch| {
+                let scratch = load_float_scratch::<T>(instructions, values[0]);
+                float_jump_if_less(
+                    instructions,
+                    Precision::Single,
+                    scratch,
+                    values[1].reg(),
+                    branch,
+                )
+            },
+        },
+        Rule {
+            pattern: flt(Ty::F32, &Pattern::FloatImm, &Pattern::FloatImm),
+            cost: 5,
+            emit: |instructions, values, branch| {
+                let target = if float_less(Precision::Single, values[0], values[1]) {
+                    branch.then_label
+                } else {
+                    branch.else_label
+                };
+                instructions.add_instruction(code!(jmp { target }))
+            },
+        },
+    ];
+}
+
+fn load_imm(instructions: &mut Instructions<Register>, values: &[Value<Register>], dst: Register) {
+    instructions.add_instruction(code!(loadi { values[0].imm() }, { dst }))
+}
+
+/// Adds a constant to `dst`, loading it in the scratch register first if it does not fit in 32
+/// bits.
+fn add_imm(instructions: &mut Instructions<Register>, imm: Value<Register>, dst: Register) {
+    match Imm32::try_from(imm.imm()) {
+        Ok(imm) => instructions.add_instruction(code!(addi { imm }, { dst })),
+        Err(_) => {
+            let scratch = load_scratch(instructions, imm);
+            instructions.add_instruction(code!(add { scratch }, { dst }))
+        }
+    }
+}
+
+/// Compares `reg` with a constant, loading it in the scratch register first if it does not fit in
+/// 32 bits.
+fn compare_imm(instructions: &mut Instructions<Register>, reg: Register, imm: Value<Register>) {
+    match Imm32::try_from(imm.imm()) {
+        Ok(imm) => instructions.add_instruction(code!(cmpi { reg }, { imm })),
+        Err(_) => {
+            let scratch = load_scratch(instructions, imm);
+            instructions.add_instruction(code!(cmp { reg }, { scratch }))
+        }
+    }
+}
+
+/// Loads a constant in the scratch register for an instruction that only takes registers.
+fn load_scratch(instructions: &mut Instructions<Register>, imm: Value<Register>) -> Register {
+    let scratch = X86_64::SCRATCH_REGISTER;
+    instructions.add_instruction(code!(loadi { imm.imm() }, { scratch }));
+    scratch
+}
+
+/// Loads a float constant in the float scratch register of `T`, going through the general one.
+fn load_float_scratch<T: Target<Register = Register>>(
+    instructions: &mut Instructions<Register>,
+    imm: Value<Register>,
+) -> Register {
+    let scratch = load_scratch(instructions, imm);
+    let float_scratch = T::scratch_register(RegisterClass::Float);
+    instructions.add_instruction(code!(mov { scratch }, { float_scratch }));
+    float_scratch
+}
+
+/// Compares two float constants given as their bits.
+fn float_less(precision: Precision, lhs: Value<Register>, rhs: Value<Register>) -> bool {
+    let value = |imm: Value<Register>| match precision {
+        Precision::Single => f64::from(f32::from_bits(imm.imm() as u32)),
+        Precision::Double => f64::from_bits(imm.imm() as u64),
+    };
+    value(lhs) < value(rhs)
+}
+
+/// Jumps to the `THEN` block if `lhs < rhs`, which is false if either of them is NaN.
+fn float_jump_if_less(
+    instructions: &mut Instructions<Register>,
+    precision: Precision,
+    lhs: Register,
+    rhs: Register,
+    branch: Branch,
+) {
+    let compare = match precision {
+        Precision::Single => code!(ucomiss { rhs }, { lhs }),
+        Precision::Double => code!(ucomisd { rhs }, { lhs }),
+    };
+    instructions.add_instruction(compare);
+    jump_if(instructions, Condition::Above, branch);
+}
+
+/// Jumps to the `THEN` block if `cond` holds and to the `ELSE` block otherwise. The conditional
+/// jump is inverted so that it does not jump to the block lowered next.
+fn jump_if(instructions: &mut Instructions<Register>, cond: Condition, branch: Branch) {
+    let (cond, target, other) = if branch.else_is_next {
+        (cond, branch.then_label, branch.else_label)
+    } else {
+        (cond.negate(), branch.else_label, branch.then_label)
+    };
+    instructions.add_instruction(code!(jcc { cond }, { target }));
+    instructions.add_instruction(code!(jmp { other }));
+}
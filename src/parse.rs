@@ -0,0 +1,256 @@
+//! Tokenizer and parsing utilities shared by the textual representations of MIR and assembly.
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    /// A sequence of alphanumeric characters, underscores and dots not starting with a digit.
+    Ident(String),
+    /// A decimal or hexadecimal integer, optionally preceded by a minus sign.
+    Number(i64),
+    /// A decimal number with a fractional part, optionally preceded by a minus sign and followed
+    /// by a suffix such as `f32`. It is kept as written so that it can be parsed at the precision
+    /// of its type.
+    Float(String),
+    /// The `->` arrow.
+    Arrow,
+    Punct(char),
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Ident(ident) => write!(f, "`{ident}`"),
+            TokenKind::Number(number) => write!(f, "`{number}`"),
+            TokenKind::Float(float) => write!(f, "`{float}`"),
+            TokenKind::Arrow => write!(f, "`->`"),
+            TokenKind::Punct(punct) => write!(f, "`{punct}`"),
+        }
+    }
+}
+
+struct Token {
+    kind: TokenKind,
+    line: usize,
+    column: usize,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+
+    for (line_index, line) in src.lines().enumerate() {
+        let chars = line.char_indices().collect::<Vec<_>>();
+        let mut i = 0;
+
+        while let Some(&(start, c)) = chars.get(i) {
+            let line_number = line_index + 1;
+            let column = start + 1;
+            let next = chars.get(i + 1).map(|&(_, c)| c);
+
+            let kind = if c.is_whitespace() {
+                i += 1;
+                continue;
+            } else if c == ';' || (c == '/' && next == Some('/')) {
+                // Comments last until the end of the line.
+                break;
+            } else if c == '-' && next == Some('>') {
+                i += 2;
+                TokenKind::Arrow
+            } else if c.is_ascii_digit() || (c == '-' && next.is_some_and(|c| c.is_ascii_digit())) {
+                let len = chars[i + 1..]
+                    .iter()
+                    .take_while(|(_, c)| c.is_ascii_alphanumeric() || *c == '.')
+                    .count()
+                    + 1;
+                let end = chars.get(i + len).map_or(line.len(), |&(end, _)| end);
+                let text = &line[start..end];
+                i += len;
+
+                if text.contains('.') {
+                    tokens.push(Token {
+                        kind: TokenKind::Float(text.to_owned()),
+                        line: line_number,
+                        column,
+                    });
+                    continue;
+                }
+
+                let (negative, digits) = match text.strip_prefix('-') {
+                    Some(digits) => (true, digits),
+                    None => (false, text),
+                };
+
+                let number = match digits.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16).map(|number| number as i64),
+                    None => digits.parse::<i64>(),
+                }
+                .map_err(|_| ParseError {
+                    line: line_number,
+                    column,
+                    message: format!("invalid number `{text}`"),
+                })?;
+
+                TokenKind::Number(if negative { -number } else { number })
+            } else if c.is_alphabetic() || c == '_' || c == '.' {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '.')
+                    .count();
+                let end = chars.get(i + len).map_or(line.len(), |&(end, _)| end);
+                i += len;
+
+                TokenKind::Ident(line[start..end].to_owned())
+            } else if "(){}[]:,=+*<#".contains(c) {
+                i += 1;
+                TokenKind::Punct(c)
+            } else {
+                return Err(ParseError {
+                    line: line_number,
+                    column,
+                    message: format!("unexpected character `{c}`"),
+                });
+            };
+
+            tokens.push(Token {
+                kind,
+                line: line_number,
+                column,
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+pub(crate) struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    pub(crate) fn new(src: &str) -> Result<Self, ParseError> {
+        Ok(Self {
+            tokens: tokenize(src)?,
+            position: 0,
+        })
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.position == self.tokens.len()
+    }
+
+    pub(crate) fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position).map(|token| &token.kind)
+    }
+
+    /// Consumes the next token.
+    pub(crate) fn bump(&mut self) {
+        self.position += 1;
+    }
+
+    /// Returns the token after the next one without consuming any tokens.
+    pub(crate) fn peek_second(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position + 1).map(|token| &token.kind)
+    }
+
+    /// Returns an error located at the next token.
+    pub(crate) fn error(&self, message: impl Into<String>) -> ParseError {
+        let (line, column) = match self.tokens.get(self.position) {
+            Some(token) => (token.line, token.column),
+            None => self
+                .tokens
+                .last()
+                .map_or((1, 1), |token| (token.line, token.column)),
+        };
+
+        ParseError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> ParseError {
+        match self.peek() {
+            Some(found) => self.error(format!("expected {expected}, found {found}")),
+            None => self.error(format!("expected {expected}, found end of input")),
+        }
+    }
+
+    pub(crate) fn eat_punct(&mut self, punct: char) -> bool {
+        let found = self.peek() == Some(&TokenKind::Punct(punct));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    pub(crate) fn expect_punct(&mut self, punct: char) -> Result<(), ParseError> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{punct}`")))
+        }
+    }
+
+    pub(crate) fn expect_arrow(&mut self) -> Result<(), ParseError> {
+        if self.peek() == Some(&TokenKind::Arrow) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected("`->`"))
+        }
+    }
+
+    pub(crate) fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(TokenKind::Ident(ident)) if ident == keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    pub(crate) fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{keyword}`")))
+        }
+    }
+
+    pub(crate) fn expect_ident(&mut self, expected: &str) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(TokenKind::Ident(ident)) => {
+                let ident = ident.clone();
+                self.position += 1;
+                Ok(ident)
+            }
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    pub(crate) fn expect_number(&mut self, expected: &str) -> Result<i64, ParseError> {
+        match self.peek() {
+            Some(&TokenKind::Number(number)) => {
+                self.position += 1;
+                Ok(number)
+            }
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+}
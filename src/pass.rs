@@ -0,0 +1,158 @@
+//! A pass manager shared by the MIR and assembly optimization pipelines.
+//!
+//! Each IR registers its passes together with the lowest optimization level that runs them. The
+//! [`PassManager`] builds the pipeline for the requested level, applies the per-pass enable and
+//! disable flags, records how long each pass takes and dumps the IR after the selected passes.
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptLevel {
+    O0,
+    O1,
+    O2,
+}
+
+/// A transformation over a unit of IR of type `T`, such as a MIR function.
+pub trait Pass<T> {
+    /// The name used to refer to this pass in the command line.
+    fn name(&self) -> &'static str;
+
+    /// Runs the pass and returns whether the IR changed.
+    fn run(&self, ir: &mut T) -> bool;
+}
+
+/// IR that can be printed between passes.
+pub trait Dump {
+    /// Returns the textual representation of a unit called `name`.
+    fn dump(&self, name: &str) -> String;
+}
+
+/// The passes of an IR and the lowest optimization level that runs each one of them, in the order
+/// they are run.
+pub type Pipeline<T> = Vec<(OptLevel, Box<dyn Pass<T>>)>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassOptions {
+    pub opt_level: OptLevel,
+    /// Passes that are run even if the optimization level does not include them.
+    pub enabled: BTreeSet<String>,
+    /// Passes that are never run.
+    pub disabled: BTreeSet<String>,
+    /// Passes after which the IR is dumped, `all` dumps the IR after every pass.
+    pub dump_after: BTreeSet<String>,
+    /// Whether to record how long each pass takes.
+    pub time_passes: bool,
+}
+
+impl Default for PassOptions {
+    fn default() -> Self {
+        Self {
+            opt_level: OptLevel::O1,
+            enabled: BTreeSet::new(),
+            disabled: BTreeSet::new(),
+            dump_after: BTreeSet::new(),
+            time_passes: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassStatistics {
+    pub name: &'static str,
+    /// The number of times the pass has been run.
+    pub runs: usize,
+    /// The number of runs that changed the IR.
+    pub changes: usize,
+    /// The total time spent running the pass, only recorded if `time_passes` is set.
+    pub time: Duration,
+}
+
+struct ScheduledPass<T> {
+    pass: Box<dyn Pass<T>>,
+    enabled: bool,
+    dump: bool,
+    statistics: PassStatistics,
+}
+
+pub struct PassManager<T> {
+    passes: Vec<ScheduledPass<T>>,
+    time_passes: bool,
+    dumps: String,
+}
+
+impl<T: Dump> PassManager<T> {
+    pub fn new(pipeline: Pipeline<T>, options: &PassOptions) -> Self {
+        let passes = pipeline
+            .into_iter()
+            .map(|(level, pass)| {
+                let name = pass.name();
+
+                ScheduledPass {
+                    enabled: !options.disabled.contains(name)
+                        && (level <= options.opt_level || options.enabled.contains(name)),
+                    dump: options.dump_after.contains(name) || options.dump_after.contains("all"),
+                    statistics: PassStatistics {
+                        name,
+                        runs: 0,
+                        changes: 0,
+                        time: Duration::ZERO,
+                    },
+                    pass,
+                }
+            })
+            .collect();
+
+        Self {
+            passes,
+            time_passes: options.time_passes,
+            dumps: String::new(),
+        }
+    }
+
+    /// Returns whether a pass called `name` has been registered, even if it is not enabled.
+    pub fn contains(&self, name: &str) -> bool {
+        self.passes.iter().any(|pass| pass.statistics.name == name)
+    }
+
+    /// Returns the names of the passes that are run, in order.
+    pub fn pipeline(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.passes
+            .iter()
+            .filter(|pass| pass.enabled)
+            .map(|pass| pass.statistics.name)
+    }
+
+    /// Runs the enabled passes over the unit of IR called `name`.
+    pub fn run(&mut self, name: &str, ir: &mut T) {
+        for scheduled in self.passes.iter_mut().filter(|pass| pass.enabled) {
+            let start = self.time_passes.then(Instant::now);
+            let changed = scheduled.pass.run(ir);
+
+            let statistics = &mut scheduled.statistics;
+            if let Some(start) = start {
+                statistics.time += start.elapsed();
+            }
+            statistics.runs += 1;
+            statistics.changes += usize::from(changed);
+
+            if scheduled.dump {
+                self.dumps += &format!("; after {} on {name}\n", statistics.name);
+                self.dumps += &ir.dump(name);
+            }
+        }
+    }
+
+    /// Returns the statistics of the enabled passes.
+    pub fn statistics(&self) -> impl Iterator<Item = &PassStatistics> {
+        self.passes
+            .iter()
+            .filter(|pass| pass.enabled)
+            .map(|pass| &pass.statistics)
+    }
+
+    /// Returns the IR dumped since the last call to this method.
+    pub fn take_dumps(&mut self) -> String {
+        std::mem::take(&mut self.dumps)
+    }
+}
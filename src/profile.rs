@@ -0,0 +1,141 @@
+//! Profile-guided optimization with block counters.
+//!
+//! An instrumented build gives every MIR block a 64-bit counter in the [`COUNTERS_SYMBOL`] array,
+//! which is placed in `.bss` and incremented at the start of the block. The counters are assigned
+//! before any optimization, so they count how many times each block of the input ran even if the
+//! block is copied or merged into another one. When the program exits, the runtime in
+//! [`RUNTIME`] writes a profile with one line per block:
+//! ```text
+//! duplicate bb1 11
+//! ```
+//! A later compilation reads the profile back and stores the count of each block in its
+//! [`BlockProfile`](crate::mir::BlockProfile), where block placement, inlining and unrolling can
+//! find it. The lines of a block that appears several times are added up, so the profiles of
+//! several runs can be concatenated.
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::emit::{EmitError, ObjectEmitter, Visibility};
+use crate::mir::Module;
+use crate::parse::ParseError;
+use crate::target::Target;
+
+/// The array of counters, with one 64-bit counter per instrumented block.
+pub const COUNTERS_SYMBOL: &str = "__pijama_counters";
+/// The names of the counters, one `function block` per line and terminated by a NUL byte.
+pub const NAMES_SYMBOL: &str = "__pijama_counter_names";
+/// The NUL-terminated path where the profile is written by default.
+pub const PATH_SYMBOL: &str = "__pijama_profile_path";
+
+/// The C source of the runtime that writes the profile, which must be linked with instrumented
+/// objects.
+pub const RUNTIME: &str = include_str!("profile/runtime.c");
+
+/// The counters of an instrumented module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Instrumentation {
+    /// The function and block counted by each counter.
+    names: Vec<String>,
+}
+
+impl Instrumentation {
+    /// Gives a counter to every block of `module`.
+    pub fn new(module: &mut Module) -> Self {
+        let mut names = Vec::new();
+
+        for (name, func) in &mut module.functions {
+            for (bb, bb_data) in &mut func.basic_blocks {
+                bb_data.profile.counters = vec![names.len()];
+                names.push(format!("{name} {bb}"));
+            }
+        }
+
+        Self { names }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Adds the counters and the data read by the runtime, which writes the profile to `path`
+    /// unless it is overridden when the program runs.
+    pub fn emit<T: Target>(
+        &self,
+        emitter: &mut ObjectEmitter<T>,
+        path: &Path,
+    ) -> Result<(), EmitError> {
+        let mut names = self
+            .names
+            .iter()
+            .flat_map(|name| [name.as_bytes(), b"\n"])
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        names.push(0);
+
+        let mut path = path.to_string_lossy().into_owned().into_bytes();
+        path.push(0);
+
+        emitter.add_bss(COUNTERS_SYMBOL, Visibility::Hidden, 8 * self.len())?;
+        emitter.add_data(NAMES_SYMBOL, Visibility::Hidden, &names)?;
+        emitter.add_data(PATH_SYMBOL, Visibility::Hidden, &path)
+    }
+}
+
+/// The number of times each block ran, by function and by block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    counts: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl Profile {
+    pub fn parse(src: &str) -> Result<Self, ParseError> {
+        let mut counts = BTreeMap::<String, BTreeMap<String, u64>>::new();
+
+        for (index, line) in src.lines().enumerate() {
+            let error = |message: &str| ParseError {
+                line: index + 1,
+                column: 1,
+                message: message.to_owned(),
+            };
+
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let [function, bb, count] = fields[..] else {
+                if fields.is_empty() {
+                    continue;
+                }
+                return Err(error("expected a function, a block and a count"));
+            };
+            let count = count
+                .parse::<u64>()
+                .map_err(|_| error(&format!("invalid count `{count}`")))?;
+
+            let total = counts
+                .entry(function.to_owned())
+                .or_default()
+                .entry(bb.to_owned())
+                .or_default();
+            *total = total.saturating_add(count);
+        }
+
+        Ok(Self { counts })
+    }
+
+    /// Returns how many times block `bb` of function `function` ran.
+    pub fn count(&self, function: &str, bb: &str) -> Option<u64> {
+        self.counts.get(function)?.get(bb).copied()
+    }
+
+    /// Stores the count of every block of `module` that appears in the profile.
+    pub fn apply(&self, module: &mut Module) {
+        for (name, func) in &mut module.functions {
+            for (bb, bb_data) in &mut func.basic_blocks {
+                bb_data.profile.count = self.count(name, &bb.to_string());
+            }
+        }
+    }
+}
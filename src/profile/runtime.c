@@ -0,0 +1,38 @@
+// Writes the block counters of a program instrumented with `--profile-generate` when it exits.
+//
+// Every counter has a line in `__pijama_counter_names` with the function and the block it counts,
+// and the profile has a line with that name followed by the count. The profile is written to the
+// path given by the `PIJAMA_PROFILE` environment variable or to the one given to the compiler.
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+extern unsigned long long __pijama_counters[];
+extern const char __pijama_counter_names[];
+extern const char __pijama_profile_path[];
+
+static void pijama_write_profile(void) {
+    const char *path = getenv("PIJAMA_PROFILE");
+    if (path == NULL) {
+        path = __pijama_profile_path;
+    }
+
+    FILE *file = fopen(path, "w");
+    if (file == NULL) {
+        perror(path);
+        return;
+    }
+
+    const char *name = __pijama_counter_names;
+    for (size_t i = 0; *name != '\0'; i++) {
+        const char *end = strchr(name, '\n');
+        fprintf(file, "%.*s %llu\n", (int)(end - name), name, __pijama_counters[i]);
+        name = end + 1;
+    }
+
+    fclose(file);
+}
+
+__attribute__((constructor)) static void pijama_register_profile(void) {
+    atexit(pijama_write_profile);
+}
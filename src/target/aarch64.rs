@@ -0,0 +1,108 @@
+use object::{elf, Architecture, RelocationEncoding};
+
+use crate::asm::aarch64::{self, Register};
+use crate::asm::{AssemblerError, Instructions, Relocation, RelocationKind};
+use crate::emit::{Arch, Format};
+use crate::mir_lowering::aarch64::RULES;
+use crate::mir_lowering::isel::Rules;
+use crate::target::{RegisterClass, Target};
+
+/// AArch64 following the AAPCS64 calling convention, emitted as ELF only.
+///
+/// The locals that are not arguments live in the temporary registers `x9` to `x14` and in the
+/// argument registers that the function does not use. `x15` and `x16` are kept for the lowering
+/// and `x17` for the assembler. Every push takes 16 bytes so the stack stays aligned.
+pub struct Aarch64;
+
+impl Target for Aarch64 {
+    type Register = Register;
+
+    const ARCH: Arch = Arch::Aarch64;
+    const ARCHITECTURE: Architecture = Architecture::Aarch64;
+    const FORMATS: &'static [Format] = &[Format::Elf];
+
+    const ALLOCATABLE_REGISTERS: &'static [Register] = &[
+        Register::X9,
+        Register::X10,
+        Register::X11,
+        Register::X12,
+        Register::X13,
+        Register::X14,
+        Register::X0,
+        Register::X1,
+        Register::X2,
+        Register::X3,
+        Register::X4,
+        Register::X5,
+        Register::X6,
+        Register::X7,
+    ];
+    const ARGUMENT_REGISTERS: &'static [Register] = &[
+        Register::X0,
+        Register::X1,
+        Register::X2,
+        Register::X3,
+        Register::X4,
+        Register::X5,
+        Register::X6,
+        Register::X7,
+    ];
+    const RETURN_REGISTER: Register = Register::X0;
+    const CALLEE_SAVED_REGISTERS: &'static [Register] = &[
+        Register::X19,
+        Register::X20,
+        Register::X21,
+        Register::X22,
+        Register::X23,
+        Register::X24,
+        Register::X25,
+        Register::X26,
+        Register::X27,
+        Register::X28,
+        Register::X29,
+    ];
+    const LINK_REGISTER: Option<Register> = Some(Register::X30);
+    const STACK_POINTER: Register = Register::Sp;
+    const STACK_ALIGNMENT: i32 = 16;
+    const PUSH_SIZE: i32 = 16;
+
+    const SCRATCH_REGISTER: Register = Register::X16;
+    const COUNTER_REGISTER: Register = Register::X15;
+
+    fn register_class(_reg: Register) -> RegisterClass {
+        RegisterClass::General
+    }
+
+    fn rules() -> &'static Rules<Instructions<Register>, Register> {
+        &RULES
+    }
+
+    fn assemble(
+        instructions: Instructions<Register>,
+        buf: &mut Vec<u8>,
+    ) -> Result<Vec<Relocation>, AssemblerError> {
+        aarch64::assemble(instructions, buf)
+    }
+
+    /// Every relocation patches the immediate of a single instruction.
+    fn relocation(
+        format: Format,
+        kind: RelocationKind,
+    ) -> (object::RelocationKind, RelocationEncoding) {
+        assert_eq!(format, Format::Elf, "AArch64 is only emitted as ELF");
+
+        let r_type = match kind {
+            RelocationKind::Branch => elf::R_AARCH64_CALL26,
+            RelocationKind::PageRelative => elf::R_AARCH64_ADR_PREL_PG_HI21,
+            RelocationKind::PageOffset => elf::R_AARCH64_ADD_ABS_LO12_NC,
+            RelocationKind::GotPageRelative => elf::R_AARCH64_ADR_GOT_PAGE,
+            RelocationKind::GotPageOffset => elf::R_AARCH64_LD64_GOT_LO12_NC,
+            _ => panic!("{kind:?} relocations are not used on AArch64"),
+        };
+
+        (
+            object::RelocationKind::Elf(r_type),
+            RelocationEncoding::Generic,
+        )
+    }
+}
@@ -0,0 +1,153 @@
+//! The architectures that code can be generated for.
+//!
+//! A [`Target`] describes everything about an architecture that lowering, instruction selection,
+//! assembling and emission depend on: its registers and calling convention, its instruction
+//! selection rules, its assembler and how its relocations are written in object files. Those
+//! stages are generic over the target, so adding a backend does not require forking them.
+mod aarch64;
+mod riscv64;
+mod x86_64;
+
+use std::fmt;
+use std::str::FromStr;
+
+use object::{Architecture, RelocationEncoding};
+
+use crate::asm::{AssemblerError, Instructions, Relocation, RelocationKind};
+use crate::emit::{Arch, Format};
+use crate::mir::Ty;
+use crate::mir_lowering::isel::Rules;
+
+pub use aarch64::Aarch64;
+pub use riscv64::Riscv64;
+pub use x86_64::{X86_64Windows, X86_64};
+
+/// The kinds of values that a register can hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterClass {
+    /// Integers, booleans and addresses.
+    General,
+    /// Floats of either precision.
+    Float,
+}
+
+impl RegisterClass {
+    /// Returns the class of the registers that hold values of type `ty`.
+    pub fn of(ty: &Ty) -> Self {
+        match ty {
+            Ty::Int | Ty::Bool => Self::General,
+            Ty::F32 | Ty::F64 => Self::Float,
+        }
+    }
+}
+
+/// An architecture together with its calling convention and object file formats.
+pub trait Target {
+    /// The registers that the instructions of the target operate on.
+    type Register: Copy + Ord + fmt::Debug + fmt::Display + FromStr + 'static;
+
+    /// The architecture selected with `--target`.
+    const ARCH: Arch;
+    /// The architecture written in the header of the object files.
+    const ARCHITECTURE: Architecture;
+    /// The object file formats that the code can be emitted as.
+    const FORMATS: &'static [Format];
+
+    /// The registers that locals can be assigned to, in order of preference. None of them can be
+    /// the scratch or the counter register.
+    const ALLOCATABLE_REGISTERS: &'static [Self::Register];
+    /// The registers that the arguments of a call are passed in, in order, see
+    /// [`Target::argument_registers`].
+    const ARGUMENT_REGISTERS: &'static [Self::Register];
+    /// Whether the `n`th argument is passed in the `n`th argument register of its class, leaving
+    /// the registers of the other class at the same position unused, instead of the next free
+    /// argument register of its class.
+    const POSITIONAL_ARGUMENTS: bool = false;
+    /// The register that the return value is passed in.
+    const RETURN_REGISTER: Self::Register;
+    /// The register that a float return value is passed in, or `None` if the target has no float
+    /// registers.
+    const FLOAT_RETURN_REGISTER: Option<Self::Register> = None;
+    /// The registers that a function must preserve for its caller.
+    const CALLEE_SAVED_REGISTERS: &'static [Self::Register];
+    /// The register that receives the return address of a call, or `None` if the call pushes the
+    /// return address on the stack.
+    const LINK_REGISTER: Option<Self::Register>;
+    const STACK_POINTER: Self::Register;
+    /// The alignment in bytes of the stack pointer when a function is called.
+    const STACK_ALIGNMENT: i32;
+    /// The number of bytes that a push moves the stack pointer by.
+    const PUSH_SIZE: i32;
+    /// The number of bytes that a caller reserves right below the return address, which the
+    /// callee can use to spill its register arguments.
+    const SHADOW_SPACE: i32 = 0;
+
+    /// A register that is never assigned to a local, used to materialize immediates for
+    /// instructions that only take registers.
+    const SCRATCH_REGISTER: Self::Register;
+    /// A float register that is never assigned to a local, used to materialize float constants.
+    const FLOAT_SCRATCH_REGISTER: Option<Self::Register> = None;
+    /// A register that is never assigned to a local, used to hold the value of a profile counter.
+    const COUNTER_REGISTER: Self::Register;
+
+    fn register_class(reg: Self::Register) -> RegisterClass;
+
+    /// Returns the register that a return value of `class` is passed in.
+    fn return_register(class: RegisterClass) -> Self::Register {
+        match class {
+            RegisterClass::General => Self::RETURN_REGISTER,
+            RegisterClass::Float => Self::FLOAT_RETURN_REGISTER
+                .unwrap_or_else(|| panic!("{:?} has no float registers", Self::ARCH)),
+        }
+    }
+
+    /// Returns the registers that arguments of the given classes are passed in, or `None` if there
+    /// are not enough argument registers.
+    fn argument_registers(
+        classes: impl IntoIterator<Item = RegisterClass>,
+    ) -> Option<Vec<Self::Register>> {
+        let mut registers = Vec::new();
+
+        for (position, class) in classes.into_iter().enumerate() {
+            let mut candidates = Self::ARGUMENT_REGISTERS
+                .iter()
+                .copied()
+                .filter(|reg| Self::register_class(*reg) == class);
+            let reg = if Self::POSITIONAL_ARGUMENTS {
+                candidates.nth(position)
+            } else {
+                candidates.find(|reg| !registers.contains(reg))
+            }?;
+            registers.push(reg);
+        }
+
+        Some(registers)
+    }
+
+    /// Returns the scratch register of `class`.
+    fn scratch_register(class: RegisterClass) -> Self::Register {
+        match class {
+            RegisterClass::General => Self::SCRATCH_REGISTER,
+            RegisterClass::Float => Self::FLOAT_SCRATCH_REGISTER
+                .unwrap_or_else(|| panic!("{:?} has no float registers", Self::ARCH)),
+        }
+    }
+
+    /// Returns the rules used to select the instructions that compute expression trees.
+    fn rules() -> &'static Rules<Instructions<Self::Register>, Self::Register>;
+
+    /// Assembles `instructions` into `buf`.
+    ///
+    /// Returns the relocations that must be applied to `buf` to resolve the symbols used by the
+    /// instructions.
+    fn assemble(
+        instructions: Instructions<Self::Register>,
+        buf: &mut Vec<u8>,
+    ) -> Result<Vec<Relocation>, AssemblerError>;
+
+    /// Returns the relocation kind and encoding used by `format` for a relocation of `kind`.
+    fn relocation(
+        format: Format,
+        kind: RelocationKind,
+    ) -> (object::RelocationKind, RelocationEncoding);
+}
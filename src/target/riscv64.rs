@@ -0,0 +1,107 @@
+use object::{elf, Architecture, RelocationEncoding};
+
+use crate::asm::riscv64::{self, Register};
+use crate::asm::{AssemblerError, Instructions, Relocation, RelocationKind};
+use crate::emit::{Arch, Format};
+use crate::mir_lowering::isel::Rules;
+use crate::mir_lowering::riscv64::RULES;
+use crate::target::{RegisterClass, Target};
+
+/// RISC-V RV64IM following the LP64 calling convention, emitted as ELF only.
+///
+/// The locals that are not arguments live in the temporary registers `t0` to `t3` and in the
+/// argument registers that the function does not use. `t4` and `t5` are kept for the lowering
+/// and `t6` for the assembler. Every push takes 16 bytes so the stack stays aligned.
+pub struct Riscv64;
+
+impl Target for Riscv64 {
+    type Register = Register;
+
+    const ARCH: Arch = Arch::Riscv64;
+    const ARCHITECTURE: Architecture = Architecture::Riscv64;
+    const FORMATS: &'static [Format] = &[Format::Elf];
+
+    const ALLOCATABLE_REGISTERS: &'static [Register] = &[
+        Register::T0,
+        Register::T1,
+        Register::T2,
+        Register::T3,
+        Register::A0,
+        Register::A1,
+        Register::A2,
+        Register::A3,
+        Register::A4,
+        Register::A5,
+        Register::A6,
+        Register::A7,
+    ];
+    const ARGUMENT_REGISTERS: &'static [Register] = &[
+        Register::A0,
+        Register::A1,
+        Register::A2,
+        Register::A3,
+        Register::A4,
+        Register::A5,
+        Register::A6,
+        Register::A7,
+    ];
+    const RETURN_REGISTER: Register = Register::A0;
+    const CALLEE_SAVED_REGISTERS: &'static [Register] = &[
+        Register::S0,
+        Register::S1,
+        Register::S2,
+        Register::S3,
+        Register::S4,
+        Register::S5,
+        Register::S6,
+        Register::S7,
+        Register::S8,
+        Register::S9,
+        Register::S10,
+        Register::S11,
+    ];
+    const LINK_REGISTER: Option<Register> = Some(Register::Ra);
+    const STACK_POINTER: Register = Register::Sp;
+    const STACK_ALIGNMENT: i32 = 16;
+    const PUSH_SIZE: i32 = 16;
+
+    const SCRATCH_REGISTER: Register = Register::T5;
+    const COUNTER_REGISTER: Register = Register::T4;
+
+    fn register_class(_reg: Register) -> RegisterClass {
+        RegisterClass::General
+    }
+
+    fn rules() -> &'static Rules<Instructions<Register>, Register> {
+        &RULES
+    }
+
+    fn assemble(
+        instructions: Instructions<Register>,
+        buf: &mut Vec<u8>,
+    ) -> Result<Vec<Relocation>, AssemblerError> {
+        riscv64::assemble(instructions, buf)
+    }
+
+    /// A relocation patches the immediate of a single instruction, except `R_RISCV_CALL_PLT`,
+    /// which patches an `auipc` and the `jalr` after it.
+    fn relocation(
+        format: Format,
+        kind: RelocationKind,
+    ) -> (object::RelocationKind, RelocationEncoding) {
+        assert_eq!(format, Format::Elf, "RISC-V is only emitted as ELF");
+
+        let r_type = match kind {
+            RelocationKind::Branch => elf::R_RISCV_CALL_PLT,
+            RelocationKind::PcRelativeHigh => elf::R_RISCV_PCREL_HI20,
+            RelocationKind::GotPcRelativeHigh => elf::R_RISCV_GOT_HI20,
+            RelocationKind::PcRelativeLow => elf::R_RISCV_PCREL_LO12_I,
+            _ => panic!("{kind:?} relocations are not used on RISC-V"),
+        };
+
+        (
+            object::RelocationKind::Elf(r_type),
+            RelocationEncoding::Generic,
+        )
+    }
+}
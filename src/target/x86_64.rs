@@ -0,0 +1,227 @@
+use object::{elf, Architecture, RelocationEncoding};
+
+use crate::asm::x86_64::{self, Register};
+use crate::asm::{AssemblerError, Instructions, Relocation, RelocationKind};
+use crate::emit::{Arch, Format};
+use crate::mir_lowering::isel::Rules;
+use crate::mir_lowering::x86_64::X86_64Rules;
+use crate::target::{RegisterClass, Target};
+
+/// x86-64 following the System V calling convention.
+///
+/// The locals are assigned in the order of the System V argument registers after `rax`, so `_0`
+/// is already in the return register and every argument stays in the register it is passed in.
+/// Floats are passed and returned in `xmm0` to `xmm7`, none of the SSE registers is callee-saved.
+pub struct X86_64;
+
+impl Target for X86_64 {
+    type Register = Register;
+
+    const ARCH: Arch = Arch::X86_64;
+    const ARCHITECTURE: Architecture = Architecture::X86_64;
+    const FORMATS: &'static [Format] = &[Format::Elf, Format::MachO];
+
+    const ALLOCATABLE_REGISTERS: &'static [Register] = &[
+        Register::Ax,
+        Register::Di,
+        Register::Si,
+        Register::Dx,
+        Register::Cx,
+        Register::Xmm0,
+        Register::Xmm1,
+        Register::Xmm2,
+        Register::Xmm3,
+        Register::Xmm4,
+        Register::Xmm5,
+        Register::Xmm6,
+        Register::Xmm7,
+    ];
+    const ARGUMENT_REGISTERS: &'static [Register] = &[
+        Register::Di,
+        Register::Si,
+        Register::Dx,
+        Register::Xmm0,
+        Register::Xmm1,
+        Register::Xmm2,
+        Register::Xmm3,
+        Register::Xmm4,
+        Register::Xmm5,
+        Register::Xmm6,
+        Register::Xmm7,
+    ];
+    const RETURN_REGISTER: Register = Register::Ax;
+    const FLOAT_RETURN_REGISTER: Option<Register> = Some(Register::Xmm0);
+    const CALLEE_SAVED_REGISTERS: &'static [Register] = &[
+        Register::Bx,
+        Register::Bp,
+        Register::R12,
+        Register::R13,
+        Register::R14,
+        Register::R15,
+    ];
+    const LINK_REGISTER: Option<Register> = None;
+    const STACK_POINTER: Register = Register::Sp;
+    const STACK_ALIGNMENT: i32 = 16;
+    const PUSH_SIZE: i32 = 8;
+
+    const SCRATCH_REGISTER: Register = Register::R11;
+    const FLOAT_SCRATCH_REGISTER: Option<Register> = Some(Register::Xmm15);
+    const COUNTER_REGISTER: Register = Register::R10;
+
+    fn register_class(reg: Register) -> RegisterClass {
+        if reg.is_xmm() {
+            RegisterClass::Float
+        } else {
+            RegisterClass::General
+        }
+    }
+
+    fn rules() -> &'static Rules<Instructions<Register>, Register> {
+        X86_64Rules::<Self>::RULES
+    }
+
+    fn assemble(
+        instructions: Instructions<Register>,
+        buf: &mut Vec<u8>,
+    ) -> Result<Vec<Relocation>, AssemblerError> {
+        x86_64::assemble(instructions, buf)
+    }
+
+    /// Calls go through the PLT on ELF, use `X86_64_RELOC_BRANCH` on Mach-O and
+    /// `IMAGE_REL_AMD64_REL32` on COFF. Global offset table accesses use
+    /// `R_X86_64_REX_GOTPCRELX` on ELF so the linker can relax them into a `lea` and
+    /// `X86_64_RELOC_GOT_LOAD` on Mach-O.
+    fn relocation(
+        format: Format,
+        kind: RelocationKind,
+    ) -> (object::RelocationKind, RelocationEncoding) {
+        match (format, kind) {
+            (
+                _,
+                RelocationKind::PageRelative
+                | RelocationKind::PageOffset
+                | RelocationKind::GotPageRelative
+                | RelocationKind::GotPageOffset
+                | RelocationKind::PcRelativeHigh
+                | RelocationKind::GotPcRelativeHigh
+                | RelocationKind::PcRelativeLow,
+            ) => panic!("{kind:?} relocations are not used on x86-64"),
+            (Format::Elf | Format::MachO, RelocationKind::Branch) => (
+                object::RelocationKind::PltRelative,
+                RelocationEncoding::X86Branch,
+            ),
+            (Format::Elf | Format::MachO, RelocationKind::Relative) => (
+                object::RelocationKind::Relative,
+                RelocationEncoding::X86RipRelative,
+            ),
+            (Format::Elf, RelocationKind::GotRelative) => (
+                object::RelocationKind::Elf(elf::R_X86_64_REX_GOTPCRELX),
+                RelocationEncoding::Generic,
+            ),
+            (Format::MachO, RelocationKind::GotRelative) => (
+                object::RelocationKind::GotRelative,
+                RelocationEncoding::X86RipRelativeMovq,
+            ),
+            // COFF has no global offset table, the address of an imported symbol is read from
+            // its `__imp_` pointer instead.
+            (Format::Coff, _) => (
+                object::RelocationKind::Relative,
+                RelocationEncoding::Generic,
+            ),
+        }
+    }
+}
+
+/// x86-64 following the Windows x64 calling convention, used for COFF objects.
+///
+/// The first four arguments are passed by position in `rcx`, `rdx`, `r8` and `r9`, or in `xmm0`
+/// to `xmm3` for floats, and the caller reserves 32 bytes of shadow space for them above the
+/// return address. `rdi`, `rsi` and `xmm6` to `xmm15` are callee-saved, so the locals only use
+/// the volatile registers and `xmm5` is the float scratch register. Everything else is shared
+/// with [`X86_64`].
+pub struct X86_64Windows;
+
+impl Target for X86_64Windows {
+    type Register = Register;
+
+    const ARCH: Arch = Arch::X86_64;
+    const ARCHITECTURE: Architecture = Architecture::X86_64;
+    const FORMATS: &'static [Format] = &[Format::Coff];
+
+    const ALLOCATABLE_REGISTERS: &'static [Register] = &[
+        Register::Ax,
+        Register::Cx,
+        Register::Dx,
+        Register::R8,
+        Register::R9,
+        Register::Xmm0,
+        Register::Xmm1,
+        Register::Xmm2,
+        Register::Xmm3,
+        Register::Xmm4,
+    ];
+    const ARGUMENT_REGISTERS: &'static [Register] = &[
+        Register::Cx,
+        Register::Dx,
+        Register::R8,
+        Register::R9,
+        Register::Xmm0,
+        Register::Xmm1,
+        Register::Xmm2,
+        Register::Xmm3,
+    ];
+    const POSITIONAL_ARGUMENTS: bool = true;
+    const RETURN_REGISTER: Register = Register::Ax;
+    const FLOAT_RETURN_REGISTER: Option<Register> = Some(Register::Xmm0);
+    const CALLEE_SAVED_REGISTERS: &'static [Register] = &[
+        Register::Bx,
+        Register::Bp,
+        Register::Di,
+        Register::Si,
+        Register::R12,
+        Register::R13,
+        Register::R14,
+        Register::R15,
+        Register::Xmm6,
+        Register::Xmm7,
+        Register::Xmm8,
+        Register::Xmm9,
+        Register::Xmm10,
+        Register::Xmm11,
+        Register::Xmm12,
+        Register::Xmm13,
+        Register::Xmm14,
+        Register::Xmm15,
+    ];
+    const LINK_REGISTER: Option<Register> = None;
+    const STACK_POINTER: Register = Register::Sp;
+    const STACK_ALIGNMENT: i32 = 16;
+    const PUSH_SIZE: i32 = 8;
+    const SHADOW_SPACE: i32 = 32;
+
+    const SCRATCH_REGISTER: Register = X86_64::SCRATCH_REGISTER;
+    const FLOAT_SCRATCH_REGISTER: Option<Register> = Some(Register::Xmm5);
+    const COUNTER_REGISTER: Register = X86_64::COUNTER_REGISTER;
+
+    fn register_class(reg: Register) -> RegisterClass {
+        X86_64::register_class(reg)
+    }
+
+    fn rules() -> &'static Rules<Instructions<Register>, Register> {
+        X86_64Rules::<Self>::RULES
+    }
+
+    fn assemble(
+        instructions: Instructions<Register>,
+        buf: &mut Vec<u8>,
+    ) -> Result<Vec<Relocation>, AssemblerError> {
+        X86_64::assemble(instructions, buf)
+    }
+
+    fn relocation(
+        format: Format,
+        kind: RelocationKind,
+    ) -> (object::RelocationKind, RelocationEncoding) {
+        X86_64::relocation(format, kind)
+    }
+}
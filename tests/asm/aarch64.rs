@@ -0,0 +1,343 @@
+//! The expected encodings are the ones produced by `llvm-mc -triple=aarch64 -show-encoding`.
+use pijama::{
+    asm::{
+        aarch64::{assemble, Register},
+        AssemblerError, Condition, Instructions, Relocation, RelocationKind,
+    },
+    code,
+};
+
+/// Assembles the instructions added by `f` and checks that they are encoded as `expected`.
+fn check(f: impl FnOnce(&mut Instructions<Register>), expected: &[u32]) -> Vec<Relocation> {
+    let mut instructions = Instructions::new();
+    f(&mut instructions);
+
+    let mut bytes = Vec::new();
+    let relocations = assemble(instructions, &mut bytes).unwrap();
+
+    let found = bytes
+        .chunks(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found
+            .iter()
+            .map(|word| format!("{word:08x}"))
+            .collect::<Vec<_>>(),
+        expected
+            .iter()
+            .map(|word| format!("{word:08x}"))
+            .collect::<Vec<_>>()
+    );
+
+    relocations
+}
+
+#[test]
+fn loadi() {
+    check(
+        |instructions| {
+            instructions.add_instruction(code!(loadi { 0 }, { Register::X3 }));
+            instructions.add_instruction(code!(loadi { 0x1234 }, { Register::X3 }));
+            instructions.add_instruction(code!(loadi { 0xdeadbeef }, { Register::X3 }));
+            instructions.add_instruction(code!(loadi { -1 }, { Register::X3 }));
+            instructions.add_instruction(code!(loadi { -17185 }, { Register::X3 }));
+            instructions.add_instruction(code!(loadi { 0xdeadbeefdeadbeefu64 as i64 }, {
+                Register::X5
+            }));
+        },
+        &[
+            // mov x3,0x0
+            0xd2800003, //
+            // mov x3,0x1234
+            0xd2824683, //
+            // mov x3,0xbeef; movk x3,0xdead,lsl 16
+            0xd297dde3, 0xf2bbd5a3, //
+            // mov x3,-0x1
+            0x92800003, //
+            // mov x3,-0x4321
+            0x92886403, //
+            // mov x5,0xbeef; movk x5,0xdead,lsl 16; movk x5,0xbeef,lsl 32; movk x5,0xdead,lsl 48
+            0xd297dde5, 0xf2bbd5a5, 0xf2d7dde5, 0xf2fbd5a5,
+        ],
+    );
+}
+
+#[test]
+fn load_and_store() {
+    check(
+        |instructions| {
+            instructions.add_instruction(code!(load { Register::X2 } + { 8 }, { Register::X1 }));
+            instructions.add_instruction(code!(load { Register::Sp } + { 32760 }, {
+                Register::X1
+            }));
+            instructions.add_instruction(code!(load { Register::X2 } + { -8 }, { Register::X1 }));
+            instructions.add_instruction(code!(load { Register::X2 } + { 4 }, { Register::X1 }));
+            instructions.add_instruction(code!(load { Register::X2 } + { 0x8000 }, {
+                Register::X1
+            }));
+            instructions.add_instruction(code!(store { Register::X2 }, { Register::X1 } + { 16 }));
+            instructions
+                .add_instruction(code!(store { Register::X29 }, { Register::X1 } + { -16 }));
+            instructions.add_instruction(code!(store { Register::X2 }, { Register::X1 } + {
+                0x8000
+            }));
+        },
+        &[
+            // ldr x1,[x2,8]
+            0xf9400441, //
+            // ldr x1,[sp,32760]
+            0xf97fffe1, //
+            // ldur x1,[x2,-8]
+            0xf85f8041, //
+            // ldur x1,[x2,4]
+            0xf8404041, //
+            // mov x17,0x8000; ldr x1,[x2,x17]
+            0xd2900011, 0xf8716841, //
+            // str x1,[x2,16]
+            0xf9000841, //
+            // stur x1,[x29,-16]
+            0xf81f03a1, //
+            // mov x17,0x8000; str x1,[x2,x17]
+            0xd2900011, 0xf8316841,
+        ],
+    );
+}
+
+#[test]
+fn symbols() {
+    let relocations = check(
+        |instructions| {
+            let sym = instructions.add_symbol("sym");
+            instructions.add_instruction(code!(lea sym { sym }, { Register::X4 }));
+            instructions.add_instruction(code!(load got { sym }, { Register::X4 }));
+            instructions.add_instruction(code!(call sym { sym }));
+        },
+        &[
+            // adrp x4,sym; add x4,x4,:lo12:sym
+            0x90000004, 0x91000084, //
+            // adrp x4,:got:sym; ldr x4,[x4,:got_lo12:sym]
+            0x90000004, 0xf9400084, //
+            // bl sym
+            0x94000000,
+        ],
+    );
+
+    let kinds = relocations
+        .iter()
+        .map(|relocation| (relocation.offset, relocation.kind, relocation.addend))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            (0, RelocationKind::PageRelative, 0),
+            (4, RelocationKind::PageOffset, 0),
+            (8, RelocationKind::GotPageRelative, 0),
+            (12, RelocationKind::GotPageOffset, 0),
+            (16, RelocationKind::Branch, 0),
+        ]
+    );
+}
+
+#[test]
+fn registers() {
+    check(
+        |instructions| {
+            instructions.add_instruction(code!(lea { Register::X1 } + { Register::X2 }, {
+                Register::X0
+            }));
+            instructions.add_instruction(code!(lea { Register::X2 } + { Register::Sp }, {
+                Register::X0
+            }));
+            instructions.add_instruction(code!(add { Register::X2 }, { Register::Sp }));
+            instructions.add_instruction(code!(mov { Register::X1 }, { Register::X0 }));
+            instructions.add_instruction(code!(mov { Register::Sp }, { Register::X0 }));
+            instructions.add_instruction(code!(mov { Register::X0 }, { Register::Sp }));
+            instructions.add_instruction(code!(push { Register::X30 }));
+            instructions.add_instruction(code!(pop { Register::X30 }));
+        },
+        &[
+            // add x0,x1,x2
+            0x8b020020, //
+            // add x0,sp,x2
+            0x8b2263e0, //
+            // add sp,sp,x2
+            0x8b2263ff, //
+            // mov x0,x1
+            0xaa0103e0, //
+            // mov x0,sp
+            0x910003e0, //
+            // mov sp,x0
+            0x9100001f, //
+            // str x30,[sp,-16]!
+            0xf81f0ffe, //
+            // ldr x30,[sp],16
+            0xf84107fe,
+        ],
+    );
+}
+
+#[test]
+fn addi() {
+    check(
+        |instructions| {
+            instructions.add_instruction(code!(addi { 4095 }, { Register::X3 }));
+            instructions.add_instruction(code!(addi { -1 }, { Register::X3 }));
+            instructions.add_instruction(code!(addi { 16 }, { Register::Sp }));
+            instructions.add_instruction(code!(addi { 0x1000 }, { Register::X3 }));
+            instructions.add_instruction(code!(addi { -0x2000 }, { Register::X3 }));
+            instructions.add_instruction(code!(addi { 0x1234 }, { Register::X3 }));
+        },
+        &[
+            // add x3,x3,4095
+            0x913ffc63, //
+            // sub x3,x3,1
+            0xd1000463, //
+            // add sp,sp,16
+            0x910043ff, //
+            // add x3,x3,1,lsl 12
+            0x91400463, //
+            // sub x3,x3,2,lsl 12
+            0xd1400863, //
+            // mov x17,0x1234; add x3,x3,x17
+            0xd2824691, 0x8b110063,
+        ],
+    );
+}
+
+#[test]
+fn compare() {
+    check(
+        |instructions| {
+            instructions.add_instruction(code!(cmp { Register::X1 }, { Register::X2 }));
+            instructions.add_instruction(code!(cmp { Register::Sp }, { Register::X2 }));
+            instructions.add_instruction(code!(cmpi { Register::X1 }, { 10 }));
+            instructions.add_instruction(code!(cmpi { Register::X1 }, { -1 }));
+            instructions.add_instruction(code!(cmpi { Register::X1 }, { 0x1000 }));
+            instructions.add_instruction(code!(test { Register::X1 }, { Register::X2 }));
+            instructions.add_instruction(code!(slt { Register::X1 }, { Register::X2 }, {
+                Register::X3
+            }));
+        },
+        &[
+            // cmp x1,x2
+            0xeb02003f, //
+            // cmp sp,x2
+            0xeb2263ff, //
+            // cmp x1,#10
+            0xf100283f, //
+            // cmn x1,#1
+            0xb100043f, //
+            // cmp x1,#1,lsl #12
+            0xf140043f, //
+            // tst x1,x2
+            0xea02003f, //
+            // cmp x1,x2; cset x3,lt
+            0xeb02003f, 0x9a9fa7e3,
+        ],
+    );
+}
+
+#[test]
+fn jumps() {
+    check(
+        |instructions| {
+            let start = instructions.add_label();
+            let end = instructions.add_label();
+
+            instructions.add_instruction(code!(start: jmp { end }));
+            instructions.add_instruction(code!(jz { Register::X2 }, { end }));
+            instructions.add_instruction(code!(jcc { Condition::Equal }, { start }));
+            instructions.add_instruction(code!(jcc { Condition::NotEqual }, { end }));
+            instructions.add_instruction(code!(jcc { Condition::Less }, { start }));
+            instructions.add_instruction(code!(jcc { Condition::GreaterOrEqual }, { end }));
+            instructions.add_instruction(code!(jcc { Condition::Greater }, { start }));
+            instructions.add_instruction(code!(jcc { Condition::LessOrEqual }, { end }));
+            instructions.add_instruction(code!(call { Register::X8 }));
+            instructions.add_instruction(code!(nop));
+            instructions.add_instruction(code!(end: ret));
+        },
+        &[
+            // b .+36
+            0x14000009, //
+            // cbz x2,.+32
+            0xb4000102, //
+            // b.eq .-8
+            0x54ffffc0, //
+            // b.ne .+24
+            0x540000c1, //
+            // b.lt .-16
+            0x54ffff8b, //
+            // b.ge .+16
+            0x5400008a, //
+            // b.gt .-24
+            0x54ffff4c, //
+            // b.le .+8
+            0x5400004d, //
+            // blr x8
+            0xd63f0100, //
+            // ret
+            0xd65f03c0,
+        ],
+    );
+}
+
+#[test]
+fn label_out_of_range() {
+    let mut instructions = Instructions::<Register>::new();
+    let end = instructions.add_label();
+    instructions.add_instruction(code!(jz { Register::X0 }, { end }));
+    // `cbz` can only jump 1 MiB forward.
+    for _ in 0..(1 << 18) {
+        instructions.add_instruction(code!(ret));
+    }
+    instructions.bind_label(end);
+
+    let mut bytes = Vec::new();
+    let error = assemble(instructions, &mut bytes).unwrap_err();
+    assert!(matches!(error, AssemblerError::LabelOutOfRange(label) if label == end));
+}
+
+#[test]
+fn mul() {
+    check(
+        |instructions| {
+            instructions.add_instruction(code!(mul { Register::X1 }, { Register::X3 }));
+            instructions.add_instruction(code!(mul { Register::X17 }, { Register::X0 }));
+        },
+        &[
+            // mul x3,x3,x1
+            0x9b017c63, //
+            // mul x0,x0,x17
+            0x9b117c00,
+        ],
+    );
+}
+
+#[test]
+fn unsigned_jumps() {
+    check(
+        |instructions| {
+            let start = instructions.add_label();
+            let end = instructions.add_label();
+
+            instructions.add_instruction(code!(start: jcc { Condition::Above }, { end }));
+            instructions.add_instruction(code!(jcc { Condition::AboveOrEqual }, { end }));
+            instructions.add_instruction(code!(jcc { Condition::Below }, { start }));
+            instructions.add_instruction(code!(jcc { Condition::BelowOrEqual }, { start }));
+            instructions.add_instruction(code!(end: ret));
+        },
+        &[
+            // b.hi .+16
+            0x54000088, //
+            // b.hs .+12
+            0x54000062, //
+            // b.lo .-8
+            0x54ffffc3, //
+            // b.ls .-12
+            0x54ffffa9, //
+            // ret
+            0xd65f03c0,
+        ],
+    );
+}
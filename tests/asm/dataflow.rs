@@ -0,0 +1,128 @@
+use std::collections::BTreeSet;
+
+use pijama::{
+    asm::{
+        dataflow::{
+            DefiniteInit, Definition, InstructionGraph, LiveFlags, Liveness, ReachingDefinitions,
+        },
+        parse::parse_functions,
+        x86_64::Register::{self, *},
+        Instructions,
+    },
+    code,
+    dataflow::{solve, Graph},
+};
+
+const DUPLICATE: &str = "
+duplicate:
+    loadi 0x0,rax
+    loadi 0x0,rsi
+.L1:
+    slt rsi,rdi,rdx
+    jz rdx,.L3
+    addi 0x2,rax
+    addi 0x1,rsi
+    jmp .L1
+.L3:
+    ret
+";
+
+fn duplicate() -> Instructions<Register> {
+    parse_functions(DUPLICATE).unwrap().remove(0).1
+}
+
+fn set<T: Ord + Copy>(items: &[T]) -> BTreeSet<T> {
+    items.iter().copied().collect()
+}
+
+#[test]
+fn graph() {
+    let instructions = duplicate();
+    let graph = InstructionGraph::new(&instructions);
+
+    assert_eq!(graph.successors(3), [4, 7]);
+    assert_eq!(graph.successors(6), [2]);
+    assert_eq!(graph.successors(7), []);
+    assert_eq!(graph.predecessors(2), [1, 6]);
+    assert_eq!(graph.nodes(), [0, 1, 2, 3, 7, 4, 5, 6]);
+}
+
+#[test]
+fn liveness() {
+    let instructions = duplicate();
+    let results = solve(
+        &InstructionGraph::new(&instructions),
+        &Liveness::new(&instructions, set(&[Ax])),
+    );
+
+    assert_eq!(results.before[&0], set(&[Di]));
+    assert_eq!(results.before[&2], set(&[Ax, Si, Di]));
+    assert_eq!(results.before[&3], set(&[Ax, Dx, Si, Di]));
+    assert_eq!(results.after[&6], set(&[Ax, Si, Di]));
+    assert_eq!(results.before[&7], set(&[Ax]));
+}
+
+#[test]
+fn reaching_definitions() {
+    let instructions = duplicate();
+    let results = solve(
+        &InstructionGraph::new(&instructions),
+        &ReachingDefinitions::new(&instructions, set(&[Di])),
+    );
+
+    let definition = |reg, site| Definition { reg, site };
+    assert_eq!(
+        results.before[&7],
+        set(&[
+            definition(Ax, Some(0)),
+            definition(Ax, Some(4)),
+            definition(Dx, Some(2)),
+            definition(Si, Some(1)),
+            definition(Si, Some(5)),
+            definition(Di, None),
+        ])
+    );
+}
+
+#[test]
+fn definite_init() {
+    let instructions = duplicate();
+    let results = solve(
+        &InstructionGraph::new(&instructions),
+        &DefiniteInit::new(&instructions, set(&[Ax, Si, Dx])),
+    );
+
+    assert_eq!(results.before[&1], set(&[Si, Dx]));
+    assert_eq!(results.before[&2], set(&[Dx]));
+    assert_eq!(results.before[&7], set(&[]));
+}
+
+#[test]
+fn live_flags() {
+    let src = "
+f:
+    loadi 0x0,rax
+.L1:
+    cmp rax,rdi
+    mov rdi,rsi
+    jge .L2
+    addi 0x1,rax
+    jmp .L1
+.L2:
+    ret
+";
+    let instructions = parse_functions::<Register>(src).unwrap().remove(0).1;
+    let results = solve(
+        &InstructionGraph::new(&instructions),
+        &LiveFlags::new(&instructions),
+    );
+
+    let live = (0..instructions.len())
+        .map(|index| results.after[&index])
+        .collect::<Vec<_>>();
+    assert_eq!(live, [false, true, true, false, false, false, false]);
+
+    // Loading a zero is assembled as a `xor`, so it cannot be placed where the flags are live.
+    assert!(code!(loadi { 0x0 }, { rax }).kind.clobbers_flags());
+    assert!(!code!(loadi { 0x1 }, { rax }).kind.clobbers_flags());
+}
@@ -0,0 +1,109 @@
+use pijama::{
+    asm::{parse::parse_functions, x86_64::Register, Instructions},
+    code,
+};
+
+fn parse(src: &str) -> Instructions<Register> {
+    parse_functions(src).unwrap().remove(0).1
+}
+
+const LOOP: &str = "
+f:
+    loadi 0x0,rax
+.L0:
+.L1:
+    addi 0x1,rax
+    jz rax,.L0
+.L2:
+    ret
+";
+
+#[test]
+fn labels_by_position() {
+    let instructions = parse(LOOP);
+    let labels = instructions.labels_by_position();
+
+    assert_eq!(labels.len(), instructions.len() + 1);
+    assert!(labels[0].is_empty());
+    assert_eq!(labels[1].len(), 2);
+    assert_eq!(labels[3].len(), 1);
+    assert!(labels[4].is_empty());
+}
+
+#[test]
+fn remove() {
+    let mut instructions = parse(LOOP);
+
+    // The labels of the removed instruction are bound to the next one.
+    instructions.remove(1);
+    instructions.remove(2);
+    assert_eq!(
+        instructions.to_string(),
+        "    loadi 0x0,rax\n.L0:\n.L1:\n    jz rax,.L0\n.L2:\n"
+    );
+}
+
+#[test]
+fn insert() {
+    let mut instructions = parse(LOOP);
+
+    // The labels bound to the position of the new instruction point to it.
+    instructions.insert(1, code!(loadi { 0x2 }, { rdx }));
+    let label = instructions.add_label();
+    instructions.insert(3, code!(label: mov { rax }, { rdx }));
+    assert_eq!(
+        instructions.to_string(),
+        "    loadi 0x0,rax
+.L0:
+.L1:
+    loadi 0x2,rdx
+    addi 0x1,rax
+.L3:
+    mov rax,rdx
+    jz rax,.L0
+.L2:
+    ret
+"
+    );
+}
+
+#[test]
+fn alias() {
+    let mut instructions = parse(LOOP);
+    let labels = instructions.labels_by_position();
+    let (l0, l2) = (labels[1][0], labels[3][0]);
+
+    // `.L2` keeps pointing wherever `.L0` points after `.L0` is bound elsewhere.
+    instructions.alias(l2, l0);
+    assert_eq!(instructions.resolve(l2), l0);
+    instructions.bind_label_at(l0, 0);
+    assert_eq!(instructions.position(l2), Some(0));
+    assert_eq!(
+        instructions.to_string(),
+        ".L0:\n.L2:\n    loadi 0x0,rax\n.L1:\n    addi 0x1,rax\n    jz rax,.L0\n    ret\n"
+    );
+}
+
+#[test]
+fn union() {
+    let mut instructions = parse(LOOP);
+    let labels = instructions.labels_by_position();
+    let (l0, l1) = (labels[1][0], labels[1][1]);
+
+    let root = instructions.union(l0, l1);
+    assert_eq!(root, l0);
+    assert_eq!(instructions.resolve(l1), l0);
+
+    // An unbound label takes the position of the other one.
+    let l3 = instructions.add_label();
+    assert_eq!(instructions.union(l3, l1), l0);
+    assert_eq!(instructions.position(l3), Some(1));
+}
+
+#[test]
+#[should_panic = "cannot unite labels bound to different positions"]
+fn union_of_different_positions() {
+    let mut instructions = parse(LOOP);
+    let labels = instructions.labels_by_position();
+    instructions.union(labels[1][0], labels[3][0]);
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeSet;
+
+use pijama::{
+    asm::{
+        machine::{BlockLiveness, MachineCfg, MachineFunction, MachineTerminator},
+        parse::parse_functions,
+        x86_64::Register,
+        Instructions,
+    },
+    dataflow::solve,
+};
+
+fn parse(src: &str) -> Instructions<Register> {
+    parse_functions(src).unwrap().remove(0).1
+}
+
+const BRANCH: &str = "
+f:
+    loadi 0x0,rax
+    cmp rdi,rsi
+    jl .L0
+    loadi 0x1,rax
+    jmp .L1
+.L0:
+    mov rdi,rax
+.L1:
+    ret
+";
+
+#[test]
+fn blocks() {
+    let func = MachineFunction::new(parse(BRANCH));
+    let blocks = func
+        .layout
+        .iter()
+        .map(|id| &func.blocks[id])
+        .collect::<Vec<_>>();
+
+    assert_eq!(blocks.len(), 4);
+    assert_eq!(blocks[0].instructions.len(), 2);
+    assert!(matches!(
+        blocks[0].terminator,
+        MachineTerminator::JumpIf { then_bb, else_bb, .. }
+            if then_bb == func.layout[2] && else_bb == func.layout[1]
+    ));
+    assert!(matches!(
+        blocks[1].terminator,
+        MachineTerminator::Jump(target) if target == func.layout[3]
+    ));
+    // The block of `.L0` falls through to the block of `.L1`.
+    assert_eq!(blocks[2].labels.len(), 1);
+    assert!(matches!(
+        blocks[2].terminator,
+        MachineTerminator::Jump(target) if target == func.layout[3]
+    ));
+    assert!(blocks[3].instructions.is_empty());
+    assert!(matches!(blocks[3].terminator, MachineTerminator::Return));
+
+    assert_eq!(
+        func.into_instructions().to_string(),
+        parse(BRANCH).to_string()
+    );
+}
+
+#[test]
+fn cfg() {
+    let func = MachineFunction::new(parse(BRANCH));
+    let cfg = MachineCfg::new(&func);
+    let [entry, fallthrough, taken, exit] = func.layout[..] else {
+        panic!("expected four blocks");
+    };
+
+    assert_eq!(cfg.entry(), entry);
+    assert_eq!(cfg.successors(entry), [taken, fallthrough]);
+    assert_eq!(cfg.predecessors(exit), [fallthrough, taken]);
+    assert!(cfg.successors(exit).is_empty());
+    assert_eq!(cfg.reverse_postorder()[0], entry);
+    assert!(cfg.is_reachable(exit));
+}
+
+#[test]
+fn layout() {
+    let mut func = MachineFunction::new(parse(BRANCH));
+    let [entry, fallthrough, taken, exit] = func.layout[..] else {
+        panic!("expected four blocks");
+    };
+    func.layout = vec![entry, taken, exit, fallthrough];
+
+    // The condition is inverted so the taken block follows the entry block, and the block that
+    // used to fall through to `ret` needs a jump now.
+    let expected = "    loadi 0x0,rax
+    cmp rdi,rsi
+    jge .L2
+.L0:
+    mov rdi,rax
+.L1:
+    ret
+.L2:
+    loadi 0x1,rax
+    jmp .L1
+";
+
+    assert_eq!(func.into_instructions().to_string(), expected);
+}
+
+#[test]
+fn exit() {
+    let src = "
+f:
+    jz rdi,.L0
+    loadi 0x1,rax
+.L0:
+";
+    let func = MachineFunction::new(parse(src));
+
+    assert_eq!(func.layout.len(), 3);
+    assert!(matches!(
+        func.blocks[&func.layout[2]].terminator,
+        MachineTerminator::Exit
+    ));
+    assert_eq!(func.into_instructions().to_string(), parse(src).to_string());
+}
+
+#[test]
+fn block_liveness() {
+    let func = MachineFunction::new(parse(BRANCH));
+    let [entry, fallthrough, taken, exit] = func.layout[..] else {
+        panic!("expected four blocks");
+    };
+    let results = solve(
+        &MachineCfg::new(&func),
+        &BlockLiveness::new(&func, BTreeSet::from([Register::Ax])),
+    );
+
+    assert_eq!(
+        results.before[&entry],
+        BTreeSet::from([Register::Di, Register::Si])
+    );
+    assert_eq!(results.after[&entry], BTreeSet::from([Register::Di]));
+    assert!(results.before[&fallthrough].is_empty());
+    assert_eq!(results.before[&taken], BTreeSet::from([Register::Di]));
+    assert_eq!(results.before[&exit], BTreeSet::from([Register::Ax]));
+}
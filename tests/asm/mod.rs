@@ -1 +1,8 @@
+mod aarch64;
+mod dataflow;
+mod instructions;
+mod machine;
+mod optimize;
+mod parse;
+mod riscv64;
 mod x86_64;
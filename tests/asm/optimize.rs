@@ -0,0 +1,219 @@
+use pijama::{
+    asm::{
+        optimize::{BranchFolding, DeadJumps, Peephole, RedundantLoads},
+        parse::parse_functions,
+        x86_64::Register,
+        Instructions,
+    },
+    pass::Pass,
+};
+
+fn parse(src: &str) -> Instructions<Register> {
+    parse_functions(src).unwrap().remove(0).1
+}
+
+/// Runs `pass` on `src` and checks that the result is `expected`. Returns whether the pass
+/// changed the instructions.
+fn check(pass: impl Pass<Instructions<Register>>, src: &str, expected: &str) -> bool {
+    let mut instructions = parse(src);
+    let changed = pass.run(&mut instructions);
+    assert_eq!(instructions.to_string(), expected);
+    changed
+}
+
+#[test]
+fn redundant_moves() {
+    let src = "
+f:
+    mov rax,rax
+.L0:
+    mov rdi,rdi
+    mov rdi,rsi
+    ret
+";
+
+    // The label is bound to the instruction after the removed one.
+    let expected = "\
+.L0:
+    mov rdi,rsi
+    ret
+";
+
+    assert!(check(Peephole::default(), src, expected));
+}
+
+#[test]
+fn additions() {
+    let src = "
+f:
+    mov rdi,rax
+    add rsi,rax
+    mov rdi,rdx
+    add rdx,rdx
+    mov rdi,rcx
+.L0:
+    add rsi,rcx
+    ret
+";
+
+    // The second `add` reads the result of the `mov` and the third one can be reached without
+    // executing the `mov`.
+    let expected = "    lea rdi+rsi,rax
+    mov rdi,rdx
+    add rdx,rdx
+    mov rdi,rcx
+.L0:
+    add rsi,rcx
+    ret
+";
+
+    assert!(check(Peephole::default(), src, expected));
+}
+
+#[test]
+fn live_flags() {
+    // `lea` does not set the flags read by `jl`.
+    let src = "
+f:
+    mov rdi,rax
+    add rsi,rax
+    jl .L0
+    ret
+.L0:
+    ret
+";
+
+    assert!(!check(Peephole::default(), src, &src["\nf:\n".len()..]));
+}
+
+#[test]
+fn compare_with_zero() {
+    let src = "
+f:
+.L0:
+    cmpi rax,0x0
+    jl .L0
+    ret
+";
+
+    let expected = "\
+.L0:
+    test rax,rax
+    jl .L0
+    ret
+";
+
+    assert!(check(Peephole::default(), src, expected));
+
+    // The carry flag read by `jb` and the flags read after the jump are kept.
+    let src = "
+f:
+.L0:
+    cmpi rax,0x0
+    jb .L0
+    cmpi rdi,0x0
+    je .L0
+    jl .L0
+    cmpi rsi,0x1
+    je .L0
+    ret
+";
+
+    assert!(!check(Peephole::default(), src, &src["\nf:\n".len()..]));
+}
+
+#[test]
+fn redundant_loads() {
+    let src = "
+f:
+    loadi 0x1,rax
+    loadi 0x1,rax
+    mov rax,rdx
+    loadi 0x1,rdx
+    addi 0x1,rax
+    loadi 0x1,rax
+    call g
+    loadi 0x1,rdx
+.L0:
+    loadi 0x1,rdx
+    loadi 0x1,rdx
+    ret
+";
+
+    // Values are forgotten when the register is written, at calls and at labels.
+    let expected = "    loadi 0x1,rax
+    mov rax,rdx
+    addi 0x1,rax
+    loadi 0x1,rax
+    call g
+    loadi 0x1,rdx
+.L0:
+    loadi 0x1,rdx
+    ret
+";
+
+    assert!(check(RedundantLoads, src, expected));
+}
+
+#[test]
+fn dead_jumps() {
+    let src = "
+f:
+    jmp .L0
+.L0:
+    jmp .L1
+.L1:
+.L2:
+    jmp .L3
+    ret
+.L3:
+";
+
+    // The labels of a removed jump are bound to the next instruction, and a jump to the end of
+    // the function is not removed unless it is the last instruction.
+    let expected = "\
+.L0:
+.L1:
+.L2:
+    jmp .L3
+    ret
+.L3:
+";
+
+    assert!(check(DeadJumps, src, expected));
+}
+
+#[test]
+fn branch_folding() {
+    let src = "
+f:
+    cmp rdi,rsi
+    jl .L0
+    jmp .L0
+.L0:
+    jz rdi,.L1
+    jmp .L3
+.L1:
+    jmp .L2
+.L4:
+    loadi 0x1,rax
+.L2:
+    mov rdi,rax
+    jmp .L3
+.L3:
+    ret
+";
+
+    // `.L1` only jumps to `.L2`, so the `jl` has the same targets as the jump after it. Then `.L0`
+    // is only entered from the first block, `.L4` cannot be reached and `.L2` falls through to `.L3`.
+    let expected = "    cmp rdi,rsi
+    jz rdi,.L3
+    jmp .L2
+.L3:
+    mov rdi,rax
+.L2:
+    ret
+";
+
+    assert!(check(BranchFolding, src, expected));
+}
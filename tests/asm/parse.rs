@@ -0,0 +1,98 @@
+use pijama::asm::{parse::parse_functions, x86_64::Register};
+
+#[test]
+fn round_trip() {
+    let src = "\
+start:
+    loadi 0xa,rax
+    load rsp-0x8,rcx
+    store rcx,r12+0x10
+    lea table,rdx
+    lea rdx+r12,rdi
+    load got counter,rsi
+    call helper
+    call r8
+.L0:
+    slt rsi,rdi,rdx
+    jz rdx,.L1
+    addi -0x1,rax
+    cmp rax,rsi
+    jl .L0
+    test rax,rax
+    jne .L1
+    jmp .L0
+.L1:
+    ret
+";
+
+    let functions = parse_functions::<Register>(src).unwrap();
+    assert_eq!(functions.len(), 1);
+    assert_eq!(functions[0].0, "start");
+    assert_eq!(functions[0].1.to_string(), &src["start:\n".len()..]);
+}
+
+#[test]
+fn consecutive_labels() {
+    let err = parse_functions::<Register>(".L0:\n.L1: ret").unwrap_err();
+    assert_eq!(err.message, "local label `.L0` outside of a function");
+
+    let functions = parse_functions::<Register>("f:\n.a:\n.b: jmp .a\nret\ng: ret").unwrap();
+    let names = functions
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["f", "g"]);
+    // Both labels are bound to the `jmp`.
+    assert_eq!(
+        functions[0].1.to_string(),
+        ".L0:\n.L1:\n    jmp .L0\n    ret\n"
+    );
+}
+
+#[test]
+fn undefined_label() {
+    let err = parse_functions::<Register>("f:\n    jmp .L7\n").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 9));
+    assert_eq!(err.message, "label `.L7` is not defined");
+}
+
+#[test]
+fn unknown_register() {
+    let err = parse_functions::<Register>("f: mov rax,eax").unwrap_err();
+    assert_eq!((err.line, err.column), (1, 12));
+    assert_eq!(err.message, "expected register");
+}
+
+#[test]
+fn conditional_jumps() {
+    let src = "f:\n    je .a\n    jne .a\n    jg .a\n.a:\n    jle .a\n    jge .a\n";
+    let functions = parse_functions::<Register>(src).unwrap();
+    assert_eq!(
+        functions[0].1.to_string(),
+        "    je .L0\n    jne .L0\n    jg .L0\n.L0:\n    jle .L0\n    jge .L0\n"
+    );
+}
+
+#[test]
+fn float_instructions() {
+    let src = "\
+f:
+    mul rsi,rdi
+    mov rdi,xmm1
+    cvtsi2sd rdi,xmm2
+    addsd xmm1,xmm2
+    mulss xmm3,xmm4
+    cvtss2sd xmm4,xmm5
+    cvtsd2ss xmm2,xmm3
+    cvttsd2si xmm2,rax
+    sltsd xmm1,xmm2,rcx
+    ucomisd xmm2,xmm1
+    ja .L0
+    jbe .L0
+.L0:
+    ret
+";
+
+    let functions = parse_functions::<Register>(src).unwrap();
+    assert_eq!(functions[0].1.to_string(), &src["f:\n".len()..]);
+}
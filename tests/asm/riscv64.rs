@@ -0,0 +1,320 @@
+//! The expected encodings are the ones produced by `llvm-mc -triple=riscv64 -show-encoding`.
+use pijama::{
+    asm::{
+        riscv64::{assemble, Register},
+        AssemblerError, Condition, Instructions, Relocation, RelocationKind,
+    },
+    code,
+};
+
+/// Assembles the instructions added by `f` and checks that they are encoded as `expected`.
+fn check(f: impl FnOnce(&mut Instructions<Register>), expected: &[u32]) -> Vec<Relocation> {
+    let mut instructions = Instructions::new();
+    f(&mut instructions);
+
+    let mut bytes = Vec::new();
+    let relocations = assemble(instructions, &mut bytes).unwrap();
+
+    let found = bytes
+        .chunks(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found
+            .iter()
+            .map(|word| format!("{word:08x}"))
+            .collect::<Vec<_>>(),
+        expected
+            .iter()
+            .map(|word| format!("{word:08x}"))
+            .collect::<Vec<_>>()
+    );
+
+    relocations
+}
+
+#[test]
+fn loadi() {
+    check(
+        |instructions| {
+            for imm in [
+                0,
+                2047,
+                -2048,
+                0x1234,
+                0x7ffff800,
+                0xdeadbeef,
+                0x100000000,
+                -1,
+            ] {
+                instructions.add_instruction(code!(loadi { imm }, { Register::T0 }));
+            }
+            instructions.add_instruction(code!(loadi { 0xdeadbeefdeadbeefu64 as i64 }, {
+                Register::A5
+            }));
+        },
+        &[
+            // li t0,0
+            0x00000293, //
+            // li t0,2047
+            0x7ff00293, //
+            // li t0,-2048
+            0x80000293, //
+            // lui t0,1; addiw t0,t0,564
+            0x000012b7, 0x2342829b, //
+            // lui t0,524288; addiw t0,t0,-2048
+            0x800002b7, 0x8002829b, //
+            // lui t0,228023; slli t0,t0,2; addi t0,t0,-273
+            0x37ab72b7, 0x00229293, 0xeef28293, //
+            // li t0,1; slli t0,t0,32
+            0x00100293, 0x02029293, //
+            // li t0,-1
+            0xfff00293, //
+            // lui a5,1048309; addiw a5,a5,1759; slli a5,a5,12; addi a5,a5,1919;
+            // slli a5,a5,15; addi a5,a5,-1353; slli a5,a5,14; addi a5,a5,-273
+            0xffef57b7, 0x6df7879b, 0x00c79793, 0x77f78793, 0x00f79793, 0xab778793, 0x00e79793,
+            0xeef78793,
+        ],
+    );
+}
+
+#[test]
+fn load_and_store() {
+    check(
+        |instructions| {
+            instructions.add_instruction(code!(load { Register::A2 } + { 8 }, { Register::A1 }));
+            instructions.add_instruction(code!(load { Register::Sp } + { -2048 }, {
+                Register::A1
+            }));
+            instructions.add_instruction(code!(load { Register::A2 } + { 0x800 }, {
+                Register::A1
+            }));
+            instructions.add_instruction(code!(store { Register::A2 }, { Register::A1 } + { 16 }));
+            instructions.add_instruction(code!(store { Register::Sp }, { Register::S0 } + { -16 }));
+        },
+        &[
+            // ld a1,8(a2)
+            0x00863583, //
+            // ld a1,-2048(sp)
+            0x80013583, //
+            // lui t6,1; addiw t6,t6,-2048; add t6,t6,a2; ld a1,0(t6)
+            0x00001fb7, 0x800f8f9b, 0x00cf8fb3, 0x000fb583, //
+            // sd a1,16(a2)
+            0x00b63823, //
+            // sd s0,-16(sp)
+            0xfe813823,
+        ],
+    );
+}
+
+#[test]
+fn symbols() {
+    let relocations = check(
+        |instructions| {
+            let sym = instructions.add_symbol("sym");
+            instructions.add_instruction(code!(lea sym { sym }, { Register::T0 }));
+            instructions.add_instruction(code!(load got { sym }, { Register::T0 }));
+            instructions.add_instruction(code!(call sym { sym }));
+        },
+        &[
+            // auipc t0,%pcrel_hi(sym); addi t0,t0,%pcrel_lo(sym)
+            0x00000297, 0x00028293, //
+            // auipc t0,%got_pcrel_hi(sym); ld t0,%pcrel_lo(sym)(t0)
+            0x00000297, 0x0002b283, //
+            // call sym
+            0x00000097, 0x000080e7,
+        ],
+    );
+
+    let kinds = relocations
+        .iter()
+        .map(|relocation| (relocation.offset, relocation.kind, relocation.addend))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            (0, RelocationKind::PcRelativeHigh, 0),
+            (4, RelocationKind::PcRelativeLow, 0),
+            (8, RelocationKind::GotPcRelativeHigh, 0),
+            (12, RelocationKind::PcRelativeLow, 0),
+            (16, RelocationKind::Branch, 0),
+        ]
+    );
+}
+
+#[test]
+fn registers() {
+    check(
+        |instructions| {
+            instructions.add_instruction(code!(lea { Register::A1 } + { Register::A2 }, {
+                Register::A0
+            }));
+            instructions.add_instruction(code!(add { Register::A1 }, { Register::A0 }));
+            instructions.add_instruction(code!(mov { Register::A1 }, { Register::A0 }));
+            instructions.add_instruction(code!(push { Register::Ra }));
+            instructions.add_instruction(code!(pop { Register::Ra }));
+            instructions.add_instruction(code!(addi { 2047 }, { Register::T0 }));
+            instructions.add_instruction(code!(addi { -2048 }, { Register::T0 }));
+            instructions.add_instruction(code!(addi { 0x1234 }, { Register::T0 }));
+            instructions.add_instruction(code!(slt { Register::A0 }, { Register::A1 }, {
+                Register::A2
+            }));
+        },
+        &[
+            // add a0,a1,a2
+            0x00c58533, //
+            // add a0,a0,a1
+            0x00b50533, //
+            // mv a0,a1
+            0x00058513, //
+            // addi sp,sp,-16; sd ra,0(sp)
+            0xff010113, 0x00113023, //
+            // ld ra,0(sp); addi sp,sp,16
+            0x00013083, 0x01010113, //
+            // addi t0,t0,2047
+            0x7ff28293, //
+            // addi t0,t0,-2048
+            0x80028293, //
+            // lui t6,1; addiw t6,t6,564; add t0,t0,t6
+            0x00001fb7, 0x234f8f9b, 0x01f282b3, //
+            // slt a2,a0,a1
+            0x00b52633,
+        ],
+    );
+}
+
+#[test]
+fn jumps() {
+    check(
+        |instructions| {
+            let start = instructions.add_label();
+            let end = instructions.add_label();
+
+            instructions.add_instruction(code!(start: jmp { end }));
+            instructions.add_instruction(code!(jz { Register::A2 }, { end }));
+            instructions.add_instruction(code!(cmp { Register::A0 }, { Register::A1 }));
+            instructions.add_instruction(code!(jcc { Condition::Equal }, { start }));
+            instructions.add_instruction(code!(jcc { Condition::NotEqual }, { end }));
+            instructions.add_instruction(code!(jcc { Condition::Less }, { start }));
+            instructions.add_instruction(code!(jcc { Condition::GreaterOrEqual }, { end }));
+            instructions.add_instruction(code!(jcc { Condition::Greater }, { start }));
+            instructions.add_instruction(code!(jcc { Condition::LessOrEqual }, { end }));
+            instructions.add_instruction(code!(test { Register::A3 }, { Register::A3 }));
+            instructions.add_instruction(code!(jcc { Condition::NotEqual }, { end }));
+            instructions.add_instruction(code!(test { Register::A3 }, { Register::A4 }));
+            instructions.add_instruction(code!(jcc { Condition::Equal }, { start }));
+            instructions.add_instruction(code!(call { Register::A5 }));
+            instructions.add_instruction(code!(nop));
+            instructions.add_instruction(code!(end: ret));
+        },
+        &[
+            // j .+48
+            0x0300006f, //
+            // beqz a2,.+44
+            0x02060663, //
+            // beq a0,a1,.-8
+            0xfeb50ce3, //
+            // bne a0,a1,.+36
+            0x02b51263, //
+            // blt a0,a1,.-16
+            0xfeb548e3, //
+            // bge a0,a1,.+28
+            0x00b55e63, //
+            // blt a1,a0,.-24
+            0xfea5c4e3, //
+            // bge a1,a0,.+20
+            0x00a5da63, //
+            // bnez a3,.+16
+            0x00069863, //
+            // and t6,a3,a4; beqz t6,.-40
+            0x00e6ffb3, 0xfc0f8ce3, //
+            // jalr a5
+            0x000780e7, //
+            // ret
+            0x00008067,
+        ],
+    );
+}
+
+#[test]
+fn long_branches() {
+    let mut expected = vec![
+        // bnez a1,.+8; j .+4100
+        0x00059463, 0x0040106f, //
+        // bnez a0,.+8; j .+8188
+        0x00051463, 0x7fd0106f,
+    ];
+    // ret
+    expected.resize(expected.len() + 1022 + 1024, 0x00008067);
+
+    check(
+        |instructions| {
+            let end = instructions.add_label();
+            let far = instructions.add_label();
+
+            // The first branch only goes out of range after the second one is replaced.
+            instructions.add_instruction(code!(jz { Register::A1 }, { end }));
+            instructions.add_instruction(code!(jz { Register::A0 }, { far }));
+            for _ in 0..1022 {
+                instructions.add_instruction(code!(ret));
+            }
+            instructions.bind_label(end);
+            for _ in 0..1024 {
+                instructions.add_instruction(code!(ret));
+            }
+            instructions.bind_label(far);
+        },
+        &expected,
+    );
+}
+
+#[test]
+fn label_out_of_range() {
+    let mut instructions = Instructions::<Register>::new();
+    let end = instructions.add_label();
+    instructions.add_instruction(code!(jmp { end }));
+    // `jal` can only jump 1 MiB forward.
+    for _ in 0..(1 << 18) {
+        instructions.add_instruction(code!(ret));
+    }
+    instructions.bind_label(end);
+
+    let mut bytes = Vec::new();
+    let error = assemble(instructions, &mut bytes).unwrap_err();
+    assert!(matches!(error, AssemblerError::LabelOutOfRange(label) if label == end));
+}
+
+#[test]
+fn mul_and_unsigned_jumps() {
+    check(
+        |instructions| {
+            let start = instructions.add_label();
+            let end = instructions.add_label();
+
+            instructions.add_instruction(code!(start: mul { Register::A1 }, { Register::A0 }));
+            instructions.add_instruction(code!(mul { Register::T6 }, { Register::T0 }));
+            instructions.add_instruction(code!(cmp { Register::A0 }, { Register::A1 }));
+            instructions.add_instruction(code!(jcc { Condition::Above }, { start }));
+            instructions.add_instruction(code!(jcc { Condition::AboveOrEqual }, { end }));
+            instructions.add_instruction(code!(jcc { Condition::Below }, { start }));
+            instructions.add_instruction(code!(jcc { Condition::BelowOrEqual }, { end }));
+            instructions.add_instruction(code!(end: ret));
+        },
+        &[
+            // mul a0,a0,a1
+            0x02b50533, //
+            // mul t0,t0,t6
+            0x03f282b3, //
+            // bltu a1,a0,.-8
+            0xfea5ece3, //
+            // bgeu a0,a1,.+12
+            0x00b57663, //
+            // bltu a0,a1,.-16
+            0xfeb568e3, //
+            // bgeu a1,a0,.+4
+            0x00a5f263, //
+            // ret
+            0x00008067,
+        ],
+    );
+}
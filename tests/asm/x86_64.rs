@@ -1,7 +1,7 @@
 use pijama::{
     asm::{
         x86_64::{assemble, Register},
-        Instructions,
+        Condition, Instructions,
     },
     code,
 };
@@ -27,6 +27,25 @@ const REGISTERS: [Register; 16] = [
     Register::R15,
 ];
 
+const FLOATS: [Register; 16] = [
+    Register::Xmm0,
+    Register::Xmm1,
+    Register::Xmm2,
+    Register::Xmm3,
+    Register::Xmm4,
+    Register::Xmm5,
+    Register::Xmm6,
+    Register::Xmm7,
+    Register::Xmm8,
+    Register::Xmm9,
+    Register::Xmm10,
+    Register::Xmm11,
+    Register::Xmm12,
+    Register::Xmm13,
+    Register::Xmm14,
+    Register::Xmm15,
+];
+
 const DEADBEEF32: i32 = 0xdeadbeefu32 as i32;
 const DEADBEEF64: i64 = 0xdeadbeefdeadbeefu64 as i64;
 
@@ -139,6 +158,9 @@ asm_test!(loadi, |instructions: &mut Instructions<Register>| {
     for dst in REGISTERS {
         instructions.add_instruction(code!(loadi { DEADBEEF32.into() }, { dst }));
     }
+    for dst in REGISTERS {
+        instructions.add_instruction(code!(loadi { 0xdeadbeef }, { dst }));
+    }
 });
 
 asm_test!(load, |instructions: &mut Instructions<Register>| {
@@ -149,6 +171,40 @@ asm_test!(load, |instructions: &mut Instructions<Register>| {
     }
 });
 
+asm_test!(leasym, |instructions: &mut Instructions<Register>| {
+    let sym = instructions.add_symbol("sym");
+
+    for dst in REGISTERS {
+        instructions.add_instruction(code!(lea sym { sym }, { dst }));
+    }
+});
+
+asm_test!(loadgot, |instructions: &mut Instructions<Register>| {
+    let sym = instructions.add_symbol("sym");
+
+    for dst in REGISTERS {
+        instructions.add_instruction(code!(load got { sym }, { dst }));
+    }
+});
+
+asm_test!(lea, |instructions: &mut Instructions<Register>| {
+    // `rsp` cannot be an index and `rbp` and `r13` cannot be a base without a displacement, so
+    // these are the operands that are never swapped.
+    for base in REGISTERS {
+        if let Register::Bp | Register::R13 = base {
+            continue;
+        }
+        for index in REGISTERS {
+            if index == Register::Sp {
+                continue;
+            }
+            for dst in REGISTERS {
+                instructions.add_instruction(code!(lea { base } + { index }, { dst }));
+            }
+        }
+    }
+});
+
 asm_test!(store, |instructions: &mut Instructions<Register>| {
     for src in REGISTERS {
         for dst in REGISTERS {
@@ -204,6 +260,31 @@ asm_test!(slt, |instructions: &mut Instructions<Register>| {
     }
 });
 
+asm_test!(cmp, |instructions: &mut Instructions<Register>| {
+    for src1 in REGISTERS {
+        for src2 in REGISTERS {
+            instructions.add_instruction(code!(cmp { src1 }, { src2 }));
+        }
+    }
+});
+
+asm_test!(cmpi, |instructions: &mut Instructions<Register>| {
+    for src in REGISTERS {
+        instructions.add_instruction(code!(cmpi { src }, { DEADBEEF32 }));
+    }
+    for src in REGISTERS {
+        instructions.add_instruction(code!(cmpi { src }, { 0x7f }));
+    }
+});
+
+asm_test!(test, |instructions: &mut Instructions<Register>| {
+    for src1 in REGISTERS {
+        for src2 in REGISTERS {
+            instructions.add_instruction(code!(test { src1 }, { src2 }));
+        }
+    }
+});
+
 asm_test!(jmp, |instructions: &mut Instructions<Register>| {
     let lbl = instructions.add_label();
 
@@ -221,6 +302,26 @@ asm_test!(jz, |instructions: &mut Instructions<Register>| {
     }
 });
 
+asm_test!(jcc, |instructions: &mut Instructions<Register>| {
+    let lbl = instructions.add_label();
+
+    instructions.add_instruction(code!(lbl: nop));
+    for cond in [
+        Condition::Equal,
+        Condition::NotEqual,
+        Condition::Less,
+        Condition::GreaterOrEqual,
+        Condition::Greater,
+        Condition::LessOrEqual,
+        Condition::Above,
+        Condition::AboveOrEqual,
+        Condition::Below,
+        Condition::BelowOrEqual,
+    ] {
+        instructions.add_instruction(code!(jcc { cond }, { lbl }));
+    }
+});
+
 asm_test!(ret, |instructions: &mut Instructions<Register>| {
     instructions.add_instruction(code!(ret));
 });
@@ -230,3 +331,155 @@ asm_test!(call, |instructions: &mut Instructions<Register>| {
         instructions.add_instruction(code!(call { reg }));
     }
 });
+
+asm_test!(mul, |instructions: &mut Instructions<Register>| {
+    for src in REGISTERS {
+        for dst in REGISTERS {
+            instructions.add_instruction(code!(mul { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(mov_float, |instructions: &mut Instructions<Register>| {
+    for src in FLOATS {
+        for dst in FLOATS {
+            instructions.add_instruction(code!(mov { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(mov_to_float, |instructions: &mut Instructions<Register>| {
+    for src in REGISTERS {
+        for dst in FLOATS {
+            instructions.add_instruction(code!(mov { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(mov_from_float, |instructions: &mut Instructions<
+    Register,
+>| {
+    for src in FLOATS {
+        for dst in REGISTERS {
+            instructions.add_instruction(code!(mov { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(push_float, |instructions: &mut Instructions<Register>| {
+    for reg in FLOATS {
+        instructions.add_instruction(code!(push { reg }));
+    }
+});
+
+asm_test!(pop_float, |instructions: &mut Instructions<Register>| {
+    for reg in FLOATS {
+        instructions.add_instruction(code!(pop { reg }));
+    }
+});
+
+asm_test!(addsd, |instructions: &mut Instructions<Register>| {
+    for src in FLOATS {
+        for dst in FLOATS {
+            instructions.add_instruction(code!(addsd { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(addss, |instructions: &mut Instructions<Register>| {
+    for src in FLOATS {
+        for dst in FLOATS {
+            instructions.add_instruction(code!(addss { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(mulsd, |instructions: &mut Instructions<Register>| {
+    for src in FLOATS {
+        for dst in FLOATS {
+            instructions.add_instruction(code!(mulsd { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(mulss, |instructions: &mut Instructions<Register>| {
+    for src in FLOATS {
+        for dst in FLOATS {
+            instructions.add_instruction(code!(mulss { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(sltsd, |instructions: &mut Instructions<Register>| {
+    for src1 in FLOATS {
+        for src2 in FLOATS {
+            for dst in REGISTERS {
+                instructions.add_instruction(code!(sltsd { src1 }, { src2 }, { dst }));
+            }
+        }
+    }
+});
+
+asm_test!(ucomisd, |instructions: &mut Instructions<Register>| {
+    for src1 in FLOATS {
+        for src2 in FLOATS {
+            instructions.add_instruction(code!(ucomisd { src1 }, { src2 }));
+        }
+    }
+});
+
+asm_test!(ucomiss, |instructions: &mut Instructions<Register>| {
+    for src1 in FLOATS {
+        for src2 in FLOATS {
+            instructions.add_instruction(code!(ucomiss { src1 }, { src2 }));
+        }
+    }
+});
+
+asm_test!(cvtsi2sd, |instructions: &mut Instructions<Register>| {
+    for src in REGISTERS {
+        for dst in FLOATS {
+            instructions.add_instruction(code!(cvtsi2sd { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(cvtsi2ss, |instructions: &mut Instructions<Register>| {
+    for src in REGISTERS {
+        for dst in FLOATS {
+            instructions.add_instruction(code!(cvtsi2ss { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(cvttsd2si, |instructions: &mut Instructions<Register>| {
+    for src in FLOATS {
+        for dst in REGISTERS {
+            instructions.add_instruction(code!(cvttsd2si { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(cvttss2si, |instructions: &mut Instructions<Register>| {
+    for src in FLOATS {
+        for dst in REGISTERS {
+            instructions.add_instruction(code!(cvttss2si { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(cvtsd2ss, |instructions: &mut Instructions<Register>| {
+    for src in FLOATS {
+        for dst in FLOATS {
+            instructions.add_instruction(code!(cvtsd2ss { src }, { dst }));
+        }
+    }
+});
+
+asm_test!(cvtss2sd, |instructions: &mut Instructions<Register>| {
+    for src in FLOATS {
+        for dst in FLOATS {
+            instructions.add_instruction(code!(cvtss2sd { src }, { dst }));
+        }
+    }
+});
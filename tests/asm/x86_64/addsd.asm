@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    addsd xmm0,%1
+    addsd xmm1,%1
+    addsd xmm2,%1
+    addsd xmm3,%1
+    addsd xmm4,%1
+    addsd xmm5,%1
+    addsd xmm6,%1
+    addsd xmm7,%1
+    addsd xmm8,%1
+    addsd xmm9,%1
+    addsd xmm10,%1
+    addsd xmm11,%1
+    addsd xmm12,%1
+    addsd xmm13,%1
+    addsd xmm14,%1
+    addsd xmm15,%1
+%endmacro
+
+expand xmm0
+expand xmm1
+expand xmm2
+expand xmm3
+expand xmm4
+expand xmm5
+expand xmm6
+expand xmm7
+expand xmm8
+expand xmm9
+expand xmm10
+expand xmm11
+expand xmm12
+expand xmm13
+expand xmm14
+expand xmm15
@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    addss xmm0,%1
+    addss xmm1,%1
+    addss xmm2,%1
+    addss xmm3,%1
+    addss xmm4,%1
+    addss xmm5,%1
+    addss xmm6,%1
+    addss xmm7,%1
+    addss xmm8,%1
+    addss xmm9,%1
+    addss xmm10,%1
+    addss xmm11,%1
+    addss xmm12,%1
+    addss xmm13,%1
+    addss xmm14,%1
+    addss xmm15,%1
+%endmacro
+
+expand xmm0
+expand xmm1
+expand xmm2
+expand xmm3
+expand xmm4
+expand xmm5
+expand xmm6
+expand xmm7
+expand xmm8
+expand xmm9
+expand xmm10
+expand xmm11
+expand xmm12
+expand xmm13
+expand xmm14
+expand xmm15
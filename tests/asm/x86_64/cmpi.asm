@@ -0,0 +1,34 @@
+BITS 64
+cmp rax,-0x21524111
+cmp rcx,-0x21524111
+cmp rdx,-0x21524111
+cmp rbx,-0x21524111
+cmp rsp,-0x21524111
+cmp rbp,-0x21524111
+cmp rsi,-0x21524111
+cmp rdi,-0x21524111
+cmp r8,-0x21524111
+cmp r9,-0x21524111
+cmp r10,-0x21524111
+cmp r11,-0x21524111
+cmp r12,-0x21524111
+cmp r13,-0x21524111
+cmp r14,-0x21524111
+cmp r15,-0x21524111
+
+cmp rax,byte 0x7f
+cmp rcx,byte 0x7f
+cmp rdx,byte 0x7f
+cmp rbx,byte 0x7f
+cmp rsp,byte 0x7f
+cmp rbp,byte 0x7f
+cmp rsi,byte 0x7f
+cmp rdi,byte 0x7f
+cmp r8,byte 0x7f
+cmp r9,byte 0x7f
+cmp r10,byte 0x7f
+cmp r11,byte 0x7f
+cmp r12,byte 0x7f
+cmp r13,byte 0x7f
+cmp r14,byte 0x7f
+cmp r15,byte 0x7f
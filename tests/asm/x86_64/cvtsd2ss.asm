@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    cvtsd2ss xmm0,%1
+    cvtsd2ss xmm1,%1
+    cvtsd2ss xmm2,%1
+    cvtsd2ss xmm3,%1
+    cvtsd2ss xmm4,%1
+    cvtsd2ss xmm5,%1
+    cvtsd2ss xmm6,%1
+    cvtsd2ss xmm7,%1
+    cvtsd2ss xmm8,%1
+    cvtsd2ss xmm9,%1
+    cvtsd2ss xmm10,%1
+    cvtsd2ss xmm11,%1
+    cvtsd2ss xmm12,%1
+    cvtsd2ss xmm13,%1
+    cvtsd2ss xmm14,%1
+    cvtsd2ss xmm15,%1
+%endmacro
+
+expand xmm0
+expand xmm1
+expand xmm2
+expand xmm3
+expand xmm4
+expand xmm5
+expand xmm6
+expand xmm7
+expand xmm8
+expand xmm9
+expand xmm10
+expand xmm11
+expand xmm12
+expand xmm13
+expand xmm14
+expand xmm15
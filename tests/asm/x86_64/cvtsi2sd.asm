@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    cvtsi2sd xmm0,%1
+    cvtsi2sd xmm1,%1
+    cvtsi2sd xmm2,%1
+    cvtsi2sd xmm3,%1
+    cvtsi2sd xmm4,%1
+    cvtsi2sd xmm5,%1
+    cvtsi2sd xmm6,%1
+    cvtsi2sd xmm7,%1
+    cvtsi2sd xmm8,%1
+    cvtsi2sd xmm9,%1
+    cvtsi2sd xmm10,%1
+    cvtsi2sd xmm11,%1
+    cvtsi2sd xmm12,%1
+    cvtsi2sd xmm13,%1
+    cvtsi2sd xmm14,%1
+    cvtsi2sd xmm15,%1
+%endmacro
+
+expand rax
+expand rcx
+expand rdx
+expand rbx
+expand rsp
+expand rbp
+expand rsi
+expand rdi
+expand r8
+expand r9
+expand r10
+expand r11
+expand r12
+expand r13
+expand r14
+expand r15
@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    cvtsi2ss xmm0,%1
+    cvtsi2ss xmm1,%1
+    cvtsi2ss xmm2,%1
+    cvtsi2ss xmm3,%1
+    cvtsi2ss xmm4,%1
+    cvtsi2ss xmm5,%1
+    cvtsi2ss xmm6,%1
+    cvtsi2ss xmm7,%1
+    cvtsi2ss xmm8,%1
+    cvtsi2ss xmm9,%1
+    cvtsi2ss xmm10,%1
+    cvtsi2ss xmm11,%1
+    cvtsi2ss xmm12,%1
+    cvtsi2ss xmm13,%1
+    cvtsi2ss xmm14,%1
+    cvtsi2ss xmm15,%1
+%endmacro
+
+expand rax
+expand rcx
+expand rdx
+expand rbx
+expand rsp
+expand rbp
+expand rsi
+expand rdi
+expand r8
+expand r9
+expand r10
+expand r11
+expand r12
+expand r13
+expand r14
+expand r15
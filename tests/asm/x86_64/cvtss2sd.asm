@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    cvtss2sd xmm0,%1
+    cvtss2sd xmm1,%1
+    cvtss2sd xmm2,%1
+    cvtss2sd xmm3,%1
+    cvtss2sd xmm4,%1
+    cvtss2sd xmm5,%1
+    cvtss2sd xmm6,%1
+    cvtss2sd xmm7,%1
+    cvtss2sd xmm8,%1
+    cvtss2sd xmm9,%1
+    cvtss2sd xmm10,%1
+    cvtss2sd xmm11,%1
+    cvtss2sd xmm12,%1
+    cvtss2sd xmm13,%1
+    cvtss2sd xmm14,%1
+    cvtss2sd xmm15,%1
+%endmacro
+
+expand xmm0
+expand xmm1
+expand xmm2
+expand xmm3
+expand xmm4
+expand xmm5
+expand xmm6
+expand xmm7
+expand xmm8
+expand xmm9
+expand xmm10
+expand xmm11
+expand xmm12
+expand xmm13
+expand xmm14
+expand xmm15
@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    cvttsd2si rax,%1
+    cvttsd2si rcx,%1
+    cvttsd2si rdx,%1
+    cvttsd2si rbx,%1
+    cvttsd2si rsp,%1
+    cvttsd2si rbp,%1
+    cvttsd2si rsi,%1
+    cvttsd2si rdi,%1
+    cvttsd2si r8,%1
+    cvttsd2si r9,%1
+    cvttsd2si r10,%1
+    cvttsd2si r11,%1
+    cvttsd2si r12,%1
+    cvttsd2si r13,%1
+    cvttsd2si r14,%1
+    cvttsd2si r15,%1
+%endmacro
+
+expand xmm0
+expand xmm1
+expand xmm2
+expand xmm3
+expand xmm4
+expand xmm5
+expand xmm6
+expand xmm7
+expand xmm8
+expand xmm9
+expand xmm10
+expand xmm11
+expand xmm12
+expand xmm13
+expand xmm14
+expand xmm15
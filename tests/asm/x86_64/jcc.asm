@@ -0,0 +1,12 @@
+BITS 64
+
+je  0x0
+jne 0x0
+jl  0x0
+jge 0x0
+jg  0x0
+jle 0x0
+ja  0x0
+jae 0x0
+jb  0x0
+jbe 0x0
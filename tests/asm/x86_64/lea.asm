@@ -0,0 +1,231 @@
+BITS 64
+
+%macro lea_all 2
+    lea rax,[%1+%2*1]
+    lea rcx,[%1+%2*1]
+    lea rdx,[%1+%2*1]
+    lea rbx,[%1+%2*1]
+    lea rsp,[%1+%2*1]
+    lea rbp,[%1+%2*1]
+    lea rsi,[%1+%2*1]
+    lea rdi,[%1+%2*1]
+    lea r8,[%1+%2*1]
+    lea r9,[%1+%2*1]
+    lea r10,[%1+%2*1]
+    lea r11,[%1+%2*1]
+    lea r12,[%1+%2*1]
+    lea r13,[%1+%2*1]
+    lea r14,[%1+%2*1]
+    lea r15,[%1+%2*1]
+%endmacro
+
+lea_all rax,rax
+lea_all rax,rcx
+lea_all rax,rdx
+lea_all rax,rbx
+lea_all rax,rbp
+lea_all rax,rsi
+lea_all rax,rdi
+lea_all rax,r8
+lea_all rax,r9
+lea_all rax,r10
+lea_all rax,r11
+lea_all rax,r12
+lea_all rax,r13
+lea_all rax,r14
+lea_all rax,r15
+lea_all rcx,rax
+lea_all rcx,rcx
+lea_all rcx,rdx
+lea_all rcx,rbx
+lea_all rcx,rbp
+lea_all rcx,rsi
+lea_all rcx,rdi
+lea_all rcx,r8
+lea_all rcx,r9
+lea_all rcx,r10
+lea_all rcx,r11
+lea_all rcx,r12
+lea_all rcx,r13
+lea_all rcx,r14
+lea_all rcx,r15
+lea_all rdx,rax
+lea_all rdx,rcx
+lea_all rdx,rdx
+lea_all rdx,rbx
+lea_all rdx,rbp
+lea_all rdx,rsi
+lea_all rdx,rdi
+lea_all rdx,r8
+lea_all rdx,r9
+lea_all rdx,r10
+lea_all rdx,r11
+lea_all rdx,r12
+lea_all rdx,r13
+lea_all rdx,r14
+lea_all rdx,r15
+lea_all rbx,rax
+lea_all rbx,rcx
+lea_all rbx,rdx
+lea_all rbx,rbx
+lea_all rbx,rbp
+lea_all rbx,rsi
+lea_all rbx,rdi
+lea_all rbx,r8
+lea_all rbx,r9
+lea_all rbx,r10
+lea_all rbx,r11
+lea_all rbx,r12
+lea_all rbx,r13
+lea_all rbx,r14
+lea_all rbx,r15
+lea_all rsp,rax
+lea_all rsp,rcx
+lea_all rsp,rdx
+lea_all rsp,rbx
+lea_all rsp,rbp
+lea_all rsp,rsi
+lea_all rsp,rdi
+lea_all rsp,r8
+lea_all rsp,r9
+lea_all rsp,r10
+lea_all rsp,r11
+lea_all rsp,r12
+lea_all rsp,r13
+lea_all rsp,r14
+lea_all rsp,r15
+lea_all rsi,rax
+lea_all rsi,rcx
+lea_all rsi,rdx
+lea_all rsi,rbx
+lea_all rsi,rbp
+lea_all rsi,rsi
+lea_all rsi,rdi
+lea_all rsi,r8
+lea_all rsi,r9
+lea_all rsi,r10
+lea_all rsi,r11
+lea_all rsi,r12
+lea_all rsi,r13
+lea_all rsi,r14
+lea_all rsi,r15
+lea_all rdi,rax
+lea_all rdi,rcx
+lea_all rdi,rdx
+lea_all rdi,rbx
+lea_all rdi,rbp
+lea_all rdi,rsi
+lea_all rdi,rdi
+lea_all rdi,r8
+lea_all rdi,r9
+lea_all rdi,r10
+lea_all rdi,r11
+lea_all rdi,r12
+lea_all rdi,r13
+lea_all rdi,r14
+lea_all rdi,r15
+lea_all r8,rax
+lea_all r8,rcx
+lea_all r8,rdx
+lea_all r8,rbx
+lea_all r8,rbp
+lea_all r8,rsi
+lea_all r8,rdi
+lea_all r8,r8
+lea_all r8,r9
+lea_all r8,r10
+lea_all r8,r11
+lea_all r8,r12
+lea_all r8,r13
+lea_all r8,r14
+lea_all r8,r15
+lea_all r9,rax
+lea_all r9,rcx
+lea_all r9,rdx
+lea_all r9,rbx
+lea_all r9,rbp
+lea_all r9,rsi
+lea_all r9,rdi
+lea_all r9,r8
+lea_all r9,r9
+lea_all r9,r10
+lea_all r9,r11
+lea_all r9,r12
+lea_all r9,r13
+lea_all r9,r14
+lea_all r9,r15
+lea_all r10,rax
+lea_all r10,rcx
+lea_all r10,rdx
+lea_all r10,rbx
+lea_all r10,rbp
+lea_all r10,rsi
+lea_all r10,rdi
+lea_all r10,r8
+lea_all r10,r9
+lea_all r10,r10
+lea_all r10,r11
+lea_all r10,r12
+lea_all r10,r13
+lea_all r10,r14
+lea_all r10,r15
+lea_all r11,rax
+lea_all r11,rcx
+lea_all r11,rdx
+lea_all r11,rbx
+lea_all r11,rbp
+lea_all r11,rsi
+lea_all r11,rdi
+lea_all r11,r8
+lea_all r11,r9
+lea_all r11,r10
+lea_all r11,r11
+lea_all r11,r12
+lea_all r11,r13
+lea_all r11,r14
+lea_all r11,r15
+lea_all r12,rax
+lea_all r12,rcx
+lea_all r12,rdx
+lea_all r12,rbx
+lea_all r12,rbp
+lea_all r12,rsi
+lea_all r12,rdi
+lea_all r12,r8
+lea_all r12,r9
+lea_all r12,r10
+lea_all r12,r11
+lea_all r12,r12
+lea_all r12,r13
+lea_all r12,r14
+lea_all r12,r15
+lea_all r14,rax
+lea_all r14,rcx
+lea_all r14,rdx
+lea_all r14,rbx
+lea_all r14,rbp
+lea_all r14,rsi
+lea_all r14,rdi
+lea_all r14,r8
+lea_all r14,r9
+lea_all r14,r10
+lea_all r14,r11
+lea_all r14,r12
+lea_all r14,r13
+lea_all r14,r14
+lea_all r14,r15
+lea_all r15,rax
+lea_all r15,rcx
+lea_all r15,rdx
+lea_all r15,rbx
+lea_all r15,rbp
+lea_all r15,rsi
+lea_all r15,rdi
+lea_all r15,r8
+lea_all r15,r9
+lea_all r15,r10
+lea_all r15,r11
+lea_all r15,r12
+lea_all r15,r13
+lea_all r15,r14
+lea_all r15,r15
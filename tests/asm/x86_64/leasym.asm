@@ -0,0 +1,24 @@
+BITS 64
+
+; The displacement is zero because it is patched by the linker.
+%macro leasym 1
+    lea %1,[rel %%next]
+%%next:
+%endmacro
+
+leasym rax
+leasym rcx
+leasym rdx
+leasym rbx
+leasym rsp
+leasym rbp
+leasym rsi
+leasym rdi
+leasym r8
+leasym r9
+leasym r10
+leasym r11
+leasym r12
+leasym r13
+leasym r14
+leasym r15
@@ -0,0 +1,24 @@
+BITS 64
+
+; The displacement is zero because it is patched by the linker.
+%macro loadgot 1
+    mov %1,[rel %%next]
+%%next:
+%endmacro
+
+loadgot rax
+loadgot rcx
+loadgot rdx
+loadgot rbx
+loadgot rsp
+loadgot rbp
+loadgot rsi
+loadgot rdi
+loadgot r8
+loadgot r9
+loadgot r10
+loadgot r11
+loadgot r12
+loadgot r13
+loadgot r14
+loadgot r15
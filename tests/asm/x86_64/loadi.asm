@@ -33,6 +33,23 @@ xor r13d,r13d
 xor r14d,r14d
 xor r15d,r15d
 
+mov rax,strict dword -0x21524111
+mov rcx,strict dword -0x21524111
+mov rdx,strict dword -0x21524111
+mov rbx,strict dword -0x21524111
+mov rsp,strict dword -0x21524111
+mov rbp,strict dword -0x21524111
+mov rsi,strict dword -0x21524111
+mov rdi,strict dword -0x21524111
+mov r8,strict dword -0x21524111
+mov r9,strict dword -0x21524111
+mov r10,strict dword -0x21524111
+mov r11,strict dword -0x21524111
+mov r12,strict dword -0x21524111
+mov r13,strict dword -0x21524111
+mov r14,strict dword -0x21524111
+mov r15,strict dword -0x21524111
+
 mov eax,0xdeadbeef
 mov ecx,0xdeadbeef
 mov edx,0xdeadbeef
@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    movsd xmm0,%1
+    movsd xmm1,%1
+    movsd xmm2,%1
+    movsd xmm3,%1
+    movsd xmm4,%1
+    movsd xmm5,%1
+    movsd xmm6,%1
+    movsd xmm7,%1
+    movsd xmm8,%1
+    movsd xmm9,%1
+    movsd xmm10,%1
+    movsd xmm11,%1
+    movsd xmm12,%1
+    movsd xmm13,%1
+    movsd xmm14,%1
+    movsd xmm15,%1
+%endmacro
+
+expand xmm0
+expand xmm1
+expand xmm2
+expand xmm3
+expand xmm4
+expand xmm5
+expand xmm6
+expand xmm7
+expand xmm8
+expand xmm9
+expand xmm10
+expand xmm11
+expand xmm12
+expand xmm13
+expand xmm14
+expand xmm15
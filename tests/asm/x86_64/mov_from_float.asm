@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    movq rax,%1
+    movq rcx,%1
+    movq rdx,%1
+    movq rbx,%1
+    movq rsp,%1
+    movq rbp,%1
+    movq rsi,%1
+    movq rdi,%1
+    movq r8,%1
+    movq r9,%1
+    movq r10,%1
+    movq r11,%1
+    movq r12,%1
+    movq r13,%1
+    movq r14,%1
+    movq r15,%1
+%endmacro
+
+expand xmm0
+expand xmm1
+expand xmm2
+expand xmm3
+expand xmm4
+expand xmm5
+expand xmm6
+expand xmm7
+expand xmm8
+expand xmm9
+expand xmm10
+expand xmm11
+expand xmm12
+expand xmm13
+expand xmm14
+expand xmm15
@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    movq xmm0,%1
+    movq xmm1,%1
+    movq xmm2,%1
+    movq xmm3,%1
+    movq xmm4,%1
+    movq xmm5,%1
+    movq xmm6,%1
+    movq xmm7,%1
+    movq xmm8,%1
+    movq xmm9,%1
+    movq xmm10,%1
+    movq xmm11,%1
+    movq xmm12,%1
+    movq xmm13,%1
+    movq xmm14,%1
+    movq xmm15,%1
+%endmacro
+
+expand rax
+expand rcx
+expand rdx
+expand rbx
+expand rsp
+expand rbp
+expand rsi
+expand rdi
+expand r8
+expand r9
+expand r10
+expand r11
+expand r12
+expand r13
+expand r14
+expand r15
@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    imul rax,%1
+    imul rcx,%1
+    imul rdx,%1
+    imul rbx,%1
+    imul rsp,%1
+    imul rbp,%1
+    imul rsi,%1
+    imul rdi,%1
+    imul r8,%1
+    imul r9,%1
+    imul r10,%1
+    imul r11,%1
+    imul r12,%1
+    imul r13,%1
+    imul r14,%1
+    imul r15,%1
+%endmacro
+
+expand rax
+expand rcx
+expand rdx
+expand rbx
+expand rsp
+expand rbp
+expand rsi
+expand rdi
+expand r8
+expand r9
+expand r10
+expand r11
+expand r12
+expand r13
+expand r14
+expand r15
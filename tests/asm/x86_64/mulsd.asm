@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    mulsd xmm0,%1
+    mulsd xmm1,%1
+    mulsd xmm2,%1
+    mulsd xmm3,%1
+    mulsd xmm4,%1
+    mulsd xmm5,%1
+    mulsd xmm6,%1
+    mulsd xmm7,%1
+    mulsd xmm8,%1
+    mulsd xmm9,%1
+    mulsd xmm10,%1
+    mulsd xmm11,%1
+    mulsd xmm12,%1
+    mulsd xmm13,%1
+    mulsd xmm14,%1
+    mulsd xmm15,%1
+%endmacro
+
+expand xmm0
+expand xmm1
+expand xmm2
+expand xmm3
+expand xmm4
+expand xmm5
+expand xmm6
+expand xmm7
+expand xmm8
+expand xmm9
+expand xmm10
+expand xmm11
+expand xmm12
+expand xmm13
+expand xmm14
+expand xmm15
@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    mulss xmm0,%1
+    mulss xmm1,%1
+    mulss xmm2,%1
+    mulss xmm3,%1
+    mulss xmm4,%1
+    mulss xmm5,%1
+    mulss xmm6,%1
+    mulss xmm7,%1
+    mulss xmm8,%1
+    mulss xmm9,%1
+    mulss xmm10,%1
+    mulss xmm11,%1
+    mulss xmm12,%1
+    mulss xmm13,%1
+    mulss xmm14,%1
+    mulss xmm15,%1
+%endmacro
+
+expand xmm0
+expand xmm1
+expand xmm2
+expand xmm3
+expand xmm4
+expand xmm5
+expand xmm6
+expand xmm7
+expand xmm8
+expand xmm9
+expand xmm10
+expand xmm11
+expand xmm12
+expand xmm13
+expand xmm14
+expand xmm15
@@ -0,0 +1,34 @@
+BITS 64
+
+movsd xmm0,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm1,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm2,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm3,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm4,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm5,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm6,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm7,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm8,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm9,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm10,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm11,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm12,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm13,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm14,[rsp]
+lea rsp,[byte rsp+8]
+movsd xmm15,[rsp]
+lea rsp,[byte rsp+8]
@@ -0,0 +1,34 @@
+BITS 64
+
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm0
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm1
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm2
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm3
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm4
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm5
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm6
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm7
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm8
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm9
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm10
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm11
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm12
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm13
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm14
+lea rsp,[byte rsp-8]
+movsd [rsp],xmm15
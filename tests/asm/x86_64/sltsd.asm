@@ -0,0 +1,62 @@
+BITS 64
+
+%macro sltsd 5
+    xor %4,%4
+    ucomisd %2,%1
+    seta %5
+%endmacro
+
+%macro sltsd2 2
+    sltsd %1,%2,rax,eax,al
+    sltsd %1,%2,rcx,ecx,cl
+    sltsd %1,%2,rdx,edx,dl
+    sltsd %1,%2,rbx,ebx,bl
+    sltsd %1,%2,rsp,esp,spl
+    sltsd %1,%2,rbp,ebp,bpl
+    sltsd %1,%2,rsi,esi,sil
+    sltsd %1,%2,rdi,edi,dil
+    sltsd %1,%2,r8,r8d,r8b
+    sltsd %1,%2,r9,r9d,r9b
+    sltsd %1,%2,r10,r10d,r10b
+    sltsd %1,%2,r11,r11d,r11b
+    sltsd %1,%2,r12,r12d,r12b
+    sltsd %1,%2,r13,r13d,r13b
+    sltsd %1,%2,r14,r14d,r14b
+    sltsd %1,%2,r15,r15d,r15b
+%endmacro
+
+%macro expand 1
+    sltsd2 %1,xmm0
+    sltsd2 %1,xmm1
+    sltsd2 %1,xmm2
+    sltsd2 %1,xmm3
+    sltsd2 %1,xmm4
+    sltsd2 %1,xmm5
+    sltsd2 %1,xmm6
+    sltsd2 %1,xmm7
+    sltsd2 %1,xmm8
+    sltsd2 %1,xmm9
+    sltsd2 %1,xmm10
+    sltsd2 %1,xmm11
+    sltsd2 %1,xmm12
+    sltsd2 %1,xmm13
+    sltsd2 %1,xmm14
+    sltsd2 %1,xmm15
+%endmacro
+
+expand xmm0
+expand xmm1
+expand xmm2
+expand xmm3
+expand xmm4
+expand xmm5
+expand xmm6
+expand xmm7
+expand xmm8
+expand xmm9
+expand xmm10
+expand xmm11
+expand xmm12
+expand xmm13
+expand xmm14
+expand xmm15
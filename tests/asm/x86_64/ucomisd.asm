@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    ucomisd %1,xmm0
+    ucomisd %1,xmm1
+    ucomisd %1,xmm2
+    ucomisd %1,xmm3
+    ucomisd %1,xmm4
+    ucomisd %1,xmm5
+    ucomisd %1,xmm6
+    ucomisd %1,xmm7
+    ucomisd %1,xmm8
+    ucomisd %1,xmm9
+    ucomisd %1,xmm10
+    ucomisd %1,xmm11
+    ucomisd %1,xmm12
+    ucomisd %1,xmm13
+    ucomisd %1,xmm14
+    ucomisd %1,xmm15
+%endmacro
+
+expand xmm0
+expand xmm1
+expand xmm2
+expand xmm3
+expand xmm4
+expand xmm5
+expand xmm6
+expand xmm7
+expand xmm8
+expand xmm9
+expand xmm10
+expand xmm11
+expand xmm12
+expand xmm13
+expand xmm14
+expand xmm15
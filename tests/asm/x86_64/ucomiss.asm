@@ -0,0 +1,37 @@
+BITS 64
+
+%macro expand 1
+    ucomiss %1,xmm0
+    ucomiss %1,xmm1
+    ucomiss %1,xmm2
+    ucomiss %1,xmm3
+    ucomiss %1,xmm4
+    ucomiss %1,xmm5
+    ucomiss %1,xmm6
+    ucomiss %1,xmm7
+    ucomiss %1,xmm8
+    ucomiss %1,xmm9
+    ucomiss %1,xmm10
+    ucomiss %1,xmm11
+    ucomiss %1,xmm12
+    ucomiss %1,xmm13
+    ucomiss %1,xmm14
+    ucomiss %1,xmm15
+%endmacro
+
+expand xmm0
+expand xmm1
+expand xmm2
+expand xmm3
+expand xmm4
+expand xmm5
+expand xmm6
+expand xmm7
+expand xmm8
+expand xmm9
+expand xmm10
+expand xmm11
+expand xmm12
+expand xmm13
+expand xmm14
+expand xmm15
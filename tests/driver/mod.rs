@@ -0,0 +1,1069 @@
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use object::{elf, read::File, Object, ObjectSection, ObjectSymbol, RelocationKind, SymbolFlags};
+use pijama::{
+    driver::{run, DriverError, Emit, Options},
+    emit::{Arch, Format},
+    pass::OptLevel,
+};
+
+const LIB: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/lib.mir");
+const MAIN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/main.c");
+
+fn parse(args: &[&str]) -> Result<Options, DriverError> {
+    Options::parse(args.iter().map(|arg| arg.to_string()))
+}
+
+/// Returns an empty directory for the outputs of a test.
+fn output_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pijama-driver-{name}"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn options() {
+    let options = parse(&[
+        "lib.mir",
+        "-O2",
+        "--emit=obj,asm",
+        "--target",
+        "x86_64-macos",
+        "-o",
+        "out",
+        "--disable-pass=dead-jumps",
+        "--time-passes",
+        "--unroll-factor=8",
+    ])
+    .unwrap();
+
+    assert_eq!(options.inputs, [PathBuf::from("lib.mir")]);
+    assert_eq!(options.output, Some(PathBuf::from("out")));
+    assert_eq!(options.passes.opt_level, OptLevel::O2);
+    assert!(options.passes.disabled.contains("dead-jumps"));
+    assert!(options.passes.time_passes);
+    assert_eq!(options.unroll_factor, 8);
+    assert_eq!(options.format, Format::MachO);
+    assert_eq!(options.arch, Arch::X86_64);
+    assert_eq!(options.emit, [Emit::Asm, Emit::Obj]);
+
+    let options = parse(&["lib.mir", "--target=aarch64-linux"]).unwrap();
+    assert_eq!(options.format, Format::Elf);
+    assert_eq!(options.arch, Arch::Aarch64);
+
+    let options = parse(&["lib.mir", "--target=riscv64-linux"]).unwrap();
+    assert_eq!(options.format, Format::Elf);
+    assert_eq!(options.arch, Arch::Riscv64);
+}
+
+#[test]
+fn usage_errors() {
+    for args in [
+        &[][..],
+        &["lib.mir", "--emit=wasm"],
+        &["lib.mir", "--target", "mips"],
+        &["lib.mir", "-o"],
+        &["lib.mir", "--frobnicate"],
+        &["lib.mir", "--unroll-factor=0"],
+        &["lib.mir", "--profile-use"],
+    ] {
+        let err = parse(args).unwrap_err();
+        assert!(matches!(err, DriverError::Usage(_)), "{args:?}: {err}");
+        assert_eq!(err.exit_code(), 2);
+    }
+}
+
+#[test]
+fn unknown_pass() {
+    let err = run(&parse(&[LIB, "--enable-pass=unroll-everything"]).unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "unknown pass `unroll-everything`");
+    assert_eq!(err.exit_code(), 2);
+}
+
+#[test]
+fn parse_error() {
+    let dir = output_dir("parse-error");
+    let input = dir.join("bad.mir");
+    fs::write(&input, "fn f() -> int {\n    bb0: JUMP bb1\n}\n").unwrap();
+
+    let err = run(&parse(&[input.to_str().unwrap()]).unwrap()).unwrap_err();
+    assert_eq!(err.exit_code(), 1);
+    assert_eq!(
+        err.to_string(),
+        format!("{}:2:15: block `bb1` is not defined", input.display())
+    );
+}
+
+#[test]
+fn lowering_error() {
+    let dir = output_dir("lowering-error");
+    let input = dir.join("locals.mir");
+    let output = dir.join("locals");
+    fs::write(
+        &input,
+        "\
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: int
+    let _4: int
+    let _5: int
+
+    bb0: _2 = _1 + 1
+         _3 = _2 + 1
+         _4 = _3 + 1
+         _5 = _4 + 1
+         _0 = USE _5
+         RETURN
+}
+",
+    )
+    .unwrap();
+    let args = |emit| {
+        [
+            input.to_str().unwrap(),
+            "-O0",
+            emit,
+            "-o",
+            output.to_str().unwrap(),
+        ]
+    };
+
+    let err = run(&parse(&args("--emit=obj")).unwrap()).unwrap_err();
+    assert_eq!(err.exit_code(), 1);
+    assert_eq!(
+        err.to_string(),
+        "cannot lower `f`: not enough registers for 6 locals"
+    );
+
+    // The functions are only lowered when the output needs their instructions.
+    run(&parse(&args("--emit=mir")).unwrap()).unwrap();
+    assert!(fs::read_to_string(&output).unwrap().starts_with("fn f("));
+}
+
+#[test]
+fn emit_obj_and_asm() {
+    let dir = output_dir("obj-and-asm");
+    let output = dir.join("lib");
+
+    run(&parse(&[LIB, "--emit=asm,obj", "-o", output.to_str().unwrap()]).unwrap()).unwrap();
+
+    let asm = fs::read_to_string(output.with_extension("s")).unwrap();
+    assert!(asm.starts_with("duplicate:\n"));
+    assert!(asm.contains("start:\n.L0:\n    loadi 0xa,rax\n    ret\n"));
+
+    let bytes = fs::read(output.with_extension("o")).unwrap();
+    let file = File::parse(&*bytes).unwrap();
+    let mut names = file
+        .symbols()
+        .filter(|symbol| symbol.is_global())
+        .map(|symbol| symbol.name().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["duplicate", "start"]);
+}
+
+#[test]
+fn symbol_visibility() {
+    let dir = output_dir("visibility");
+    let input = dir.join("address.mir");
+    let output = dir.join("address.o");
+    fs::write(
+        &input,
+        "\
+fn address() -> int {
+    bb0: _0 = ADDR address
+         RETURN
+}
+",
+    )
+    .unwrap();
+    let compile = |visibility| {
+        run(&parse(&[
+            input.to_str().unwrap(),
+            "--target=x86_64-linux",
+            "--pic",
+            visibility,
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .unwrap())
+        .unwrap();
+
+        let bytes = fs::read(&output).unwrap();
+        let file = File::parse(&*bytes).unwrap();
+        let symbol = file
+            .symbols()
+            .find(|symbol| symbol.name() == Ok("address"))
+            .unwrap();
+        let (_, relocation) = file
+            .section_by_name(".text")
+            .unwrap()
+            .relocations()
+            .next()
+            .unwrap();
+        (symbol.is_global(), symbol.flags(), relocation.kind())
+    };
+
+    // Symbols that can be preempted are accessed through the global offset table.
+    assert_eq!(
+        compile("--visibility=default"),
+        (
+            true,
+            SymbolFlags::Elf {
+                st_info: (elf::STB_GLOBAL << 4) | elf::STT_FUNC,
+                st_other: elf::STV_DEFAULT,
+            },
+            RelocationKind::Elf(elf::R_X86_64_REX_GOTPCRELX),
+        )
+    );
+    assert_eq!(
+        compile("--visibility=hidden"),
+        (
+            true,
+            SymbolFlags::Elf {
+                st_info: (elf::STB_GLOBAL << 4) | elf::STT_FUNC,
+                st_other: elf::STV_HIDDEN,
+            },
+            RelocationKind::Relative,
+        )
+    );
+    assert_eq!(
+        compile("--visibility=protected"),
+        (
+            true,
+            SymbolFlags::Elf {
+                st_info: (elf::STB_GLOBAL << 4) | elf::STT_FUNC,
+                st_other: elf::STV_PROTECTED,
+            },
+            RelocationKind::Relative,
+        )
+    );
+
+    assert!(matches!(
+        parse(&["lib.mir", "--visibility=internal"]),
+        Err(DriverError::Usage(..))
+    ));
+}
+
+#[test]
+fn emit_aarch64_obj() {
+    let dir = output_dir("aarch64-obj");
+    let output = dir.join("lib.o");
+
+    run(&parse(&[
+        LIB,
+        "--target=aarch64-linux",
+        "-o",
+        output.to_str().unwrap(),
+    ])
+    .unwrap())
+    .unwrap();
+
+    let bytes = fs::read(output).unwrap();
+    let file = File::parse(&*bytes).unwrap();
+    assert_eq!(file.architecture(), object::Architecture::Aarch64);
+
+    let start = file
+        .symbols()
+        .find(|symbol| symbol.name() == Ok("start"))
+        .unwrap();
+    let text = file
+        .section_by_index(start.section_index().unwrap())
+        .unwrap();
+    let code = &text.data().unwrap()[start.address() as usize..][..12];
+    // `mov x9,0xa`, `mov x0,x9` and `ret`.
+    assert_eq!(
+        code,
+        [0x49, 0x01, 0x80, 0xd2, 0xe0, 0x03, 0x09, 0xaa, 0xc0, 0x03, 0x5f, 0xd6]
+    );
+}
+
+#[test]
+fn emit_riscv64_obj() {
+    let dir = output_dir("riscv64-obj");
+    let output = dir.join("lib.o");
+
+    run(&parse(&[
+        LIB,
+        "--target=riscv64-linux",
+        "-o",
+        output.to_str().unwrap(),
+    ])
+    .unwrap())
+    .unwrap();
+
+    let bytes = fs::read(output).unwrap();
+    let file = File::parse(&*bytes).unwrap();
+    assert_eq!(file.architecture(), object::Architecture::Riscv64);
+
+    let start = file
+        .symbols()
+        .find(|symbol| symbol.name() == Ok("start"))
+        .unwrap();
+    let text = file
+        .section_by_index(start.section_index().unwrap())
+        .unwrap();
+    let code = &text.data().unwrap()[start.address() as usize..][..12];
+    // `li t0,10`, `mv a0,t0` and `ret`.
+    assert_eq!(
+        code,
+        [0x93, 0x02, 0xa0, 0x00, 0x13, 0x85, 0x02, 0x00, 0x67, 0x80, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn emit_windows_obj() {
+    let dir = output_dir("windows-obj");
+    let output = dir.join("lib");
+
+    run(&parse(&[
+        LIB,
+        "--target=x86_64-windows",
+        "--emit=asm,obj",
+        "-o",
+        output.to_str().unwrap(),
+    ])
+    .unwrap())
+    .unwrap();
+
+    // The argument of `duplicate` arrives in `rcx` following the Windows x64 convention.
+    let asm = fs::read_to_string(output.with_extension("s")).unwrap();
+    assert!(asm.starts_with(
+        "duplicate:\n.L0:\n    loadi 0x0,rax\n    loadi 0x0,rdx\n.L1:\n    cmp rdx,rcx\n"
+    ));
+
+    let bytes = fs::read(output.with_extension("obj")).unwrap();
+    let file = File::parse(&*bytes).unwrap();
+    assert_eq!(file.format(), object::BinaryFormat::Coff);
+    assert_eq!(file.architecture(), object::Architecture::X86_64);
+}
+
+#[test]
+fn emit_exe() {
+    let dir = output_dir("exe");
+    let output = dir.join("main");
+
+    run(&parse(&[LIB, MAIN, "--emit=exe", "-o", output.to_str().unwrap()]).unwrap()).unwrap();
+
+    let result = Command::new(&output).output().unwrap();
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), "20\n");
+}
+
+#[test]
+fn fused_compare_and_branch() {
+    let dir = output_dir("fused-branch");
+    let output = dir.join("main");
+
+    let options = parse(&[
+        LIB,
+        MAIN,
+        "-O0",
+        "--emit=asm,exe",
+        "-o",
+        output.to_str().unwrap(),
+    ])
+    .unwrap();
+    run(&options).unwrap();
+
+    // The condition of the loop is only read by the jump, so it is never stored in a register.
+    let asm = fs::read_to_string(output.with_extension("s")).unwrap();
+    assert!(asm.contains(".L1:\n    cmp rsi,rdi\n    jge .L3\n    jmp .L2\n"));
+    assert!(!asm.contains("slt"));
+
+    let result = Command::new(&output).output().unwrap();
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), "20\n");
+}
+
+#[test]
+fn return_value_read_by_statement() {
+    let dir = output_dir("return-value-read");
+    let lib = dir.join("seven.mir");
+    let main = dir.join("main.c");
+    let output = dir.join("main");
+
+    fs::write(
+        &lib,
+        "\
+fn seven(_1: int) -> int {
+    let _2: bool
+
+    bb0: _0 = USE 7
+         _2 = _0 < _1
+         RETURN
+}
+",
+    )
+    .unwrap();
+    fs::write(
+        &main,
+        "\
+#include <stdio.h>
+
+extern long seven(long);
+
+int main() {
+  printf(\"%ld\\n\", seven(3));
+  return 0;
+}
+",
+    )
+    .unwrap();
+
+    // `_0` is read by the comparison and by the return, so it is not folded into the comparison.
+    let options = parse(&[
+        lib.to_str().unwrap(),
+        main.to_str().unwrap(),
+        "-O0",
+        "--emit=exe",
+        "-o",
+        output.to_str().unwrap(),
+    ])
+    .unwrap();
+    run(&options).unwrap();
+
+    let result = Command::new(&output).output().unwrap();
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), "7\n");
+}
+
+#[test]
+fn integers_are_64_bits() {
+    let dir = output_dir("integers");
+    let lib = dir.join("integers.mir");
+    let main = dir.join("main.c");
+    fs::write(
+        &lib,
+        "\
+fn overflow() -> bool {
+    let _1: int
+
+    bb0: _1 = 2147483647 + 1
+         _0 = _1 < 0
+         RETURN
+}
+
+fn square() -> int {
+    let _1: int
+
+    bb0: _1 = USE 65536
+         _0 = _1 * _1
+         RETURN
+}
+
+fn negative() -> int {
+    let _1: int
+
+    bb0: _1 = USE 1
+         _0 = _1 + -3
+         RETURN
+}
+",
+    )
+    .unwrap();
+    fs::write(
+        &main,
+        "\
+#include <stdio.h>
+
+extern long overflow(void);
+extern long square(void);
+extern long negative(void);
+
+int main() {
+  printf(\"%ld %ld %ld\\n\", overflow(), square(), negative());
+  return 0;
+}
+",
+    )
+    .unwrap();
+
+    // Folding the constants gives the same results as computing them in registers.
+    for opt_level in ["-O0", "-O1"] {
+        let output = dir.join(format!("main{opt_level}"));
+        let options = parse(&[
+            lib.to_str().unwrap(),
+            main.to_str().unwrap(),
+            opt_level,
+            "--emit=exe",
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .unwrap();
+        run(&options).unwrap();
+
+        let result = Command::new(&output).output().unwrap();
+        assert!(result.status.success());
+        assert_eq!(
+            String::from_utf8(result.stdout).unwrap(),
+            "0 4294967296 -2\n",
+            "{opt_level}"
+        );
+    }
+}
+
+#[test]
+fn float_exe() {
+    let dir = output_dir("float-exe");
+    let lib = dir.join("scale.mir");
+    let main = dir.join("main.c");
+    let output = dir.join("main");
+
+    fs::write(
+        &lib,
+        "\
+fn scale(_1: f64, _2: int) -> f64 {
+    let _3: f64
+    let _4: bool
+
+    bb0: _3 = CAST _2 AS f64
+         _3 = _3 * _1
+         _4 = _3 < 0.0
+         JUMP IF _4 THEN bb1 ELSE bb2
+
+    bb1: _3 = _3 * -1.0
+         JUMP bb2
+
+    bb2: _0 = CALL mix(_3, _2, 0.5f32)
+         RETURN
+}
+",
+    )
+    .unwrap();
+    fs::write(
+        &main,
+        "\
+#include <stdio.h>
+
+extern double scale(double, long);
+
+double mix(double a, long b, float c) { return a + b * c; }
+
+int main() {
+  printf(\"%g %g\\n\", scale(1.25, 4), scale(-2.5, 3));
+  return 0;
+}
+",
+    )
+    .unwrap();
+
+    let options = parse(&[
+        lib.to_str().unwrap(),
+        main.to_str().unwrap(),
+        "--emit=exe",
+        "-o",
+        output.to_str().unwrap(),
+    ])
+    .unwrap();
+    run(&options).unwrap();
+
+    let result = Command::new(&output).output().unwrap();
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), "7 9\n");
+}
+
+#[test]
+fn floats_without_float_registers() {
+    let dir = output_dir("floats-unsupported");
+    let mir = dir.join("half.mir");
+    let asm = dir.join("add.s");
+    let output = dir.join("out.o");
+    fs::write(
+        &mir,
+        "\
+fn half(_1: f64) -> f64 {
+    bb0: _0 = _1 * 0.5
+         RETURN
+}
+",
+    )
+    .unwrap();
+    fs::write(&asm, "add:\n    addsd x0,x1\n    ret\n").unwrap();
+
+    for target in ["--target=aarch64-linux", "--target=riscv64-linux"] {
+        let args = [
+            mir.to_str().unwrap(),
+            target,
+            "-o",
+            output.to_str().unwrap(),
+        ];
+        let err = run(&parse(&args).unwrap()).unwrap_err();
+        assert_eq!(err.exit_code(), 1);
+        assert_eq!(
+            err.to_string(),
+            "`half` uses floats, which the target does not support"
+        );
+    }
+
+    // Assembly inputs with float instructions are rejected before they reach the assembler.
+    let args = [
+        asm.to_str().unwrap(),
+        "--target=aarch64-linux",
+        "-o",
+        output.to_str().unwrap(),
+    ];
+    let err = run(&parse(&args).unwrap()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`add` uses floats, which the target does not support"
+    );
+}
+
+#[test]
+fn cold_blocks_are_placed_last() {
+    let dir = output_dir("cold-blocks");
+    let input = dir.join("clamp.mir");
+    let output = dir.join("clamp.s");
+
+    fs::write(
+        &input,
+        "\
+fn clamp(_1: int) -> int {
+    let _2: bool
+
+    bb0: _2 = _1 < 0
+         JUMP IF _2 THEN bb1 ELSE bb2
+
+    #[cold]
+    bb1: _0 = USE 0
+         RETURN
+
+    bb2: _0 = USE _1
+         RETURN
+}
+",
+    )
+    .unwrap();
+
+    let options = parse(&[
+        input.to_str().unwrap(),
+        "-O1",
+        "--emit=asm",
+        "-o",
+        output.to_str().unwrap(),
+    ])
+    .unwrap();
+    run(&options).unwrap();
+
+    // The jump goes to the cold block, so the other one is reached by falling through.
+    let asm = fs::read_to_string(&output).unwrap();
+    assert!(asm.contains("    jl .L2\n.L1:\n    mov rdi,rax\n    ret\n.L2:\n"));
+}
+
+#[test]
+fn emit_exe_optimized() {
+    let dir = output_dir("exe-O2");
+    let output = dir.join("main");
+
+    let options = parse(&[
+        LIB,
+        MAIN,
+        "-O2",
+        "--emit=asm,exe",
+        "-o",
+        output.to_str().unwrap(),
+    ])
+    .unwrap();
+    run(&options).unwrap();
+
+    // The loop of `duplicate` is replaced by its closed form.
+    let asm = fs::read_to_string(output.with_extension("s")).unwrap();
+    assert!(!asm.contains("jmp"));
+
+    let result = Command::new(&output).output().unwrap();
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), "20\n");
+}
+
+#[test]
+fn loop_uses_every_register() {
+    let dir = output_dir("every-register");
+    let lib = dir.join("power.mir");
+    let main = dir.join("main.c");
+    let output = dir.join("main");
+
+    // The five locals need all the allocatable registers of x86-64, so the copies and temporaries
+    // introduced by SSA form cannot be left in the function.
+    fs::write(
+        &lib,
+        "\
+fn power(_1: int) -> int {
+    let _2: int
+    let _3: int
+    let _4: bool
+
+    bb0: _0 = USE 0
+         _2 = USE 0
+         _3 = USE 1
+         JUMP bb1
+
+    bb1: _4 = _2 < _1
+         JUMP IF _4 THEN bb2 ELSE bb3
+
+    bb2: _0 = _3 * _1
+         _3 = _3 * _3
+         _3 = _0 * _3
+         _2 = _2 + 1
+         JUMP bb1
+
+    bb3: RETURN
+}
+",
+    )
+    .unwrap();
+    fs::write(
+        &main,
+        "\
+#include <stdio.h>
+
+extern long power(long);
+
+int main() {
+  printf(\"%ld\\n\", power(3));
+  return 0;
+}
+",
+    )
+    .unwrap();
+
+    let options = parse(&[
+        lib.to_str().unwrap(),
+        main.to_str().unwrap(),
+        "-O2",
+        "--emit=exe",
+        "-o",
+        output.to_str().unwrap(),
+    ])
+    .unwrap();
+    run(&options).unwrap();
+
+    let result = Command::new(&output).output().unwrap();
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), "243\n");
+}
+
+#[test]
+fn loop_at_entry() {
+    let dir = output_dir("loop-at-entry");
+    let lib = dir.join("count.mir");
+    let main = dir.join("main.c");
+    let output = dir.join("main");
+
+    // The entry block is the header of the loop, so the argument reaches it from the caller and
+    // from the back edge.
+    fs::write(
+        &lib,
+        "\
+fn count(_1: int) -> int {
+    let _2: bool
+
+    bb0: _1 = _1 + -1
+         _2 = 0 < _1
+         JUMP IF _2 THEN bb0 ELSE bb1
+
+    bb1: _0 = USE _1
+         RETURN
+}
+",
+    )
+    .unwrap();
+    fs::write(
+        &main,
+        "\
+#include <stdio.h>
+
+extern long count(long);
+
+int main() {
+  printf(\"%ld\\n\", count(5));
+  return 0;
+}
+",
+    )
+    .unwrap();
+
+    let options = parse(&[
+        lib.to_str().unwrap(),
+        main.to_str().unwrap(),
+        "-O2",
+        "--emit=exe",
+        "-o",
+        output.to_str().unwrap(),
+    ])
+    .unwrap();
+    run(&options).unwrap();
+
+    // A miscompiled loop never ends.
+    let mut child = Command::new(&output)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let start = Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if start.elapsed() > Duration::from_secs(10) {
+            child.kill().unwrap();
+            panic!("`count(5)` did not return");
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let result = child.wait_with_output().unwrap();
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), "0\n");
+}
+
+#[test]
+fn unrolling_respects_registers() {
+    let dir = output_dir("unroll-registers");
+    let lib = dir.join("scale.mir");
+    let main = dir.join("main.c");
+    let output = dir.join("main");
+
+    // Unrolling the loop would need a sixth register, so it is left as it is.
+    fs::write(
+        &lib,
+        "\
+fn scale(_1: int) -> int {
+    let _2: int
+    let _3: int
+    let _4: bool
+
+    bb0: _0 = USE 0
+         _2 = USE 0
+         _3 = USE 4
+         JUMP bb1
+
+    bb1: _4 = _2 < _1
+         JUMP IF _4 THEN bb2 ELSE bb3
+
+    bb2: _0 = _3 * _1
+         _3 = _3 * 2
+         _3 = _0 * _3
+         _0 = _0 + _0
+         _2 = _2 + 1
+         JUMP bb1
+
+    bb3: RETURN
+}
+",
+    )
+    .unwrap();
+    fs::write(
+        &main,
+        "\
+#include <stdio.h>
+
+extern long scale(long);
+
+int main() {
+  printf(\"%ld\\n\", scale(3));
+  return 0;
+}
+",
+    )
+    .unwrap();
+
+    for factor in ["4", "2"] {
+        let options = parse(&[
+            lib.to_str().unwrap(),
+            main.to_str().unwrap(),
+            "-O2",
+            "--unroll-factor",
+            factor,
+            "--emit=exe",
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .unwrap();
+        run(&options).unwrap();
+
+        let result = Command::new(&output).output().unwrap();
+        assert!(result.status.success());
+        assert_eq!(String::from_utf8(result.stdout).unwrap(), "331776\n");
+    }
+}
+
+#[test]
+fn fully_unrolled_loops_are_folded() {
+    let dir = output_dir("full-unroll");
+    let input = dir.join("sum.mir");
+    let output = dir.join("sum.mir.out");
+
+    fs::write(
+        &input,
+        "\
+fn sum() -> int {
+    let _1: int
+    let _2: bool
+
+    bb0: _0 = USE 0
+         _1 = USE 0
+         JUMP bb1
+
+    bb1: _2 = _1 < 3
+         JUMP IF _2 THEN bb2 ELSE bb3
+
+    bb2: _0 = _0 + _1
+         _1 = _1 + 1
+         JUMP bb1
+
+    bb3: RETURN
+}
+",
+    )
+    .unwrap();
+
+    let options = parse(&[
+        input.to_str().unwrap(),
+        "-O2",
+        "--emit=mir",
+        "-o",
+        output.to_str().unwrap(),
+    ])
+    .unwrap();
+    run(&options).unwrap();
+
+    // The copies of the body are folded after the loop is unrolled.
+    let mir = fs::read_to_string(&output).unwrap();
+    assert!(mir.contains("_0 = USE 3\n"));
+    assert!(!mir.contains('+'));
+}
+
+/// A module equivalent to `lib.mir` where every value is computed by calling another function.
+const CALLS: &str = "
+#[inline(never)]
+fn ten() -> int {
+    bb0: _0 = USE 10
+         RETURN
+}
+
+fn start() -> int {
+    bb0: _0 = CALL ten()
+         RETURN
+}
+
+fn add(_1: int, _2: int) -> int {
+    bb0: _0 = _1 + _2
+         RETURN
+}
+
+fn duplicate(_1: int) -> int {
+    let _2: int
+
+    bb0: _2 = CALL add(_1, _1)
+         _0 = USE _2
+         RETURN
+}
+";
+
+#[test]
+fn emit_exe_with_calls() {
+    for opt_level in ["-O0", "-O1"] {
+        let dir = output_dir(&format!("calls{opt_level}"));
+        let input = dir.join("calls.mir");
+        let output = dir.join("main");
+        fs::write(&input, CALLS).unwrap();
+
+        let options = parse(&[
+            input.to_str().unwrap(),
+            MAIN,
+            opt_level,
+            "--emit=asm,exe",
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .unwrap();
+        run(&options).unwrap();
+
+        // `add` is inlined at `-O1` but `ten` is never inlined.
+        let asm = fs::read_to_string(output.with_extension("s")).unwrap();
+        assert!(asm.contains("call ten"));
+        assert_eq!(asm.contains("call add"), opt_level == "-O0");
+
+        let result = Command::new(&output).output().unwrap();
+        assert!(result.status.success());
+        assert_eq!(String::from_utf8(result.stdout).unwrap(), "20\n");
+    }
+}
+
+#[test]
+fn profile_guided_optimization() {
+    let dir = output_dir("pgo");
+    let instrumented = dir.join("instrumented");
+    let profile = dir.join("main.profile");
+
+    let options = parse(&[
+        LIB,
+        MAIN,
+        "-O1",
+        "--emit=exe",
+        "--profile-generate",
+        profile.to_str().unwrap(),
+        "-o",
+        instrumented.to_str().unwrap(),
+    ])
+    .unwrap();
+    assert_eq!(options.profile_generate, Some(profile.clone()));
+    run(&options).unwrap();
+
+    let result = Command::new(&instrumented).output().unwrap();
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), "20\n");
+
+    // The loop header runs once more than the body.
+    let counts = fs::read_to_string(&profile).unwrap();
+    assert_eq!(
+        counts,
+        "duplicate bb0 1\nduplicate bb1 11\nduplicate bb2 10\nduplicate bb3 1\nstart bb0 1\n"
+    );
+
+    // The path can be overridden when the program runs.
+    let other = dir.join("other.profile");
+    let result = Command::new(&instrumented)
+        .env("PIJAMA_PROFILE", &other)
+        .output()
+        .unwrap();
+    assert!(result.status.success());
+    assert_eq!(fs::read_to_string(&other).unwrap(), counts);
+
+    let output = dir.join("main");
+    let options = parse(&[
+        LIB,
+        MAIN,
+        "-O2",
+        "--emit=exe",
+        "--profile-use",
+        profile.to_str().unwrap(),
+        "-o",
+        output.to_str().unwrap(),
+    ])
+    .unwrap();
+    assert_eq!(options.profile_use, Some(profile));
+    run(&options).unwrap();
+
+    let result = Command::new(&output).output().unwrap();
+    assert!(result.status.success());
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), "20\n");
+}
+
+#[test]
+fn invalid_profile() {
+    let dir = output_dir("invalid-profile");
+    let profile = dir.join("bad.profile");
+    fs::write(&profile, "duplicate bb0 many\n").unwrap();
+
+    let err = run(&parse(&[LIB, "--profile-use", profile.to_str().unwrap()]).unwrap()).unwrap_err();
+    assert_eq!(err.exit_code(), 1);
+    assert_eq!(
+        err.to_string(),
+        format!("{}:1:1: invalid count `many`", profile.display())
+    );
+}
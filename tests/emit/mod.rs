@@ -0,0 +1,364 @@
+use object::{
+    elf,
+    read::{File, Object, ObjectSection, ObjectSymbol},
+    BinaryFormat, RelocationEncoding, RelocationKind, RelocationTarget, SymbolFlags, SymbolKind,
+};
+use pijama::{
+    asm::{aarch64, riscv64, x86_64::assemble, Instructions},
+    code,
+    emit::{EmitError, Format, ObjectEmitter, Visibility},
+    mir::{BasicBlock, BlockProfile, Function, Rvalue, Statement, Terminator, Ty},
+    mir_lowering::{lower_function, LowerOptions, RelocationModel},
+    target::{Aarch64, Riscv64, X86_64},
+};
+
+/// Emits an object with a `start` function calling an undefined `helper` function.
+fn emit(format: Format) -> Vec<u8> {
+    let mut instructions = Instructions::new();
+
+    let helper = instructions.add_symbol("helper");
+    instructions.add_instruction(code!(call sym { helper }));
+    instructions.add_instruction(code!(ret));
+
+    let mut code = Vec::new();
+    let relocations = assemble(instructions, &mut code).unwrap();
+
+    let mut emitter = ObjectEmitter::new(format);
+    emitter
+        .add_function("start", Visibility::Default, &code, relocations)
+        .unwrap();
+    emitter.write().unwrap()
+}
+
+fn check_object(
+    format: Format,
+    binary_format: BinaryFormat,
+    section_name: &str,
+    symbol_prefix: &str,
+    expected_relocation: (RelocationKind, RelocationEncoding),
+) {
+    let bytes = emit(format);
+    let file = File::parse(&*bytes).unwrap();
+
+    assert_eq!(file.format(), binary_format);
+    assert_eq!(file.architecture(), object::Architecture::X86_64);
+
+    let section = file.section_by_name(section_name).unwrap();
+    // `call rel32` and `ret`.
+    assert_eq!(&section.data().unwrap()[..6], &[0xe8, 0, 0, 0, 0, 0xc3]);
+
+    let start = file
+        .symbols()
+        .find(|symbol| symbol.name() == Ok(&format!("{symbol_prefix}start")))
+        .unwrap();
+    assert_eq!(start.kind(), SymbolKind::Text);
+    assert!(start.is_global());
+    assert_eq!(start.section_index(), Some(section.index()));
+
+    let (offset, relocation) = section.relocations().next().unwrap();
+    assert_eq!(offset, 1);
+    assert_eq!(
+        (relocation.kind(), relocation.encoding()),
+        expected_relocation
+    );
+
+    let helper = match relocation.target() {
+        RelocationTarget::Symbol(index) => file.symbol_by_index(index).unwrap(),
+        target => panic!("unexpected relocation target {target:?}"),
+    };
+    assert_eq!(helper.name(), Ok(&*format!("{symbol_prefix}helper")));
+    assert!(helper.is_undefined());
+}
+
+#[test]
+fn elf() {
+    check_object(
+        Format::Elf,
+        BinaryFormat::Elf,
+        ".text",
+        "",
+        (RelocationKind::PltRelative, RelocationEncoding::Generic),
+    );
+}
+
+#[test]
+fn macho() {
+    check_object(
+        Format::MachO,
+        BinaryFormat::MachO,
+        "__text",
+        "_",
+        (RelocationKind::Relative, RelocationEncoding::X86Branch),
+    );
+}
+
+#[test]
+fn coff() {
+    check_object(
+        Format::Coff,
+        BinaryFormat::Coff,
+        ".text",
+        "",
+        (RelocationKind::Relative, RelocationEncoding::Generic),
+    );
+}
+
+#[test]
+fn duplicate_function() {
+    let mut emitter = ObjectEmitter::new(Format::Elf);
+    emitter
+        .add_function("start", Visibility::Default, &[0xc3], Vec::new())
+        .unwrap();
+    assert!(emitter
+        .add_function("start", Visibility::Default, &[0xc3], Vec::new())
+        .is_err());
+}
+
+/// Returns a function that computes the address of `table` and `counter`.
+fn addresses_mir() -> Function {
+    let mut builder = Function::builder(0);
+
+    let output = builder.add_local(Ty::Int);
+    let counter = builder.add_local(Ty::Int);
+
+    let bb0 = builder.add_block();
+
+    *builder.block_mut(bb0) = Some(BasicBlock {
+        statements: vec![
+            Statement::Assign {
+                lhs: output,
+                rhs: Rvalue::SymbolAddr("table".to_owned()),
+            },
+            Statement::Assign {
+                lhs: counter,
+                rhs: Rvalue::SymbolAddr("counter".to_owned()),
+            },
+        ],
+        terminator: Terminator::Return,
+        cold: false,
+        profile: BlockProfile::default(),
+    });
+
+    builder.finish()
+}
+
+fn emit_pic(format: Format) -> Result<Vec<u8>, pijama::emit::EmitError> {
+    let options = LowerOptions {
+        relocation_model: RelocationModel::Pic,
+        local_symbols: ["table".to_owned()].into(),
+    };
+
+    let mut code = Vec::new();
+    let relocations = assemble(
+        lower_function::<X86_64>(&addresses_mir(), &options).unwrap(),
+        &mut code,
+    )
+    .unwrap();
+
+    let mut emitter = ObjectEmitter::new(format);
+    emitter.add_data("table", Visibility::Hidden, &[0; 16])?;
+    emitter.add_function("addresses", Visibility::Protected, &code, relocations)?;
+    emitter.write()
+}
+
+#[test]
+fn elf_pic() {
+    let bytes = emit_pic(Format::Elf).unwrap();
+    let file = File::parse(&*bytes).unwrap();
+
+    let text = file.section_by_name(".text").unwrap();
+    // `lea rax,[rip+table]` and `mov rdi,[rip+counter@GOTPCREL]`.
+    assert_eq!(&text.data().unwrap()[..3], &[0x48, 0x8d, 0x05]);
+    assert_eq!(&text.data().unwrap()[7..10], &[0x48, 0x8b, 0x3d]);
+
+    let relocations = text.relocations().collect::<Vec<_>>();
+    assert_eq!(relocations.len(), 2);
+
+    assert_eq!(relocations[0].0, 3);
+    assert_eq!(relocations[0].1.kind(), RelocationKind::Relative);
+
+    assert_eq!(relocations[1].0, 10);
+    assert_eq!(
+        relocations[1].1.kind(),
+        RelocationKind::Elf(elf::R_X86_64_REX_GOTPCRELX)
+    );
+    match relocations[1].1.target() {
+        RelocationTarget::Symbol(index) => {
+            let counter = file.symbol_by_index(index).unwrap();
+            assert_eq!(counter.name(), Ok("counter"));
+            assert!(counter.is_undefined());
+        }
+        target => panic!("unexpected relocation target {target:?}"),
+    }
+
+    let visibility = |name| match file
+        .symbols()
+        .find(|symbol| symbol.name() == Ok(name))
+        .unwrap()
+        .flags()
+    {
+        SymbolFlags::Elf { st_other, .. } => st_other & 0x3,
+        flags => panic!("unexpected flags {flags:?}"),
+    };
+
+    assert_eq!(visibility("table"), elf::STV_HIDDEN);
+    assert_eq!(visibility("addresses"), elf::STV_PROTECTED);
+}
+
+#[test]
+fn macho_pic() {
+    // Mach-O has no protected visibility.
+    assert!(emit_pic(Format::MachO).is_err());
+}
+
+#[test]
+fn aarch64_elf_pic() {
+    let options = LowerOptions {
+        relocation_model: RelocationModel::Pic,
+        local_symbols: ["table".to_owned()].into(),
+    };
+
+    let instructions = lower_function::<Aarch64>(&addresses_mir(), &options).unwrap();
+    let mut code = Vec::new();
+    let relocations = aarch64::assemble(instructions, &mut code).unwrap();
+
+    let mut emitter = ObjectEmitter::<Aarch64>::for_target(Format::Elf).unwrap();
+    emitter
+        .add_data("table", Visibility::Hidden, &[0; 16])
+        .unwrap();
+    emitter
+        .add_function("addresses", Visibility::Default, &code, relocations)
+        .unwrap();
+    let bytes = emitter.write().unwrap();
+
+    let file = File::parse(&*bytes).unwrap();
+    assert_eq!(file.format(), BinaryFormat::Elf);
+    assert_eq!(file.architecture(), object::Architecture::Aarch64);
+
+    // `adrp x9,table`, `add x9,x9,:lo12:table`, `adrp x10,:got:counter` and
+    // `ldr x10,[x10,:got_lo12:counter]`.
+    let text = file.section_by_name(".text").unwrap();
+    assert_eq!(
+        &text.data().unwrap()[..16],
+        &[
+            0x09, 0x00, 0x00, 0x90, 0x29, 0x01, 0x00, 0x91, 0x0a, 0x00, 0x00, 0x90, 0x4a, 0x01,
+            0x40, 0xf9
+        ]
+    );
+
+    let relocations = text
+        .relocations()
+        .map(|(offset, relocation)| (offset, relocation.kind()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        relocations,
+        [
+            (0, RelocationKind::Elf(elf::R_AARCH64_ADR_PREL_PG_HI21)),
+            (4, RelocationKind::Elf(elf::R_AARCH64_ADD_ABS_LO12_NC)),
+            (8, RelocationKind::Elf(elf::R_AARCH64_ADR_GOT_PAGE)),
+            (12, RelocationKind::Elf(elf::R_AARCH64_LD64_GOT_LO12_NC)),
+        ]
+    );
+}
+
+#[test]
+fn aarch64_is_elf_only() {
+    for format in [Format::MachO, Format::Coff] {
+        assert!(matches!(
+            ObjectEmitter::<Aarch64>::for_target(format),
+            Err(EmitError::UnsupportedArch(..))
+        ));
+    }
+}
+
+#[test]
+fn riscv64_elf_pic() {
+    let options = LowerOptions {
+        relocation_model: RelocationModel::Pic,
+        local_symbols: ["table".to_owned()].into(),
+    };
+
+    let instructions = lower_function::<Riscv64>(&addresses_mir(), &options).unwrap();
+    let mut code = Vec::new();
+    let relocations = riscv64::assemble(instructions, &mut code).unwrap();
+
+    let mut emitter = ObjectEmitter::<Riscv64>::for_target(Format::Elf).unwrap();
+    emitter
+        .add_data("table", Visibility::Hidden, &[0; 16])
+        .unwrap();
+    emitter
+        .add_function("addresses", Visibility::Default, &code, relocations)
+        .unwrap();
+    let bytes = emitter.write().unwrap();
+
+    let file = File::parse(&*bytes).unwrap();
+    assert_eq!(file.format(), BinaryFormat::Elf);
+    assert_eq!(file.architecture(), object::Architecture::Riscv64);
+
+    // `auipc t0,%pcrel_hi(table)`, `addi t0,t0,%pcrel_lo(.L)`, `auipc t1,%got_pcrel_hi(counter)`
+    // and `ld t1,%pcrel_lo(.L)(t1)`.
+    let text = file.section_by_name(".text").unwrap();
+    assert_eq!(
+        &text.data().unwrap()[..16],
+        &[
+            0x97, 0x02, 0x00, 0x00, 0x93, 0x82, 0x02, 0x00, 0x17, 0x03, 0x00, 0x00, 0x03, 0x33,
+            0x03, 0x00
+        ]
+    );
+
+    // The low parts point to local labels at the `auipc` before them.
+    let relocations = text
+        .relocations()
+        .map(|(offset, relocation)| {
+            let target = match relocation.target() {
+                RelocationTarget::Symbol(index) => file.symbol_by_index(index).unwrap(),
+                target => panic!("unexpected target {target:?}"),
+            };
+            (
+                offset,
+                relocation.kind(),
+                target.name().unwrap().to_owned(),
+                target.address(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        relocations,
+        [
+            (
+                0,
+                RelocationKind::Elf(elf::R_RISCV_PCREL_HI20),
+                "table".to_owned(),
+                0
+            ),
+            (
+                4,
+                RelocationKind::Elf(elf::R_RISCV_PCREL_LO12_I),
+                ".Lpcrel_hi0".to_owned(),
+                0
+            ),
+            (
+                8,
+                RelocationKind::Elf(elf::R_RISCV_GOT_HI20),
+                "counter".to_owned(),
+                0
+            ),
+            (
+                12,
+                RelocationKind::Elf(elf::R_RISCV_PCREL_LO12_I),
+                ".Lpcrel_hi8".to_owned(),
+                8
+            ),
+        ]
+    );
+}
+
+#[test]
+fn riscv64_is_elf_only() {
+    for format in [Format::MachO, Format::Coff] {
+        assert!(matches!(
+            ObjectEmitter::<Riscv64>::for_target(format),
+            Err(EmitError::UnsupportedArch(..))
+        ));
+    }
+}
@@ -1 +1,7 @@
 mod asm;
+mod driver;
+mod emit;
+mod mir;
+mod mir_lowering;
+mod pass;
+mod profile;
@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use pijama::mir::{
+    cfg::{Cfg, Dominators, Loops},
+    parse::parse_module,
+    BasicBlockId, Function,
+};
+
+/// Two nested loops and an unreachable block.
+const NESTED_LOOPS: &str = "
+fn f(_1: bool, _2: bool) -> int {
+    bb0: JUMP bb1
+
+    bb1: JUMP IF _1 THEN bb2 ELSE bb5
+
+    bb2: JUMP IF _2 THEN bb3 ELSE bb4
+
+    bb3: JUMP bb2
+
+    bb4: JUMP bb1
+
+    bb5: RETURN
+
+    bb6: JUMP bb2
+}";
+
+fn function() -> (Function, Vec<BasicBlockId>) {
+    let func = parse_module(NESTED_LOOPS)
+        .unwrap()
+        .functions
+        .remove("f")
+        .unwrap();
+    let blocks = func.basic_blocks.keys().copied().collect();
+    (func, blocks)
+}
+
+fn names<'a>(blocks: impl IntoIterator<Item = &'a BasicBlockId>) -> Vec<String> {
+    blocks.into_iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn edges() {
+    let (func, bb) = function();
+    let cfg = Cfg::new(&func);
+
+    assert_eq!(cfg.entry(), bb[0]);
+    assert_eq!(names(cfg.successors(bb[1])), ["bb2", "bb5"]);
+    assert_eq!(names(cfg.predecessors(bb[2])), ["bb1", "bb3", "bb6"]);
+    assert_eq!(
+        names(cfg.reverse_postorder()),
+        ["bb0", "bb1", "bb5", "bb2", "bb4", "bb3"]
+    );
+    assert!(!cfg.is_reachable(bb[6]));
+}
+
+#[test]
+fn dominators() {
+    let (func, bb) = function();
+    let cfg = Cfg::new(&func);
+    let dominators = Dominators::new(&cfg);
+
+    let idoms = bb
+        .iter()
+        .map(|bb| {
+            dominators
+                .immediate_dominator(*bb)
+                .map(|idom| idom.to_string())
+        })
+        .collect::<Vec<_>>();
+    let expected = [
+        None,
+        Some("bb0"),
+        Some("bb1"),
+        Some("bb2"),
+        Some("bb2"),
+        Some("bb1"),
+        None,
+    ];
+    assert_eq!(idoms, expected.map(|idom| idom.map(str::to_owned)));
+
+    assert_eq!(names(dominators.children(bb[1])), ["bb5", "bb2"]);
+    assert!(dominators.dominates(bb[1], bb[3]));
+    assert!(dominators.dominates(bb[3], bb[3]));
+    assert!(!dominators.dominates(bb[3], bb[4]));
+    assert!(!dominators.dominates(bb[0], bb[6]));
+}
+
+#[test]
+fn frontiers() {
+    let (func, bb) = function();
+    let cfg = Cfg::new(&func);
+    let frontiers = Dominators::new(&cfg).frontiers(&cfg);
+
+    let expected = BTreeMap::from([
+        (bb[0], BTreeSet::new()),
+        (bb[1], BTreeSet::from([bb[1]])),
+        (bb[2], BTreeSet::from([bb[1], bb[2]])),
+        (bb[3], BTreeSet::from([bb[2]])),
+        (bb[4], BTreeSet::from([bb[1]])),
+        (bb[5], BTreeSet::new()),
+    ]);
+    assert_eq!(frontiers, expected);
+}
+
+#[test]
+fn entry_in_own_frontier() {
+    let func = parse_module(
+        "
+fn f(_1: bool) -> int {
+    bb0: JUMP bb1
+
+    bb1: JUMP IF _1 THEN bb0 ELSE bb2
+
+    bb2: RETURN
+}",
+    )
+    .unwrap()
+    .functions
+    .remove("f")
+    .unwrap();
+    let bb = func.basic_blocks.keys().copied().collect::<Vec<_>>();
+    let cfg = Cfg::new(&func);
+    let frontiers = Dominators::new(&cfg).frontiers(&cfg);
+
+    // The back edge reaches the entry block, which is also reached from the caller.
+    let expected = BTreeMap::from([
+        (bb[0], BTreeSet::from([bb[0]])),
+        (bb[1], BTreeSet::from([bb[0]])),
+        (bb[2], BTreeSet::new()),
+    ]);
+    assert_eq!(frontiers, expected);
+}
+
+#[test]
+fn loops() {
+    let (func, bb) = function();
+    let cfg = Cfg::new(&func);
+    let loops = Loops::new(&cfg, &Dominators::new(&cfg));
+
+    let [outer, inner] = loops.loops() else {
+        panic!("expected two loops, found {:?}", loops.loops());
+    };
+
+    assert_eq!(outer.header, bb[1]);
+    assert_eq!(outer.latches, [bb[4]]);
+    assert_eq!(names(&outer.blocks), ["bb1", "bb2", "bb3", "bb4"]);
+    assert_eq!((outer.parent, outer.depth), (None, 1));
+
+    assert_eq!(inner.header, bb[2]);
+    assert_eq!(inner.latches, [bb[3]]);
+    assert_eq!(names(&inner.blocks), ["bb2", "bb3"]);
+    assert_eq!((inner.parent, inner.depth), (Some(0), 2));
+
+    let depths = bb.iter().map(|bb| loops.depth(*bb)).collect::<Vec<_>>();
+    assert_eq!(depths, [0, 1, 2, 2, 1, 0, 0]);
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeSet;
+
+use pijama::{
+    dataflow::solve,
+    mir::{
+        cfg::Cfg,
+        dataflow::{DefiniteInit, Definition, DefinitionSite, Liveness, ReachingDefinitions},
+        parse::parse_module,
+        BasicBlockId, Function, Local,
+    },
+};
+
+fn duplicate() -> (Function, Vec<BasicBlockId>, Vec<Local>) {
+    let func = parse_module(include_str!("../../lib.mir"))
+        .unwrap()
+        .functions
+        .remove("duplicate")
+        .unwrap();
+    let blocks = func.basic_blocks.keys().copied().collect();
+    let locals = func.local_types.keys().copied().collect();
+    (func, blocks, locals)
+}
+
+fn set<T: Ord + Copy>(items: &[T]) -> BTreeSet<T> {
+    items.iter().copied().collect()
+}
+
+#[test]
+fn liveness() {
+    let (func, bb, l) = duplicate();
+    let results = solve(&Cfg::new(&func), &Liveness::new(&func));
+
+    assert_eq!(results.before[&bb[0]], set(&[l[1]]));
+    assert_eq!(results.before[&bb[1]], set(&[l[0], l[1], l[2]]));
+    assert_eq!(results.after[&bb[1]], set(&[l[0], l[1], l[2]]));
+    assert_eq!(results.before[&bb[3]], set(&[l[0]]));
+    assert_eq!(results.after[&bb[3]], set(&[]));
+}
+
+#[test]
+fn reaching_definitions() {
+    let (func, bb, l) = duplicate();
+    let results = solve(&Cfg::new(&func), &ReachingDefinitions::new(&func));
+
+    let statement = |local: Local, bb: BasicBlockId, index| Definition {
+        local,
+        site: DefinitionSite::Statement(bb, index),
+    };
+    let argument = Definition {
+        local: l[1],
+        site: DefinitionSite::Argument,
+    };
+
+    assert_eq!(results.before[&bb[0]], set(&[argument]));
+    assert_eq!(
+        results.before[&bb[1]],
+        set(&[
+            argument,
+            statement(l[0], bb[0], 0),
+            statement(l[0], bb[2], 0),
+            statement(l[2], bb[0], 1),
+            statement(l[2], bb[2], 1),
+            statement(l[3], bb[1], 0),
+        ])
+    );
+}
+
+#[test]
+fn definite_init() {
+    let (func, bb, l) = duplicate();
+    let results = solve(&Cfg::new(&func), &DefiniteInit::new(&func));
+
+    assert_eq!(results.before[&bb[0]], set(&[l[0], l[2], l[3]]));
+    // `_3` is only assigned inside the loop.
+    assert_eq!(results.before[&bb[1]], set(&[l[3]]));
+    assert_eq!(results.before[&bb[3]], set(&[]));
+}
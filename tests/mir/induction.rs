@@ -0,0 +1,139 @@
+use pijama::mir::{
+    cfg::{Cfg, Dominators, Loops},
+    induction::{InductionVariable, InductionVariables, TripCount},
+    parse::parse_module,
+    Function,
+};
+
+/// `duplicate` in SSA form, with a derived induction variable in the header.
+const COUNTED_LOOP: &str = "
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: int
+    let _4: bool
+    let _5: int
+    let _6: int
+    let _7: int
+    let _8: int
+
+    bb0: JUMP bb1
+
+    bb1: _2 = PHI(bb0: 0, bb2: _5)
+         _3 = PHI(bb0: 3, bb2: _6)
+         _4 = _3 < _1
+         _7 = _2 + 5
+         JUMP IF _4 THEN bb2 ELSE bb3
+
+    bb2: _8 = _2 + 1
+         _5 = _8 + 1
+         _6 = 1 + _3
+         JUMP bb1
+
+    bb3: _0 = USE _7
+         RETURN
+}";
+
+fn analyze(src: &str) -> (Function, Option<InductionVariables>, Option<TripCount>) {
+    let func = parse_module(src).unwrap().functions.remove("f").unwrap();
+    let cfg = Cfg::new(&func);
+    let loops = Loops::new(&cfg, &Dominators::new(&cfg));
+    let l = &loops.loops()[0];
+
+    let ivs = InductionVariables::new(&func, &cfg, l);
+    let trip_count = ivs.as_ref().and_then(|ivs| TripCount::new(&func, l, ivs));
+    (func, ivs, trip_count)
+}
+
+fn describe(iv: &InductionVariable) -> String {
+    match iv {
+        InductionVariable::Basic { init, step, next } => {
+            format!("{init} + {step} * i, next in {next}")
+        }
+        InductionVariable::Derived { base, offset } => format!("{base} + {offset}"),
+    }
+}
+
+#[test]
+fn induction_variables() {
+    let (_, ivs, _) = analyze(COUNTED_LOOP);
+    let ivs = ivs.unwrap();
+
+    assert_eq!(ivs.entering().to_string(), "bb0");
+    assert_eq!(ivs.latch().to_string(), "bb2");
+
+    let described = ivs
+        .iter()
+        .map(|(local, iv)| format!("{local}: {}", describe(iv)))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        described,
+        [
+            "_2: 0 + 2 * i, next in _5",
+            "_3: 3 + 1 * i, next in _6",
+            "_5: _2 + 2",
+            "_6: _3 + 1",
+            "_7: _2 + 5",
+            "_8: _2 + 1",
+        ]
+    );
+}
+
+#[test]
+fn trip_count() {
+    let (_, ivs, trip_count) = analyze(COUNTED_LOOP);
+    let trip_count = trip_count.unwrap();
+
+    assert_eq!(trip_count.iv.to_string(), "_3");
+    assert_eq!(trip_count.bound.to_string(), "_1");
+    assert_eq!(trip_count.cond.to_string(), "_4");
+    assert_eq!(trip_count.exit.to_string(), "bb3");
+    assert_eq!(trip_count.constant(&ivs.unwrap()), None);
+}
+
+#[test]
+fn constant_trip_count() {
+    let (_, ivs, trip_count) = analyze(&COUNTED_LOOP.replace("_3 < _1", "_3 < 10"));
+    assert_eq!(trip_count.unwrap().constant(&ivs.unwrap()), Some(7));
+
+    // The comparison is signed, so a loop from 3 to -1 is never entered.
+    let (_, ivs, trip_count) = analyze(&COUNTED_LOOP.replace("_3 < _1", "_3 < -1"));
+    assert_eq!(trip_count.unwrap().constant(&ivs.unwrap()), Some(0));
+}
+
+#[test]
+fn not_counted() {
+    // The step of `_2` is 2.
+    let (_, _, trip_count) = analyze(&COUNTED_LOOP.replace("_3 < _1", "_2 < _1"));
+    assert!(trip_count.is_none());
+
+    // The bound changes in every iteration.
+    let (_, _, trip_count) = analyze(&COUNTED_LOOP.replace("_3 < _1", "_3 < _2"));
+    assert!(trip_count.is_none());
+
+    // The loop is left when the condition holds.
+    let (_, _, trip_count) =
+        analyze(&COUNTED_LOOP.replace("THEN bb2 ELSE bb3", "THEN bb3 ELSE bb2"));
+    assert!(trip_count.is_none());
+}
+
+#[test]
+fn many_latches() {
+    let src = "
+fn f(_1: bool) -> int {
+    let _2: int
+    let _3: int
+
+    bb0: JUMP bb1
+
+    bb1: _2 = PHI(bb0: 0, bb2: _3, bb3: _3)
+         _3 = _2 + 1
+         JUMP IF _1 THEN bb2 ELSE bb3
+
+    bb2: JUMP bb1
+
+    bb3: JUMP bb1
+}";
+
+    let (_, ivs, _) = analyze(src);
+    assert!(ivs.is_none());
+}
@@ -0,0 +1,5 @@
+mod cfg;
+mod dataflow;
+mod induction;
+mod optimize;
+mod parse;
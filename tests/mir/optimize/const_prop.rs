@@ -0,0 +1,180 @@
+use pijama::mir::optimize::ConstProp;
+
+use super::{check, dump_duplicate, DUPLICATE};
+
+#[test]
+fn fold() {
+    let src = "
+fn f() -> bool {
+    let _1: int
+    let _2: int
+
+    bb0: _1 = USE 2
+         _2 = _1 + -3
+         _0 = _2 < 0
+         RETURN
+}";
+
+    let expected = "\
+fn f() -> bool {
+    let _1: int
+    let _2: int
+
+    bb0: _1 = USE 2
+         _2 = USE -1
+         _0 = USE true
+         RETURN
+}
+";
+
+    assert!(check(ConstProp, src, expected));
+}
+
+#[test]
+fn fold_64_bit_integers() {
+    let src = "
+fn f() -> bool {
+    let _1: int
+    let _2: int
+    let _3: bool
+
+    bb0: _1 = 2147483647 + 1
+         _2 = 65536 * 65536
+         _3 = _1 < 0
+         _1 = 9223372036854775807 + 1
+         _0 = _1 < 0
+         RETURN
+}";
+
+    // Integers only wrap around when they do not fit in 64 bits, like in registers.
+    let expected = "\
+fn f() -> bool {
+    let _1: int
+    let _2: int
+    let _3: bool
+
+    bb0: _1 = USE 2147483648
+         _2 = USE 4294967296
+         _3 = USE false
+         _1 = USE -9223372036854775808
+         _0 = USE true
+         RETURN
+}
+";
+
+    assert!(check(ConstProp, src, expected));
+}
+
+#[test]
+fn constant_branch() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: bool
+
+    bb0: _2 = 1 < 2
+         JUMP IF _2 THEN bb1 ELSE bb2
+
+    bb1: _0 = _1 + 1
+         RETURN
+
+    bb2: _0 = USE 0
+         RETURN
+}";
+
+    let expected = "\
+fn f(_1: int) -> int {
+    let _2: bool
+
+    bb0: _2 = USE true
+         JUMP bb1
+
+    bb1: _0 = _1 + 1
+         RETURN
+}
+";
+
+    assert!(check(ConstProp, src, expected));
+}
+
+#[test]
+fn merge() {
+    let src = "
+fn f(_1: bool) -> int {
+    let _2: int
+
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: _2 = USE 5
+         JUMP bb3
+
+    bb2: _2 = 2 + 3
+         JUMP bb3
+
+    bb3: _0 = _2 < _0
+         RETURN
+}";
+
+    // `_0` is undefined when it is read, so it is not replaced.
+    let expected = "\
+fn f(_1: bool) -> int {
+    let _2: int
+
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: _2 = USE 5
+         JUMP bb3
+
+    bb2: _2 = USE 5
+         JUMP bb3
+
+    bb3: _0 = 5 < _0
+         RETURN
+}
+";
+
+    assert!(check(ConstProp, src, expected));
+}
+
+#[test]
+fn loop_variable() {
+    assert!(!check(ConstProp, DUPLICATE, &dump_duplicate()));
+}
+
+#[test]
+fn fold_floats() {
+    let src = "
+fn f() -> int {
+    let _1: f64
+    let _2: f32
+    let _3: bool
+
+    bb0: _1 = CAST 3 AS f64
+         _1 = _1 * 0.5
+         _2 = CAST _1 AS f32
+         _2 = _2 + 0.25f32
+         _3 = _2 < 1.5f32
+         _0 = CAST _1 AS int
+         _0 = _0 * -7
+         RETURN
+}";
+
+    // Converting a float to an integer truncates it towards zero.
+    let expected = "\
+fn f() -> int {
+    let _1: f64
+    let _2: f32
+    let _3: bool
+
+    bb0: _1 = USE 3.0
+         _1 = USE 1.5
+         _2 = USE 1.5f32
+         _2 = USE 1.75f32
+         _3 = USE false
+         _0 = USE 1
+         _0 = USE -7
+         RETURN
+}
+";
+
+    assert!(check(ConstProp, src, expected));
+}
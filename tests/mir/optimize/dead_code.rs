@@ -0,0 +1,75 @@
+use pijama::mir::optimize::DeadCode;
+
+use super::{check, dump_duplicate, DUPLICATE};
+
+#[test]
+fn dead_assignments() {
+    let src = "
+fn f(_1: int, _2: int) -> int {
+    let _3: int
+    let _4: int
+    let _5: bool
+
+    bb0: _3 = _1 + 1
+         _4 = _3 + _2
+         _5 = _1 < _2
+         _0 = USE 7
+         _0 = _2 + 1
+         JUMP IF _5 THEN bb1 ELSE bb2
+
+    bb1: _3 = USE 0
+         RETURN
+
+    bb2: RETURN
+}";
+
+    // `_4` is never read, which makes `_3` dead too. The first assignment to `_0` is overwritten
+    // before it is read.
+    let expected = "\
+fn f(_1: int, _2: int) -> int {
+    let _3: bool
+
+    bb0: _3 = _1 < _2
+         _0 = _2 + 1
+         JUMP IF _3 THEN bb1 ELSE bb2
+
+    bb1: RETURN
+
+    bb2: RETURN
+}
+";
+
+    assert!(check(DeadCode, src, expected));
+}
+
+#[test]
+fn dead_across_blocks() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: int
+
+    bb0: _2 = _1 + 1
+         JUMP bb1
+
+    bb1: _3 = _2 + 1
+         _0 = USE _1
+         RETURN
+}";
+
+    let expected = "\
+fn f(_1: int) -> int {
+    bb0: JUMP bb1
+
+    bb1: _0 = USE _1
+         RETURN
+}
+";
+
+    assert!(check(DeadCode, src, expected));
+}
+
+#[test]
+fn loop_variables() {
+    assert!(!check(DeadCode, DUPLICATE, &dump_duplicate()));
+}
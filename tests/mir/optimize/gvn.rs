@@ -0,0 +1,228 @@
+use pijama::{
+    mir::{
+        optimize::{Gvn, IntoSsa},
+        parse::parse_module,
+    },
+    pass::{Dump, Pass},
+};
+
+use super::{check, dump_duplicate, DUPLICATE};
+
+#[test]
+fn redundant_operations() {
+    let src = "
+fn f(_1: int, _2: int) -> int {
+    let _3: int
+    let _4: int
+    let _5: int
+    let _6: bool
+    let _7: bool
+
+    bb0: _3 = _1 + _2
+         _4 = USE _2
+         _5 = _4 + _1
+         _6 = _1 < _2
+         _7 = _2 < _1
+         JUMP bb1
+
+    bb1: _0 = _3 + _5
+         RETURN
+}";
+
+    // Additions are commutative and `_4` is a copy of `_2`, but comparisons are not commutative.
+    let expected = "\
+fn f(_1: int, _2: int) -> int {
+    let _3: int
+    let _4: int
+    let _5: int
+    let _6: bool
+    let _7: bool
+
+    bb0: _3 = _1 + _2
+         _4 = USE _2
+         _5 = USE _3
+         _6 = _1 < _2
+         _7 = _2 < _1
+         JUMP bb1
+
+    bb1: _0 = _3 + _5
+         RETURN
+}
+";
+
+    assert!(check(Gvn, src, expected));
+}
+
+#[test]
+fn dominating_blocks() {
+    let src = "
+fn f(_1: bool, _2: int) -> int {
+    let _3: int
+    let _4: int
+    let _5: int
+    let _6: int
+
+    bb0: _3 = _2 + 1
+         JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: _4 = _2 + 1
+         _5 = _2 + 2
+         JUMP bb3
+
+    bb2: _6 = _2 + 2
+         JUMP bb3
+
+    bb3: _0 = _2 + 2
+         RETURN
+}";
+
+    // `bb0` dominates every other block, but `bb1` and `bb2` do not dominate `bb3`.
+    let expected = "\
+fn f(_1: bool, _2: int) -> int {
+    let _3: int
+    let _4: int
+    let _5: int
+    let _6: int
+
+    bb0: _3 = _2 + 1
+         JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: _4 = USE _3
+         _5 = _2 + 2
+         JUMP bb3
+
+    bb2: _6 = _2 + 2
+         JUMP bb3
+
+    bb3: _0 = _2 + 2
+         RETURN
+}
+";
+
+    assert!(check(Gvn, src, expected));
+}
+
+#[test]
+fn reassigned_locals() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: int
+    let _4: int
+
+    bb0: _2 = _1 + 1
+         _3 = _1 + 1
+         _1 = USE 0
+         _4 = _1 + 1
+         _0 = _3 + _4
+         RETURN
+}";
+
+    // `_1` is assigned twice, so `_1 + 1` can have different values.
+    let expected = "\
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: int
+    let _4: int
+
+    bb0: _2 = _1 + 1
+         _3 = _1 + 1
+         _1 = USE 0
+         _4 = _1 + 1
+         _0 = _3 + _4
+         RETURN
+}
+";
+
+    assert!(!check(Gvn, src, expected));
+}
+
+#[test]
+fn loop_carried_values() {
+    // `_2` is only assigned once, but it is read before that assignment in the next iteration.
+    let src = "
+fn f(_1: bool) -> int {
+    let _2: int
+    let _3: int
+    let _4: int
+
+    bb0: JUMP bb1
+
+    bb1: _3 = _2 + 1
+         _2 = USE 5
+         _4 = _2 + 1
+         JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb2: _0 = _3 + _4
+         RETURN
+}";
+
+    let expected = "\
+fn f(_1: bool) -> int {
+    let _2: int
+    let _3: int
+    let _4: int
+
+    bb0: JUMP bb1
+
+    bb1: _3 = _2 + 1
+         _2 = USE 5
+         _4 = _2 + 1
+         JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb2: _0 = _3 + _4
+         RETURN
+}
+";
+
+    assert!(!check(Gvn, src, expected));
+}
+
+#[test]
+fn duplicate() {
+    assert!(!check(Gvn, DUPLICATE, &dump_duplicate()));
+}
+
+#[test]
+fn ssa_form() {
+    let src = "
+fn f(_1: int, _2: bool) -> int {
+    let _3: int
+
+    bb0: _3 = _1 + 1
+         JUMP IF _2 THEN bb1 ELSE bb2
+
+    bb1: _3 = _1 + 1
+         JUMP bb2
+
+    bb2: _0 = USE _3
+         RETURN
+}";
+
+    let module = parse_module(src).unwrap();
+    let (name, mut func) = module.functions.into_iter().next().unwrap();
+    IntoSsa.run(&mut func);
+
+    let expected = "\
+fn f(_1: int, _2: bool) -> int {
+    let _3: int
+    let _4: int
+    let _5: int
+    let _6: int
+
+    bb0: _3 = _1 + 1
+         JUMP IF _2 THEN bb1 ELSE bb2
+
+    bb1: _4 = USE _3
+         JUMP bb2
+
+    bb2: _5 = PHI(bb0: _3, bb1: _4)
+         _6 = USE _5
+         _0 = USE _6
+         RETURN
+}
+";
+
+    assert!(Gvn.run(&mut func));
+    assert_eq!(func.dump(&name), expected);
+}
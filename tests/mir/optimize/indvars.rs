@@ -0,0 +1,200 @@
+use pijama::{
+    mir::{
+        optimize::{ConstProp, IndVars, IntoSsa},
+        parse::parse_module,
+    },
+    pass::{Dump, Pass},
+};
+
+use super::{check, DUPLICATE};
+
+#[test]
+fn closed_form() {
+    let module = parse_module(DUPLICATE).unwrap();
+    let (name, mut func) = module.functions.into_iter().next().unwrap();
+
+    IntoSsa.run(&mut func);
+    ConstProp.run(&mut func);
+    assert!(IndVars.run(&mut func));
+
+    // `_0` is `2 * _1` if the loop is entered, computed as `_1 + _1`.
+    let expected = "\
+fn duplicate(_1: int) -> int {
+    let _2: int
+    let _3: bool
+    let _4: int
+    let _5: int
+    let _6: int
+    let _7: int
+    let _8: int
+    let _9: int
+
+    bb0: _4 = USE 0
+         _2 = USE 0
+         JUMP bb1
+
+    bb1: _3 = 0 < _1
+         JUMP IF _3 THEN bb4 ELSE bb5
+
+    bb3: _0 = USE _5
+         RETURN
+
+    bb4: _9 = _1 + _1
+         JUMP bb5
+
+    bb5: _5 = PHI(bb1: 0, bb4: _9)
+         _6 = PHI(bb1: 0, bb4: _1)
+         JUMP bb3
+}
+";
+    assert_eq!(func.dump(&name), expected);
+}
+
+#[test]
+fn constant_trip_count() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: int
+    let _4: bool
+    let _5: int
+    let _6: int
+    let _7: int
+
+    bb0: JUMP bb1
+
+    bb1: _2 = PHI(bb0: _1, bb2: _5)
+         _3 = PHI(bb0: 2, bb2: _6)
+         _4 = _3 < 12
+         _7 = _2 + -1
+         JUMP IF _4 THEN bb2 ELSE bb3
+
+    bb2: _5 = _2 + 7
+         _6 = _3 + 1
+         JUMP bb1
+
+    bb3: _0 = _7 + _3
+         RETURN
+}";
+
+    // The loop runs 10 times, and `_7` is derived from `_2`.
+    let expected = "\
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: int
+    let _4: bool
+    let _5: int
+    let _6: int
+    let _7: int
+
+    bb0: JUMP bb1
+
+    bb1: _2 = _1 + 70
+         _3 = USE 12
+         _7 = _2 + -1
+         JUMP bb3
+
+    bb3: _0 = _7 + _3
+         RETURN
+}
+";
+
+    assert!(check(IndVars, src, expected));
+}
+
+#[test]
+fn large_step() {
+    let src = "
+fn f(_1: int, _2: int) -> int {
+    let _3: int
+    let _4: int
+    let _5: bool
+    let _6: int
+    let _7: int
+
+    bb0: JUMP bb1
+
+    bb1: _3 = PHI(bb0: _2, bb2: _6)
+         _4 = PHI(bb0: 5, bb2: _7)
+         _5 = _4 < _1
+         JUMP IF _5 THEN bb2 ELSE bb3
+
+    bb2: _6 = _3 + 11
+         _7 = _4 + 1
+         JUMP bb1
+
+    bb3: _0 = USE _3
+         RETURN
+}";
+
+    // `11 * (_1 - 5)` is computed by doubling `_1 - 5` for every bit of 11 after the first one,
+    // adding `_1 - 5` again for the bits that are set.
+    let expected = "\
+fn f(_1: int, _2: int) -> int {
+    let _3: int
+    let _4: int
+    let _5: bool
+    let _6: int
+    let _7: int
+    let _8: int
+    let _9: int
+    let _10: int
+    let _11: int
+    let _12: int
+    let _13: int
+    let _14: int
+
+    bb0: JUMP bb1
+
+    bb1: _5 = 5 < _1
+         JUMP IF _5 THEN bb4 ELSE bb5
+
+    bb3: _0 = USE _3
+         RETURN
+
+    bb4: _8 = _1 + -5
+         _9 = _8 + _8
+         _10 = _9 + _9
+         _11 = _10 + _8
+         _12 = _11 + _11
+         _13 = _12 + _8
+         _14 = _2 + _13
+         JUMP bb5
+
+    bb5: _3 = PHI(bb1: _2, bb4: _14)
+         _4 = PHI(bb1: 5, bb4: _1)
+         JUMP bb3
+}
+";
+
+    assert!(check(IndVars, src, expected));
+}
+
+#[test]
+fn side_effects() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: bool
+    let _4: int
+    let _5: int
+
+    bb0: JUMP bb1
+
+    bb1: _2 = PHI(bb0: 0, bb2: _4)
+         _3 = _2 < _1
+         JUMP IF _3 THEN bb2 ELSE bb3
+
+    bb2: _5 = CALL g(_2)
+         _4 = _2 + 1
+         JUMP bb1
+
+    bb3: _0 = USE _2
+         RETURN
+}";
+
+    // The call must be executed in every iteration.
+    let module = parse_module(src).unwrap();
+    let expected = module.functions["f"].dump("f");
+    assert!(!check(IndVars, src, &expected));
+}
@@ -0,0 +1,330 @@
+use pijama::{
+    mir::{optimize::Inliner, parse::parse_module},
+    pass::{Dump, Pass},
+    profile::Profile,
+};
+
+/// Runs the inliner over `src` and checks that the result is `expected`. Returns whether the
+/// inliner reported a change.
+fn check(src: &str, expected: &str) -> bool {
+    let mut module = parse_module(src).unwrap();
+    let changed = Inliner.run(&mut module);
+    assert_eq!(module.to_string(), expected);
+    changed
+}
+
+#[test]
+fn small_functions() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: bool
+
+    bb0: _0 = CALL start()
+         _0 = CALL add(_0, _1)
+         _2 = _0 < 5
+         JUMP IF _2 THEN bb1 ELSE bb2
+
+    bb1: RETURN
+
+    bb2: _0 = USE 5
+         RETURN
+}
+
+fn add(_1: int, _2: int) -> int {
+    bb0: _0 = _1 + _2
+         RETURN
+}
+
+fn start() -> int {
+    bb0: _0 = USE 10
+         RETURN
+}";
+
+    // The return value of `add` is not assigned directly to `_0` because `_0` is an argument.
+    let expected = "\
+fn add(_1: int, _2: int) -> int {
+    bb0: _0 = _1 + _2
+         RETURN
+}
+
+fn f(_1: int) -> int {
+    let _2: bool
+    let _3: int
+
+    bb0: JUMP bb3
+
+    bb1: RETURN
+
+    bb2: _0 = USE 5
+         RETURN
+
+    bb3: _0 = USE 10
+         JUMP bb4
+
+    bb4: JUMP bb5
+
+    bb5: _3 = _0 + _1
+         JUMP bb6
+
+    bb6: _0 = USE _3
+         _2 = _0 < 5
+         JUMP IF _2 THEN bb1 ELSE bb2
+}
+
+fn start() -> int {
+    bb0: _0 = USE 10
+         RETURN
+}
+";
+
+    assert!(check(src, expected));
+}
+
+#[test]
+fn assigned_arguments() {
+    let src = "
+fn f(_1: int) -> int {
+    bb0: _0 = CALL inc(_1)
+         RETURN
+}
+
+fn inc(_1: int) -> int {
+    bb0: _1 = _1 + 1
+         _0 = USE _1
+         RETURN
+}";
+
+    let expected = "\
+fn f(_1: int) -> int {
+    let _2: int
+
+    bb0: _2 = USE _1
+         JUMP bb1
+
+    bb1: _2 = _2 + 1
+         _0 = USE _2
+         JUMP bb2
+
+    bb2: RETURN
+}
+
+fn inc(_1: int) -> int {
+    bb0: _1 = _1 + 1
+         _0 = USE _1
+         RETURN
+}
+";
+
+    assert!(check(src, expected));
+}
+
+#[test]
+fn callees_first() {
+    let src = "
+fn a() -> int {
+    bb0: _0 = CALL b()
+         RETURN
+}
+
+fn b() -> int {
+    bb0: _0 = CALL c()
+         RETURN
+}
+
+fn c() -> int {
+    bb0: _0 = USE 1
+         RETURN
+}";
+
+    // `c` is inlined into `b` before `b` is inlined into `a`.
+    let expected = "\
+fn a() -> int {
+    bb0: JUMP bb1
+
+    bb1: JUMP bb2
+
+    bb2: _0 = USE 1
+         JUMP bb3
+
+    bb3: JUMP bb4
+
+    bb4: RETURN
+}
+
+fn b() -> int {
+    bb0: JUMP bb1
+
+    bb1: _0 = USE 1
+         JUMP bb2
+
+    bb2: RETURN
+}
+
+fn c() -> int {
+    bb0: _0 = USE 1
+         RETURN
+}
+";
+
+    assert!(check(src, expected));
+}
+
+#[test]
+fn recursion() {
+    let src = "
+fn even(_1: int) -> bool {
+    bb0: _0 = CALL odd(_1)
+         RETURN
+}
+
+fn odd(_1: int) -> bool {
+    bb0: _0 = CALL even(_1)
+         RETURN
+}
+
+fn f(_1: int) -> bool {
+    bb0: _0 = CALL f(_1)
+         RETURN
+}
+";
+
+    let module = parse_module(src).unwrap();
+    assert!(!check(src, &module.to_string()));
+}
+
+#[test]
+fn wrong_argument_count() {
+    let src = "
+fn add(_1: int, _2: int) -> int {
+    bb0: _0 = _1 + _2
+         RETURN
+}
+
+fn f(_1: int) -> int {
+    bb0: _0 = CALL add(_1)
+         RETURN
+}
+
+fn g(_1: int) -> int {
+    bb0: _0 = CALL add(_1, _1, _1, 3)
+         RETURN
+}
+";
+
+    let module = parse_module(src).unwrap();
+    assert!(!check(src, &module.to_string()));
+}
+
+#[test]
+fn attributes() {
+    let src = "
+fn f(_1: int) -> int {
+    bb0: _0 = CALL big(_1)
+         _0 = CALL never(_0)
+         RETURN
+}
+
+#[inline(always)]
+fn big(_1: int) -> int {
+    bb0: _0 = _1 + 1
+         _0 = _0 + 1
+         _0 = _0 + 1
+         _0 = _0 + 1
+         RETURN
+}
+
+#[inline(never)]
+fn never(_1: int) -> int {
+    bb0: _0 = USE _1
+         RETURN
+}
+";
+
+    let expected = "\
+#[inline(always)]
+fn big(_1: int) -> int {
+    bb0: _0 = _1 + 1
+         _0 = _0 + 1
+         _0 = _0 + 1
+         _0 = _0 + 1
+         RETURN
+}
+
+fn f(_1: int) -> int {
+    bb0: JUMP bb1
+
+    bb1: _0 = _1 + 1
+         _0 = _0 + 1
+         _0 = _0 + 1
+         _0 = _0 + 1
+         JUMP bb2
+
+    bb2: _0 = CALL never(_0)
+         RETURN
+}
+
+#[inline(never)]
+fn never(_1: int) -> int {
+    bb0: _0 = USE _1
+         RETURN
+}
+";
+
+    assert!(check(src, expected));
+}
+
+#[test]
+fn profile_counts() {
+    let src = "
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: _0 = CALL big(1)
+         RETURN
+
+    bb2: _0 = CALL small(2)
+         RETURN
+}
+
+fn big(_1: int) -> int {
+    bb0: _0 = _1 + 1
+         _0 = _0 + 1
+         _0 = _0 + 1
+         _0 = _0 + 1
+         RETURN
+}
+
+fn small(_1: int) -> int {
+    bb0: _0 = USE _1
+         RETURN
+}
+";
+
+    // `big` is too large to be inlined without an attribute, but the call is hot. The call to
+    // `small` never ran.
+    let mut module = parse_module(src).unwrap();
+    Profile::parse("f bb0 5000\nf bb1 5000\nf bb2 0\n")
+        .unwrap()
+        .apply(&mut module);
+    assert!(Inliner.run(&mut module));
+
+    let expected = "\
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: JUMP bb3
+
+    bb2: _0 = CALL small(2)
+         RETURN
+
+    bb3: _0 = 1 + 1
+         _0 = _0 + 1
+         _0 = _0 + 1
+         _0 = _0 + 1
+         JUMP bb4
+
+    bb4: RETURN
+}
+";
+    assert_eq!(module.functions["f"].dump("f"), expected);
+}
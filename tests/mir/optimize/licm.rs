@@ -0,0 +1,162 @@
+use pijama::mir::optimize::Licm;
+
+use super::{check, dump_duplicate, DUPLICATE};
+
+#[test]
+fn invariant_statements() {
+    let src = "
+fn f(_1: int, _2: int) -> int {
+    let _3: int
+    let _4: int
+    let _5: bool
+    let _6: int
+    let _7: int
+
+    bb0: _3 = USE 0
+         JUMP bb1
+
+    bb1: _5 = _3 < _1
+         JUMP IF _5 THEN bb2 ELSE bb3
+
+    bb2: _7 = _6 + 1
+         _6 = _2 + 2
+         _4 = _3 + _6
+         _3 = _4 + _7
+         JUMP bb1
+
+    bb3: _0 = USE _3
+         RETURN
+}";
+
+    // `_7` reads `_6`, which is assigned after it in the loop but before it in the preheader.
+    let expected = "\
+fn f(_1: int, _2: int) -> int {
+    let _3: int
+    let _4: int
+    let _5: bool
+    let _6: int
+    let _7: int
+
+    bb0: _3 = USE 0
+         _6 = _2 + 2
+         _7 = _6 + 1
+         JUMP bb1
+
+    bb1: _5 = _3 < _1
+         JUMP IF _5 THEN bb2 ELSE bb3
+
+    bb2: _4 = _3 + _6
+         _3 = _4 + _7
+         JUMP bb1
+
+    bb3: _0 = USE _3
+         RETURN
+}
+";
+
+    assert!(check(Licm, src, expected));
+}
+
+#[test]
+fn new_preheader() {
+    let src = "
+fn f(_1: bool, _2: int) -> int {
+    let _3: int
+    let _4: int
+    let _5: int
+    let _6: int
+
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: JUMP IF _1 THEN bb2 ELSE bb3
+
+    bb2: _3 = PHI(bb0: 1, bb1: 2, bb2: _4)
+         _5 = _2 + 1
+         _6 = ADDR foo
+         _4 = _3 + _5
+         JUMP IF _1 THEN bb2 ELSE bb3
+
+    bb3: _0 = USE 0
+         RETURN
+}";
+
+    // The values of the phi from outside the loop are merged in the preheader.
+    let expected = "\
+fn f(_1: bool, _2: int) -> int {
+    let _3: int
+    let _4: int
+    let _5: int
+    let _6: int
+    let _7: int
+
+    bb0: JUMP IF _1 THEN bb1 ELSE bb4
+
+    bb1: JUMP IF _1 THEN bb4 ELSE bb3
+
+    bb2: _3 = PHI(bb4: _7, bb2: _4)
+         _4 = _3 + _5
+         JUMP IF _1 THEN bb2 ELSE bb3
+
+    bb3: _0 = USE 0
+         RETURN
+
+    bb4: _7 = PHI(bb0: 1, bb1: 2)
+         _5 = _2 + 1
+         _6 = ADDR foo
+         JUMP bb2
+}
+";
+
+    assert!(check(Licm, src, expected));
+}
+
+#[test]
+fn nested_loops() {
+    let src = "
+fn f(_1: bool, _2: int) -> int {
+    let _3: int
+    let _4: int
+
+    bb0: JUMP bb1
+
+    bb1: JUMP IF _1 THEN bb2 ELSE bb4
+
+    bb2: _3 = _2 + 1
+         _4 = CALL g(_3)
+         JUMP IF _1 THEN bb2 ELSE bb3
+
+    bb3: JUMP bb1
+
+    bb4: RETURN
+}";
+
+    // `_3` is moved out of both loops, but calls have side effects.
+    let expected = "\
+fn f(_1: bool, _2: int) -> int {
+    let _3: int
+    let _4: int
+
+    bb0: _3 = _2 + 1
+         JUMP bb1
+
+    bb1: JUMP IF _1 THEN bb5 ELSE bb4
+
+    bb2: _4 = CALL g(_3)
+         JUMP IF _1 THEN bb2 ELSE bb3
+
+    bb3: JUMP bb1
+
+    bb4: RETURN
+
+    bb5: JUMP bb2
+}
+";
+
+    assert!(check(Licm, src, expected));
+}
+
+#[test]
+fn variant_statements() {
+    // Every assignment in the loop of `duplicate` reads a local assigned in the loop.
+    assert!(!check(Licm, DUPLICATE, &dump_duplicate()));
+}
@@ -0,0 +1,44 @@
+mod const_prop;
+mod dead_code;
+mod gvn;
+mod indvars;
+mod inline;
+mod licm;
+mod placement;
+mod simplify_cfg;
+mod ssa;
+mod unreachable_blocks;
+mod unroll;
+
+use pijama::{
+    mir::{parse::parse_module, Function},
+    pass::{Dump, Pass},
+    profile::Profile,
+};
+
+/// Runs `pass` over the first function in `src` and checks that the result is `expected`.
+/// Returns whether the pass reported a change.
+fn check(pass: impl Pass<Function>, src: &str, expected: &str) -> bool {
+    check_with_profile(pass, src, "", expected)
+}
+
+/// Like [`check`], but the counts of the blocks are read from `profile` first.
+fn check_with_profile(pass: impl Pass<Function>, src: &str, profile: &str, expected: &str) -> bool {
+    let mut module = parse_module(src).unwrap();
+    Profile::parse(profile).unwrap().apply(&mut module);
+    let (name, mut func) = module.functions.into_iter().next().unwrap();
+
+    let changed = pass.run(&mut func);
+    assert_eq!(func.dump(&name), expected);
+
+    changed
+}
+
+/// `lib.mir`, whose `duplicate` function has a loop.
+const DUPLICATE: &str = include_str!("../../../lib.mir");
+
+/// Returns the `duplicate` function as printed after a pass that does not change it.
+fn dump_duplicate() -> String {
+    let module = parse_module(DUPLICATE).unwrap();
+    module.functions["duplicate"].dump("duplicate")
+}
@@ -0,0 +1,158 @@
+use pijama::mir::optimize::BlockPlacement;
+
+use super::{check, check_with_profile, dump_duplicate, DUPLICATE};
+
+#[test]
+fn loop_order_is_kept() {
+    assert!(!check(BlockPlacement, DUPLICATE, &dump_duplicate()));
+}
+
+#[test]
+fn loop_exit() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: bool
+    bb0: _0 = USE 0
+         JUMP bb2
+
+    bb1: RETURN
+
+    bb2: _2 = _0 < _1
+         JUMP IF _2 THEN bb1 ELSE bb3
+
+    bb3: _0 = _0 + 1
+         JUMP bb2
+}";
+
+    // The header follows the entry block, the body follows the header because the `THEN` block
+    // leaves the loop, and the exit goes last.
+
+    let expected = "\
+fn f(_1: int) -> int {
+    let _2: bool
+
+    bb0: _0 = USE 0
+         JUMP bb1
+
+    bb1: _2 = _0 < _1
+         JUMP IF _2 THEN bb3 ELSE bb2
+
+    bb2: _0 = _0 + 1
+         JUMP bb1
+
+    bb3: RETURN
+}
+";
+
+    assert!(check(BlockPlacement, src, expected));
+}
+
+#[test]
+fn cold_blocks() {
+    let src = "
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    #[cold]
+    bb1: _0 = USE 1
+         JUMP bb3
+
+    bb2: _0 = USE 2
+         JUMP bb3
+
+    bb3: RETURN
+}";
+
+    // The cold `THEN` block goes to the end, so the `ELSE` block is the fallthrough.
+    let expected = "\
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb3 ELSE bb1
+
+    bb1: _0 = USE 2
+         JUMP bb2
+
+    bb2: RETURN
+
+    #[cold]
+    bb3: _0 = USE 1
+         JUMP bb2
+}
+";
+
+    assert!(check(BlockPlacement, src, expected));
+}
+
+#[test]
+fn join_after_both_branches() {
+    let src = "
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb3 ELSE bb1
+
+    bb1: _0 = USE 1
+         JUMP bb2
+
+    bb2: RETURN
+
+    bb3: _0 = USE 2
+         JUMP bb2
+}";
+
+    // The `THEN` block is the fallthrough, and the block where both branches meet is only placed
+    // after both of them.
+    let expected = "\
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: _0 = USE 2
+         JUMP bb3
+
+    bb2: _0 = USE 1
+         JUMP bb3
+
+    bb3: RETURN
+}
+";
+
+    assert!(check(BlockPlacement, src, expected));
+}
+
+#[test]
+fn profile_counts() {
+    let src = "
+fn f(_1: bool, _2: bool) -> int {
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: JUMP IF _2 THEN bb3 ELSE bb4
+
+    bb2: _0 = USE 1
+         RETURN
+
+    bb3: _0 = USE 2
+         RETURN
+
+    bb4: _0 = USE 3
+         RETURN
+}";
+
+    // The `ELSE` block of the entry ran more often, so it is the fallthrough. The `THEN` block of
+    // `bb1` never ran, so it is placed last like a cold block.
+    let profile = "f bb0 10\nf bb1 3\nf bb2 7\nf bb3 0\nf bb4 3\n";
+    let expected = "\
+fn f(_1: bool, _2: bool) -> int {
+    bb0: JUMP IF _1 THEN bb2 ELSE bb1
+
+    bb1: _0 = USE 1
+         RETURN
+
+    bb2: JUMP IF _2 THEN bb4 ELSE bb3
+
+    bb3: _0 = USE 3
+         RETURN
+
+    bb4: _0 = USE 2
+         RETURN
+}
+";
+
+    assert!(check_with_profile(BlockPlacement, src, profile, expected));
+}
@@ -0,0 +1,140 @@
+use pijama::mir::optimize::SimplifyCfg;
+
+use super::{check, dump_duplicate, DUPLICATE};
+
+#[test]
+fn merge_blocks() {
+    let src = "
+fn f(_1: int) -> int {
+    bb0: _0 = USE _1
+         JUMP bb1
+
+    bb1: _0 = _0 + 1
+         JUMP bb2
+
+    bb2: _0 = _0 + 2
+         RETURN
+}";
+
+    let expected = "\
+fn f(_1: int) -> int {
+    bb0: _0 = USE _1
+         _0 = _0 + 1
+         _0 = _0 + 2
+         RETURN
+}
+";
+
+    assert!(check(SimplifyCfg, src, expected));
+}
+
+#[test]
+fn thread_jumps() {
+    let src = "
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb1 ELSE bb3
+
+    bb1: JUMP bb2
+
+    bb2: JUMP bb4
+
+    bb3: _0 = USE 1
+         JUMP bb5
+
+    bb4: _0 = USE 2
+         JUMP bb5
+
+    bb5: RETURN
+}";
+
+    let expected = "\
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb2 ELSE bb1
+
+    bb1: _0 = USE 1
+         RETURN
+
+    bb2: _0 = USE 2
+         RETURN
+}
+";
+
+    assert!(check(SimplifyCfg, src, expected));
+}
+
+#[test]
+fn merge_after_threading() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: int
+
+    bb0: _2 = _1 * 3
+         JUMP bb2
+
+    bb1: _0 = _2 + 1
+         RETURN
+
+    bb2: JUMP bb1
+}";
+
+    // Once `bb0` jumps straight to `bb1`, `bb2` is unreachable and `bb1` has a single predecessor.
+    let expected = "\
+fn f(_1: int) -> int {
+    let _2: int
+
+    bb0: _2 = _1 * 3
+         _0 = _2 + 1
+         RETURN
+}
+";
+
+    assert!(check(SimplifyCfg, src, expected));
+}
+
+#[test]
+fn same_targets() {
+    let src = "
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb1 ELSE bb1
+
+    bb1: _0 = USE 1
+         RETURN
+}";
+
+    let expected = "\
+fn f(_1: bool) -> int {
+    bb0: _0 = USE 1
+         RETURN
+}
+";
+
+    assert!(check(SimplifyCfg, src, expected));
+}
+
+#[test]
+fn empty_loop() {
+    let src = "
+fn f() -> int {
+    bb0: JUMP bb1
+
+    bb1: JUMP bb2
+
+    bb2: JUMP bb1
+}";
+
+    let expected = "\
+fn f() -> int {
+    bb0: JUMP bb1
+
+    bb1: JUMP bb1
+}
+";
+
+    assert!(check(SimplifyCfg, src, expected));
+}
+
+#[test]
+fn loop_header() {
+    // The loop header has two predecessors so nothing can be merged.
+    assert!(!check(SimplifyCfg, DUPLICATE, &dump_duplicate()));
+}
@@ -0,0 +1,246 @@
+use pijama::{
+    mir::{
+        optimize::{ConstProp, DeadCode, IntoSsa, OutOfSsa},
+        parse::parse_module,
+    },
+    pass::{Dump, Pass},
+};
+
+use super::{check, dump_duplicate, DUPLICATE};
+
+#[test]
+fn into_ssa() {
+    let expected = "\
+fn duplicate(_1: int) -> int {
+    let _2: int
+    let _3: bool
+    let _4: int
+    let _5: int
+    let _6: int
+    let _7: int
+    let _8: int
+
+    bb0: _4 = USE 0
+         _2 = USE 0
+         JUMP bb1
+
+    bb1: _5 = PHI(bb0: _4, bb2: _7)
+         _6 = PHI(bb0: _2, bb2: _8)
+         _3 = _6 < _1
+         JUMP IF _3 THEN bb2 ELSE bb3
+
+    bb2: _7 = _5 + 2
+         _8 = _6 + 1
+         JUMP bb1
+
+    bb3: _0 = USE _5
+         RETURN
+}
+";
+
+    assert!(check(IntoSsa, DUPLICATE, expected));
+}
+
+#[test]
+fn round_trip() {
+    let module = parse_module(DUPLICATE).unwrap();
+    let (name, mut func) = module.functions.into_iter().next().unwrap();
+
+    assert!(IntoSsa.run(&mut func));
+    assert!(OutOfSsa.run(&mut func));
+    assert_eq!(func.dump(&name), dump_duplicate());
+}
+
+#[test]
+fn already_in_ssa() {
+    let src = "
+fn f(_1: bool) -> int {
+    let _2: int
+
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: JUMP bb2
+
+    bb2: _2 = PHI(bb0: 1, bb1: 2)
+         _0 = USE _2
+         RETURN
+}";
+
+    let module = parse_module(src).unwrap();
+    let (name, mut func) = module.functions.into_iter().next().unwrap();
+    let expected = func.dump(&name);
+
+    assert!(!IntoSsa.run(&mut func));
+    assert_eq!(func.dump(&name), expected);
+}
+
+#[test]
+fn pruned_phis() {
+    // `_2` is dead after the branches join, so it does not need a phi.
+    let src = "
+fn f(_1: bool) -> int {
+    let _2: int
+
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: _2 = USE 1
+         _0 = USE _2
+         JUMP bb3
+
+    bb2: _2 = USE 2
+         _0 = USE _2
+         JUMP bb3
+
+    bb3: RETURN
+}";
+
+    let expected = "\
+fn f(_1: bool) -> int {
+    let _2: int
+    let _3: int
+    let _4: int
+    let _5: int
+    let _6: int
+
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: _4 = USE 1
+         _5 = USE _4
+         JUMP bb3
+
+    bb2: _2 = USE 2
+         _3 = USE _2
+         JUMP bb3
+
+    bb3: _6 = PHI(bb1: _5, bb2: _3)
+         _0 = USE _6
+         RETURN
+}
+";
+
+    assert!(check(IntoSsa, src, expected));
+}
+
+#[test]
+fn split_critical_edges() {
+    let src = "
+fn f(_1: bool) -> int {
+    let _2: int
+
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: JUMP bb2
+
+    bb2: _2 = PHI(bb0: 1, bb1: 2)
+         _0 = USE _2
+         RETURN
+}";
+
+    let expected = "\
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb1 ELSE bb3
+
+    bb1: _0 = USE 2
+         JUMP bb2
+
+    bb2: RETURN
+
+    bb3: _0 = USE 1
+         JUMP bb2
+}
+";
+
+    assert!(check(OutOfSsa, src, expected));
+}
+
+#[test]
+fn swap_copies() {
+    // The phis of the loop swap `_2` and `_3` on every iteration, which needs a new local. `_2` is
+    // merged with the return value.
+    let src = "
+fn f(_1: bool) -> int {
+    let _2: int
+    let _3: int
+
+    bb0: JUMP bb1
+
+    bb1: _2 = PHI(bb0: 1, bb2: _3)
+         _3 = PHI(bb0: 2, bb2: _2)
+         JUMP IF _1 THEN bb2 ELSE bb3
+
+    bb2: JUMP bb1
+
+    bb3: _0 = USE _2
+         RETURN
+}";
+
+    let expected = "\
+fn f(_1: bool) -> int {
+    let _2: int
+    let _3: int
+
+    bb0: _0 = USE 1
+         _2 = USE 2
+         JUMP bb1
+
+    bb1: JUMP IF _1 THEN bb2 ELSE bb3
+
+    bb2: _3 = USE _0
+         _0 = USE _2
+         _2 = USE _3
+         JUMP bb1
+
+    bb3: RETURN
+}
+";
+
+    assert!(check(OutOfSsa, src, expected));
+}
+
+#[test]
+fn const_prop_through_phis() {
+    // `_2` stays 0 through the loop, so the branch always exits.
+    let src = "
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: bool
+
+    bb0: _2 = USE 0
+         JUMP bb1
+
+    bb1: _3 = _1 < _2
+         JUMP IF _3 THEN bb2 ELSE bb3
+
+    bb2: _2 = USE _2
+         JUMP bb1
+
+    bb3: _0 = USE _2
+         RETURN
+}";
+
+    let module = parse_module(src).unwrap();
+    let (name, mut func) = module.functions.into_iter().next().unwrap();
+
+    IntoSsa.run(&mut func);
+    ConstProp.run(&mut func);
+    DeadCode.run(&mut func);
+    OutOfSsa.run(&mut func);
+
+    let expected = "\
+fn f(_1: int) -> int {
+    let _2: bool
+
+    bb0: JUMP bb1
+
+    bb1: _2 = _1 < 0
+         JUMP IF _2 THEN bb2 ELSE bb3
+
+    bb2: JUMP bb1
+
+    bb3: _0 = USE 0
+         RETURN
+}
+";
+
+    assert_eq!(func.dump(&name), expected);
+}
@@ -0,0 +1,52 @@
+use pijama::mir::optimize::UnreachableBlocks;
+
+use super::check;
+
+#[test]
+fn remove_and_renumber() {
+    let src = "
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb3 ELSE bb2
+
+    // Only reachable from itself.
+    bb1: JUMP bb1
+
+    bb2: _0 = USE 1
+         JUMP bb3
+
+    bb3: RETURN
+}";
+
+    let expected = "\
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb2 ELSE bb1
+
+    bb1: _0 = USE 1
+         JUMP bb2
+
+    bb2: RETURN
+}
+";
+
+    assert!(check(UnreachableBlocks, src, expected));
+}
+
+#[test]
+fn all_reachable() {
+    let src = "
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb1 ELSE bb0
+
+    bb1: RETURN
+}";
+
+    let expected = "\
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb1 ELSE bb0
+
+    bb1: RETURN
+}
+";
+
+    assert!(!check(UnreachableBlocks, src, expected));
+}
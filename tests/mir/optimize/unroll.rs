@@ -0,0 +1,237 @@
+use pijama::{
+    mir::{optimize::Unroll, parse::parse_module},
+    pass::Dump,
+};
+
+use super::{check, check_with_profile};
+
+/// A loop that calls `g` with every integer from 0 to `bound` and adds the results.
+fn counted_loop(bound: &str) -> String {
+    format!(
+        "
+fn f(_1: int) -> int {{
+    let _2: int
+    let _3: int
+    let _4: bool
+    let _5: int
+    let _6: int
+    let _7: int
+
+    bb0: JUMP bb1
+
+    bb1: _2 = PHI(bb0: 0, bb2: _6)
+         _3 = PHI(bb0: 0, bb2: _7)
+         _4 = _3 < {bound}
+         JUMP IF _4 THEN bb2 ELSE bb3
+
+    bb2: _5 = CALL g(_3)
+         _6 = _2 + _5
+         _7 = _3 + 1
+         JUMP bb1
+
+    bb3: _0 = USE _2
+         RETURN
+}}"
+    )
+}
+
+#[test]
+fn full_unrolling() {
+    // Every iteration reads the values of the phis from the previous one.
+    let expected = "\
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: int
+    let _4: bool
+    let _5: int
+    let _6: int
+    let _7: int
+    let _8: bool
+    let _9: int
+    let _10: int
+    let _11: int
+    let _12: bool
+    let _13: int
+    let _14: int
+    let _15: int
+
+    bb0: JUMP bb5
+
+    bb1: _2 = USE _14
+         _3 = USE _15
+         _4 = _3 < 2
+         JUMP bb3
+
+    bb3: _0 = USE _2
+         RETURN
+
+    bb4: _9 = CALL g(0)
+         _10 = 0 + _9
+         _11 = 0 + 1
+         JUMP bb7
+
+    bb5: _8 = 0 < 2
+         JUMP bb4
+
+    bb6: _13 = CALL g(_11)
+         _14 = _10 + _13
+         _15 = _11 + 1
+         JUMP bb1
+
+    bb7: _12 = _11 < 2
+         JUMP bb6
+}
+";
+
+    assert!(check(Unroll::default(), &counted_loop("2"), expected));
+}
+
+#[test]
+fn zero_iterations() {
+    let expected = "\
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: int
+    let _4: bool
+    let _5: int
+    let _6: int
+    let _7: int
+
+    bb0: JUMP bb1
+
+    bb1: _2 = USE 0
+         _3 = USE 0
+         _4 = _3 < -5
+         JUMP bb3
+
+    bb3: _0 = USE _2
+         RETURN
+}
+";
+
+    assert!(check(Unroll::default(), &counted_loop("-5"), expected));
+}
+
+#[test]
+fn partial_unrolling() {
+    // The guard checks that there are two iterations left, the first copy reads the locals of the
+    // header and the second one the locals of the first copy. The original loop runs the last
+    // iteration if the bound is odd.
+    let expected = "\
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: int
+    let _4: bool
+    let _5: int
+    let _6: int
+    let _7: int
+    let _8: int
+    let _9: bool
+    let _10: int
+    let _11: int
+    let _12: int
+    let _13: bool
+    let _14: int
+    let _15: int
+    let _16: int
+
+    bb0: JUMP bb1
+
+    bb1: _2 = PHI(bb0: 0, bb2: _6, bb6: _15)
+         _3 = PHI(bb0: 0, bb2: _7, bb6: _16)
+         _4 = _3 < _1
+         JUMP IF _4 THEN bb4 ELSE bb3
+
+    bb2: _5 = CALL g(_3)
+         _6 = _2 + _5
+         _7 = _3 + 1
+         JUMP bb1
+
+    bb3: _0 = USE _2
+         RETURN
+
+    bb4: _8 = _1 + -1
+         _9 = _3 < _8
+         JUMP IF _9 THEN bb5 ELSE bb2
+
+    bb5: _10 = CALL g(_3)
+         _11 = _2 + _10
+         _12 = _3 + 1
+         JUMP bb7
+
+    bb6: _14 = CALL g(_12)
+         _15 = _11 + _14
+         _16 = _12 + 1
+         JUMP bb1
+
+    bb7: _13 = _12 < _1
+         JUMP bb6
+}
+";
+
+    assert!(check(
+        Unroll {
+            factor: 2,
+            ..Unroll::default()
+        },
+        &counted_loop("_1"),
+        expected
+    ));
+}
+
+#[test]
+fn large_loops() {
+    // Fully unrolling 100 iterations or unrolling by 100 would make the function too large.
+    for (bound, factor) in [("100", 1), ("_1", 100)] {
+        let src = counted_loop(bound);
+        let module = parse_module(&src).unwrap();
+        let expected = module.functions["f"].dump("f");
+
+        assert!(!check(
+            Unroll {
+                factor,
+                ..Unroll::default()
+            },
+            &src,
+            &expected
+        ));
+    }
+}
+
+#[test]
+fn register_pressure() {
+    let src = counted_loop("_1");
+    let module = parse_module(&src).unwrap();
+    let expected = module.functions["f"].dump("f");
+
+    // Once out of SSA form, the unrolled loop needs 5 locals that are integers or booleans.
+    assert!(!check(
+        Unroll {
+            general_registers: 4,
+            ..Unroll::default()
+        },
+        &src,
+        &expected
+    ));
+}
+
+#[test]
+fn profile_counts() {
+    let src = counted_loop("_1");
+    let module = parse_module(&src).unwrap();
+    let expected = module.functions["f"].dump("f");
+
+    // The loop never ran, or it ran 3 iterations every time it was entered, which is less than
+    // the factor.
+    for profile in ["f bb1 0\n", "f bb0 5\nf bb1 20\n"] {
+        assert!(!check_with_profile(
+            Unroll {
+                factor: 4,
+                ..Unroll::default()
+            },
+            &src,
+            profile,
+            &expected
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+use pijama::mir::parse::parse_module;
+
+const LIB: &str = include_str!("../../lib.mir");
+
+#[test]
+fn round_trip() {
+    let module = parse_module(LIB).unwrap();
+    assert_eq!(
+        module.functions.keys().collect::<Vec<_>>(),
+        ["duplicate", "start"]
+    );
+
+    let text = module.to_string();
+    assert_eq!(parse_module(&text).unwrap().to_string(), text);
+    assert!(text.contains("    bb1: _3 = _2 < _1\n         JUMP IF _3 THEN bb2 ELSE bb3\n"));
+}
+
+#[test]
+fn symbol_addr() {
+    let src = "fn f() -> int {\n    bb0: _0 = ADDR table\n         RETURN\n}\n";
+    assert_eq!(parse_module(src).unwrap().to_string(), src);
+}
+
+#[test]
+fn undeclared_local() {
+    let err = parse_module("fn f() -> int {\n    bb0: _0 = _1 + 2\n    RETURN\n}").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 15));
+    assert_eq!(err.message, "local `_1` is not declared");
+}
+
+#[test]
+fn undefined_block() {
+    let err = parse_module("fn f() -> int {\n    bb0: JUMP bb1\n}").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 15));
+    assert_eq!(err.message, "block `bb1` is not defined");
+}
+
+#[test]
+fn unexpected_token() {
+    let err = parse_module("fn f() -> int {\n    bb0: _0 = USE\n}").unwrap_err();
+    assert_eq!((err.line, err.column), (3, 1));
+    assert_eq!(err.message, "expected local, found `}`");
+}
+
+#[test]
+fn calls_and_attributes() {
+    let src = "\
+#[inline(never)]
+fn f(_1: int) -> int {
+    bb0: _0 = CALL g(_1, 2)
+         _0 = CALL h()
+         RETURN
+}
+
+#[inline]
+fn g(_1: int, _2: int) -> int {
+    bb0: _0 = _1 + _2
+         RETURN
+}
+";
+    assert_eq!(parse_module(src).unwrap().to_string(), src);
+}
+
+#[test]
+fn unknown_attribute() {
+    let err =
+        parse_module("#[inline(sometimes)]\nfn f() -> int {\n    bb0: RETURN\n}").unwrap_err();
+    assert_eq!((err.line, err.column), (1, 10));
+    assert_eq!(err.message, "expected `always` or `never`");
+}
+
+#[test]
+fn cold_blocks() {
+    let src = "\
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    #[cold]
+    bb1: _0 = USE 1
+         RETURN
+
+    bb2: RETURN
+}
+";
+    assert_eq!(parse_module(src).unwrap().to_string(), src);
+}
+
+#[test]
+fn floats() {
+    let src = "\
+fn f(_1: f64, _2: int) -> f32 {
+    let _3: f64
+    let _4: bool
+
+    bb0: _3 = CAST _2 AS f64
+         _3 = _3 * _1
+         _4 = _3 < 2.0
+         _0 = CAST _3 AS f32
+         _0 = _0 + 0.5f32
+         RETURN
+}
+";
+    assert_eq!(parse_module(src).unwrap().to_string(), src);
+}
+
+#[test]
+fn invalid_float() {
+    let err = parse_module("fn f() -> f64 {\n    bb0: _0 = USE 1.5.2\n}").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 19));
+    assert_eq!(err.message, "invalid float `1.5.2`");
+}
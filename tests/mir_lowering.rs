@@ -0,0 +1,659 @@
+use object::{Architecture, RelocationEncoding};
+use pijama::{
+    asm::{x86_64::Register, AssemblerError, Instructions, Relocation, RelocationKind},
+    emit::{Arch, Format},
+    mir::parse::parse_module,
+    mir_lowering::{isel::Rules, lower_function, LowerError, LowerOptions},
+    target::{Aarch64, RegisterClass, Riscv64, Target, X86_64Windows, X86_64},
+};
+
+/// Lowers the first function in `src` and checks that its instructions are `expected`.
+fn check(src: &str, expected: &str) {
+    let module = parse_module(src).unwrap();
+    let func = module.functions.values().next().unwrap();
+    let instructions = lower_function::<X86_64>(func, &LowerOptions::default()).unwrap();
+    assert_eq!(instructions.to_string(), expected);
+}
+
+/// Lowers the first function in `src` to AArch64 and checks that its instructions are `expected`.
+fn check_aarch64(src: &str, expected: &str) {
+    let module = parse_module(src).unwrap();
+    let func = module.functions.values().next().unwrap();
+    let instructions = lower_function::<Aarch64>(func, &LowerOptions::default()).unwrap();
+    assert_eq!(instructions.to_string(), expected);
+}
+
+/// Lowers the first function in `src` to RISC-V and checks that its instructions are `expected`.
+fn check_riscv64(src: &str, expected: &str) {
+    let module = parse_module(src).unwrap();
+    let func = module.functions.values().next().unwrap();
+    let instructions = lower_function::<Riscv64>(func, &LowerOptions::default()).unwrap();
+    assert_eq!(instructions.to_string(), expected);
+}
+
+#[test]
+fn three_address_add() {
+    let src = "
+fn f(_1: int, _2: int) -> int {
+    bb0: _0 = _1 + _2
+         RETURN
+}";
+
+    check(src, ".L0:\n    lea rdi+rsi,rax\n    ret\n");
+}
+
+#[test]
+fn add_to_operand() {
+    let src = "
+fn f(_1: int, _2: int) -> int {
+    bb0: _1 = _2 + _1
+         _1 = _1 + 1
+         _1 = _1 + 1000
+         _0 = USE _1
+         RETURN
+}";
+
+    // Only the last statement is folded, the others assign `_1` more than once.
+    let expected = "\
+.L0:
+    add rsi,rdi
+    addi 0x1,rdi
+    addi 0x3e8,rdi
+    mov rdi,rax
+    ret
+";
+    check(src, expected);
+}
+
+#[test]
+fn folded_statements() {
+    let src = "
+fn f(_1: int, _2: int) -> int {
+    let _3: int
+    let _4: int
+
+    bb0: _3 = _1 + _2
+         _4 = USE 5
+         _0 = _3 + _4
+         RETURN
+}";
+
+    // `_3` is computed in the destination and the constant is added to it directly.
+    let expected = "\
+.L0:
+    lea rdi+rsi,rax
+    addi 0x5,rax
+    ret
+";
+    check(src, expected);
+}
+
+#[test]
+fn operands_assigned_before_use() {
+    let src = "
+fn f(_1: int, _2: int) -> int {
+    let _3: int
+
+    bb0: _3 = _1 + _2
+         _1 = USE 0
+         _0 = _3 + _1
+         RETURN
+}";
+
+    // `_3` cannot be computed after `_1` is assigned.
+    let expected = "\
+.L0:
+    lea rdi+rsi,rdx
+    loadi 0x0,rdi
+    lea rdx+rdi,rax
+    ret
+";
+    check(src, expected);
+}
+
+#[test]
+fn constant_operands() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: bool
+    let _3: bool
+
+    bb0: _0 = 1 + 2
+         _2 = 3 < _1
+         _3 = _1 < 4
+         _0 = _0 + _2
+         _0 = _0 + _3
+         RETURN
+}";
+
+    // The comparisons are folded into the additions, and computed in the registers of `_2` and
+    // `_3` right before them.
+    let expected = "\
+.L0:
+    loadi 0x3,rax
+    loadi 0x3,r11
+    slt r11,rdi,rsi
+    add rsi,rax
+    loadi 0x4,r11
+    slt rdi,r11,rdx
+    add rdx,rax
+    ret
+";
+    check(src, expected);
+}
+
+#[test]
+fn branch_on_comparison() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: bool
+
+    bb0: _2 = _1 < 10
+         JUMP IF _2 THEN bb1 ELSE bb2
+
+    bb1: _0 = USE 1
+         RETURN
+
+    bb2: _0 = USE 0
+         RETURN
+}";
+
+    // The comparison is folded into the jump, which is inverted to fall through to `bb1`.
+    let expected = "\
+.L0:
+    cmpi rdi,0xa
+    jge .L2
+    jmp .L1
+.L1:
+    loadi 0x1,rax
+    ret
+.L2:
+    loadi 0x0,rax
+    ret
+";
+    check(src, expected);
+}
+
+#[test]
+fn branch_on_returned_comparison() {
+    let src = "
+fn f(_1: int) -> bool {
+    bb0: _0 = _1 < 10
+         JUMP IF _0 THEN bb1 ELSE bb2
+
+    bb1: RETURN
+
+    bb2: RETURN
+}";
+
+    // `_0` is also read by the returns, so the comparison is computed in its register before the
+    // jump.
+    let expected = "\
+.L0:
+    loadi 0xa,r11
+    slt rdi,r11,rax
+    jz rax,.L2
+    jmp .L1
+.L1:
+    ret
+.L2:
+    ret
+";
+    check(src, expected);
+}
+
+#[test]
+fn multiply() {
+    let src = "
+fn f(_1: int, _2: int) -> int {
+    bb0: _1 = _1 * _2
+         _0 = _1 * 3
+         RETURN
+}";
+
+    // The constant is loaded in the destination, which is then multiplied in place.
+    let expected = "\
+.L0:
+    mul rsi,rdi
+    loadi 0x3,rax
+    mul rdi,rax
+    ret
+";
+    check(src, expected);
+}
+
+#[test]
+fn float_arithmetic() {
+    let src = "
+fn f(_1: f64, _2: f64) -> f64 {
+    let _3: f64
+
+    bb0: _3 = _1 * _2
+         _0 = _3 + 1.5
+         RETURN
+}";
+
+    // The arguments stay in `xmm0` and `xmm1`, and the constant goes through `r11` because SSE
+    // instructions have no immediates.
+    let expected = "\
+.L0:
+    mov xmm0,xmm3
+    mulsd xmm1,xmm3
+    loadi 0x3ff8000000000000,r11
+    mov r11,xmm2
+    addsd xmm3,xmm2
+    mov xmm2,xmm0
+    ret
+";
+    check(src, expected);
+}
+
+#[test]
+fn float_branch() {
+    let src = "
+fn f(_1: f32, _2: f32) -> int {
+    let _3: bool
+
+    bb0: _3 = _1 < _2
+         JUMP IF _3 THEN bb1 ELSE bb2
+
+    bb1: _0 = USE 1
+         RETURN
+
+    bb2: _0 = USE 0
+         RETURN
+}";
+
+    // `_1 < _2` is tested as `_2` above `_1`, which is false when the operands are unordered.
+    let expected = "\
+.L0:
+    ucomiss xmm1,xmm0
+    jbe .L2
+    jmp .L1
+.L1:
+    loadi 0x1,rax
+    ret
+.L2:
+    loadi 0x0,rax
+    ret
+";
+    check(src, expected);
+}
+
+#[test]
+fn float_casts() {
+    let src = "
+fn f(_1: int, _2: f32) -> int {
+    let _3: f64
+    let _4: f64
+    let _5: bool
+
+    bb0: _3 = CAST _1 AS f64
+         _4 = CAST _2 AS f64
+         _5 = _3 < _4
+         _0 = CAST _4 AS int
+         _0 = _0 + _5
+         RETURN
+}";
+
+    // `_4` is read twice so it is computed on its own, and the comparison is folded into the
+    // addition together with the conversion of `_1`.
+    let expected = "\
+.L0:
+    cvtss2sd xmm0,xmm2
+    cvttsd2si xmm2,rax
+    cvtsi2sd rdi,xmm1
+    sltsd xmm1,xmm2,rsi
+    add rsi,rax
+    ret
+";
+    check(src, expected);
+}
+
+#[test]
+fn float_call() {
+    let src = "
+fn f(_1: int, _2: f64) -> f64 {
+    bb0: _0 = CALL g(_2, _1, 2.5)
+         RETURN
+}";
+
+    // Every argument takes the next register of its class, and the return value comes back in
+    // `xmm0` and is kept in `xmm15` while the saved registers are restored.
+    let expected = "\
+.L0:
+    push rdi
+    push xmm0
+    addi -0x8,rsp
+    push xmm0
+    push rdi
+    loadi 0x4004000000000000,r11
+    push r11
+    pop xmm1
+    pop rdi
+    pop xmm0
+    call g
+    mov xmm0,xmm15
+    addi 0x8,rsp
+    pop xmm0
+    pop rdi
+    mov xmm15,xmm1
+    mov xmm1,xmm0
+    ret
+";
+    check(src, expected);
+}
+
+#[test]
+fn windows_call() {
+    let src = "
+fn f(_1: int, _2: f64) -> f64 {
+    bb0: _0 = CALL g(_2, _1, 2.5)
+         RETURN
+}";
+
+    let module = parse_module(src).unwrap();
+    let func = module.functions.values().next().unwrap();
+    let instructions = lower_function::<X86_64Windows>(func, &LowerOptions::default()).unwrap();
+    // Every argument takes the register at its position, so `_1` is passed in `rdx` and the
+    // constant in `xmm2`. The 32 bytes of shadow space are reserved right before the call, and
+    // the return value is kept in `xmm5` because `xmm15` is callee-saved.
+    let expected = "\
+.L0:
+    push rcx
+    push xmm1
+    addi -0x8,rsp
+    push xmm1
+    push rcx
+    loadi 0x4004000000000000,r11
+    push r11
+    pop xmm2
+    pop rdx
+    pop xmm0
+    addi -0x20,rsp
+    call g
+    mov xmm0,xmm5
+    addi 0x28,rsp
+    pop xmm1
+    pop rcx
+    mov xmm5,xmm0
+    ret
+";
+    assert_eq!(instructions.to_string(), expected);
+}
+
+#[test]
+fn aarch64_registers() {
+    let src = "
+fn f(_1: int, _2: int) -> int {
+    let _3: int
+
+    bb0: _3 = _1 + 4095
+         _0 = _3 + _2
+         _0 = _0 + 4096
+         RETURN
+}";
+
+    // The arguments stay in `x0` and `x1`, and `4096` does not fit in the immediate of `add`.
+    let expected = "\
+.L0:
+    mov x0,x10
+    addi 0xfff,x10
+    lea x10+x1,x9
+    addi 0x1000,x9
+    mov x9,x0
+    ret
+";
+    check_aarch64(src, expected);
+}
+
+#[test]
+fn aarch64_wide_constant() {
+    let src = "
+fn f(_1: int) -> int {
+    bb0: _0 = _1 + 4294967296
+         RETURN
+}";
+
+    // The constant does not fit in 32 bits, so it is added from a register.
+    let expected = "\
+.L0:
+    mov x0,x9
+    loadi 0x100000000,x16
+    add x16,x9
+    mov x9,x0
+    ret
+";
+    check_aarch64(src, expected);
+}
+
+#[test]
+fn aarch64_call() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: int
+
+    bb0: _2 = CALL g(_1, 5)
+         _0 = _2 + _1
+         RETURN
+}";
+
+    // The link register is saved before the first block, and the other locals around the call.
+    let expected = "    push x30
+.L0:
+    push x9
+    push x0
+    push x0
+    loadi 0x5,x16
+    push x16
+    pop x1
+    pop x0
+    call g
+    mov x0,x16
+    pop x0
+    pop x9
+    mov x16,x10
+    lea x10+x0,x9
+    mov x9,x0
+    pop x30
+    ret
+";
+    check_aarch64(src, expected);
+}
+
+#[test]
+fn riscv64_branch() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: bool
+
+    bb0: _0 = USE 0
+         _2 = _1 < 10
+         JUMP IF _2 THEN bb1 ELSE bb2
+
+    bb1: _0 = _1 + 5000
+         JUMP bb2
+
+    bb2: RETURN
+}";
+
+    // The argument stays in `a0`, and the comparison is folded into the branch.
+    let expected = "\
+.L0:
+    loadi 0x0,t0
+    loadi 0xa,t5
+    cmp a0,t5
+    jge .L2
+    jmp .L1
+.L1:
+    mov a0,t0
+    addi 0x1388,t0
+    jmp .L2
+.L2:
+    mov t0,a0
+    ret
+";
+    check_riscv64(src, expected);
+}
+
+#[test]
+fn riscv64_call() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: int
+
+    bb0: _2 = CALL g(_1, 5)
+         _0 = _2 + _1
+         RETURN
+}";
+
+    // The return address is saved before the first block, and the other locals around the call.
+    let expected = "    push ra
+.L0:
+    push t0
+    push a0
+    push a0
+    loadi 0x5,t5
+    push t5
+    pop a1
+    pop a0
+    call g
+    mov a0,t5
+    pop a0
+    pop t0
+    mov t5,t1
+    lea t1+a0,t0
+    mov t0,a0
+    pop ra
+    ret
+";
+    check_riscv64(src, expected);
+}
+
+/// x86-64 with `rbx` allocated to locals after `rax`, so that functions must save it.
+struct CalleeSaved;
+
+impl Target for CalleeSaved {
+    type Register = Register;
+
+    const ARCH: Arch = X86_64::ARCH;
+    const ARCHITECTURE: Architecture = X86_64::ARCHITECTURE;
+    const FORMATS: &'static [Format] = X86_64::FORMATS;
+
+    const ALLOCATABLE_REGISTERS: &'static [Register] =
+        &[Register::Ax, Register::Bx, Register::Di, Register::Si];
+    const ARGUMENT_REGISTERS: &'static [Register] = X86_64::ARGUMENT_REGISTERS;
+    const RETURN_REGISTER: Register = X86_64::RETURN_REGISTER;
+    const FLOAT_RETURN_REGISTER: Option<Register> = X86_64::FLOAT_RETURN_REGISTER;
+    const CALLEE_SAVED_REGISTERS: &'static [Register] = X86_64::CALLEE_SAVED_REGISTERS;
+    const LINK_REGISTER: Option<Register> = X86_64::LINK_REGISTER;
+    const STACK_POINTER: Register = X86_64::STACK_POINTER;
+    const STACK_ALIGNMENT: i32 = X86_64::STACK_ALIGNMENT;
+    const PUSH_SIZE: i32 = X86_64::PUSH_SIZE;
+
+    const SCRATCH_REGISTER: Register = X86_64::SCRATCH_REGISTER;
+    const FLOAT_SCRATCH_REGISTER: Option<Register> = X86_64::FLOAT_SCRATCH_REGISTER;
+    const COUNTER_REGISTER: Register = X86_64::COUNTER_REGISTER;
+
+    fn register_class(reg: Register) -> RegisterClass {
+        X86_64::register_class(reg)
+    }
+
+    fn rules() -> &'static Rules<Instructions<Register>, Register> {
+        X86_64::rules()
+    }
+
+    fn assemble(
+        instructions: Instructions<Register>,
+        buf: &mut Vec<u8>,
+    ) -> Result<Vec<Relocation>, AssemblerError> {
+        X86_64::assemble(instructions, buf)
+    }
+
+    fn relocation(
+        format: Format,
+        kind: RelocationKind,
+    ) -> (object::RelocationKind, RelocationEncoding) {
+        X86_64::relocation(format, kind)
+    }
+}
+
+#[test]
+fn callee_saved_registers() {
+    let src = "
+fn f(_1: int) -> int {
+    let _2: int
+
+    bb0: _2 = CALL g(_1)
+         _0 = _2 + _1
+         RETURN
+}";
+
+    // `_2` lives in `rbx`, which is saved before the first block and restored before returning.
+    // It is not saved around the call, and the pushes keep the stack aligned without padding.
+    let module = parse_module(src).unwrap();
+    let func = module.functions.values().next().unwrap();
+    let instructions = lower_function::<CalleeSaved>(func, &LowerOptions::default()).unwrap();
+    let expected = "    push rbx
+.L0:
+    push rax
+    push rdi
+    push rdi
+    pop rdi
+    call g
+    mov rax,r11
+    pop rdi
+    pop rax
+    mov r11,rbx
+    lea rbx+rdi,rax
+    pop rbx
+    ret
+";
+    assert_eq!(instructions.to_string(), expected);
+}
+
+#[test]
+fn not_enough_registers() {
+    let lower = |src: &str| {
+        let module = parse_module(src).unwrap();
+        let func = module.functions.values().next().unwrap();
+        lower_function::<X86_64>(func, &LowerOptions::default()).unwrap_err()
+    };
+
+    // x86-64 passes integers in 3 registers and has 5 registers for the locals.
+    let args = "
+fn f(_1: int, _2: int, _3: int, _4: int) -> int {
+    bb0: _0 = USE _4
+         RETURN
+}";
+    assert_eq!(lower(args), LowerError::TooManyArguments(4));
+
+    let locals = "
+fn f(_1: int) -> int {
+    let _2: int
+    let _3: int
+    let _4: int
+    let _5: int
+
+    bb0: _2 = _1 + 1
+         _3 = _2 + 1
+         _4 = _3 + 1
+         _5 = _4 + 1
+         _0 = USE _5
+         RETURN
+}";
+    assert_eq!(lower(locals), LowerError::TooManyLocals(6));
+
+    let call_args = "
+fn f(_1: int) -> int {
+    bb0: _0 = CALL g(_1, _1, _1, _1)
+         RETURN
+}";
+    assert_eq!(
+        lower(call_args),
+        LowerError::TooManyCallArguments {
+            callee: "g".to_owned(),
+            args: 4
+        }
+    );
+}
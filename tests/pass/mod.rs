@@ -0,0 +1,102 @@
+use pijama::pass::{Dump, OptLevel, Pass, PassManager, PassOptions, Pipeline};
+
+/// The names of the passes that have been run.
+#[derive(Default)]
+struct Trace(Vec<&'static str>);
+
+impl Dump for Trace {
+    fn dump(&self, name: &str) -> String {
+        format!("{name}: {}\n", self.0.join(" "))
+    }
+}
+
+/// Appends its name to the trace.
+struct Append(&'static str);
+
+impl Pass<Trace> for Append {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn run(&self, trace: &mut Trace) -> bool {
+        trace.0.push(self.0);
+        true
+    }
+}
+
+fn pipeline() -> Pipeline<Trace> {
+    vec![
+        (OptLevel::O1, Box::new(Append("first"))),
+        (OptLevel::O2, Box::new(Append("second"))),
+        (OptLevel::O1, Box::new(Append("third"))),
+    ]
+}
+
+fn run(options: PassOptions) -> (Vec<&'static str>, PassManager<Trace>) {
+    let mut manager = PassManager::new(pipeline(), &options);
+    let mut trace = Trace::default();
+    manager.run("f", &mut trace);
+    (trace.0, manager)
+}
+
+#[test]
+fn opt_levels() {
+    for (opt_level, expected) in [
+        (OptLevel::O0, &[][..]),
+        (OptLevel::O1, &["first", "third"]),
+        (OptLevel::O2, &["first", "second", "third"]),
+    ] {
+        let (trace, manager) = run(PassOptions {
+            opt_level,
+            ..PassOptions::default()
+        });
+
+        assert_eq!(trace, expected);
+        assert_eq!(manager.pipeline().collect::<Vec<_>>(), expected);
+    }
+}
+
+#[test]
+fn enable_and_disable() {
+    let (trace, manager) = run(PassOptions {
+        enabled: ["second".to_owned()].into(),
+        disabled: ["first".to_owned()].into(),
+        ..PassOptions::default()
+    });
+
+    assert_eq!(trace, ["second", "third"]);
+    assert!(manager.contains("first"));
+    assert!(!manager.contains("fourth"));
+}
+
+#[test]
+fn dump_after() {
+    let (_, mut manager) = run(PassOptions {
+        dump_after: ["third".to_owned()].into(),
+        ..PassOptions::default()
+    });
+    assert_eq!(manager.take_dumps(), "; after third on f\nf: first third\n");
+    assert_eq!(manager.take_dumps(), "");
+
+    let (_, mut manager) = run(PassOptions {
+        dump_after: ["all".to_owned()].into(),
+        ..PassOptions::default()
+    });
+    assert_eq!(
+        manager.take_dumps(),
+        "; after first on f\nf: first\n; after third on f\nf: first third\n"
+    );
+}
+
+#[test]
+fn statistics() {
+    let (_, manager) = run(PassOptions {
+        time_passes: true,
+        ..PassOptions::default()
+    });
+
+    let statistics = manager.statistics().collect::<Vec<_>>();
+    assert_eq!(statistics.len(), 2);
+    assert_eq!(statistics[0].name, "first");
+    assert_eq!((statistics[0].runs, statistics[0].changes), (1, 1));
+}
@@ -0,0 +1,118 @@
+use std::path::Path;
+
+use object::read::{File, Object, ObjectSection, ObjectSymbol};
+use pijama::{
+    emit::{Format, ObjectEmitter},
+    mir::parse::parse_module,
+    profile::{Instrumentation, Profile, COUNTERS_SYMBOL, NAMES_SYMBOL, PATH_SYMBOL},
+};
+
+const SRC: &str = "
+fn f(_1: bool) -> int {
+    bb0: JUMP IF _1 THEN bb1 ELSE bb2
+
+    bb1: _0 = USE 1
+         RETURN
+
+    bb2: _0 = USE 2
+         RETURN
+}
+
+fn g() -> int {
+    bb0: _0 = USE 0
+         RETURN
+}
+";
+
+#[test]
+fn parse() {
+    let profile = Profile::parse("f bb0 10\nf bb1 0\n\ng bb0 3\nf bb0 5\n").unwrap();
+
+    // The lines of the same block are added up.
+    assert_eq!(profile.count("f", "bb0"), Some(15));
+    assert_eq!(profile.count("f", "bb1"), Some(0));
+    assert_eq!(profile.count("f", "bb2"), None);
+    assert_eq!(profile.count("g", "bb0"), Some(3));
+    assert_eq!(profile.count("h", "bb0"), None);
+}
+
+#[test]
+fn parse_errors() {
+    let error = Profile::parse("f bb0 1\nf bb1\n").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "2:1: expected a function, a block and a count"
+    );
+
+    let error = Profile::parse("f bb0 -1\n").unwrap_err();
+    assert_eq!(error.to_string(), "1:1: invalid count `-1`");
+}
+
+#[test]
+fn apply() {
+    let mut module = parse_module(SRC).unwrap();
+    Profile::parse("f bb0 10\nf bb2 4\n")
+        .unwrap()
+        .apply(&mut module);
+
+    let counts = module.functions["f"]
+        .basic_blocks
+        .values()
+        .map(|bb_data| bb_data.profile.count)
+        .collect::<Vec<_>>();
+    assert_eq!(counts, [Some(10), None, Some(4)]);
+    assert!(module.functions["g"]
+        .basic_blocks
+        .values()
+        .all(|bb_data| bb_data.profile.count.is_none()));
+}
+
+#[test]
+fn instrumentation() {
+    let mut module = parse_module(SRC).unwrap();
+    let instrumentation = Instrumentation::new(&mut module);
+    assert_eq!(instrumentation.len(), 4);
+
+    let counters = module
+        .functions
+        .values()
+        .flat_map(|func| func.basic_blocks.values())
+        .map(|bb_data| bb_data.profile.counters.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(counters, [vec![0], vec![1], vec![2], vec![3]]);
+
+    let mut emitter = ObjectEmitter::new(Format::Elf);
+    instrumentation
+        .emit(&mut emitter, Path::new("out.profile"))
+        .unwrap();
+    let bytes = emitter.write().unwrap();
+    let file = File::parse(&*bytes).unwrap();
+
+    let data = |name: &str| {
+        let symbol = file
+            .symbols()
+            .find(|symbol| symbol.name() == Ok(name))
+            .unwrap();
+        let section = file
+            .section_by_index(symbol.section_index().unwrap())
+            .unwrap();
+        let offset = (symbol.address() - section.address()) as usize;
+        (section, offset, symbol.size() as usize)
+    };
+
+    let (section, _, size) = data(COUNTERS_SYMBOL);
+    assert_eq!(section.name(), Ok(".bss"));
+    assert_eq!(size, 32);
+
+    let (section, offset, size) = data(NAMES_SYMBOL);
+    assert_eq!(
+        &section.data().unwrap()[offset..offset + size],
+        b"f bb0\nf bb1\nf bb2\ng bb0\n\0"
+    );
+
+    let (section, offset, size) = data(PATH_SYMBOL);
+    assert_eq!(
+        &section.data().unwrap()[offset..offset + size],
+        b"out.profile\0"
+    );
+}